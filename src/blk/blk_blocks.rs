@@ -0,0 +1,224 @@
+use crate::{
+    blk::BlkRecord,
+    parsers::{parse_block, parse_magic_number},
+    types::Block,
+    utils::find_block_start,
+};
+use nom::{error::ErrorKind, number::complete::le_u32, Err};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CorruptionError {
+    //the declared size runs past the end of the file, or the size itself is cut off
+    Truncated,
+    //at is where in the file the parser gave up
+    Parse { kind: ErrorKind, at: usize },
+    //the block ends this many bytes before the declared size
+    TrailingBytes(usize),
+}
+
+impl std::fmt::Display for CorruptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CorruptionError::Truncated => write!(f, "the record runs past the end of the file"),
+            CorruptionError::Parse { kind, at } => {
+                write!(f, "does not parse, {:?} error at {}", kind, at)
+            }
+            CorruptionError::TrailingBytes(n) => write!(f, "{} trailing bytes after the block", n),
+        }
+    }
+}
+
+impl std::error::Error for CorruptionError {}
+
+//a record of a blk file skipped because it does not hold a block
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Corruption {
+    //where the serialized block would start, after the magic number and size
+    pub offset: usize,
+    //None if the size is cut off
+    pub size: Option<u32>,
+    pub error: CorruptionError,
+}
+
+//whether a record may start here, after the zero padding bitcoind leaves between records
+fn at_boundary(input: &[u8]) -> bool {
+    let start = input.iter().position(|b| *b != 0).unwrap_or(input.len());
+    match parse_magic_number(&input[start..]) {
+        Ok((_, chain)) => chain.is_some(),
+        Err(_) => start == input.len(),
+    }
+}
+
+//goes through the blocks of a blk file, going on after records that do not hold one
+//a corrupt record is skipped by its declared size if a record or zero padding follows it,
+//otherwise reading resyncs at the next magic number after its own
+pub struct BlkBlocks<'a> {
+    data: &'a [u8],
+    position: usize,
+    corruptions: Vec<Corruption>,
+}
+
+impl<'a> BlkBlocks<'a> {
+    pub fn new(data: &'a [u8]) -> BlkBlocks<'a> {
+        BlkBlocks {
+            data,
+            position: 0,
+            corruptions: Vec::new(),
+        }
+    }
+
+    //the records skipped so far, all of them once the iteration ended
+    pub fn corruptions(&self) -> &[Corruption] {
+        &self.corruptions
+    }
+
+    //reads the rest of the file, the report of its corrupt records comes last
+    pub fn read_all(mut self) -> (Vec<(BlkRecord<'a>, Block)>, Vec<Corruption>) {
+        let blocks = self.by_ref().collect();
+        (blocks, self.corruptions)
+    }
+}
+
+impl<'a> Iterator for BlkBlocks<'a> {
+    type Item = (BlkRecord<'a>, Block);
+
+    fn next(&mut self) -> Option<(BlkRecord<'a>, Block)> {
+        loop {
+            let (input, chain) = find_block_start(&self.data[self.position..]).ok()?;
+            let start = self.data.len() - input.len() - 4;
+            let offset = start + 8;
+            let size = match le_u32::<()>(input) {
+                Ok((_, size)) => size,
+                Err(_) => {
+                    self.corruptions.push(Corruption {
+                        offset,
+                        size: None,
+                        error: CorruptionError::Truncated,
+                    });
+                    self.position = self.data.len();
+                    return None;
+                }
+            };
+            let data = match self.data[offset..].get(..size as usize) {
+                Some(data) => data,
+                None => {
+                    self.corruptions.push(Corruption {
+                        offset,
+                        size: Some(size),
+                        error: CorruptionError::Truncated,
+                    });
+                    self.position = start + 4;
+                    continue;
+                }
+            };
+            let end = offset + data.len();
+            let error = match parse_block(data) {
+                Ok(([], block)) => {
+                    self.position = end;
+                    let record = BlkRecord {
+                        offset,
+                        chain: chain.unwrap_or_default(),
+                        data,
+                    };
+                    return Some((record, block));
+                }
+                Ok((rest, _)) => CorruptionError::TrailingBytes(rest.len()),
+                Err(Err::Error((rest, kind))) | Err(Err::Failure((rest, kind))) => {
+                    CorruptionError::Parse {
+                        kind,
+                        at: end - rest.len(),
+                    }
+                }
+                Err(Err::Incomplete(_)) => CorruptionError::Parse {
+                    kind: ErrorKind::Eof,
+                    at: end,
+                },
+            };
+            self.corruptions.push(Corruption {
+                offset,
+                size: Some(size),
+                error,
+            });
+            self.position = match at_boundary(&self.data[end..]) {
+                true => end,
+                false => start + 4,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(data: &[u8]) -> (Vec<usize>, Vec<Corruption>) {
+        let (blocks, corruptions) = BlkBlocks::new(data).read_all();
+        for (_, block) in blocks.iter() {
+            assert_eq!(block.transactions.len(), 1);
+        }
+        let offsets = blocks.iter().map(|(record, _)| record.offset).collect();
+        (offsets, corruptions)
+    }
+
+    #[test]
+    fn test_blk_blocks() {
+        //every file is made of records of the genesis block
+        let len = 8 + 285;
+
+        //skipped by its declared size
+        let data = include_bytes!("../test_data/blk_corrupt_tx_count.bin");
+        let (offsets, corruptions) = read(data);
+        assert_eq!(offsets, vec![8, 2 * len + 8]);
+        assert_eq!(corruptions.len(), 1);
+        assert_eq!(corruptions[0].offset, len + 8);
+        assert_eq!(corruptions[0].size, Some(285));
+        match corruptions[0].error {
+            CorruptionError::Parse { at, .. } => assert_eq!(at, 2 * len),
+            error => panic!("{:?}", error),
+        }
+
+        //a transaction count no block could hold
+        let data = include_bytes!("../test_data/blk_corrupt_tx_count_overflow.bin");
+        let (offsets, corruptions) = read(data);
+        assert_eq!(offsets, vec![8, len + 8 + 89 + 8]);
+        assert_eq!(corruptions.len(), 1);
+        assert_eq!(corruptions[0].size, Some(89));
+
+        //a size too short does not end at a record, the next one is found by its magic number
+        let data = include_bytes!("../test_data/blk_corrupt_size.bin");
+        let (offsets, corruptions) = read(data);
+        assert_eq!(offsets, vec![8, 2 * len + 8]);
+        assert_eq!(corruptions.len(), 1);
+        assert_eq!(corruptions[0].offset, len + 8);
+        assert_eq!(corruptions[0].size, Some(200));
+
+        //a size too long runs past the end of the file, like the record cut short at its end
+        let data = include_bytes!("../test_data/blk_corrupt_truncated.bin");
+        let (offsets, corruptions) = read(data);
+        assert_eq!(offsets, vec![8, 2 * len + 8]);
+        assert_eq!(
+            corruptions,
+            vec![
+                Corruption {
+                    offset: len + 8,
+                    size: Some(100000),
+                    error: CorruptionError::Truncated,
+                },
+                Corruption {
+                    offset: 3 * len + 8,
+                    size: Some(285),
+                    error: CorruptionError::Truncated,
+                },
+            ]
+        );
+
+        //a block shorter than its record
+        let genesis = &data[8..len];
+        let record = |size: usize| [&data[..4], &(size as u32).to_le_bytes(), genesis].concat();
+        let data = [record(290), vec![0; 5], record(285), vec![1, 2, 3]].concat();
+        let (offsets, corruptions) = read(&data);
+        assert_eq!(offsets, vec![len + 5 + 8]);
+        assert_eq!(corruptions[0].error, CorruptionError::TrailingBytes(5));
+        assert_eq!(read(&[0; 100]), (Vec::new(), Vec::new()));
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+//bitcoind names its block files blk00000.dat, blk00001.dat, ...
+pub fn blk_file_name(number: u32) -> String {
+    format!("blk{:05}.dat", number)
+}
+
+pub fn blk_file_number(name: &str) -> Option<u32> {
+    let number = name.strip_prefix("blk")?.strip_suffix(".dat")?;
+    match number.len() >= 5 && number.bytes().all(|c| c.is_ascii_digit()) {
+        true => number.parse().ok(),
+        false => None,
+    }
+}
+
+//the undo data of the blocks in blkNNNNN.dat is in revNNNNN.dat
+pub fn rev_file_name(number: u32) -> String {
+    format!("rev{:05}.dat", number)
+}
+
+//the block files of a blocks directory, ordered by their number
+pub fn blk_files<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(u32, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(number) = entry.file_name().to_str().and_then(blk_file_number) {
+            files.push((number, entry.path()));
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_blk_file_names() {
+        assert_eq!(blk_file_name(0), "blk00000.dat");
+        assert_eq!(blk_file_name(2063), "blk02063.dat");
+        assert_eq!(rev_file_name(2063), "rev02063.dat");
+        assert_eq!(blk_file_number("blk02063.dat"), Some(2063));
+        assert_eq!(blk_file_number("blk123456.dat"), Some(123456));
+        assert_eq!(blk_file_number("rev02063.dat"), None);
+        assert_eq!(blk_file_number("blk0206.dat"), None);
+        assert_eq!(blk_file_number("blk0206a.dat"), None);
+        assert_eq!(blk_file_number("blk02063.dat.tmp"), None);
+    }
+}
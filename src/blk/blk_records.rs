@@ -0,0 +1,87 @@
+use crate::utils::find_block_start;
+use nom::number::complete::le_u32;
+
+//a block as stored in a blk file: magic number, size and the serialized block
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BlkRecord<'a> {
+    //where the serialized block starts in the file, after the magic number and size
+    pub offset: usize,
+    pub chain: &'a str,
+    pub data: &'a [u8],
+}
+
+//goes through the records of a blk file, zero padding and other bytes between records are skipped
+//the iteration ends at the first record that is cut short
+pub struct BlkRecords<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BlkRecords<'a> {
+    pub fn new(data: &'a [u8]) -> BlkRecords<'a> {
+        BlkRecords { data, position: 0 }
+    }
+}
+
+impl<'a> Iterator for BlkRecords<'a> {
+    type Item = BlkRecord<'a>;
+
+    fn next(&mut self) -> Option<BlkRecord<'a>> {
+        let input = &self.data[self.position..];
+        let (input, chain) = find_block_start(input).ok()?;
+        let (input, size) = le_u32::<()>(input).ok()?;
+        let offset = self.data.len() - input.len();
+        let data = input.get(..size as usize)?;
+        self.position = offset + data.len();
+        Some(BlkRecord {
+            offset,
+            chain: chain.unwrap_or_default(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex;
+    #[test]
+    fn test_blk_records() {
+        let block = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let record = |magic: &str| {
+            [
+                &hex::decode(magic).unwrap()[..],
+                &(block.len() as u32).to_le_bytes(),
+                block,
+            ]
+            .concat()
+        };
+        let data = [
+            record("f9beb4d9"),
+            vec![0; 3],
+            record("fabfb5da"),
+            //a record cut short ends the iteration
+            record("f9beb4d9")[..100].to_vec(),
+        ]
+        .concat();
+        let records: Vec<_> = BlkRecords::new(&data).collect();
+        assert_eq!(
+            records,
+            vec![
+                BlkRecord {
+                    offset: 8,
+                    chain: "mainnet",
+                    data: &block[..],
+                },
+                BlkRecord {
+                    offset: 8 + block.len() + 3 + 8,
+                    chain: "regtest",
+                    data: &block[..],
+                },
+            ]
+        );
+        assert_eq!(BlkRecords::new(&[0; 100]).count(), 0);
+    }
+}
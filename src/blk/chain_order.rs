@@ -0,0 +1,320 @@
+use crate::types::{Block, Hash256};
+use std::collections::{HashMap, VecDeque};
+
+//the deepest reorg followed, branches forking off further back are dropped
+//deeper reorgs would also undo coinbase outputs that may already be spent
+pub const MAX_REORG_DEPTH: u32 = 100;
+
+#[derive(Debug)]
+pub enum ChainEvent {
+    //a block joins the chain at its height
+    Connected(u32, Box<Block>),
+    //the tip at this height leaves the chain for a longer branch, before the blocks of the branch are connected
+    Disconnected(u32, Hash256),
+}
+
+//blocks are written to blk files in the order they were downloaded, not in chain order
+//blocks arriving before their parent wait until the parent is connected
+//every branch is kept, the chain follows the one with the most work and the first block seen wins a tie
+//a branch that loses a reorg is dropped, bitcoind keeps the blocks it already has of it
+pub struct ChainOrder {
+    next_height: u32,
+    //the hashes of the last connected blocks and the work of the chain up to them, the tip last
+    //the work is counted from the first of them
+    recent: VecDeque<(Hash256, u128)>,
+    //blocks not connected by the hash of their parent
+    pending: HashMap<Hash256, Vec<Block>>,
+    //the parent and arrival of every pending block
+    parents: HashMap<Hash256, (Hash256, u64)>,
+    //the heights and chain work of pending blocks descending from a recent block
+    branches: HashMap<Hash256, (u32, u128)>,
+    arrivals: u64,
+}
+
+impl ChainOrder {
+    //starts at the genesis block, whose parent is all zeros
+    pub fn new() -> ChainOrder {
+        ChainOrder::from_chain(Vec::new(), 0)
+    }
+
+    //continues after an already processed block
+    pub fn from_tip(hash: Hash256, height: u32) -> ChainOrder {
+        ChainOrder::from_chain(vec![(hash, 0)], height + 1)
+    }
+
+    //continues after already processed blocks, given by their hash and work, the last of them in chain order
+    //a reorg can go back as far as they reach
+    pub fn from_chain(blocks: Vec<(Hash256, u128)>, next_height: u32) -> ChainOrder {
+        let mut chain_work = 0u128;
+        let mut recent: VecDeque<_> = blocks
+            .into_iter()
+            .map(|(hash, work)| {
+                chain_work = chain_work.saturating_add(work);
+                (hash, chain_work)
+            })
+            .collect();
+        //the parent of the genesis block
+        if recent.is_empty() {
+            recent.push_back((Hash256::default(), 0));
+        }
+        ChainOrder {
+            next_height,
+            recent,
+            pending: HashMap::new(),
+            parents: HashMap::new(),
+            branches: HashMap::new(),
+            arrivals: 0,
+        }
+    }
+
+    pub fn tip(&self) -> Hash256 {
+        self.recent.back().unwrap().0
+    }
+
+    //the height of the next block to be connected
+    pub fn next_height(&self) -> u32 {
+        self.next_height
+    }
+
+    pub fn pending(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_pending(&self, hash: &Hash256) -> bool {
+        self.parents.contains_key(hash)
+    }
+
+    //the height a child of hash gets and the chain work up to hash,
+    //if hash is connected or a pending block on a branch
+    fn child_of(&self, hash: &Hash256) -> Option<(u32, u128)> {
+        if let Some((height, chain_work)) = self.branches.get(hash) {
+            return Some((height + 1, *chain_work));
+        }
+        let position = self.recent.iter().rposition(|(recent, _)| recent == hash)?;
+        let height = self.next_height + position as u32 + 1 - self.recent.len() as u32;
+        Some((height, self.recent[position].1))
+    }
+
+    //the pending descendants of a block joining a branch join it as well
+    fn join_branch(&mut self, hash: Hash256, height: u32, chain_work: u128) {
+        let mut stack = vec![(hash, height, chain_work)];
+        while let Some((hash, height, chain_work)) = stack.pop() {
+            self.branches.insert(hash, (height, chain_work));
+            for child in self.pending.get(&hash).into_iter().flatten() {
+                let child_work = chain_work.saturating_add(child.header.work());
+                stack.push((child.header.hash.0, height + 1, child_work));
+            }
+        }
+    }
+
+    //drops the pending descendants of hash
+    fn drop_descendants(&mut self, hash: Hash256) {
+        let mut stack = vec![hash];
+        while let Some(hash) = stack.pop() {
+            for child in self.pending.remove(&hash).into_iter().flatten() {
+                self.parents.remove(&child.header.hash.0);
+                self.branches.remove(&child.header.hash.0);
+                stack.push(child.header.hash.0);
+            }
+        }
+    }
+
+    //takes a pending block out to be connected
+    fn take(&mut self, hash: &Hash256) -> Block {
+        let (parent, _) = self.parents.remove(hash).unwrap();
+        self.branches.remove(hash);
+        let siblings = self.pending.get_mut(&parent).unwrap();
+        let position = siblings.iter().position(|b| b.header.hash.0 == *hash);
+        let block = siblings.swap_remove(position.unwrap());
+        if siblings.is_empty() {
+            self.pending.remove(&parent);
+        }
+        block
+    }
+
+    //returns the blocks disconnected and connected by this one, in chain order
+    //a block already pending or among the recent blocks is ignored
+    pub fn push(&mut self, block: Block) -> Vec<ChainEvent> {
+        let hash = block.header.hash.0;
+        let parent = block.header.prev_block_hash.0;
+        let work = block.header.work();
+        if self.parents.contains_key(&hash) || self.recent.iter().any(|(h, _)| *h == hash) {
+            return Vec::new();
+        }
+        self.parents.insert(hash, (parent, self.arrivals));
+        self.arrivals += 1;
+        self.pending.entry(parent).or_default().push(block);
+        match self.child_of(&parent) {
+            Some((height, chain_work)) => {
+                self.join_branch(hash, height, chain_work.saturating_add(work))
+            }
+            None => return Vec::new(),
+        }
+
+        //the end of the branch with the most work, the first to arrive of those with as much
+        //like bitcoind a branch needs more work than the chain to replace it
+        let parents = &self.parents;
+        let best = self.branches.iter().max_by_key(|(hash, (_, chain_work))| {
+            (*chain_work, std::cmp::Reverse(parents[*hash].1))
+        });
+        let tip_work = self.recent.back().unwrap().1;
+        let mut hash = match best {
+            Some((hash, (_, chain_work))) if *chain_work > tip_work => *hash,
+            _ => return Vec::new(),
+        };
+        let mut branch = vec![hash];
+        while let Some((parent, _)) = self.parents.get(&hash) {
+            hash = *parent;
+            branch.push(hash);
+        }
+        let fork = branch.pop().unwrap();
+
+        let mut events = Vec::new();
+        while self.tip() != fork {
+            let (tip, _) = self.recent.pop_back().unwrap();
+            self.next_height -= 1;
+            events.push(ChainEvent::Disconnected(self.next_height, tip));
+            self.drop_descendants(tip);
+        }
+        for hash in branch.into_iter().rev() {
+            let (_, chain_work) = self.branches[&hash];
+            let block = self.take(&hash);
+            events.push(ChainEvent::Connected(self.next_height, Box::new(block)));
+            self.next_height += 1;
+            self.recent.push_back((hash, chain_work));
+            if self.recent.len() > MAX_REORG_DEPTH as usize + 1 {
+                let (stale, _) = self.recent.pop_front().unwrap();
+                self.drop_descendants(stale);
+            }
+        }
+        events
+    }
+}
+
+impl std::default::Default for ChainOrder {
+    fn default() -> ChainOrder {
+        ChainOrder::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{BlockHash, BlockHeader, Bytes};
+
+    //bits as the number bitcoind prints in hex
+    fn block_with_bits(prev: u8, hash: u8, bits: u32) -> Block {
+        let header = BlockHeader {
+            prev_block_hash: BlockHash(Hash256([prev; 32])),
+            hash: BlockHash(Hash256([hash; 32])),
+            bits: Bytes::new(&bits.to_le_bytes()),
+            ..BlockHeader::default()
+        };
+        Block::new(header, Vec::new())
+    }
+
+    //a block of the regtest difficulty, with a work of 2
+    fn block(prev: u8, hash: u8) -> Block {
+        block_with_bits(prev, hash, 0x207fffff)
+    }
+
+    //connected blocks as their height and hash, disconnected ones with the hash negated
+    fn hashes(events: Vec<ChainEvent>) -> Vec<(u32, i16)> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                ChainEvent::Connected(height, block) => {
+                    (height, block.header.hash.to_wire_bytes()[0] as i16)
+                }
+                ChainEvent::Disconnected(height, hash) => (height, -(hash.0[0] as i16)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chain_order() {
+        let mut order = ChainOrder::new();
+        assert_eq!(hashes(order.push(block(0, 1))), vec![(0, 1)]);
+        assert!(order.push(block(3, 4)).is_empty());
+        assert!(order.push(block(2, 3)).is_empty());
+        //a second child of block 3, not connected as block 4 came first
+        assert!(order.push(block(3, 5)).is_empty());
+        assert_eq!(order.pending(), 3);
+        assert_eq!(
+            hashes(order.push(block(1, 2))),
+            vec![(1, 2), (2, 3), (3, 4)]
+        );
+        assert_eq!(order.tip(), Hash256([4; 32]));
+        assert_eq!(order.next_height(), 4);
+        assert_eq!(order.pending(), 1);
+        assert!(order.push(block(1, 2)).is_empty());
+
+        //the branch of block 5 gets longer
+        assert!(order.push(block(6, 7)).is_empty());
+        assert_eq!(
+            hashes(order.push(block(5, 6))),
+            vec![(3, -4), (3, 5), (4, 6), (5, 7)]
+        );
+        assert_eq!(order.next_height(), 6);
+        assert_eq!(order.pending(), 0);
+
+        //a stale block first does not stall the chain
+        let mut order = ChainOrder::new();
+        order.push(block(0, 1));
+        assert_eq!(hashes(order.push(block(1, 8))), vec![(1, 8)]);
+        assert!(order.push(block(1, 2)).is_empty());
+        assert_eq!(
+            hashes(order.push(block(2, 3))),
+            vec![(1, -8), (1, 2), (2, 3)]
+        );
+
+        let mut order = ChainOrder::from_tip(Hash256([4; 32]), 3);
+        assert!(order.push(block(1, 2)).is_empty());
+        assert_eq!(hashes(order.push(block(4, 6))), vec![(4, 6)]);
+
+        //a reorg reaches back as far as the known blocks
+        let mut order =
+            ChainOrder::from_chain(vec![(Hash256([1; 32]), 2), (Hash256([2; 32]), 2)], 2);
+        assert!(order.push(block(1, 9)).is_empty());
+        assert_eq!(
+            hashes(order.push(block(9, 10))),
+            vec![(1, -2), (1, 9), (2, 10)]
+        );
+
+        //a branch with more work replaces a longer one of easier blocks
+        let mut order = ChainOrder::new();
+        order.push(block(0, 1));
+        order.push(block(1, 2));
+        order.push(block(2, 3));
+        assert_eq!(
+            hashes(order.push(block_with_bits(1, 9, 0x1f00ffff))),
+            vec![(2, -3), (1, -2), (1, 9)]
+        );
+        assert_eq!(order.next_height(), 2);
+        //the same work as the chain is not enough
+        order.push(block_with_bits(1, 10, 0x1f00ffff));
+        assert_eq!(order.tip(), Hash256([9; 32]));
+        assert_eq!(order.pending(), 1);
+        //a block with an invalid target adds no work
+        assert!(order.push(block_with_bits(10, 11, 0)).is_empty());
+        assert_eq!(
+            hashes(order.push(block(11, 12))),
+            vec![(1, -9), (1, 10), (2, 11), (3, 12)]
+        );
+    }
+
+    #[test]
+    fn test_chain_order_depth() {
+        let mut order = ChainOrder::new();
+        order.push(block(0, 1));
+        order.push(block(1, 2));
+        assert!(order.push(block(1, 200)).is_empty());
+        for hash in 2..=MAX_REORG_DEPTH as u8 {
+            assert_eq!(order.push(block(hash, hash + 1)).len(), 1);
+            assert_eq!(order.pending(), 1);
+        }
+        //the fork of the stale block is too deep for a reorg now
+        order.push(block(MAX_REORG_DEPTH as u8 + 1, MAX_REORG_DEPTH as u8 + 2));
+        assert_eq!(order.pending(), 0);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    blk::blk_file_name,
+    parsers::parse_block,
+    types::Block,
+    utils::{calculate_merkle_root, find_block_start},
+};
+use nom::number::complete::le_u32;
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+//where reading a blocks directory goes on, after the last record that was handed out
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct BlkPosition {
+    pub file: u32,
+    pub offset: u64,
+}
+
+#[derive(Debug)]
+pub enum FollowEvent {
+    //offset is where the serialized block starts, after the magic number and size
+    Block {
+        file: u32,
+        offset: u64,
+        chain: String,
+        block: Box<Block>,
+    },
+    //a record that does not hold a block with a matching merkle root,
+    //or one cut short in a file bitcoind moved on from
+    //size is None when even the size is cut off
+    Corrupt {
+        file: u32,
+        offset: u64,
+        size: Option<u32>,
+    },
+}
+
+//what comes next in the unread part of a blk file, start is where its magic number is
+enum Next<'a> {
+    Record {
+        start: usize,
+        chain: String,
+        data: &'a [u8],
+    },
+    Truncated {
+        start: usize,
+        size: Option<u32>,
+    },
+    //no magic number in the rest, zero padding of a preallocated file or nothing at all
+    End,
+}
+
+fn next_record(input: &[u8]) -> Next<'_> {
+    let (i, chain) = match find_block_start(input) {
+        Ok(found) => found,
+        Err(_) => return Next::End,
+    };
+    let start = input.len() - i.len() - 4;
+    let (i, size) = match le_u32::<()>(i) {
+        Ok(parsed) => parsed,
+        Err(_) => return Next::Truncated { start, size: None },
+    };
+    match i.get(..size as usize) {
+        Some(data) => Next::Record {
+            start,
+            chain: chain.unwrap_or_default().to_string(),
+            data,
+        },
+        None => Next::Truncated {
+            start,
+            size: Some(size),
+        },
+    }
+}
+
+//a record half written over zero padding may still parse, its merkle root gives it away
+fn complete_block(data: &[u8]) -> Option<Block> {
+    match parse_block(data) {
+        Ok(([], block)) => {
+            let txids = block.transactions.iter().map(|tx| tx.txid.0).collect();
+            match calculate_merkle_root(txids) == block.header.merkle_root_hash.0 {
+                true => Some(block),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn read_from(path: &Path, offset: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+//reads the blocks bitcoind appends to the blk files of a blocks directory while it syncs
+//a record cut short at the end of the newest file is read again by the next poll,
+//bitcoind preallocates its files so a record may also still be filled in over zero padding
+pub struct BlkFollower {
+    blocks_dir: PathBuf,
+    position: BlkPosition,
+}
+
+impl BlkFollower {
+    pub fn new<P: AsRef<Path>>(blocks_dir: P) -> BlkFollower {
+        BlkFollower::from_position(blocks_dir, BlkPosition::default())
+    }
+
+    //continues after a position returned by position
+    pub fn from_position<P: AsRef<Path>>(blocks_dir: P, position: BlkPosition) -> BlkFollower {
+        BlkFollower {
+            blocks_dir: blocks_dir.as_ref().to_path_buf(),
+            position,
+        }
+    }
+
+    pub fn position(&self) -> BlkPosition {
+        self.position
+    }
+
+    //hands every record written since the last poll to f, going on to new blk files
+    //f returns false to stop, the next poll starts after the record it was handed
+    //returns the number of records handed to f
+    pub fn poll<F: FnMut(FollowEvent) -> bool>(&mut self, mut f: F) -> io::Result<usize> {
+        let mut handed = 0;
+        loop {
+            let BlkPosition { file, offset } = self.position;
+            let data = match read_from(&self.blocks_dir.join(blk_file_name(file)), offset) {
+                Ok(data) => data,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(handed),
+                Err(e) => return Err(e),
+            };
+            //bitcoind only starts a new file once it is done with the old one
+            let finished = self.blocks_dir.join(blk_file_name(file + 1)).exists();
+            let mut position = 0;
+            loop {
+                let (event, end) = match next_record(&data[position..]) {
+                    Next::Record {
+                        start,
+                        chain,
+                        data: record,
+                    } => {
+                        let offset = offset + (position + start + 8) as u64;
+                        let end = position + start + 8 + record.len();
+                        match complete_block(record) {
+                            Some(block) => (
+                                FollowEvent::Block {
+                                    file,
+                                    offset,
+                                    chain,
+                                    block: Box::new(block),
+                                },
+                                end,
+                            ),
+                            //a record being written still ends in zero padding
+                            None if !finished && data[end..].iter().all(|b| *b == 0) => break,
+                            None => (
+                                FollowEvent::Corrupt {
+                                    file,
+                                    offset,
+                                    size: Some(record.len() as u32),
+                                },
+                                end,
+                            ),
+                        }
+                    }
+                    Next::Truncated { start, size } if finished => (
+                        FollowEvent::Corrupt {
+                            file,
+                            offset: offset + (position + start + 8) as u64,
+                            size,
+                        },
+                        data.len(),
+                    ),
+                    Next::Truncated { .. } | Next::End => break,
+                };
+                position = end;
+                self.position.offset = offset + end as u64;
+                handed += 1;
+                if !f(event) {
+                    return Ok(handed);
+                }
+            }
+            if !finished {
+                return Ok(handed);
+            }
+            self.position = BlkPosition {
+                file: file + 1,
+                offset: 0,
+            };
+        }
+    }
+
+    //polls every interval until f returns false
+    pub fn follow<F: FnMut(FollowEvent) -> bool>(
+        &mut self,
+        interval: Duration,
+        mut f: F,
+    ) -> io::Result<()> {
+        loop {
+            let mut stopped = false;
+            self.poll(|event| {
+                stopped = !f(event);
+                !stopped
+            })?;
+            if stopped {
+                return Ok(());
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    fn record(block: &[u8]) -> Vec<u8> {
+        [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(block.len() as u32).to_le_bytes(),
+            block,
+        ]
+        .concat()
+    }
+
+    fn append(path: &Path, data: &[u8]) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(data).unwrap();
+    }
+
+    //the offsets of the blocks and corrupt records, all blocks are the genesis block
+    fn poll(follower: &mut BlkFollower) -> Vec<(u32, u64, bool)> {
+        let mut events = Vec::new();
+        follower
+            .poll(|event| {
+                events.push(match event {
+                    FollowEvent::Block {
+                        file,
+                        offset,
+                        chain,
+                        block,
+                    } => {
+                        assert_eq!(chain, "mainnet");
+                        assert_eq!(block.transactions.len(), 1);
+                        (file, offset, true)
+                    }
+                    FollowEvent::Corrupt { file, offset, .. } => (file, offset, false),
+                });
+                true
+            })
+            .unwrap();
+        events
+    }
+
+    #[test]
+    fn test_follower() {
+        let block = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let record = record(block);
+        let len = record.len() as u64;
+        let dir = std::env::temp_dir().join(format!("parse_bitcoin_follow_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let blk0 = dir.join("blk00000.dat");
+        let blk1 = dir.join("blk00001.dat");
+
+        let mut follower = BlkFollower::new(&dir);
+        assert!(poll(&mut follower).is_empty());
+        append(&blk0, &record);
+        //a record cut short is read again once it is complete
+        append(&blk0, &record[..100]);
+        assert_eq!(poll(&mut follower), vec![(0, 8, true)]);
+        assert_eq!(
+            follower.position(),
+            BlkPosition {
+                file: 0,
+                offset: len
+            }
+        );
+        assert!(poll(&mut follower).is_empty());
+        append(&blk0, &record[100..]);
+        //a record still filled in over zero padding
+        let mut unwritten = record.clone();
+        unwritten[100..].iter_mut().for_each(|b| *b = 0);
+        append(&blk0, &[&unwritten[..], &[0; 20]].concat());
+        assert_eq!(poll(&mut follower), vec![(0, len + 8, true)]);
+        assert_eq!(follower.position().offset, 2 * len);
+
+        //the unwritten record is corrupt once a record follows it
+        let mut corrupt = record.clone();
+        corrupt[90] ^= 0xff;
+        corrupt[100..].iter_mut().for_each(|b| *b = 0);
+        let data = fs::read(&blk0).unwrap();
+        fs::write(
+            &blk0,
+            [&data[..2 * len as usize], &corrupt, &record].concat(),
+        )
+        .unwrap();
+        assert_eq!(
+            poll(&mut follower),
+            vec![(0, 2 * len + 8, false), (0, 3 * len + 8, true)]
+        );
+
+        //a new file finishes the old one, its record cut short is corrupt
+        append(&blk0, &record[..4]);
+        append(&blk1, &record);
+        let resumed = BlkFollower::from_position(&dir, follower.position());
+        assert_eq!(
+            poll(&mut follower),
+            vec![(0, 4 * len + 8, false), (1, 8, true)]
+        );
+        assert_eq!(
+            follower.position(),
+            BlkPosition {
+                file: 1,
+                offset: len
+            }
+        );
+
+        //stopping early keeps the records not handed out
+        let mut resumed = resumed;
+        let mut handed = 0;
+        resumed
+            .follow(Duration::from_millis(1), |_| {
+                handed += 1;
+                false
+            })
+            .unwrap();
+        assert_eq!(handed, 1);
+        assert_eq!(
+            resumed.position(),
+            BlkPosition {
+                file: 0,
+                offset: 4 * len + 4
+            }
+        );
+        assert_eq!(poll(&mut resumed), vec![(1, 8, true)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
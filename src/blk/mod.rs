@@ -0,0 +1,12 @@
+mod blk_blocks;
+pub use self::blk_blocks::{BlkBlocks, Corruption, CorruptionError};
+mod blk_files;
+pub use self::blk_files::{blk_file_name, blk_file_number, blk_files, rev_file_name};
+mod blk_records;
+pub use self::blk_records::{BlkRecord, BlkRecords};
+mod chain_order;
+pub use self::chain_order::{ChainEvent, ChainOrder, MAX_REORG_DEPTH};
+mod follower;
+pub use self::follower::{BlkFollower, BlkPosition, FollowEvent};
+mod rev_records;
+pub use self::rev_records::{RevRecord, RevRecords};
@@ -0,0 +1,151 @@
+use crate::{
+    parsers::parse_block_undo,
+    types::{Block, Hash256, OutPoint, TxOutput},
+    utils::{find_block_start, hash256},
+    utxo::BlockUndo,
+};
+use nom::number::complete::le_u32;
+use std::collections::HashMap;
+
+//the undo data of a block as stored in a rev file: magic number, size, the undo data and a checksum
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RevRecord<'a> {
+    //where the undo data starts in the file, after the magic number and size
+    pub offset: usize,
+    pub chain: &'a str,
+    pub data: &'a [u8],
+    pub checksum: Hash256,
+}
+
+impl<'a> RevRecord<'a> {
+    //the checksum is the hash256 of the hash of the parent of the block and the undo data
+    pub fn verify(&self, prev_block_hash: &Hash256) -> bool {
+        hash256(&[&prev_block_hash.0[..], self.data].concat()) == self.checksum
+    }
+
+    //None if the checksum does not match or the undo data does not parse
+    pub fn block_undo(&self, prev_block_hash: &Hash256) -> Option<BlockUndo> {
+        if !self.verify(prev_block_hash) {
+            return None;
+        }
+        match parse_block_undo(self.data) {
+            Ok(([], undo)) => Some(undo),
+            _ => None,
+        }
+    }
+
+    //the outputs spent by block, None if the record does not hold its undo data
+    pub fn prevouts(&self, block: &Block) -> Option<HashMap<OutPoint, TxOutput>> {
+        self.block_undo(&block.header.prev_block_hash.0)?
+            .prevouts(block)
+    }
+}
+
+//goes through the records of a rev file, in the order bitcoind connected the blocks
+//like BlkRecords the iteration ends at the first record that is cut short
+pub struct RevRecords<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> RevRecords<'a> {
+    pub fn new(data: &'a [u8]) -> RevRecords<'a> {
+        RevRecords { data, position: 0 }
+    }
+
+    //the undo data does not name its block, the checksum is what ties a record to it
+    pub fn find(mut self, block: &Block) -> Option<HashMap<OutPoint, TxOutput>> {
+        self.find_map(|record| record.prevouts(block))
+    }
+}
+
+impl<'a> Iterator for RevRecords<'a> {
+    type Item = RevRecord<'a>;
+
+    fn next(&mut self) -> Option<RevRecord<'a>> {
+        let input = &self.data[self.position..];
+        let (input, chain) = find_block_start(input).ok()?;
+        let (input, size) = le_u32::<()>(input).ok()?;
+        let offset = self.data.len() - input.len();
+        let data = input.get(..size as usize)?;
+        let checksum = input.get(data.len()..data.len() + 32)?;
+        self.position = offset + data.len() + 32;
+        Some(RevRecord {
+            offset,
+            chain: chain.unwrap_or_default(),
+            data,
+            checksum: Hash256::new(checksum),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parsers::parse_block, serializers::serialize_var_int};
+
+    //every input spends an empty output worth nothing, created at height 1
+    fn undo_data(block: &Block) -> Vec<u8> {
+        let mut data = serialize_var_int(block.transactions.len() as u64 - 1);
+        for tx in block.transactions[1..].iter() {
+            data.extend(serialize_var_int(tx.inputs.len() as u64));
+            for _ in tx.inputs.iter() {
+                data.extend_from_slice(&[0x02, 0x00, 0x00, 0x06]);
+            }
+        }
+        data
+    }
+
+    fn record(prev_block_hash: &Hash256, undo: &[u8]) -> Vec<u8> {
+        let checksum = hash256(&[&prev_block_hash.0[..], undo].concat());
+        [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(undo.len() as u32).to_le_bytes(),
+            undo,
+            &checksum.0,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_rev_records() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let undo = undo_data(&block);
+        let other = Hash256::new(&[0x11; 32]);
+        let data = [
+            record(&other, &[0]),
+            vec![0; 3],
+            record(&block.header.prev_block_hash.0, &undo),
+            //a record cut short ends the iteration
+            record(&other, &[0])[..40].to_vec(),
+        ]
+        .concat();
+        let records: Vec<_> = RevRecords::new(&data).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].offset, 8);
+        assert_eq!(records[0].data, &[0]);
+        assert_eq!(records[1].offset, 8 + 1 + 32 + 3 + 8);
+        assert_eq!(records[1].chain, "mainnet");
+        assert!(records[0].verify(&other));
+        assert!(!records[0].verify(&block.header.prev_block_hash.0));
+        assert!(records[1].verify(&block.header.prev_block_hash.0));
+        assert_eq!(records[0].block_undo(&other).unwrap().tx_undos.len(), 0);
+        assert!(records[0].prevouts(&block).is_none());
+
+        let inputs: usize = block.transactions[1..]
+            .iter()
+            .map(|tx| tx.inputs.len())
+            .sum();
+        let prevouts = RevRecords::new(&data).find(&block).unwrap();
+        assert_eq!(prevouts.len(), inputs);
+        assert!(prevouts.values().all(|output| output.value == 0));
+
+        //undo data that does not match its checksum
+        let mut corrupt = data.clone();
+        corrupt[records[1].offset] ^= 1;
+        assert!(RevRecords::new(&corrupt).find(&block).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::export::{TableData, Value};
+use std::io::{self, Write};
+
+//a field with a separator, quote or line break is quoted, with its quotes doubled
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+//the rows of the table after a header line with the column names
+pub fn write_csv<W: Write>(data: &TableData, writer: &mut W) -> io::Result<()> {
+    let header: Vec<_> = data
+        .table
+        .columns()
+        .iter()
+        .map(|(name, _)| csv_field(name))
+        .collect();
+    writeln!(writer, "{}", header.join(","))?;
+    for row in 0..data.rows() {
+        let fields: Vec<_> = data
+            .columns
+            .iter()
+            .map(|column| match column.get(row) {
+                Some(Value::U64(n)) => n.to_string(),
+                Some(Value::Str(s)) => csv_field(&s),
+                None => String::new(),
+            })
+            .collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        export::{Chunk, Table},
+        parsers::parse_block,
+    };
+
+    #[test]
+    fn test_write_csv() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let mut chunk = Chunk::new();
+        chunk.push_block(0, &block, "mainnet");
+        let mut csv = Vec::new();
+        write_csv(chunk.table(Table::Blocks), &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "height,hash,prev_hash,merkle_root,time,version,bits,nonce,tx_count,size,stripped_size,weight\n\
+             0,000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f,\
+             0000000000000000000000000000000000000000000000000000000000000000,\
+             4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b,\
+             1231006505,1,486604799,2083236893,1,285,285,1140\n"
+        );
+        let mut csv = Vec::new();
+        write_csv(chunk.table(Table::Witnesses), &mut csv).unwrap();
+        assert_eq!(csv, b"height,txid,input,index,item\n");
+        let mut csv = Vec::new();
+        write_csv(chunk.table(Table::Outputs), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(
+            "0,4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b,0,5000000000,pubkey,,4104"
+        ));
+
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+        assert_eq!(csv_field("ab"), "ab");
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{
+    blk::{blk_files, BlkRecords, ChainEvent, ChainOrder, MAX_REORG_DEPTH},
+    export::{write_csv, Chunk, TABLES},
+    parsers::parse_block,
+    serializers::serialize_columnar,
+    types::Block,
+};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Columnar,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Columnar => "col",
+        }
+    }
+}
+
+//writes the tables of every blocks_per_chunk blocks to their own files in dir,
+//named after the table and the height of the first block, like txs_00000000.csv
+//blocks are held back until MAX_REORG_DEPTH blocks follow them, deeper than a reorg can reach
+pub struct Exporter {
+    dir: PathBuf,
+    format: ExportFormat,
+    network: String,
+    blocks_per_chunk: usize,
+    chunk: Chunk,
+    //the blocks that can still leave the chain, the tip last
+    held: VecDeque<(u32, Block)>,
+    written: Vec<PathBuf>,
+}
+
+impl Exporter {
+    //network is the chain name of the blocks, addresses are encoded for it
+    pub fn new<P: AsRef<Path>>(
+        dir: P,
+        format: ExportFormat,
+        network: &str,
+        blocks_per_chunk: usize,
+    ) -> Exporter {
+        Exporter {
+            dir: dir.as_ref().to_path_buf(),
+            format,
+            network: network.to_string(),
+            blocks_per_chunk: blocks_per_chunk.max(1),
+            chunk: Chunk::new(),
+            held: VecDeque::new(),
+            written: Vec::new(),
+        }
+    }
+
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+
+    //the files written so far
+    pub fn written(&self) -> &[PathBuf] {
+        &self.written
+    }
+
+    pub fn push(&mut self, height: u32, block: Block) -> io::Result<()> {
+        self.held.push_back((height, block));
+        if self.held.len() > MAX_REORG_DEPTH as usize {
+            let (height, block) = self.held.pop_front().unwrap();
+            self.add(height, &block)?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, height: u32, block: &Block) -> io::Result<()> {
+        self.chunk.push_block(height, block, &self.network);
+        if self.chunk.blocks >= self.blocks_per_chunk {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    //drops the blocks from height on as they left the chain, an error if they are no longer held
+    pub fn disconnect(&mut self, height: u32) -> io::Result<()> {
+        match self.held.front() {
+            Some((first_height, _)) if height >= *first_height => {
+                let kept = self.held.iter().take_while(|(h, _)| *h < height).count();
+                self.held.truncate(kept);
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("block {} left the chain after it was written", height),
+            )),
+        }
+    }
+
+    //writes the blocks added since the last chunk, as a shorter chunk, held blocks are left out
+    pub fn flush(&mut self) -> io::Result<()> {
+        let first_height = match self.chunk.first_height {
+            Some(height) => height,
+            None => return Ok(()),
+        };
+        fs::create_dir_all(&self.dir)?;
+        for table in TABLES.iter() {
+            let name = format!(
+                "{}_{:08}.{}",
+                table.name(),
+                first_height,
+                self.format.extension()
+            );
+            let path = self.dir.join(name);
+            let mut writer = BufWriter::new(File::create(&path)?);
+            match self.format {
+                ExportFormat::Csv => write_csv(self.chunk.table(*table), &mut writer)?,
+                ExportFormat::Columnar => {
+                    writer.write_all(&serialize_columnar(self.chunk.table(*table)))?
+                }
+            }
+            writer.flush()?;
+            self.written.push(path);
+        }
+        self.chunk.clear();
+        Ok(())
+    }
+
+    //writes the held blocks and the last chunk and returns all files written
+    pub fn finish(mut self) -> io::Result<Vec<PathBuf>> {
+        while let Some((height, block)) = self.held.pop_front() {
+            self.add(height, &block)?;
+        }
+        self.flush()?;
+        Ok(self.written)
+    }
+}
+
+//exports the blocks of the exporter's network in a blk file as they are connected by chain
+//blocks leaving the chain in a reorg are taken out before they are written
+//returns the number of blocks exported, the ones taken out again included
+pub fn export_blk_file(
+    data: &[u8],
+    chain: &mut ChainOrder,
+    exporter: &mut Exporter,
+) -> io::Result<usize> {
+    let mut exported = 0;
+    for record in BlkRecords::new(data) {
+        if record.chain != exporter.network() {
+            continue;
+        }
+        let block = match parse_block(record.data) {
+            Ok((_, block)) => block,
+            Err(_) => continue,
+        };
+        for event in chain.push(block) {
+            match event {
+                ChainEvent::Connected(height, block) => {
+                    exporter.push(height, *block)?;
+                    exported += 1;
+                }
+                ChainEvent::Disconnected(height, _) => exporter.disconnect(height)?,
+            }
+        }
+    }
+    Ok(exported)
+}
+
+//exports the blk files of a blocks directory from the genesis block on
+pub fn export_blocks_dir<P: AsRef<Path>>(
+    blocks_dir: P,
+    exporter: &mut Exporter,
+) -> io::Result<usize> {
+    let mut chain = ChainOrder::new();
+    let mut exported = 0;
+    for (_, path) in blk_files(blocks_dir)? {
+        exported += export_blk_file(&fs::read(path)?, &mut chain, exporter)?;
+    }
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        export::{Table, TableData, Value},
+        parsers::parse_columnar,
+        types::Hash256,
+    };
+
+    //a chain of blocks made from the genesis block, linked by their headers only
+    fn chain(n: usize) -> Vec<Vec<u8>> {
+        let genesis = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let mut blocks = vec![genesis.to_vec()];
+        for _ in 1..n {
+            let (_, parent) = parse_block(blocks.last().unwrap()).unwrap();
+            let mut block = genesis.to_vec();
+            block[4..36].copy_from_slice(&parent.header.hash.to_wire_bytes());
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn record(block: &[u8]) -> Vec<u8> {
+        [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(block.len() as u32).to_le_bytes(),
+            block,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_export_blocks_dir() {
+        let dir = std::env::temp_dir().join(format!("parse_bitcoin_export_{}", std::process::id()));
+        let blocks_dir = dir.join("blocks");
+        fs::create_dir_all(&blocks_dir).unwrap();
+        let blocks = chain(5);
+        //blocks 3 and 4 come before their parent
+        let blk0 = [record(&blocks[0]), record(&blocks[1]), record(&blocks[3])].concat();
+        let blk1 = [record(&blocks[4]), vec![0; 10], record(&blocks[2])].concat();
+        fs::write(blocks_dir.join("blk00000.dat"), blk0).unwrap();
+        fs::write(blocks_dir.join("blk00001.dat"), blk1).unwrap();
+
+        let out = dir.join("csv");
+        let mut exporter = Exporter::new(&out, ExportFormat::Csv, "mainnet", 2);
+        assert_eq!(export_blocks_dir(&blocks_dir, &mut exporter).unwrap(), 5);
+        let written = exporter.finish().unwrap();
+        //chunks of heights 0-1, 2-3 and 4
+        assert_eq!(written.len(), 15);
+        assert_eq!(written[0], out.join("blocks_00000000.csv"));
+        assert_eq!(written[14], out.join("witnesses_00000004.csv"));
+        let csv = fs::read_to_string(out.join("blocks_00000002.csv")).unwrap();
+        let heights: Vec<_> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap())
+            .collect();
+        assert_eq!(heights, vec!["2", "3"]);
+
+        let out = dir.join("col");
+        let mut exporter = Exporter::new(&out, ExportFormat::Columnar, "mainnet", 10);
+        assert_eq!(export_blocks_dir(&blocks_dir, &mut exporter).unwrap(), 5);
+        let written = exporter.finish().unwrap();
+        assert_eq!(written.len(), 5);
+        let data = fs::read(out.join("blocks_00000000.col")).unwrap();
+        let (_, blocks_table): (_, TableData) = parse_columnar(&data).unwrap();
+        assert_eq!(blocks_table.table, Table::Blocks);
+        assert_eq!(blocks_table.rows(), 5);
+        let hash = |row: usize| match &blocks_table.row(row).unwrap()[1] {
+            Value::Str(hash) => hash.parse::<Hash256>().unwrap(),
+            _ => unreachable!(),
+        };
+        for (height, block) in blocks.iter().enumerate() {
+            let (_, block) = parse_block(block).unwrap();
+            assert_eq!(hash(height), block.header.hash.0);
+        }
+
+        //other networks are left out
+        let mut exporter = Exporter::new(dir.join("regtest"), ExportFormat::Csv, "regtest", 10);
+        assert_eq!(export_blocks_dir(&blocks_dir, &mut exporter).unwrap(), 0);
+        assert!(exporter.finish().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_reorg() {
+        let dir = std::env::temp_dir().join(format!("parse_bitcoin_reorg_{}", std::process::id()));
+        let blocks = chain(3);
+        //a sibling of block 1 comes first and is connected until block 2 arrives
+        let mut stale = blocks[1].clone();
+        stale[68] ^= 1;
+        let data = [&blocks[0], &stale, &blocks[1], &blocks[2]]
+            .iter()
+            .map(|block| record(block))
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut exporter = Exporter::new(&dir, ExportFormat::Csv, "mainnet", 10);
+        let mut chain = ChainOrder::new();
+        assert_eq!(
+            export_blk_file(&data, &mut chain, &mut exporter).unwrap(),
+            4
+        );
+        exporter.finish().unwrap();
+        let csv = fs::read_to_string(dir.join("blocks_00000000.csv")).unwrap();
+        let hashes: Vec<_> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(1).unwrap().to_string())
+            .collect();
+        let hash = |block: &[u8]| parse_block(block).unwrap().1.header.hash.to_string();
+        assert_eq!(hashes, blocks.iter().map(|b| hash(b)).collect::<Vec<_>>());
+
+        //a block per chunk, the stale block is taken out before it is written
+        fs::remove_dir_all(&dir).unwrap();
+        let mut exporter = Exporter::new(&dir, ExportFormat::Csv, "mainnet", 1);
+        let mut chain = ChainOrder::new();
+        assert_eq!(
+            export_blk_file(&data, &mut chain, &mut exporter).unwrap(),
+            4
+        );
+        assert_eq!(exporter.finish().unwrap().len(), 3 * TABLES.len());
+        for (height, block) in blocks.iter().enumerate() {
+            let csv = fs::read_to_string(dir.join(format!("blocks_{:08}.csv", height))).unwrap();
+            let hashes: Vec<_> = csv
+                .lines()
+                .skip(1)
+                .map(|line| line.split(',').nth(1).unwrap().to_string())
+                .collect();
+            assert_eq!(hashes, vec![hash(block)]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        //a block is written once MAX_REORG_DEPTH blocks follow it, it can not leave the chain then
+        let mut exporter = Exporter::new(&dir, ExportFormat::Csv, "mainnet", 1);
+        for height in 0..=MAX_REORG_DEPTH {
+            let (_, block) = parse_block(&blocks[0]).unwrap();
+            exporter.push(height, block).unwrap();
+        }
+        assert_eq!(exporter.written().len(), TABLES.len());
+        assert_eq!(
+            exporter.disconnect(0).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        exporter.disconnect(1).unwrap();
+        assert_eq!(exporter.finish().unwrap().len(), TABLES.len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
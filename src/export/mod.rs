@@ -0,0 +1,6 @@
+mod table;
+pub use self::table::{Chunk, Column, ColumnType, Table, TableData, Value, TABLES};
+mod csv;
+pub use self::csv::write_csv;
+mod exporter;
+pub use self::exporter::{export_blk_file, export_blocks_dir, ExportFormat, Exporter};
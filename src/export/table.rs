@@ -0,0 +1,418 @@
+use crate::{
+    script::script_type,
+    serializers::serialize_block,
+    types::{Block, Witness},
+    utils::script_to_address,
+};
+
+//the flat tables a block is exported to
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub enum Table {
+    Blocks,
+    Txs,
+    Inputs,
+    Outputs,
+    Witnesses,
+}
+
+pub const TABLES: [Table; 5] = [
+    Table::Blocks,
+    Table::Txs,
+    Table::Inputs,
+    Table::Outputs,
+    Table::Witnesses,
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColumnType {
+    U64,
+    Str,
+}
+
+use self::ColumnType::{Str, U64};
+
+//hashes are in the order bitcoind prints them, scripts and witness items in hex
+//address is empty for scripts without one
+const BLOCKS: [(&str, ColumnType); 12] = [
+    ("height", U64),
+    ("hash", Str),
+    ("prev_hash", Str),
+    ("merkle_root", Str),
+    ("time", U64),
+    ("version", U64),
+    ("bits", U64),
+    ("nonce", U64),
+    ("tx_count", U64),
+    ("size", U64),
+    ("stripped_size", U64),
+    ("weight", U64),
+];
+const TXS: [(&str, ColumnType); 13] = [
+    ("height", U64),
+    ("txid", Str),
+    ("wtxid", Str),
+    ("index", U64),
+    ("version", U64),
+    ("locktime", U64),
+    ("input_count", U64),
+    ("output_count", U64),
+    ("output_value", U64),
+    ("size", U64),
+    ("vsize", U64),
+    ("weight", U64),
+    ("coinbase", U64),
+];
+const INPUTS: [(&str, ColumnType); 8] = [
+    ("height", U64),
+    ("txid", Str),
+    ("index", U64),
+    ("prev_txid", Str),
+    ("prev_vout", U64),
+    ("script_sig", Str),
+    ("sequence", U64),
+    ("witness_items", U64),
+];
+const OUTPUTS: [(&str, ColumnType); 7] = [
+    ("height", U64),
+    ("txid", Str),
+    ("vout", U64),
+    ("value", U64),
+    ("script_type", Str),
+    ("address", Str),
+    ("script_pub_key", Str),
+];
+const WITNESSES: [(&str, ColumnType); 5] = [
+    ("height", U64),
+    ("txid", Str),
+    ("input", U64),
+    ("index", U64),
+    ("item", Str),
+];
+
+impl Table {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Blocks => "blocks",
+            Table::Txs => "txs",
+            Table::Inputs => "inputs",
+            Table::Outputs => "outputs",
+            Table::Witnesses => "witnesses",
+        }
+    }
+
+    pub fn columns(&self) -> &'static [(&'static str, ColumnType)] {
+        match self {
+            Table::Blocks => &BLOCKS,
+            Table::Txs => &TXS,
+            Table::Inputs => &INPUTS,
+            Table::Outputs => &OUTPUTS,
+            Table::Witnesses => &WITNESSES,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    U64(u64),
+    Str(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Column {
+    U64(Vec<u64>),
+    Str(Vec<String>),
+}
+
+impl Column {
+    pub fn new(column_type: ColumnType) -> Column {
+        match column_type {
+            ColumnType::U64 => Column::U64(Vec::new()),
+            ColumnType::Str => Column::Str(Vec::new()),
+        }
+    }
+
+    pub fn column_type(&self) -> ColumnType {
+        match self {
+            Column::U64(_) => ColumnType::U64,
+            Column::Str(_) => ColumnType::Str,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::U64(values) => values.len(),
+            Column::Str(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, row: usize) -> Option<Value> {
+        match self {
+            Column::U64(values) => values.get(row).map(|v| Value::U64(*v)),
+            Column::Str(values) => values.get(row).map(|v| Value::Str(v.clone())),
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        match self {
+            Column::U64(values) => values.truncate(len),
+            Column::Str(values) => values.truncate(len),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match (self, value) {
+            (Column::U64(values), Value::U64(v)) => values.push(v),
+            (Column::Str(values), Value::Str(v)) => values.push(v),
+            (column, value) => panic!("{:?} pushed to a {:?} column", value, column.column_type()),
+        }
+    }
+}
+
+//the rows of a table, stored by column
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableData {
+    pub table: Table,
+    pub columns: Vec<Column>,
+}
+
+impl TableData {
+    pub fn new(table: Table) -> TableData {
+        TableData {
+            table,
+            columns: table
+                .columns()
+                .iter()
+                .map(|(_, t)| Column::new(*t))
+                .collect(),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.columns[0].len()
+    }
+
+    pub fn row(&self, row: usize) -> Option<Vec<Value>> {
+        self.columns.iter().map(|column| column.get(row)).collect()
+    }
+
+    fn push(&mut self, row: Vec<Value>) {
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+
+    //every table starts with the height, rows are in chain order
+    fn truncate_at(&mut self, height: u32) {
+        let rows = match &self.columns[0] {
+            Column::U64(heights) => heights.iter().filter(|h| **h < u64::from(height)).count(),
+            Column::Str(_) => unreachable!(),
+        };
+        for column in self.columns.iter_mut() {
+            column.truncate(rows);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for column in self.columns.iter_mut() {
+            *column = Column::new(column.column_type());
+        }
+    }
+}
+
+//the rows of consecutive blocks in all tables
+#[derive(Debug, PartialEq, Clone)]
+pub struct Chunk {
+    pub first_height: Option<u32>,
+    pub blocks: usize,
+    pub tables: Vec<TableData>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk {
+            first_height: None,
+            blocks: 0,
+            tables: TABLES.iter().map(|table| TableData::new(*table)).collect(),
+        }
+    }
+
+    pub fn table(&self, table: Table) -> &TableData {
+        &self.tables[table as usize]
+    }
+
+    pub fn clear(&mut self) {
+        self.first_height = None;
+        self.blocks = 0;
+        self.tables.iter_mut().for_each(TableData::clear);
+    }
+
+    //drops the rows of the blocks from height on, when they leave the chain in a reorg
+    pub fn truncate(&mut self, height: u32) {
+        self.tables
+            .iter_mut()
+            .for_each(|table| table.truncate_at(height));
+        self.blocks = self.table(Table::Blocks).rows();
+        if self.blocks == 0 {
+            self.first_height = None;
+        }
+    }
+
+    //network is the chain name addresses are encoded for
+    pub fn push_block(&mut self, height: u32, block: &Block, network: &str) {
+        self.first_height.get_or_insert(height);
+        self.blocks += 1;
+        let height = Value::U64(u64::from(height));
+        let int = |n: usize| Value::U64(n as u64);
+        let header = &block.header;
+        let size = serialize_block(block).len();
+        let witness_size: usize = block
+            .transactions
+            .iter()
+            .map(|tx| tx.size - tx.stripped_size())
+            .sum();
+        let stripped_size = size - witness_size;
+        let mut nonce = [0; 4];
+        nonce.copy_from_slice(&header.nonce.0[..4]);
+        self.tables[Table::Blocks as usize].push(vec![
+            height.clone(),
+            Value::Str(header.hash.to_string()),
+            Value::Str(header.prev_block_hash.to_string()),
+            Value::Str(header.merkle_root_hash.to_string()),
+            Value::U64(u64::from(header.time)),
+            Value::U64(u64::from(header.version)),
+            Value::U64(u64::from(header.compact_target())),
+            Value::U64(u64::from(u32::from_le_bytes(nonce))),
+            int(block.transactions.len()),
+            int(size),
+            int(stripped_size),
+            int(stripped_size * 3 + size),
+        ]);
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let txid = Value::Str(tx.txid.to_string());
+            self.tables[Table::Txs as usize].push(vec![
+                height.clone(),
+                txid.clone(),
+                Value::Str(tx.wtxid.to_string()),
+                int(index),
+                Value::U64(u64::from(tx.version)),
+                Value::U64(u64::from(tx.lock_time)),
+                int(tx.inputs.len()),
+                int(tx.outputs.len()),
+                Value::U64(tx.output_value()),
+                int(tx.size),
+                int(tx.vsize()),
+                int(tx.weight()),
+                Value::U64(tx.is_coinbase() as u64),
+            ]);
+            for (i, input) in tx.inputs.iter().enumerate() {
+                //an empty stack is parsed as a single missing item, missing items within a stack are empty
+                let witness = match tx.witnesses.as_ref().and_then(|w| w.get(i)) {
+                    Some(stack) if stack[..] != [Witness(None)] => &stack[..],
+                    _ => &[],
+                };
+                self.tables[Table::Inputs as usize].push(vec![
+                    height.clone(),
+                    txid.clone(),
+                    int(i),
+                    Value::Str(input.previous_tx_hash.to_string()),
+                    Value::U64(u64::from(input.vout)),
+                    Value::Str(hex::encode(&input.script_sig.0)),
+                    Value::U64(u64::from(input.sequence)),
+                    int(witness.len()),
+                ]);
+                for (j, item) in witness.iter().enumerate() {
+                    let item = item.0.as_ref().map(|item| hex::encode(&item.0));
+                    self.tables[Table::Witnesses as usize].push(vec![
+                        height.clone(),
+                        txid.clone(),
+                        int(i),
+                        int(j),
+                        Value::Str(item.unwrap_or_default()),
+                    ]);
+                }
+            }
+            for (vout, output) in tx.outputs.iter().enumerate() {
+                let script = &output.script_pub_key.0;
+                self.tables[Table::Outputs as usize].push(vec![
+                    height.clone(),
+                    txid.clone(),
+                    int(vout),
+                    Value::U64(output.value),
+                    Value::Str(script_type(script).to_string()),
+                    Value::Str(script_to_address(script, network).unwrap_or_default()),
+                    Value::Str(hex::encode(script)),
+                ]);
+            }
+        }
+    }
+}
+
+impl std::default::Default for Chunk {
+    fn default() -> Chunk {
+        Chunk::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+
+    #[test]
+    fn test_push_block() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let mut chunk = Chunk::new();
+        chunk.push_block(609015, &block, "mainnet");
+        assert_eq!(chunk.first_height, Some(609015));
+        let blocks = chunk.table(Table::Blocks);
+        assert_eq!(blocks.rows(), 1);
+        let row = blocks.row(0).unwrap();
+        assert_eq!(
+            row[1],
+            Value::Str(
+                "0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d".to_string()
+            )
+        );
+        //size, strippedsize and weight of getblock
+        assert_eq!(
+            row[9..],
+            [Value::U64(165526), Value::U64(121296), Value::U64(529414)]
+        );
+
+        let txs = chunk.table(Table::Txs);
+        assert_eq!(txs.rows(), block.transactions.len());
+        assert_eq!(txs.row(0).unwrap()[12], Value::U64(1));
+        let inputs: usize = block.transactions.iter().map(|tx| tx.inputs.len()).sum();
+        let outputs: usize = block.transactions.iter().map(|tx| tx.outputs.len()).sum();
+        assert_eq!(chunk.table(Table::Inputs).rows(), inputs);
+        assert_eq!(chunk.table(Table::Outputs).rows(), outputs);
+        //the witness items are counted on their inputs
+        let witness_items = match &chunk.table(Table::Inputs).columns[7] {
+            Column::U64(counts) => counts.iter().sum::<u64>() as usize,
+            _ => unreachable!(),
+        };
+        assert_eq!(chunk.table(Table::Witnesses).rows(), witness_items);
+        assert!(witness_items > 0);
+        for table in chunk.tables.iter() {
+            assert_eq!(table.columns.len(), table.table.columns().len());
+            assert!(table.columns.iter().all(|c| c.len() == table.rows()));
+        }
+
+        let cleared = chunk.clone();
+        chunk.push_block(609016, &block, "mainnet");
+        chunk.truncate(609016);
+        assert_eq!(chunk, cleared);
+        chunk.truncate(609015);
+        assert_eq!(chunk, Chunk::new());
+        chunk.push_block(609015, &block, "mainnet");
+        chunk.clear();
+        assert_eq!(chunk, Chunk::new());
+    }
+}
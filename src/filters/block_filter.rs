@@ -0,0 +1,188 @@
+use crate::{
+    filters::GcsFilter,
+    script::opcodes::OP_RETURN,
+    types::{Block, Hash256},
+    utils::hash256,
+};
+use std::collections::BTreeSet;
+
+//the basic filter of BIP158, the only type defined so far
+pub const BASIC_FILTER_TYPE: u8 = 0;
+pub const BASIC_FILTER_P: u8 = 19;
+pub const BASIC_FILTER_M: u64 = 784931;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockFilter {
+    pub block_hash: Hash256,
+    //the encoded set, as served in cfilter messages
+    pub filter: Vec<u8>,
+}
+
+impl BlockFilter {
+    pub fn new(block_hash: Hash256, filter: Vec<u8>) -> BlockFilter {
+        BlockFilter { block_hash, filter }
+    }
+
+    //the scripts of the outputs created by the block and of the outputs spent by it
+    //prevout_scripts are the spent scripts, for example from the block's undo data, coinbases spend none
+    //OP_RETURN outputs and empty scripts are left out
+    pub fn basic(block: &Block, prevout_scripts: &[&[u8]]) -> BlockFilter {
+        let outputs = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .map(|output| &output.script_pub_key.0[..])
+            .filter(|script| script.first().is_some_and(|op| *op != OP_RETURN));
+        let prevouts = prevout_scripts.iter().copied().filter(|s| !s.is_empty());
+        let items: BTreeSet<&[u8]> = outputs.chain(prevouts).collect();
+        let items: Vec<&[u8]> = items.into_iter().collect();
+        let filter = BlockFilter::gcs(&block.header.hash.0).build(&items);
+        BlockFilter::new(block.header.hash.0, filter)
+    }
+
+    fn gcs(block_hash: &Hash256) -> GcsFilter {
+        let mut key = [0; 16];
+        key.copy_from_slice(&block_hash.0[..16]);
+        GcsFilter::new(&key, BASIC_FILTER_P, BASIC_FILTER_M)
+    }
+
+    pub fn filter_hash(&self) -> Hash256 {
+        hash256(&self.filter)
+    }
+
+    //the filter header commits to the filter and all filters before it
+    pub fn header(&self, prev_header: &Hash256) -> Hash256 {
+        hash256(&[self.filter_hash().0, prev_header.0].concat())
+    }
+
+    //none if the filter is malformed
+    pub fn match_any(&self, scripts: &[&[u8]]) -> Option<bool> {
+        BlockFilter::gcs(&self.block_hash).match_any(&self.filter, scripts)
+    }
+
+    pub fn match_script(&self, script: &[u8]) -> Option<bool> {
+        self.match_any(&[script])
+    }
+}
+
+//the headers of consecutive filters, the genesis block's filter follows the all zero header
+pub fn filter_header_chain<'a, I>(prev_header: Hash256, filters: I) -> Vec<Hash256>
+where
+    I: IntoIterator<Item = &'a BlockFilter>,
+{
+    let mut header = prev_header;
+    filters
+        .into_iter()
+        .map(|filter| {
+            header = filter.header(&header);
+            header
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+    use hex;
+
+    //hashes as shown by bitcoind, the reverse of the wire order
+    fn hash(s: &str) -> Hash256 {
+        let mut bytes = hex::decode(s).unwrap();
+        bytes.reverse();
+        Hash256::new(&bytes)
+    }
+
+    #[test]
+    fn test_genesis_filters() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let filter = BlockFilter::basic(&block, &[]);
+        assert_eq!(hex::encode(&filter.filter), "017fa880");
+        assert_eq!(
+            filter.header(&Hash256::default()),
+            hash("02c2392180d0ce2b5b6f8b08d39a11ffe831c673311a3ecf77b97fc3f0303c9f")
+        );
+
+        //the testnet genesis block, the first entry of BIP158's blockfilters.json
+        let mut data = data.to_vec();
+        data[68..72].copy_from_slice(&1296688602u32.to_le_bytes());
+        data[76..80].copy_from_slice(&414098458u32.to_le_bytes());
+        let (_, block) = parse_block(&data).unwrap();
+        assert_eq!(
+            block.header.hash.0,
+            hash("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+        );
+        let filter = BlockFilter::basic(&block, &[]);
+        assert_eq!(hex::encode(&filter.filter), "019dfca8");
+        assert_eq!(
+            filter_header_chain(Hash256::default(), std::slice::from_ref(&filter)),
+            vec![hash(
+                "21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"
+            )]
+        );
+        let script = &block.transactions[0].outputs[0].script_pub_key.0;
+        assert_eq!(filter.match_script(script), Some(true));
+        assert_eq!(filter.match_script(&[0x51]), Some(false));
+    }
+
+    #[test]
+    fn test_blockfilters_json() {
+        let tests: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../test_data/blockfilters.json")).unwrap();
+        //[height, block hash, block, spent scripts, previous header, filter, header, notes]
+        let tests: Vec<_> = tests
+            .iter()
+            .map(|test| test.as_array().unwrap())
+            .filter(|test| test.len() == 8)
+            .collect();
+        assert!(!tests.is_empty());
+        for test in tests {
+            let field = |n: usize| test[n].as_str().unwrap();
+            let data = hex::decode(field(2)).unwrap();
+            let (_, block) = parse_block(&data).unwrap();
+            assert_eq!(block.header.hash.0, hash(field(1)));
+            let prevout_scripts: Vec<_> = test[3]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|script| hex::decode(script.as_str().unwrap()).unwrap())
+                .collect();
+            let prevout_scripts: Vec<_> = prevout_scripts.iter().map(|s| &s[..]).collect();
+            let filter = BlockFilter::basic(&block, &prevout_scripts);
+            assert_eq!(hex::encode(&filter.filter), field(5), "{}", test[0]);
+            let header = filter.header(&hash(field(4)));
+            assert_eq!(header, hash(field(6)), "{}", test[0]);
+        }
+    }
+
+    #[test]
+    fn test_basic_filter() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let spent = [&[0x51][..], &[0x52], &[]];
+        let filter = BlockFilter::basic(&block, &spent);
+        let scripts: BTreeSet<&[u8]> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .map(|output| &output.script_pub_key.0[..])
+            .collect();
+        let op_returns = scripts.iter().filter(|s| s[0] == OP_RETURN).count();
+        assert!(op_returns > 0);
+        let (_, n) = crate::parsers::parse_var_int(&filter.filter).unwrap();
+        assert_eq!(n as usize, scripts.len() - op_returns + 2);
+        for script in scripts.iter().filter(|s| s[0] != OP_RETURN) {
+            assert_eq!(filter.match_script(script), Some(true));
+        }
+        assert_eq!(filter.match_any(&[&[0x53], &[0x51]]), Some(true));
+        assert_eq!(filter.match_any(&[&[0x53]]), Some(false));
+        //the filter is keyed with the block hash
+        let other = BlockFilter::new(Hash256::default(), filter.filter.clone());
+        assert_eq!(other.match_any(&[&[0x51], &[0x52]]), Some(false));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{parsers::parse_var_int, serializers::serialize_var_int, utils::siphash_2_4};
+
+//writes bits most significant first
+struct BitWriter {
+    data: Vec<u8>,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            if self.bits % 8 == 0 {
+                self.data.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.data.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+            self.bits += 1;
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn read_bit(&mut self) -> Option<u64> {
+        let byte = self.data.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Some(u64::from(bit))
+    }
+    fn read(&mut self, count: u32) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Some(value)
+    }
+}
+
+//Golomb-Coded Set parameters, see BIP158
+//items are hashed with SipHash keyed by k0 and k1 into [0, N * m) and sorted,
+//the differences are Golomb-Rice coded with parameter p
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GcsFilter {
+    k0: u64,
+    k1: u64,
+    p: u8,
+    m: u64,
+}
+
+impl GcsFilter {
+    //the key is the first 16 bytes of the block hash for block filters
+    pub fn new(key: &[u8; 16], p: u8, m: u64) -> GcsFilter {
+        let mut k0 = [0; 8];
+        let mut k1 = [0; 8];
+        k0.copy_from_slice(&key[..8]);
+        k1.copy_from_slice(&key[8..]);
+        GcsFilter {
+            k0: u64::from_le_bytes(k0),
+            k1: u64::from_le_bytes(k1),
+            p,
+            m,
+        }
+    }
+
+    //maps the hash uniformly to [0, f) without a division
+    fn hash_to_range(&self, item: &[u8], f: u64) -> u64 {
+        ((u128::from(siphash_2_4(self.k0, self.k1, item)) * u128::from(f)) >> 64) as u64
+    }
+
+    fn hashed_set(&self, items: &[&[u8]], n: u64) -> Vec<u64> {
+        let f = n * self.m;
+        let mut hashes: Vec<u64> = items
+            .iter()
+            .map(|item| self.hash_to_range(item, f))
+            .collect();
+        hashes.sort_unstable();
+        hashes
+    }
+
+    //the number of items followed by the coded set, items have to be unique
+    pub fn build(&self, items: &[&[u8]]) -> Vec<u8> {
+        let n = items.len() as u64;
+        let mut writer = BitWriter {
+            data: serialize_var_int(n),
+            bits: 0,
+        };
+        let mut last = 0;
+        for hash in self.hashed_set(items, n) {
+            let delta = hash - last;
+            last = hash;
+            //the quotient in unary, then the remainder in p bits
+            for _ in 0..delta >> self.p {
+                writer.write(1, 1);
+            }
+            writer.write(0, 1);
+            writer.write(delta, u32::from(self.p));
+        }
+        writer.data
+    }
+
+    //the hashed items of a filter in ascending order, none for a malformed filter
+    pub fn decode(&self, filter: &[u8]) -> Option<Vec<u64>> {
+        let (data, n) = parse_var_int(filter).ok()?;
+        let mut reader = BitReader { data, position: 0 };
+        let mut hashes = Vec::new();
+        let mut last = 0u64;
+        for _ in 0..n {
+            let mut quotient = 0u64;
+            while reader.read_bit()? == 1 {
+                quotient += 1;
+            }
+            let remainder = reader.read(u32::from(self.p))?;
+            last = last.checked_add((quotient << self.p) | remainder)?;
+            hashes.push(last);
+        }
+        Some(hashes)
+    }
+
+    //whether any of the items is in the filter, false positives happen with probability 1 / m per item
+    pub fn match_any(&self, filter: &[u8], items: &[&[u8]]) -> Option<bool> {
+        let set = self.decode(filter)?;
+        let queries = self.hashed_set(items, set.len() as u64);
+        //both are sorted, walk them together
+        let (mut i, mut j) = (0, 0);
+        while i < set.len() && j < queries.len() {
+            match set[i].cmp(&queries[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => return Some(true),
+            }
+        }
+        Some(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_gcs_filter() {
+        let gcs = GcsFilter::new(&[7; 16], 19, 784931);
+        let items: Vec<Vec<u8>> = (0..100u32).map(|i| i.to_le_bytes().to_vec()).collect();
+        let items: Vec<&[u8]> = items.iter().map(|i| &i[..]).collect();
+        let filter = gcs.build(&items);
+        assert_eq!(filter[0], 100);
+        let decoded = gcs.decode(&filter).unwrap();
+        assert_eq!(decoded, gcs.hashed_set(&items, 100));
+        for item in items.iter() {
+            assert_eq!(gcs.match_any(&filter, &[item]), Some(true));
+        }
+        assert_eq!(gcs.match_any(&filter, &[b"not in the set"]), Some(false));
+        assert_eq!(
+            gcs.match_any(&filter, &[b"not in the set", items[50]]),
+            Some(true)
+        );
+        assert_eq!(gcs.match_any(&filter, &[]), Some(false));
+        //a filter with fewer bits than its item count needs
+        assert_eq!(
+            gcs.match_any(&filter[..filter.len() / 2], &[items[0]]),
+            None
+        );
+        //the empty set
+        assert_eq!(gcs.build(&[]), vec![0]);
+        assert_eq!(gcs.match_any(&[0], &[items[0]]), Some(false));
+    }
+}
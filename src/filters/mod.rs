@@ -0,0 +1,6 @@
+mod gcs;
+pub use self::gcs::GcsFilter;
+mod block_filter;
+pub use self::block_filter::{
+    filter_header_chain, BlockFilter, BASIC_FILTER_M, BASIC_FILTER_P, BASIC_FILTER_TYPE,
+};
@@ -0,0 +1,318 @@
+use crate::utils::siphash_2_4;
+use std::{
+    convert::TryInto,
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+//slot states
+const EMPTY: u8 = 0;
+const FULL: u8 = 1;
+const REMOVED: u8 = 2;
+
+//key size, value size, slots, len, used slots, mark, dirty
+const HEADER_SIZE: u64 = 4 + 4 + 8 + 8 + 8 + 8 + 1;
+const MIN_SLOTS: u64 = 1024;
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "corrupted disk map")
+}
+
+//path with suffix appended, index.dat and .scripts give index.dat.scripts
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+//a hash table of fixed size keys and values kept in a file, slots are read as keys are looked up
+//collisions go to the next free slot, removed entries leave their slot used until the table is rebuilt
+//the owner commits a mark telling how far its own records are in the table, a table changed
+//after its last commit is dirty and counts its entries again when it is opened
+pub(crate) struct DiskMap {
+    path: PathBuf,
+    file: File,
+    key_size: usize,
+    value_size: usize,
+    slots: u64,
+    len: u64,
+    //full and removed slots
+    used: u64,
+    mark: u64,
+    dirty: bool,
+}
+
+impl DiskMap {
+    //creates an empty table or opens the one in the file
+    pub fn open(path: &Path, key_size: usize, value_size: usize) -> io::Result<DiskMap> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut map = DiskMap {
+            path: path.to_path_buf(),
+            file,
+            key_size,
+            value_size,
+            slots: 0,
+            len: 0,
+            used: 0,
+            mark: 0,
+            dirty: false,
+        };
+        let file_len = map.file.metadata()?.len();
+        if file_len == 0 {
+            map.create(MIN_SLOTS)?;
+            return Ok(map);
+        }
+        let mut header = [0; HEADER_SIZE as usize];
+        map.file.read_exact(&mut header)?;
+        let u32_at = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+        if u32_at(0) as usize != key_size || u32_at(4) as usize != value_size {
+            return Err(invalid_data());
+        }
+        map.slots = u64_at(8);
+        map.len = u64_at(16);
+        map.used = u64_at(24);
+        map.mark = u64_at(32);
+        map.dirty = header[40] != 0;
+        if map.slots == 0 || file_len != HEADER_SIZE + map.slots * map.slot_size() {
+            return Err(invalid_data());
+        }
+        if map.dirty {
+            map.recount()?;
+        }
+        Ok(map)
+    }
+
+    fn slot_size(&self) -> u64 {
+        (1 + self.key_size + self.value_size) as u64
+    }
+
+    fn create(&mut self, slots: u64) -> io::Result<()> {
+        self.slots = slots;
+        self.len = 0;
+        self.used = 0;
+        self.file.set_len(0)?;
+        //the slots start out zeroed, that is empty
+        self.file.set_len(HEADER_SIZE + slots * self.slot_size())?;
+        self.write_header()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let header = [
+            &(self.key_size as u32).to_le_bytes()[..],
+            &(self.value_size as u32).to_le_bytes(),
+            &self.slots.to_le_bytes(),
+            &self.len.to_le_bytes(),
+            &self.used.to_le_bytes(),
+            &self.mark.to_le_bytes(),
+            &[self.dirty as u8],
+        ]
+        .concat();
+        self.write_at(0, &header)
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+        self.file.flush()
+    }
+
+    //counts the entries after changes that were not committed
+    fn recount(&mut self) -> io::Result<()> {
+        let mut reader = BufReader::new(&self.file);
+        reader.seek(SeekFrom::Start(HEADER_SIZE))?;
+        let mut slot = vec![0; self.slot_size() as usize];
+        let (mut len, mut used) = (0, 0);
+        for _ in 0..self.slots {
+            reader.read_exact(&mut slot)?;
+            match slot[0] {
+                EMPTY => (),
+                FULL => {
+                    len += 1;
+                    used += 1;
+                }
+                REMOVED => used += 1,
+                _ => return Err(invalid_data()),
+            }
+        }
+        self.len = len;
+        self.used = used;
+        self.write_header()
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    //the mark of the last commit
+    pub fn mark(&self) -> u64 {
+        self.mark
+    }
+
+    //writes the counts and the mark, the table is clean until it is changed again
+    pub fn commit(&mut self, mark: u64) -> io::Result<()> {
+        self.mark = mark;
+        self.dirty = false;
+        self.write_header()
+    }
+
+    fn touch(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            self.dirty = true;
+            self.write_header()?;
+        }
+        Ok(())
+    }
+
+    //reading goes through &File so lookups do not need exclusive access
+    fn read_slot(&self, slot: u64) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; self.slot_size() as usize];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(HEADER_SIZE + slot * self.slot_size()))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    //the slot holding key, or the first slot it can go in, with the data of the slot
+    //at most half of the slots are used, so an empty one ends the search
+    fn find(&self, key: &[u8]) -> io::Result<(u64, Vec<u8>)> {
+        let mut slot = siphash_2_4(0, 0, key) % self.slots;
+        let mut free = None;
+        loop {
+            let data = self.read_slot(slot)?;
+            match data[0] {
+                EMPTY => return Ok(free.unwrap_or((slot, data))),
+                FULL if &data[1..1 + self.key_size] == key => return Ok((slot, data)),
+                FULL => (),
+                REMOVED if free.is_none() => free = Some((slot, data)),
+                REMOVED => (),
+                _ => return Err(invalid_data()),
+            }
+            slot = (slot + 1) % self.slots;
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let (_, data) = self.find(key)?;
+        match data[0] {
+            FULL => Ok(Some(data[1 + self.key_size..].to_vec())),
+            _ => Ok(None),
+        }
+    }
+
+    //adds the entry or replaces the value of key
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        if (self.used + 1) * 2 > self.slots {
+            self.rebuild()?;
+        }
+        let (slot, data) = self.find(key)?;
+        self.touch()?;
+        let offset = HEADER_SIZE + slot * self.slot_size();
+        if data[0] == FULL {
+            return self.write_at(offset + 1 + self.key_size as u64, value);
+        }
+        self.write_at(offset, &[&[FULL][..], key, value].concat())?;
+        self.len += 1;
+        if data[0] == EMPTY {
+            self.used += 1;
+        }
+        Ok(())
+    }
+
+    //returns whether key was in the table
+    pub fn remove(&mut self, key: &[u8]) -> io::Result<bool> {
+        let (slot, data) = self.find(key)?;
+        if data[0] != FULL {
+            return Ok(false);
+        }
+        self.touch()?;
+        self.write_at(HEADER_SIZE + slot * self.slot_size(), &[REMOVED])?;
+        self.len -= 1;
+        Ok(true)
+    }
+
+    //copies the entries to a new file with at most a quarter of the slots full
+    fn rebuild(&mut self) -> io::Result<()> {
+        let mut slots = MIN_SLOTS;
+        while slots < (self.len + 1) * 4 {
+            slots *= 2;
+        }
+        let path = with_suffix(&self.path, ".rebuild");
+        //left over from a rebuild that was interrupted
+        let _ = std::fs::remove_file(&path);
+        let mut map = DiskMap::open(&path, self.key_size, self.value_size)?;
+        map.create(slots)?;
+        {
+            let mut reader = BufReader::new(&self.file);
+            reader.seek(SeekFrom::Start(HEADER_SIZE))?;
+            let mut slot = vec![0; self.slot_size() as usize];
+            for _ in 0..self.slots {
+                reader.read_exact(&mut slot)?;
+                if slot[0] == FULL {
+                    map.insert(&slot[1..1 + self.key_size], &slot[1 + self.key_size..])?;
+                }
+            }
+        }
+        map.mark = self.mark;
+        map.dirty = self.dirty;
+        map.write_header()?;
+        std::fs::rename(&path, &self.path)?;
+        map.path = self.path.clone();
+        *self = map;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disk_map() {
+        let path =
+            std::env::temp_dir().join(format!("parse_bitcoin_disk_map_{}", std::process::id()));
+        let key = |i: u32| [&i.to_le_bytes()[..], &[0; 28]].concat();
+        let mut map = DiskMap::open(&path, 32, 4).unwrap();
+        //enough entries to rebuild the table a few times
+        for i in 0..3000 {
+            map.insert(&key(i), &i.to_le_bytes()).unwrap();
+        }
+        assert!(map.slots >= 6000);
+        for i in (0..3000).step_by(2) {
+            assert!(map.remove(&key(i)).unwrap());
+        }
+        assert!(!map.remove(&key(0)).unwrap());
+        map.insert(&key(1), &[9; 4]).unwrap();
+        assert_eq!(map.len(), 1500);
+        assert_eq!(map.get(&key(0)).unwrap(), None);
+        assert_eq!(map.get(&key(1)).unwrap(), Some(vec![9; 4]));
+        assert_eq!(
+            map.get(&key(2999)).unwrap(),
+            Some(2999u32.to_le_bytes().to_vec())
+        );
+        map.commit(7).unwrap();
+
+        //changes after the last commit leave the table dirty, its entries are counted when it is opened
+        map.insert(&key(0), &[0; 4]).unwrap();
+        map.len = 0;
+        drop(map);
+        let map = DiskMap::open(&path, 32, 4).unwrap();
+        assert!(map.dirty);
+        assert_eq!(map.mark(), 7);
+        assert_eq!(map.len(), 1501);
+        assert_eq!(map.get(&key(0)).unwrap(), Some(vec![0; 4]));
+        assert_eq!(
+            DiskMap::open(&path, 32, 8).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+        drop(map);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+mod disk_map;
+mod script_index;
+pub use self::script_index::{script_hash, HistoryEntry, IndexError, ScriptIndex};
+mod tx_index;
+pub use self::tx_index::{TxIndex, TxLocation};
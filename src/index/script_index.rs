@@ -0,0 +1,817 @@
+use super::disk_map::{with_suffix, DiskMap};
+use crate::{
+    blk::{BlkRecords, ChainEvent, ChainOrder, MAX_REORG_DEPTH},
+    parsers::parse_block,
+    types::{Block, Hash256, OutPoint},
+    utils::{address_to_script, sha256, AddressError},
+    utxo::is_unspendable,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryInto,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+//record kinds of the index file, each record starts with its kind
+const FUNDING: u8 = 0;
+const SPENDING: u8 = 1;
+//written after the records of a block, records without a block record after them are discarded
+const BLOCK: u8 = 2;
+//the previous record of the first record of a script
+const NONE: u64 = u64::MAX;
+
+fn record_size(kind: u8) -> Option<usize> {
+    match kind {
+        //script hash, txid, vout, height, value, previous record of the script
+        FUNDING => Some(32 + 32 + 4 + 4 + 8 + 8),
+        //script hash, txid, input index, height, value, spent txid and vout, previous record
+        SPENDING => Some(32 + 32 + 4 + 4 + 8 + 32 + 4 + 8),
+        //height, block hash, block work, where the records of the block start
+        BLOCK => Some(4 + 32 + 16 + 8),
+        _ => None,
+    }
+}
+
+//txid and vout, the key of the unspent outputs table
+fn out_point_key(out_point: &OutPoint) -> Vec<u8> {
+    [&out_point.txid.0[..], &out_point.vout.to_le_bytes()].concat()
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "corrupted script index record")
+}
+
+//the key scripts are indexed by, the same as electrum servers use
+pub fn script_hash(script: &[u8]) -> Hash256 {
+    sha256(script)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HistoryEntry {
+    //an output paying to the script
+    Funding {
+        out_point: OutPoint,
+        height: u32,
+        value: u64,
+    },
+    //an input spending such an output
+    Spending {
+        txid: Hash256,
+        input_index: u32,
+        height: u32,
+        value: u64,
+        spent: OutPoint,
+    },
+}
+
+impl HistoryEntry {
+    pub fn txid(&self) -> Hash256 {
+        match self {
+            HistoryEntry::Funding { out_point, .. } => out_point.txid,
+            HistoryEntry::Spending { txid, .. } => *txid,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            HistoryEntry::Funding { height, .. } | HistoryEntry::Spending { height, .. } => *height,
+        }
+    }
+
+    fn serialize(&self, script_hash: &Hash256, prev: u64) -> Vec<u8> {
+        let mut data = match self {
+            HistoryEntry::Funding {
+                out_point,
+                height,
+                value,
+            } => [
+                &[FUNDING][..],
+                &script_hash.0,
+                &out_point.txid.0,
+                &out_point.vout.to_le_bytes(),
+                &height.to_le_bytes(),
+                &value.to_le_bytes(),
+            ]
+            .concat(),
+            HistoryEntry::Spending {
+                txid,
+                input_index,
+                height,
+                value,
+                spent,
+            } => [
+                &[SPENDING][..],
+                &script_hash.0,
+                &txid.0,
+                &input_index.to_le_bytes(),
+                &height.to_le_bytes(),
+                &value.to_le_bytes(),
+                &spent.txid.0,
+                &spent.vout.to_le_bytes(),
+            ]
+            .concat(),
+        };
+        data.extend(&prev.to_le_bytes());
+        data
+    }
+
+    //data is a funding or spending record without its kind and the previous record
+    fn parse(kind: u8, data: &[u8]) -> (Hash256, HistoryEntry) {
+        let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+        let value = u64::from_le_bytes(data[72..80].try_into().unwrap());
+        let entry = match kind {
+            FUNDING => HistoryEntry::Funding {
+                out_point: OutPoint::new(Hash256::new(&data[32..64]), u32_at(64)),
+                height: u32_at(68),
+                value,
+            },
+            _ => HistoryEntry::Spending {
+                txid: Hash256::new(&data[32..64]),
+                input_index: u32_at(64),
+                height: u32_at(68),
+                value,
+                spent: OutPoint::new(Hash256::new(&data[80..112]), u32_at(112)),
+            },
+        };
+        (Hash256::new(&data[..32]), entry)
+    }
+}
+
+#[derive(Debug)]
+pub enum IndexError {
+    Address(AddressError),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IndexError::Address(e) => write!(f, "invalid address: {}", e),
+            IndexError::Io(e) => write!(f, "index io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+impl From<AddressError> for IndexError {
+    fn from(e: AddressError) -> IndexError {
+        IndexError::Address(e)
+    }
+}
+
+impl From<io::Error> for IndexError {
+    fn from(e: io::Error) -> IndexError {
+        IndexError::Io(e)
+    }
+}
+
+//for every script hash the outputs paying to it and the inputs spending them
+//records are appended to a file, each pointing to the record before it for the same script
+//the last record of every script and the unspent outputs are kept in tables next to the file,
+//they are brought up to date with the blocks written after their last commit when opening
+pub struct ScriptIndex {
+    file: File,
+    end: u64,
+    //script hash to the position of its last record
+    scripts: DiskMap,
+    //txid and vout to the script hash and value of the output
+    unspent: DiskMap,
+    tip: Option<(u32, Hash256)>,
+    //the hashes and work of the last blocks and where their records start, the tip last
+    recent: VecDeque<(Hash256, u128, u64)>,
+    //blocks that were missing from the tables when opening
+    replayed: usize,
+}
+
+impl ScriptIndex {
+    //creates the file or continues with the blocks already in it
+    //the tables are index.scripts and index.unspent for an index file named index
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ScriptIndex> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut index = ScriptIndex {
+            file,
+            end: 0,
+            scripts: DiskMap::open(&with_suffix(path, ".scripts"), 32, 8)?,
+            unspent: DiskMap::open(&with_suffix(path, ".unspent"), 36, 32 + 8)?,
+            tip: None,
+            recent: VecDeque::new(),
+            replayed: 0,
+        };
+        index.load()?;
+        Ok(index)
+    }
+
+    fn load(&mut self) -> io::Result<()> {
+        self.end = self.scripts.mark().min(self.unspent.mark());
+        if self.end > self.file.metadata()?.len() {
+            return Err(invalid_data());
+        }
+        //the blocks in the tables, from their block records back to front
+        let mut end = self.end;
+        while end > 0 && self.recent.len() <= MAX_REORG_DEPTH as usize {
+            let size = 1 + record_size(BLOCK).unwrap() as u64;
+            let data = match end.checked_sub(size) {
+                Some(offset) => self.read_block_record(offset)?,
+                None => return Err(invalid_data()),
+            };
+            let height = u32::from_le_bytes(data[..4].try_into().unwrap());
+            let work = u128::from_le_bytes(data[36..52].try_into().unwrap());
+            let start = u64::from_le_bytes(data[52..].try_into().unwrap());
+            if start > end - size {
+                return Err(invalid_data());
+            }
+            if self.tip.is_none() {
+                self.tip = Some((height, Hash256::new(&data[4..36])));
+            }
+            self.recent
+                .push_front((Hash256::new(&data[4..36]), work, start));
+            end = start;
+        }
+
+        //blocks written to the file after the tables were committed
+        let mut reader = BufReader::new(self.file.try_clone()?);
+        reader.seek(SeekFrom::Start(self.end))?;
+        let mut position = self.end;
+        let mut block = Vec::new();
+        loop {
+            let mut kind = [0];
+            if reader.read(&mut kind)? == 0 {
+                break;
+            }
+            let size = record_size(kind[0]).ok_or_else(invalid_data)?;
+            let mut data = vec![0; size];
+            //a record cut short by an interrupted write
+            if reader.read_exact(&mut data).is_err() {
+                break;
+            }
+            match kind[0] {
+                BLOCK => {
+                    for (offset, script_hash, entry) in block.drain(..) {
+                        self.add(offset, script_hash, &entry)?;
+                    }
+                    let height = u32::from_le_bytes(data[..4].try_into().unwrap());
+                    let work = u128::from_le_bytes(data[36..52].try_into().unwrap());
+                    self.push_tip(height, Hash256::new(&data[4..36]), work);
+                    self.end = position + 1 + size as u64;
+                    self.replayed += 1;
+                }
+                kind => {
+                    let (script_hash, entry) = HistoryEntry::parse(kind, &data);
+                    block.push((position, script_hash, entry));
+                }
+            }
+            position += 1 + size as u64;
+        }
+        //drops the records of a block that was not written completely
+        self.file.set_len(self.end)?;
+        self.commit()
+    }
+
+    fn read_block_record(&self, offset: u64) -> io::Result<Vec<u8>> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; 1 + record_size(BLOCK).unwrap()];
+        file.read_exact(&mut data)?;
+        match data[0] {
+            BLOCK => Ok(data.split_off(1)),
+            _ => Err(invalid_data()),
+        }
+    }
+
+    //the tables hold the records up to the end of the file
+    fn commit(&mut self) -> io::Result<()> {
+        self.scripts.commit(self.end)?;
+        self.unspent.commit(self.end)
+    }
+
+    //adding a record again leaves the tables the same
+    fn add(&mut self, offset: u64, script_hash: Hash256, entry: &HistoryEntry) -> io::Result<()> {
+        self.scripts.insert(&script_hash.0, &offset.to_le_bytes())?;
+        match entry {
+            HistoryEntry::Funding {
+                out_point, value, ..
+            } => {
+                let funding = [&script_hash.0[..], &value.to_le_bytes()].concat();
+                self.unspent.insert(&out_point_key(out_point), &funding)
+            }
+            HistoryEntry::Spending { spent, .. } => {
+                self.unspent.remove(&out_point_key(spent)).map(|_| ())
+            }
+        }
+    }
+
+    //the script hash and value of an output that is not spent
+    fn funding(&self, out_point: &OutPoint) -> io::Result<Option<(Hash256, u64)>> {
+        Ok(self.unspent.get(&out_point_key(out_point))?.map(|funding| {
+            let value = u64::from_le_bytes(funding[32..].try_into().unwrap());
+            (Hash256::new(&funding[..32]), value)
+        }))
+    }
+
+    //the position of the last record of a script
+    fn last_record(&self, script_hash: &Hash256) -> io::Result<u64> {
+        Ok(match self.scripts.get(&script_hash.0)? {
+            Some(offset) => u64::from_le_bytes(offset[..].try_into().unwrap()),
+            None => NONE,
+        })
+    }
+
+    //the records of the new tip start at the current end
+    fn push_tip(&mut self, height: u32, hash: Hash256, work: u128) {
+        self.tip = Some((height, hash));
+        self.recent.push_back((hash, work, self.end));
+        if self.recent.len() > MAX_REORG_DEPTH as usize + 1 {
+            self.recent.pop_front();
+        }
+    }
+
+    //height and hash of the last indexed block
+    pub fn tip(&self) -> Option<(u32, Hash256)> {
+        self.tip
+    }
+
+    //the number of distinct scripts seen
+    pub fn len(&self) -> usize {
+        self.scripts.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.len() == 0
+    }
+
+    //continues where the index stopped, or at the genesis block for an empty index
+    //a reorg can go back as far as the last MAX_REORG_DEPTH blocks
+    pub fn chain_order(&self) -> ChainOrder {
+        match self.tip {
+            Some((height, _)) => {
+                let blocks = self
+                    .recent
+                    .iter()
+                    .map(|(hash, work, _)| (*hash, *work))
+                    .collect();
+                ChainOrder::from_chain(blocks, height + 1)
+            }
+            None => ChainOrder::new(),
+        }
+    }
+
+    //blocks have to be indexed in chain order, the first one can be at any height
+    //inputs spending outputs from before the first block can not be resolved and are left out
+    //returns the number of such inputs
+    pub fn index_block(&mut self, block: &Block, height: u32) -> io::Result<usize> {
+        if let Some((tip_height, tip_hash)) = self.tip {
+            if height != tip_height + 1 || block.header.prev_block_hash.0 != tip_hash {
+                return Err(invalid_input("block does not extend the indexed chain"));
+            }
+        }
+        let mut unresolved = 0;
+        let mut entries = Vec::new();
+        //outputs created earlier in the same block
+        let mut created = HashMap::new();
+        for tx in block.transactions.iter() {
+            if !tx.is_coinbase() {
+                for (input_index, input) in tx.inputs.iter().enumerate() {
+                    let spent = input.out_point();
+                    let funding = match created.remove(&spent) {
+                        Some(funding) => Some(funding),
+                        None => self.funding(&spent)?,
+                    };
+                    let (script_hash, value) = match funding {
+                        Some(funding) => funding,
+                        None => {
+                            unresolved += 1;
+                            continue;
+                        }
+                    };
+                    let entry = HistoryEntry::Spending {
+                        txid: tx.txid.0,
+                        input_index: input_index as u32,
+                        height,
+                        value,
+                        spent,
+                    };
+                    entries.push((script_hash, entry));
+                }
+            }
+            for (vout, output) in tx.outputs.iter().enumerate() {
+                if is_unspendable(output) {
+                    continue;
+                }
+                let script_hash = script_hash(&output.script_pub_key.0);
+                let out_point = OutPoint::new(tx.txid.0, vout as u32);
+                created.insert(out_point, (script_hash, output.value));
+                let entry = HistoryEntry::Funding {
+                    out_point,
+                    height,
+                    value: output.value,
+                };
+                entries.push((script_hash, entry));
+            }
+        }
+
+        let mut data = Vec::new();
+        let mut offsets = Vec::with_capacity(entries.len());
+        //the last record of the scripts of the block so far
+        let mut last = HashMap::new();
+        for (script_hash, entry) in entries.iter() {
+            let offset = self.end + data.len() as u64;
+            let prev = match last.insert(*script_hash, offset) {
+                Some(prev) => prev,
+                None => self.last_record(script_hash)?,
+            };
+            offsets.push(offset);
+            data.extend(entry.serialize(script_hash, prev));
+        }
+        data.push(BLOCK);
+        data.extend(&height.to_le_bytes());
+        data.extend(&block.header.hash.to_wire_bytes());
+        data.extend(&block.header.work().to_le_bytes());
+        data.extend(&self.end.to_le_bytes());
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&data)?;
+        self.file.flush()?;
+
+        //the block is in the file, if the tables are not committed it is added again when opening
+        for (offset, (script_hash, entry)) in offsets.into_iter().zip(entries) {
+            self.add(offset, script_hash, &entry)?;
+        }
+        self.push_tip(height, block.header.hash.0, block.header.work());
+        self.end += data.len() as u64;
+        self.commit()?;
+        Ok(unresolved)
+    }
+
+    //takes the tip out of the index when it leaves the chain in a reorg
+    //the block before it becomes the tip, as long as it is one of the last MAX_REORG_DEPTH blocks
+    pub fn disconnect_block(&mut self, hash: &Hash256) -> io::Result<()> {
+        let (height, start) = match (self.tip, self.recent.back()) {
+            (Some((height, tip)), Some((_, _, start))) if tip == *hash => (height, *start),
+            _ => return Err(invalid_input("block is not the tip of the index")),
+        };
+        //the first block of the index has no block before it
+        if self.recent.len() == 1 && start > 0 {
+            return Err(invalid_input(
+                "block is too deep in the index to disconnect",
+            ));
+        }
+        let mut records = Vec::new();
+        let mut offset = start;
+        while offset < self.end - 1 - record_size(BLOCK).unwrap() as u64 {
+            let (script_hash, entry, prev) = self.read(offset)?;
+            let kind = match entry {
+                HistoryEntry::Funding { .. } => FUNDING,
+                HistoryEntry::Spending { .. } => SPENDING,
+            };
+            offset += 1 + record_size(kind).unwrap() as u64;
+            records.push((script_hash, entry, prev));
+        }
+        //until the block is out of the file, opening adds it to the tables again
+        self.scripts.commit(start)?;
+        self.unspent.commit(start)?;
+        //the records are undone last to first, an output spent in the same block is funded again first
+        for (script_hash, entry, prev) in records.into_iter().rev() {
+            match prev {
+                NONE => self.scripts.remove(&script_hash.0).map(|_| ())?,
+                prev => self.scripts.insert(&script_hash.0, &prev.to_le_bytes())?,
+            }
+            match entry {
+                HistoryEntry::Funding { out_point, .. } => {
+                    self.unspent.remove(&out_point_key(&out_point))?;
+                }
+                HistoryEntry::Spending { spent, value, .. } => {
+                    let funding = [&script_hash.0[..], &value.to_le_bytes()].concat();
+                    self.unspent.insert(&out_point_key(&spent), &funding)?;
+                }
+            }
+        }
+        self.file.set_len(start)?;
+        self.end = start;
+        self.commit()?;
+        self.recent.pop_back();
+        self.tip = self.recent.back().map(|(hash, _, _)| (height - 1, *hash));
+        Ok(())
+    }
+
+    //indexes the blocks connected by a chain order and takes out the ones it disconnected
+    //returns the number of blocks indexed
+    pub fn index_events(&mut self, events: Vec<ChainEvent>) -> io::Result<usize> {
+        let mut indexed = 0;
+        for event in events {
+            match event {
+                ChainEvent::Connected(height, block) => {
+                    self.index_block(&block, height)?;
+                    indexed += 1;
+                }
+                ChainEvent::Disconnected(_, hash) => self.disconnect_block(&hash)?,
+            }
+        }
+        Ok(indexed)
+    }
+
+    //indexes the blocks of a blk file that extend the index, blocks that do not parse are skipped
+    //blocks whose parent is not indexed yet stay in order until it is, so files can be read one by one
+    pub fn index_blk_file(&mut self, data: &[u8], order: &mut ChainOrder) -> io::Result<usize> {
+        let mut indexed = 0;
+        for record in BlkRecords::new(data) {
+            let block = match parse_block(record.data) {
+                Ok((_, block)) => block,
+                Err(_) => continue,
+            };
+            indexed += self.index_events(order.push(block))?;
+        }
+        Ok(indexed)
+    }
+
+    //the script hash, the entry and the previous record of the script
+    fn read(&self, offset: u64) -> io::Result<(Hash256, HistoryEntry, u64)> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut kind = [0];
+        file.read_exact(&mut kind)?;
+        let size = match kind[0] {
+            FUNDING | SPENDING => record_size(kind[0]).unwrap(),
+            _ => return Err(invalid_data()),
+        };
+        let mut data = vec![0; size];
+        file.read_exact(&mut data)?;
+        let prev = u64::from_le_bytes(data[size - 8..].try_into().unwrap());
+        let (script_hash, entry) = HistoryEntry::parse(kind[0], &data);
+        Ok((script_hash, entry, prev))
+    }
+
+    //funding and spending of outputs paying to script, in chain order
+    pub fn history(&self, script: &[u8]) -> io::Result<Vec<HistoryEntry>> {
+        let mut history = Vec::new();
+        let mut offset = self.last_record(&script_hash(script))?;
+        while offset != NONE {
+            let (_, entry, prev) = self.read(offset)?;
+            history.push(entry);
+            offset = prev;
+        }
+        history.reverse();
+        Ok(history)
+    }
+
+    //the value of the outputs paying to script that are not spent
+    pub fn balance(&self, script: &[u8]) -> io::Result<u64> {
+        let mut balance = 0;
+        for entry in self.history(script)? {
+            match entry {
+                HistoryEntry::Funding { value, .. } => balance += value,
+                HistoryEntry::Spending { value, .. } => balance -= value,
+            }
+        }
+        Ok(balance)
+    }
+
+    pub fn address_history(&self, address: &str) -> Result<Vec<HistoryEntry>, IndexError> {
+        Ok(self.history(&address_to_script(address)?)?)
+    }
+
+    pub fn address_balance(&self, address: &str) -> Result<u64, IndexError> {
+        Ok(self.balance(&address_to_script(address)?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        types::{BlockHash, BlockHeader, Bytes, TransactionBuilder, TxInputBuilder, TxOutput},
+        utils::script_to_address,
+    };
+
+    fn block() -> (Block, u32) {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let height = block.transactions[0].coinbase().unwrap().height().unwrap() as u32;
+        (block, height)
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("parse_bitcoin_{}_{}", name, std::process::id()))
+    }
+
+    fn remove_files(path: &Path) {
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(with_suffix(path, ".scripts")).unwrap();
+        std::fs::remove_file(with_suffix(path, ".unspent")).unwrap();
+    }
+
+    #[test]
+    fn test_script_index() {
+        let (block, height) = block();
+        let path = temp_path("script_index");
+        let mut index = ScriptIndex::open(&path).unwrap();
+        assert_eq!(index.tip(), None);
+        let inputs = block.transactions[1..]
+            .iter()
+            .map(|tx| tx.inputs.len())
+            .sum::<usize>();
+        let unresolved = index.index_block(&block, height).unwrap();
+        assert_eq!(index.tip(), Some((height, block.header.hash.0)));
+
+        //an output spent in the same block
+        let txids: HashMap<_, _> = block
+            .transactions
+            .iter()
+            .map(|tx| (tx.txid.0, tx))
+            .collect();
+        let (spending_tx, input_index, spent) = block.transactions[1..]
+            .iter()
+            .flat_map(|tx| {
+                tx.inputs
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, input)| (tx, i, input))
+            })
+            .find(|(_, _, input)| txids.contains_key(&input.previous_tx_hash))
+            .map(|(tx, i, input)| (tx, i, input.out_point()))
+            .unwrap();
+        let spent_in_block = block.transactions[1..]
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .filter(|input| txids.contains_key(&input.previous_tx_hash))
+            .count();
+        assert_eq!(unresolved, inputs - spent_in_block);
+        let output = &txids[&spent.txid].outputs[spent.vout as usize];
+        let script = &output.script_pub_key.0;
+        let history = index.history(script).unwrap();
+        assert!(history.contains(&HistoryEntry::Funding {
+            out_point: spent,
+            height,
+            value: output.value,
+        }));
+        assert!(history.contains(&HistoryEntry::Spending {
+            txid: spending_tx.txid.0,
+            input_index: input_index as u32,
+            height,
+            value: output.value,
+            spent,
+        }));
+
+        //the coinbase output, spent in a made up next block
+        let coinbase = &block.transactions[0];
+        let script = coinbase.outputs[0].script_pub_key.0.clone();
+        let value = coinbase.outputs[0].value;
+        let funded: u64 = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .filter(|output| output.script_pub_key.0 == script)
+            .map(|output| output.value)
+            .sum();
+        assert_eq!(index.balance(&script).unwrap(), funded);
+        let address = script_to_address(&script, "mainnet").unwrap();
+        assert_eq!(
+            index.address_history(&address).unwrap(),
+            index.history(&script).unwrap()
+        );
+
+        let tx = TransactionBuilder::new()
+            .input(
+                TxInputBuilder::new()
+                    .previous_tx_hash(coinbase.txid.0)
+                    .vout(0)
+                    .build(),
+            )
+            .output(TxOutput::new(value, &[0x51]))
+            .txid(Hash256([1; 32]))
+            .build();
+        //made up blocks have the regtest difficulty
+        let bits = Bytes::new(&0x207fffffu32.to_le_bytes());
+        let header = BlockHeader {
+            prev_block_hash: block.header.hash,
+            hash: BlockHash(Hash256([2; 32])),
+            bits: bits.clone(),
+            ..BlockHeader::default()
+        };
+        let next = Block::new(header, vec![tx]);
+        assert_eq!(
+            index.index_block(&next, height + 2).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(index.index_block(&next, height + 1).unwrap(), 0);
+        assert_eq!(index.balance(&script).unwrap(), funded - value);
+
+        //the block leaves the chain and comes back
+        let history = index.history(&script).unwrap();
+        assert_eq!(
+            index
+                .disconnect_block(&block.header.hash.0)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        index.disconnect_block(&next.header.hash.0).unwrap();
+        assert_eq!(index.tip(), Some((height, block.header.hash.0)));
+        assert_eq!(index.balance(&script).unwrap(), funded);
+        assert!(index.history(&[0x51]).unwrap().is_empty());
+        assert_eq!(index.index_block(&next, height + 1).unwrap(), 0);
+        assert_eq!(index.history(&script).unwrap(), history);
+        assert_eq!(
+            index.history(&[0x51]).unwrap(),
+            vec![HistoryEntry::Funding {
+                out_point: OutPoint::new(Hash256([1; 32]), 0),
+                height: height + 1,
+                value,
+            }]
+        );
+
+        //everything is found again after reopening, an unfinished block is dropped
+        let history = index.history(&script).unwrap();
+        let scripts = index.len();
+        drop(index);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(
+            &HistoryEntry::Funding {
+                out_point: OutPoint::new(Hash256([3; 32]), 0),
+                height: height + 2,
+                value,
+            }
+            .serialize(&script_hash(&script), NONE),
+        )
+        .unwrap();
+        file.write_all(&[BLOCK, 0]).unwrap();
+        drop(file);
+        let index = ScriptIndex::open(&path).unwrap();
+        assert_eq!(index.replayed, 0);
+        assert_eq!(index.tip(), Some((height + 1, Hash256([2; 32]))));
+        assert_eq!(index.len(), scripts);
+        assert_eq!(index.history(&script).unwrap(), history);
+        assert_eq!(index.balance(&script).unwrap(), funded - value);
+        drop(index);
+
+        //tables behind the file are brought up to date, adding a block again changes nothing
+        let mut table = DiskMap::open(&with_suffix(&path, ".scripts"), 32, 8).unwrap();
+        table.commit(0).unwrap();
+        drop(table);
+        std::fs::remove_file(with_suffix(&path, ".unspent")).unwrap();
+        let mut index = ScriptIndex::open(&path).unwrap();
+        assert_eq!(index.replayed, 2);
+        assert_eq!(index.len(), scripts);
+        assert_eq!(index.history(&script).unwrap(), history);
+        assert_eq!(index.balance(&script).unwrap(), funded - value);
+        assert!(matches!(
+            index.address_balance("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            Err(IndexError::Address(AddressError::InvalidEncoding))
+        ));
+
+        //a longer branch replaces the last block after reopening
+        let mut order = index.chain_order();
+        let header = |prev: Hash256, hash: u8| BlockHeader {
+            prev_block_hash: BlockHash(prev),
+            hash: BlockHash(Hash256([hash; 32])),
+            bits: bits.clone(),
+            ..BlockHeader::default()
+        };
+        let sibling = Block::new(header(block.header.hash.0, 4), Vec::new());
+        let child = Block::new(header(Hash256([4; 32]), 5), Vec::new());
+        assert_eq!(index.index_events(order.push(sibling)).unwrap(), 0);
+        assert_eq!(index.index_events(order.push(child)).unwrap(), 2);
+        assert_eq!(index.tip(), Some((height + 2, Hash256([5; 32]))));
+        assert_eq!(index.balance(&script).unwrap(), funded);
+        drop(index);
+        remove_files(&path);
+    }
+
+    #[test]
+    fn test_index_blk_file() {
+        let block = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let data = [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(block.len() as u32).to_le_bytes(),
+            block,
+        ]
+        .concat();
+        let path = temp_path("script_index_blk");
+        let mut index = ScriptIndex::open(&path).unwrap();
+        let mut order = index.chain_order();
+        assert_eq!(index.index_blk_file(&data, &mut order).unwrap(), 1);
+        let (_, genesis) = parse_block(block).unwrap();
+        assert_eq!(index.tip(), Some((0, genesis.header.hash.0)));
+        let script = &genesis.transactions[0].outputs[0].script_pub_key.0;
+        assert_eq!(index.balance(script).unwrap(), 50 * 100_000_000);
+        //the same block again does not extend the chain
+        assert_eq!(index.index_blk_file(&data, &mut order).unwrap(), 0);
+        drop(index);
+        remove_files(&path);
+    }
+}
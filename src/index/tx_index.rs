@@ -0,0 +1,484 @@
+use super::disk_map::{with_suffix, DiskMap};
+use crate::{
+    blk::{blk_file_name, blk_files, BlkRecords, ChainEvent, ChainOrder, MAX_REORG_DEPTH},
+    parsers::{parse_block, parse_transaction},
+    serializers::serialize_var_int,
+    types::{Block, Hash256, Transaction},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryInto,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+//block hash, file number, block offset, transaction offset, size
+const LOCATION_SIZE: usize = 32 + 4 + 8 + 4 + 4;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+//where a transaction is stored in the blk files
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TxLocation {
+    pub block_hash: Hash256,
+    //the number of the blk file
+    pub file: u32,
+    //where the serialized block starts in the blk file, after the magic number and size
+    pub block_offset: u64,
+    //where the transaction starts in the serialized block
+    pub tx_offset: u32,
+    pub size: u32,
+}
+
+impl TxLocation {
+    fn serialize(&self) -> Vec<u8> {
+        [
+            &self.block_hash.0[..],
+            &self.file.to_le_bytes(),
+            &self.block_offset.to_le_bytes(),
+            &self.tx_offset.to_le_bytes(),
+            &self.size.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    fn parse(data: &[u8]) -> TxLocation {
+        let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+        TxLocation {
+            block_hash: Hash256::new(&data[..32]),
+            file: u32_at(32),
+            block_offset: u64::from_le_bytes(data[36..44].try_into().unwrap()),
+            tx_offset: u32_at(44),
+            size: u32_at(48),
+        }
+    }
+}
+
+//reads the numbers of the index file in order
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, size: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < size {
+            return Err(invalid_data("corrupted txindex file"));
+        }
+        let (field, rest) = self.data.split_at(size);
+        self.data = rest;
+        Ok(field)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+//the location of every transaction of the active chain in the blk files of a blocks directory, like bitcoind's -txindex
+//blocks are put in chain order, the transactions of a block leaving the chain in a reorg are taken out
+//the locations are looked up in a table next to the index file, index.txids for an index file named index
+//the index file holds how far the blk files were read, the last blocks of the chain and the blocks
+//waiting for their parent, it is replaced after every blk file and the table committed with it
+pub struct TxIndex {
+    path: PathBuf,
+    blocks_dir: PathBuf,
+    //txid to location
+    transactions: DiskMap,
+    //how often the index file was written
+    sequence: u64,
+    order: ChainOrder,
+    //the hashes, work and blk file positions of the last blocks of the chain, the tip last
+    recent: VecDeque<(Hash256, u128, u32, u64)>,
+    //the blk file positions of the blocks waiting in order
+    pending: HashMap<Hash256, (u32, u64)>,
+    //how far each blk file was indexed
+    files: HashMap<u32, u64>,
+}
+
+impl TxIndex {
+    //creates the index file at path or continues with the blk files already indexed in it
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(path: P, blocks_dir: Q) -> io::Result<TxIndex> {
+        let path = path.as_ref();
+        let mut index = TxIndex {
+            path: path.to_path_buf(),
+            blocks_dir: blocks_dir.as_ref().to_path_buf(),
+            transactions: DiskMap::open(&with_suffix(path, ".txids"), 32, LOCATION_SIZE)?,
+            sequence: 0,
+            order: ChainOrder::new(),
+            recent: VecDeque::new(),
+            pending: HashMap::new(),
+            files: HashMap::new(),
+        };
+        index.load()?;
+        Ok(index)
+    }
+
+    fn load(&mut self) -> io::Result<()> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut pending = Vec::new();
+        if !data.is_empty() {
+            let mut fields = Fields { data: &data };
+            self.sequence = fields.u64()?;
+            let next_height = fields.u32()?;
+            for _ in 0..fields.u32()? {
+                let hash = Hash256::new(fields.take(32)?);
+                let work = u128::from_le_bytes(fields.take(16)?.try_into().unwrap());
+                self.recent
+                    .push_back((hash, work, fields.u32()?, fields.u64()?));
+            }
+            for _ in 0..fields.u32()? {
+                self.files.insert(fields.u32()?, fields.u64()?);
+            }
+            for _ in 0..fields.u32()? {
+                pending.push((fields.u32()?, fields.u64()?));
+            }
+            if !self.recent.is_empty() {
+                let blocks = self
+                    .recent
+                    .iter()
+                    .map(|(hash, work, _, _)| (*hash, *work))
+                    .collect();
+                self.order = ChainOrder::from_chain(blocks, next_height);
+            }
+        }
+        //the table is committed right after the index file is written, an interrupted commit
+        //leaves it one behind, its changes were all made before the index file was written
+        let mark = self.transactions.mark();
+        if mark != self.sequence && mark + 1 != self.sequence {
+            return Err(invalid_data(
+                "the txindex table does not belong to the index file",
+            ));
+        }
+        self.transactions.commit(self.sequence)?;
+        //the blocks waiting for their parent go back into the chain order, in the order they came
+        for (file, block_offset) in pending {
+            let block = self.read_block(file, block_offset)?;
+            self.push(block, file, block_offset)?;
+        }
+        Ok(())
+    }
+
+    //writes the index file next to the old one and replaces it, then commits the table
+    fn save(&mut self) -> io::Result<()> {
+        let order = &self.order;
+        self.pending.retain(|hash, _| order.is_pending(hash));
+        let mut pending: Vec<_> = self.pending.values().copied().collect();
+        pending.sort_unstable();
+        let mut data = Vec::new();
+        data.extend(&(self.sequence + 1).to_le_bytes());
+        data.extend(&self.order.next_height().to_le_bytes());
+        data.extend(&(self.recent.len() as u32).to_le_bytes());
+        for (hash, work, file, block_offset) in self.recent.iter() {
+            data.extend(&hash.0);
+            data.extend(&work.to_le_bytes());
+            data.extend(&file.to_le_bytes());
+            data.extend(&block_offset.to_le_bytes());
+        }
+        data.extend(&(self.files.len() as u32).to_le_bytes());
+        for (number, length) in self.files.iter() {
+            data.extend(&number.to_le_bytes());
+            data.extend(&length.to_le_bytes());
+        }
+        data.extend(&(pending.len() as u32).to_le_bytes());
+        for (file, block_offset) in pending {
+            data.extend(&file.to_le_bytes());
+            data.extend(&block_offset.to_le_bytes());
+        }
+        let new = with_suffix(&self.path, ".new");
+        std::fs::write(&new, &data)?;
+        std::fs::rename(&new, &self.path)?;
+        self.sequence += 1;
+        self.transactions.commit(self.sequence)
+    }
+
+    //the block of a blk file record starting at block_offset, after the magic number and size
+    fn read_block(&self, file: u32, block_offset: u64) -> io::Result<Block> {
+        let not_found = || invalid_data("the indexed location does not hold the block");
+        let mut blk = File::open(self.blocks_dir.join(blk_file_name(file)))?;
+        blk.seek(SeekFrom::Start(
+            block_offset.checked_sub(4).ok_or_else(not_found)?,
+        ))?;
+        let mut size = [0; 4];
+        blk.read_exact(&mut size)?;
+        let mut data = vec![0; u32::from_le_bytes(size) as usize];
+        blk.read_exact(&mut data)?;
+        match parse_block(&data) {
+            Ok((_, block)) => Ok(block),
+            Err(_) => Err(not_found()),
+        }
+    }
+
+    //puts a block in order and indexes the blocks it connects, returns the number of transactions indexed
+    fn push(&mut self, block: Block, file: u32, block_offset: u64) -> io::Result<usize> {
+        self.pending
+            .entry(block.header.hash.0)
+            .or_insert((file, block_offset));
+        let mut indexed = 0;
+        for event in self.order.push(block) {
+            match event {
+                ChainEvent::Connected(_, block) => {
+                    indexed += self.connect(&block)?;
+                }
+                ChainEvent::Disconnected(_, hash) => self.disconnect(&hash)?,
+            }
+        }
+        Ok(indexed)
+    }
+
+    fn connect(&mut self, block: &Block) -> io::Result<usize> {
+        let hash = block.header.hash.0;
+        let (file, block_offset) = self
+            .pending
+            .remove(&hash)
+            .ok_or_else(|| invalid_data("connected block was not pushed"))?;
+        //the transactions follow the header and their count
+        let mut tx_offset = 80 + serialize_var_int(block.transactions.len() as u64).len();
+        for tx in block.transactions.iter() {
+            let location = TxLocation {
+                block_hash: hash,
+                file,
+                block_offset,
+                tx_offset: tx_offset as u32,
+                size: tx.size as u32,
+            };
+            self.transactions
+                .insert(&tx.txid.to_wire_bytes(), &location.serialize())?;
+            tx_offset += tx.size;
+        }
+        self.recent
+            .push_back((hash, block.header.work(), file, block_offset));
+        if self.recent.len() > MAX_REORG_DEPTH as usize + 1 {
+            self.recent.pop_front();
+        }
+        Ok(block.transactions.len())
+    }
+
+    fn disconnect(&mut self, hash: &Hash256) -> io::Result<()> {
+        let (file, block_offset) = match self.recent.pop_back() {
+            Some((tip, _, file, block_offset)) if tip == *hash => (file, block_offset),
+            _ => return Err(invalid_data("disconnected block is not the tip")),
+        };
+        let block = self.read_block(file, block_offset)?;
+        for tx in block.transactions.iter() {
+            //a txid in more than one block keeps the location of the other block
+            if let Some(location) = self.location(&tx.txid.0)? {
+                if location.block_hash == *hash {
+                    self.transactions.remove(&tx.txid.to_wire_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //the number of transactions in the active chain
+    pub fn len(&self) -> usize {
+        self.transactions.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.len() == 0
+    }
+
+    //height and hash of the last block of the chain
+    pub fn tip(&self) -> Option<(u32, Hash256)> {
+        self.recent
+            .back()
+            .map(|(hash, _, _, _)| (self.order.next_height() - 1, *hash))
+    }
+
+    pub fn location(&self, txid: &Hash256) -> io::Result<Option<TxLocation>> {
+        let location = self.transactions.get(&txid.0)?;
+        Ok(location.map(|location| TxLocation::parse(&location)))
+    }
+
+    //indexes the blocks of blk file number that were not read yet, data is the whole file
+    //a file still being written can be indexed again once it grew, a record cut short is left for later
+    //returns the number of transactions of the blocks connected
+    pub fn index_blk_file(&mut self, number: u32, data: &[u8]) -> io::Result<usize> {
+        let start = self.files.get(&number).copied().unwrap_or(0) as usize;
+        let mut end = start;
+        let mut indexed = 0;
+        for record in BlkRecords::new(data.get(start..).unwrap_or_default()) {
+            let block_offset = start + record.offset;
+            end = block_offset + record.data.len();
+            if let Ok((_, block)) = parse_block(record.data) {
+                indexed += self.push(block, number, block_offset as u64)?;
+            }
+        }
+        if end == start {
+            return Ok(0);
+        }
+        self.files.insert(number, end as u64);
+        self.save()?;
+        Ok(indexed)
+    }
+    //indexes what is new in the blk files of the blocks directory
+    pub fn index_blocks_dir(&mut self) -> io::Result<usize> {
+        let mut indexed = 0;
+        for (number, path) in blk_files(&self.blocks_dir)? {
+            let length = std::fs::metadata(&path)?.len();
+            if self.files.get(&number) == Some(&length) {
+                continue;
+            }
+            let data = std::fs::read(&path)?;
+            indexed += self.index_blk_file(number, &data)?;
+        }
+        Ok(indexed)
+    }
+
+    //reads and parses only the transaction, none if the txid is not indexed
+    pub fn get_transaction(&self, txid: &Hash256) -> io::Result<Option<Transaction>> {
+        let location = match self.location(txid)? {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        let mut file = File::open(self.blocks_dir.join(blk_file_name(location.file)))?;
+        file.seek(SeekFrom::Start(
+            location.block_offset + u64::from(location.tx_offset),
+        ))?;
+        let mut data = vec![0; location.size as usize];
+        file.read_exact(&mut data)?;
+        match parse_transaction(&data) {
+            Ok((_, tx)) if tx.txid.0 == *txid => Ok(Some(tx)),
+            _ => Err(invalid_data(
+                "the indexed location does not hold the transaction",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        serializers::serialize_block,
+        types::{BlockHash, BlockHeader, Bytes},
+    };
+    use std::fs;
+
+    fn record(block: &[u8]) -> Vec<u8> {
+        [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(block.len() as u32).to_le_bytes(),
+            block,
+        ]
+        .concat()
+    }
+
+    //a made up block of the regtest difficulty, nonce tells siblings apart
+    fn child(prev: Hash256, nonce: u8, transactions: Vec<Transaction>) -> (Vec<u8>, Hash256) {
+        let header = BlockHeader {
+            prev_block_hash: BlockHash(prev),
+            bits: Bytes::new(&0x207fffffu32.to_le_bytes()),
+            nonce: Bytes::new(&[nonce, 0, 0, 0]),
+            ..BlockHeader::default()
+        };
+        let data = serialize_block(&Block::new(header, transactions));
+        let (_, block) = parse_block(&data).unwrap();
+        (data, block.header.hash.0)
+    }
+
+    #[test]
+    fn test_tx_index() {
+        let genesis = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let block_data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, genesis_block) = parse_block(genesis).unwrap();
+        let (_, block) = parse_block(block_data).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("parse_bitcoin_txindex_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let index_path = dir.join("txindex.dat");
+        let blk_path = dir.join(blk_file_name(3));
+        fs::write(&blk_path, [record(genesis), vec![0; 5]].concat()).unwrap();
+
+        let mut index = TxIndex::open(&index_path, &dir).unwrap();
+        assert_eq!(index.index_blocks_dir().unwrap(), 1);
+        assert_eq!(index.tip(), Some((0, genesis_block.header.hash.0)));
+        let coinbase = genesis_block.transactions[0].txid.0;
+        assert_eq!(
+            index.location(&coinbase).unwrap(),
+            Some(TxLocation {
+                block_hash: genesis_block.header.hash.0,
+                file: 3,
+                block_offset: 8,
+                tx_offset: 81,
+                size: 204,
+            })
+        );
+        //nothing new to index
+        assert_eq!(index.index_blocks_dir().unwrap(), 0);
+
+        //the file grows, a block without its parent waits and a child of the genesis block is indexed
+        let txs = &block.transactions;
+        let (first, first_hash) = child(genesis_block.header.hash.0, 0, txs[..2].to_vec());
+        let data = [
+            record(genesis),
+            vec![0; 5],
+            record(block_data),
+            record(&first),
+        ]
+        .concat();
+        fs::write(&blk_path, &data).unwrap();
+        assert_eq!(index.index_blocks_dir().unwrap(), 2);
+        assert_eq!(
+            index.location(&txs[1].txid.0).unwrap().unwrap().block_hash,
+            first_hash
+        );
+        assert_eq!(index.location(&txs[446].txid.0).unwrap(), None);
+        for tx in [&txs[1], &genesis_block.transactions[0]].iter() {
+            let found = index.get_transaction(&tx.txid.0).unwrap().unwrap();
+            assert_eq!(found.txid, tx.txid);
+            assert_eq!(found.size, tx.size);
+        }
+        assert!(index.get_transaction(&Hash256([1; 32])).unwrap().is_none());
+        drop(index);
+
+        //after reopening a branch with more work replaces the child of the genesis block,
+        //its transactions are found in the new branch, the block still without its parent waits on
+        let (second, second_hash) = child(genesis_block.header.hash.0, 1, txs[..2].to_vec());
+        let (third, third_hash) = child(second_hash, 0, vec![txs[446].clone()]);
+        fs::write(
+            dir.join(blk_file_name(4)),
+            [record(&second), record(&third)].concat(),
+        )
+        .unwrap();
+        let mut index = TxIndex::open(&index_path, &dir).unwrap();
+        assert_eq!(index.tip(), Some((1, first_hash)));
+        assert_eq!(index.pending.len(), 1);
+        assert_eq!(index.index_blocks_dir().unwrap(), 3);
+        assert_eq!(index.tip(), Some((2, third_hash)));
+        assert_eq!(index.len(), 4);
+        assert_eq!(
+            index.location(&txs[1].txid.0).unwrap().unwrap().block_hash,
+            second_hash
+        );
+        assert_eq!(index.location(&txs[2].txid.0).unwrap(), None);
+        let tx = index.get_transaction(&txs[446].txid.0).unwrap().unwrap();
+        assert_eq!(tx.txid, txs[446].txid);
+        drop(index);
+
+        //the locations are looked up in the table without reading the blk files of the chain again
+        fs::remove_file(dir.join(blk_file_name(4))).unwrap();
+        let index = TxIndex::open(&index_path, &dir).unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.location(&coinbase).unwrap().unwrap().file, 3);
+        assert_eq!(index.location(&txs[446].txid.0).unwrap().unwrap().file, 4);
+        drop(index);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,18 @@
+pub mod blk;
+pub mod export;
+pub mod filters;
+pub mod index;
+pub mod mempool;
+pub mod p2p;
 pub mod parsers;
+pub mod psbt;
+pub mod script;
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
+pub mod serializers;
+pub mod stats;
 pub mod types;
 pub mod utils;
+pub mod utxo;
 #[macro_use]
 extern crate partial_application;
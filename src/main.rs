@@ -1,113 +1,1056 @@
-use hex;
-use nom::number::complete::le_u32;
-use parse_bitcoin::parsers::parse_block;
-use parse_bitcoin::utils::find_block_start;
-use std::collections::HashMap;
-use std::error::Error;
-use std::io;
-use std::io::prelude::*;
-
-fn read_file(filename: &str) -> std::io::Result<()> {
-    let mut file = std::fs::File::open(filename)?;
-    let mut buffer = Vec::new();
-    let read_bytes = file.read_to_end(&mut buffer)?;
-    println!(
-        "read {} MiB from {} file",
-        read_bytes / 1024 / 1024,
-        filename
-    );
-    let mut input = &buffer[..];
-    // let n = 3;
-    // let mut blocks = Vec::with_capacity(n);
-    let mut blocks = Vec::new();
-    // for x in 0..n {
-    let mut blk_count = 0;
-    let mut error_count = 0;
-    let mut chains = HashMap::new();
-    loop {
-        // println!("part of input: {}", hex::encode(&input[0..63]));
-        // println!("searching for a block start ...");
-        let (i, blockchain) = match find_block_start(input) {
-            Ok(res) => res,
-            Err(nom::Err::Error(([0, 0, 0], nom::error::ErrorKind::Eof))) => {
-                // println!("no magic number found, but fond 0 bytes in a row, incrementing counter");
-                // error_count += 1;
-                // if error_count > 3 {
-                //     println!("we probably have a ton of zeros, input(64): {}", hex::encode(&input[0..63]));
-                //     println!("aborting");
-                //     break;
-                // }
-                // continue;
-                println!("no magic number found and we have 0 bytes following, aborting read!");
-                break;
-            }
-            Err(nom::Err::Error(([], nom::error::ErrorKind::Eof))) => {
-                println!("end of file reached, exiting search for magic number");
-                break;
-            }
-            Err(e) => {
-                println!("no magic number found, skipping a byte: {:?}", e);
-                error_count += 1;
-                continue;
+use parse_bitcoin::{
+    blk::{
+        blk_file_name, blk_files, BlkBlocks, BlkFollower, BlkPosition, BlkRecords, ChainEvent,
+        ChainOrder, FollowEvent, MAX_REORG_DEPTH,
+    },
+    index::TxIndex,
+    p2p::{AddrInfo, AddrV2},
+    parsers::{
+        chain_magic, parse_anchors, parse_block, parse_block_header, parse_fee_estimates,
+        parse_mempool, parse_peers,
+    },
+    script::{script_to_asm, script_type},
+    stats::BlockStats,
+    types::{Block, BlockHash, BlockHeader, Transaction, Txid},
+    utils::{calculate_merkle_root, script_to_address},
+};
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+const USAGE: &str = "usage: parse_bitcoin [options] <command> <argument>
+
+commands:
+  parse-blk <file|dir>      count the blocks and transactions of blk files
+  show-block <hash|height>  print a block of the blocks directory
+  show-tx <txid>            print a transaction of the blocks directory
+  decode-tx <hex>           print a serialized transaction, - reads the hex from stdin
+  decode-header <hex>       print a serialized block header, - reads the hex from stdin
+  verify <file>             check the merkle root and proof of work of the blocks of a blk file
+  follow <number>           print the blocks appended to the blk files from this one on
+  mempool <file>            print the transactions of a mempool.dat
+  peers <file>              print the addresses of a peers.dat or anchors.dat
+  fee-estimates <file>      print the state of the fee estimator in a fee_estimates.dat
+
+options:
+  --network <name>   mainnet, testnet, regtest or namecoin, mainnet by default
+  --datadir <dir>    the data directory of bitcoind, ~/.bitcoin by default
+  --txindex <file>   find transactions with this index, it is created or updated first
+  --format <format>  text or json, text by default
+  -h, --help         print this help
+
+exit codes:
+  0  success
+  1  the block or transaction was not found, or a block failed verification
+  2  invalid arguments or input
+  3  a file could not be read
+";
+
+#[derive(Debug)]
+enum Failure {
+    NotFound(String),
+    //the number of blocks failing verification and the number of blocks checked
+    Verification(usize, usize),
+    Usage(String),
+    Invalid(String),
+    Io(PathBuf, io::Error),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::NotFound(_) | Failure::Verification(..) => 1,
+            Failure::Usage(_) | Failure::Invalid(_) => 2,
+            Failure::Io(..) => 3,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::NotFound(what) => write!(f, "{} not found", what),
+            Failure::Verification(failed, checked) => {
+                write!(f, "{} of {} blocks failed verification", failed, checked)
             }
+            Failure::Usage(msg) | Failure::Invalid(msg) => write!(f, "{}", msg),
+            Failure::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    network: String,
+    datadir: PathBuf,
+    txindex: Option<PathBuf>,
+    format: Format,
+    command: String,
+    argument: String,
+}
+
+impl Options {
+    //where bitcoind of the network keeps its blk files
+    fn blocks_dir(&self) -> PathBuf {
+        match self.network.as_str() {
+            "testnet" => self.datadir.join("testnet3").join("blocks"),
+            "regtest" => self.datadir.join("regtest").join("blocks"),
+            _ => self.datadir.join("blocks"),
+        }
+    }
+}
+
+//options can be given before or after the command, as --name value or --name=value
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, Failure> {
+    let mut network = "mainnet".to_string();
+    let mut datadir = None;
+    let mut txindex = None;
+    let mut format = Format::Text;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            positional = vec!["help".to_string(), String::new()];
+            break;
+        }
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        let (name, value) = match arg.find('=') {
+            Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+            None => (arg.clone(), None),
         };
-        let blkch_counter = chains.entry(blockchain).or_insert(0);
-        *blkch_counter += 1;
-        // println!("part of input after find_block_start: {}", hex::encode(&i[0..63]));
-        // println!("found block with magic number signalling: {}", blockchain.unwrap());
-        let (i, block_size) = match le_u32::<()>(i) {
-            Ok(res) => res,
-            Err(e) => {
-                println!("There was an error {:?}", e);
-                break;
+        let value = match value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(Failure::Usage(format!("{} needs a value", name))),
+        };
+        match name.as_str() {
+            "--network" if chain_magic(&value).is_some() => network = value,
+            "--network" => return Err(Failure::Usage(format!("unknown network {}", value))),
+            "--datadir" => datadir = Some(PathBuf::from(value)),
+            "--txindex" => txindex = Some(PathBuf::from(value)),
+            "--format" => {
+                format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" if cfg!(feature = "serde") => Format::Json,
+                    "json" => {
+                        return Err(Failure::Usage(
+                            "json output needs the serde feature".to_string(),
+                        ))
+                    }
+                    _ => return Err(Failure::Usage(format!("unknown format {}", value))),
+                }
             }
+            _ => return Err(Failure::Usage(format!("unknown option {}", name))),
+        }
+    }
+    let (command, argument) = match &positional[..] {
+        [command, argument] => (command.clone(), argument.clone()),
+        [] => return Err(Failure::Usage("missing command".to_string())),
+        [command] => return Err(Failure::Usage(format!("{} needs an argument", command))),
+        _ => return Err(Failure::Usage("too many arguments".to_string())),
+    };
+    let datadir = datadir.unwrap_or_else(|| {
+        env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".bitcoin")
+    });
+    Ok(Options {
+        network,
+        datadir,
+        txindex,
+        format,
+        command,
+        argument,
+    })
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Failure> {
+    fs::read(path).map_err(|e| Failure::Io(path.to_path_buf(), e))
+}
+
+//the hex of an argument, - reads it from stdin
+fn read_hex(argument: &str) -> Result<String, Failure> {
+    if argument != "-" {
+        return Ok(argument.to_string());
+    }
+    let mut hex = String::new();
+    io::stdin()
+        .read_to_string(&mut hex)
+        .map_err(|e| Failure::Io(PathBuf::from("stdin"), e))?;
+    Ok(hex)
+}
+
+fn blk_paths(path: &Path) -> Result<Vec<PathBuf>, Failure> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let files = blk_files(path).map_err(|e| Failure::Io(path.to_path_buf(), e))?;
+    match files.is_empty() {
+        true => Err(Failure::NotFound(format!(
+            "blk files in {}",
+            path.display()
+        ))),
+        false => Ok(files.into_iter().map(|(_, path)| path).collect()),
+    }
+}
+
+fn btc(value: u64) -> String {
+    format!("{}.{:08}", value / 100_000_000, value % 100_000_000)
+}
+
+//prints the text, or the JSON built by the json expression, which is only compiled with the serde feature
+//a closed stdout, like that of head, is no error
+macro_rules! output {
+    ($format:expr, $text:expr, $json:expr) => {{
+        use std::io::Write;
+        let _ = match $format {
+            Format::Text => write!(io::stdout(), "{}", $text),
+            #[cfg(feature = "serde")]
+            Format::Json => writeln!(
+                io::stdout(),
+                "{}",
+                serde_json::to_string_pretty(&$json).unwrap()
+            ),
+            #[cfg(not(feature = "serde"))]
+            Format::Json => unreachable!(),
         };
-        // println!("part of input after parse_var_int: {}", hex::encode(&i[0..63]));
-        // println!("block has size {} Bytes", block_size);
-        // let (i, o) = match parse_block (&i[0..block_size as usize]){
-        let (i, o) = match parse_block(i) {
-            Ok(res) => res,
-            Err(nom::Err::Error((i, e))) => {
-                println!("therewas an error {:?}", e);
-                input = i;
-                break;
+    }};
+}
+
+//the JSON of the library has mainnet addresses
+#[cfg(feature = "serde")]
+fn to_json<T: serde::Serialize>(value: &T, network: &str) -> serde_json::Value {
+    fn set_addresses(value: &mut serde_json::Value, network: &str) {
+        use serde_json::Value;
+        match value {
+            Value::Object(map) => {
+                let script = match (map.get("hex"), map.get("type")) {
+                    (Some(Value::String(hex)), Some(_)) => hex::decode(hex).ok(),
+                    _ => None,
+                };
+                if let Some(script) = script {
+                    match script_to_address(&script, network) {
+                        Some(address) => map.insert("address".to_string(), address.into()),
+                        None => map.remove("address"),
+                    };
+                }
+                map.values_mut().for_each(|v| set_addresses(v, network));
             }
-            Err(nom::Err::Failure((i, e))) => {
-                println!("there was a failure {:?}", e);
-                input = i;
-                break;
+            Value::Array(items) => items.iter_mut().for_each(|v| set_addresses(v, network)),
+            _ => (),
+        }
+    }
+    let mut json = serde_json::to_value(value).unwrap();
+    if network != "mainnet" {
+        set_addresses(&mut json, network);
+    }
+    json
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct BlkStats {
+    files: usize,
+    bytes: usize,
+    //records skipped because they do not hold a block
+    invalid: usize,
+    chains: BTreeMap<String, usize>,
+    blocks: BlockStats,
+}
+
+impl BlkStats {
+    fn add_file(&mut self, data: &[u8]) {
+        self.files += 1;
+        self.bytes += data.len();
+        let mut blocks = BlkBlocks::new(data);
+        for (record, block) in blocks.by_ref() {
+            *self.chains.entry(record.chain.to_string()).or_insert(0) += 1;
+            self.blocks.add(&BlockStats::new(&block, None));
+        }
+        self.invalid += blocks.corruptions().len();
+    }
+
+    fn text(&self) -> String {
+        let blocks = &self.blocks;
+        let join = |map: Vec<String>| map.join(", ");
+        let lines = [
+            format!("files         {}", self.files),
+            format!("bytes         {}", self.bytes),
+            format!("blocks        {}", blocks.blocks),
+            format!("invalid       {}", self.invalid),
+            format!("transactions  {}", blocks.txs),
+            format!("inputs        {}", blocks.ins),
+            format!("outputs       {}", blocks.outs),
+            format!("total out     {}", btc(blocks.total_out)),
+            format!(
+                "segwit        {} ({:.2}%)",
+                blocks.swtxs,
+                blocks.segwit_share() * 100.0
+            ),
+            format!("avg size      {}", blocks.avg_tx_size()),
+            format!("avg weight    {}", blocks.avg_tx_weight()),
+            format!("op_return     {}", blocks.op_returns),
+            format!(
+                "script types  {}",
+                join(
+                    blocks
+                        .script_types
+                        .iter()
+                        .map(|(script_type, n)| format!("{} {}", script_type, n))
+                        .collect()
+                )
+            ),
+            format!(
+                "chains        {}",
+                join(
+                    self.chains
+                        .iter()
+                        .map(|(chain, n)| format!("{} {}", chain, n))
+                        .collect()
+                )
+            ),
+        ];
+        lines.iter().map(|line| line.clone() + "\n").collect()
+    }
+}
+
+fn parse_blk(options: &Options) -> Result<(), Failure> {
+    let mut stats = BlkStats::default();
+    for path in blk_paths(Path::new(&options.argument))? {
+        stats.add_file(&read(&path)?);
+    }
+    output!(options.format, stats.text(), {
+        let blocks = &stats.blocks;
+        serde_json::json!({
+            "files": stats.files,
+            "bytes": stats.bytes,
+            "blocks": blocks.blocks,
+            "invalid": stats.invalid,
+            "txs": blocks.txs,
+            "ins": blocks.ins,
+            "outs": blocks.outs,
+            "total_out": blocks.total_out,
+            "total_size": blocks.total_size,
+            "total_weight": blocks.total_weight,
+            "avgtxsize": blocks.avg_tx_size(),
+            "mintxsize": blocks.min_tx_size.unwrap_or(0),
+            "maxtxsize": blocks.max_tx_size,
+            "swtxs": blocks.swtxs,
+            "swtotal_size": blocks.swtotal_size,
+            "swtotal_weight": blocks.swtotal_weight,
+            "utxo_increase": blocks.utxo_increase(),
+            "op_returns": blocks.op_returns,
+            "script_types": blocks.script_types,
+            "chains": stats.chains,
+        })
+    });
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum BlockQuery {
+    Hash(BlockHash),
+    Height(u32),
+}
+
+impl BlockQuery {
+    fn parse(s: &str) -> Result<BlockQuery, Failure> {
+        if let Ok(height) = s.parse() {
+            return Ok(BlockQuery::Height(height));
+        }
+        s.parse()
+            .map(BlockQuery::Hash)
+            .map_err(|e| Failure::Invalid(format!("{} is no block hash or height: {}", s, e)))
+    }
+}
+
+//scans the blk files of the network, heights are known only when searching by height
+//a block found by height is returned once no reorg can replace it, or at the end of the files
+fn find_block(options: &Options, query: BlockQuery) -> Result<(Block, Option<u32>), Failure> {
+    let mut chain = ChainOrder::new();
+    let mut found = None;
+    for path in blk_paths(&options.blocks_dir())? {
+        let data = read(&path)?;
+        let records = BlkRecords::new(&data).filter(|record| record.chain == options.network);
+        for record in records {
+            if let BlockQuery::Hash(hash) = query {
+                match parse_block_header(record.data) {
+                    Ok((_, header)) if header.block_hash() == hash => (),
+                    _ => continue,
+                }
+            }
+            let block = match parse_block(record.data) {
+                Ok((_, block)) => block,
+                Err(_) => continue,
+            };
+            let height = match query {
+                BlockQuery::Hash(_) => return Ok((block, None)),
+                BlockQuery::Height(height) => height,
+            };
+            for event in chain.push(block) {
+                match event {
+                    ChainEvent::Connected(h, block) if h == height => {
+                        found = Some((*block, Some(h)))
+                    }
+                    ChainEvent::Disconnected(h, _) if h == height => found = None,
+                    _ => (),
+                }
+            }
+            if chain.next_height() > height + MAX_REORG_DEPTH {
+                if let Some(found) = found {
+                    return Ok(found);
+                }
+            }
+        }
+    }
+    found.ok_or_else(|| Failure::NotFound(format!("block {}", options.argument)))
+}
+
+fn header_lines(header: &BlockHeader) -> Vec<String> {
+    let mut nonce = [0; 4];
+    nonce.copy_from_slice(&header.nonce.0[..4]);
+    vec![
+        format!("hash          {}", header.block_hash()),
+        format!("previous      {}", header.prev_block_hash()),
+        format!("merkle root   {}", header.merkle_root()),
+        format!("time          {} ({})", header.time, header.time_str),
+        format!("version       {:08x}", header.version),
+        format!("bits          {:08x}", header.compact_target()),
+        format!("nonce         {}", u32::from_le_bytes(nonce)),
+        format!("difficulty    {}", header.difficulty()),
+    ]
+}
+
+fn block_text(block: &Block, height: Option<u32>) -> String {
+    let mut lines = header_lines(&block.header);
+    if let Some(height) = height {
+        lines.insert(1, format!("height        {}", height));
+    }
+    let size = block.to_hex().len() / 2;
+    let weight: usize = block.transactions.iter().map(|tx| tx.weight()).sum();
+    lines.push(format!("transactions  {}", block.transactions.len()));
+    lines.push(format!("size          {}", size));
+    //the header and the transaction count are not witness data
+    lines.push(format!(
+        "weight        {}",
+        weight + (size - block.transactions.iter().map(|tx| tx.size).sum::<usize>()) * 4
+    ));
+    lines.push(String::new());
+    lines.extend(block.transactions.iter().map(|tx| tx.txid().to_string()));
+    lines.join("\n") + "\n"
+}
+
+fn show_block(options: &Options) -> Result<(), Failure> {
+    let (block, height) = find_block(options, BlockQuery::parse(&options.argument)?)?;
+    output!(options.format, block_text(&block, height), {
+        let mut json = to_json(&block, &options.network);
+        if let Some(height) = height {
+            json["height"] = height.into();
+        }
+        json
+    });
+    Ok(())
+}
+
+fn tx_text(tx: &Transaction, network: &str) -> String {
+    let mut lines = vec![
+        format!("txid      {}", tx.txid()),
+        format!("wtxid     {}", tx.wtxid()),
+        format!("version   {}", tx.version as i32),
+        format!("locktime  {}", tx.lock_time),
+        format!(
+            "size      {}, vsize {}, weight {}",
+            tx.size,
+            tx.vsize(),
+            tx.weight()
+        ),
+        format!("inputs    {}", tx.inputs.len()),
+    ];
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let script = &input.script_sig.0;
+        match input.is_coinbase() {
+            true => lines.push(format!("  {} coinbase {}", i, hex::encode(script))),
+            false => {
+                lines.push(format!("  {} {}:{}", i, input.previous_tx_hash, input.vout));
+                lines.push(format!("      {}", script_to_asm(script, true)));
             }
-            Err(nom::Err::Incomplete(needed)) => {
-                println!("we received incomplete, need {:?} bytes", needed);
-                break;
+        }
+        lines.push(format!("      sequence {:08x}", input.sequence));
+        let witness = tx.witnesses.as_ref().and_then(|w| w.get(i));
+        let items: Vec<_> = witness
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.0.as_ref().map(|item| hex::encode(&item.0)))
+            .collect();
+        if !items.is_empty() {
+            lines.push(format!("      witness {}", items.join(" ")));
+        }
+    }
+    lines.push(format!("outputs   {}", tx.outputs.len()));
+    for (i, output) in tx.outputs.iter().enumerate() {
+        let script = &output.script_pub_key.0;
+        let address = script_to_address(script, network).unwrap_or_default();
+        lines.push(format!(
+            "  {} {} {} {}",
+            i,
+            btc(output.value),
+            script_type(script),
+            address
+        ));
+        lines.push(format!("      {}", script_to_asm(script, false)));
+    }
+    lines
+        .iter()
+        .map(|line| line.trim_end().to_string() + "\n")
+        .collect()
+}
+
+//the transaction and the hash of its block
+fn find_transaction(options: &Options, txid: Txid) -> Result<(Transaction, BlockHash), Failure> {
+    let blocks_dir = options.blocks_dir();
+    let not_found = || Failure::NotFound(format!("transaction {}", txid));
+    if let Some(path) = &options.txindex {
+        let io_error = |e| Failure::Io(path.clone(), e);
+        let mut index = TxIndex::open(path, &blocks_dir).map_err(io_error)?;
+        index.index_blocks_dir().map_err(io_error)?;
+        let location = index
+            .location(&txid.0)
+            .map_err(io_error)?
+            .ok_or_else(not_found)?;
+        let tx = index.get_transaction(&txid.0).map_err(io_error)?;
+        let block_hash = BlockHash(location.block_hash);
+        return tx.map(|tx| (tx, block_hash)).ok_or_else(not_found);
+    }
+    for path in blk_paths(&blocks_dir)? {
+        let data = read(&path)?;
+        let records = BlkRecords::new(&data).filter(|record| record.chain == options.network);
+        for record in records {
+            if let Ok((_, block)) = parse_block(record.data) {
+                let hash = block.header.block_hash();
+                if let Some(tx) = block.transactions.into_iter().find(|tx| tx.txid() == txid) {
+                    return Ok((tx, hash));
+                }
             }
+        }
+    }
+    Err(not_found())
+}
+
+fn show_tx(options: &Options) -> Result<(), Failure> {
+    let txid = options
+        .argument
+        .parse()
+        .map_err(|e| Failure::Invalid(format!("{} is no txid: {}", options.argument, e)))?;
+    let (tx, block_hash) = find_transaction(options, txid)?;
+    let text = format!(
+        "{}block     {}\n",
+        tx_text(&tx, &options.network),
+        block_hash
+    );
+    output!(options.format, text, {
+        let mut json = to_json(&tx, &options.network);
+        json["blockhash"] = block_hash.to_string().into();
+        json
+    });
+    Ok(())
+}
+
+fn decode_tx(options: &Options) -> Result<(), Failure> {
+    let tx = Transaction::from_hex(&read_hex(&options.argument)?)
+        .map_err(|e| Failure::Invalid(format!("invalid transaction: {}", e)))?;
+    output!(
+        options.format,
+        tx_text(&tx, &options.network),
+        to_json(&tx, &options.network)
+    );
+    Ok(())
+}
+
+fn decode_header(options: &Options) -> Result<(), Failure> {
+    let header = BlockHeader::from_hex(&read_hex(&options.argument)?)
+        .map_err(|e| Failure::Invalid(format!("invalid block header: {}", e)))?;
+    let text = header_lines(&header).join("\n") + "\n";
+    output!(options.format, text, to_json(&header, &options.network));
+    Ok(())
+}
+
+//a block of a blk file failing verification
+#[derive(Debug, PartialEq)]
+struct BadBlock {
+    offset: usize,
+    //None if the record does not hold a block
+    hash: Option<BlockHash>,
+    problems: Vec<String>,
+}
+
+//the blocks failing verification, in the order of the file, and the number of records checked
+fn verify_blocks(data: &[u8]) -> (Vec<BadBlock>, usize) {
+    let mut bad = Vec::new();
+    let mut checked = 0;
+    let mut blocks = BlkBlocks::new(data);
+    for (record, block) in blocks.by_ref() {
+        checked += 1;
+        let mut problems = Vec::new();
+        let txids = block.transactions.iter().map(|tx| tx.txid.0).collect();
+        if calculate_merkle_root(txids) != block.header.merkle_root_hash.0 {
+            problems.push("merkle root mismatch".to_string());
+        }
+        if !block.header.check_proof_of_work() {
+            problems.push("insufficient proof of work".to_string());
+        }
+        if !problems.is_empty() {
+            bad.push(BadBlock {
+                offset: record.offset,
+                hash: Some(block.header.block_hash()),
+                problems,
+            });
+        }
+    }
+    for corruption in blocks.corruptions() {
+        checked += 1;
+        bad.push(BadBlock {
+            offset: corruption.offset,
+            hash: None,
+            problems: vec![corruption.error.to_string()],
+        });
+    }
+    bad.sort_by_key(|block| block.offset);
+    (bad, checked)
+}
+
+fn verify(options: &Options) -> Result<(), Failure> {
+    let (bad, checked) = verify_blocks(&read(Path::new(&options.argument))?);
+    let mut text = String::new();
+    for block in bad.iter() {
+        let hash = block.hash.map(|hash| hash.to_string()).unwrap_or_default();
+        text += &format!("{} {}: {}\n", block.offset, hash, block.problems.join(", "));
+    }
+    text += &format!("{} blocks checked, {} failed\n", checked, bad.len());
+    output!(options.format, text, {
+        let bad: Vec<_> = bad
+            .iter()
+            .map(|block| {
+                serde_json::json!({
+                    "offset": block.offset,
+                    "hash": block.hash,
+                    "problems": block.problems,
+                })
+            })
+            .collect();
+        serde_json::json!({ "checked": checked, "failed": bad })
+    });
+    match bad.is_empty() {
+        true => Ok(()),
+        false => Err(Failure::Verification(bad.len(), checked)),
+    }
+}
+
+fn mempool(options: &Options) -> Result<(), Failure> {
+    let data = read(Path::new(&options.argument))?;
+    let dump = match parse_mempool(&data) {
+        Ok(([], dump)) => dump,
+        _ => {
+            return Err(Failure::Invalid(format!(
+                "{} is no mempool.dat",
+                options.argument
+            )))
+        }
+    };
+    let mut text = format!(
+        "version      {}\ntransactions {}\nvsize        {}\ndeltas       {}\nunbroadcast  {}\n",
+        dump.version,
+        dump.entries.len(),
+        dump.total_vsize(),
+        dump.deltas.len(),
+        dump.unbroadcast.len()
+    );
+    for entry in dump.entries.iter() {
+        let tx = &entry.transaction;
+        text += &format!(
+            "{} {} {} {}\n",
+            tx.txid(),
+            entry.time,
+            tx.vsize(),
+            entry.fee_delta
+        );
+    }
+    output!(options.format, text, {
+        let entries: Vec<_> = dump
+            .entries
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "txid": entry.transaction.txid(),
+                    "time": entry.time,
+                    "vsize": entry.transaction.vsize(),
+                    "fee_delta": entry.fee_delta,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "version": dump.version,
+            "size": dump.entries.len(),
+            "bytes": dump.total_vsize(),
+            "entries": entries,
+            "deltas": dump.deltas.iter().map(|(txid, delta)| serde_json::json!({ "txid": txid, "fee_delta": delta })).collect::<Vec<_>>(),
+            "unbroadcast": dump.unbroadcast,
+        })
+    });
+    Ok(())
+}
+
+//IPv4 and IPv6 addresses as usual, the others as network id and hex
+fn address_text(address: &AddrV2) -> String {
+    match address.ip_addr() {
+        Some(ip) => std::net::SocketAddr::new(ip, address.port).to_string(),
+        None => format!(
+            "{}:{}:{}",
+            address.network,
+            hex::encode(&address.addr),
+            address.port
+        ),
+    }
+}
+
+//peers.dat and anchors.dat are told apart by their contents
+fn peers(options: &Options) -> Result<(), Failure> {
+    let data = read(Path::new(&options.argument))?;
+    let (chain, tried, new) = match (parse_peers(&data), parse_anchors(&data)) {
+        (Ok(([], (chain, addrman))), _) => {
+            let addresses = |infos: &[AddrInfo]| -> Vec<AddrV2> {
+                infos.iter().map(|info| info.address.clone()).collect()
+            };
+            (chain, addresses(&addrman.tried), addresses(&addrman.new))
+        }
+        (_, Ok(([], (chain, anchors)))) => (chain, anchors, Vec::new()),
+        _ => {
+            let e = format!("{} is no peers.dat or anchors.dat", options.argument);
+            return Err(Failure::Invalid(e));
+        }
+    };
+    let mut text = format!(
+        "network {}\ntried   {}\nnew     {}\n",
+        chain,
+        tried.len(),
+        new.len()
+    );
+    for (table, address) in tried
+        .iter()
+        .map(|a| ("tried", a))
+        .chain(new.iter().map(|a| ("new", a)))
+    {
+        text += &format!(
+            "{} {} {:x}\n",
+            table,
+            address_text(address),
+            address.services
+        );
+    }
+    output!(options.format, text, {
+        let json = |addresses: &[AddrV2]| {
+            addresses
+                .iter()
+                .map(|address| {
+                    serde_json::json!({
+                        "address": address_text(address),
+                        "network": address.network,
+                        "services": address.services,
+                        "time": address.time,
+                    })
+                })
+                .collect::<Vec<_>>()
         };
-        // println!("part of input after parse_block: {}", hex::encode(&i[0..63]));
-        // println!("block found: {:?}", o);
-        blocks.push(o);
-        blk_count += 1;
-        print!("\rprocessed {} blocks  ", blk_count);
-        io::stdout().flush()?;
-        input = i;
-    }
-    // println!("res: {:?}", blocks);
-    println!("found {} blocks", blocks.len());
-    println!("chains: {:?}", chains);
+        serde_json::json!({ "network": chain, "tried": json(&tried), "new": json(&new) })
+    });
     Ok(())
 }
 
-fn main() -> Result<(), std::boxed::Box<dyn Error>> {
-    // match read_file("/home/tk/bin/bisq/docs/autosetup-regtest-dao/regtest/blocks/blk00000.dat") {
-    match read_file("/home/tk/.bitcoin/blocks/blk02063.dat") {
-        // match read_file("/home/tk/.bitcoin/blocks/blk02063.dat") {
-        // match read_file(".gitignore") {
-        Ok(_) => println!("all went fine!"),
-        // Err(e) => println!("Error is e")
-        Err(e) => println!("Error is {:?}", e),
+fn fee_estimates(options: &Options) -> Result<(), Failure> {
+    let data = read(Path::new(&options.argument))?;
+    let estimates = match parse_fee_estimates(&data) {
+        Ok((_, estimates)) => estimates,
+        Err(_) => {
+            let e = format!("{} is no fee_estimates.dat", options.argument);
+            return Err(Failure::Invalid(e));
+        }
+    };
+    let horizons = [
+        ("short", &estimates.short),
+        ("medium", &estimates.medium),
+        ("long", &estimates.long),
+    ];
+    let mut text = format!(
+        "version      {}\nbest height  {}\nhistorical   {}-{}\nbuckets      {}\n",
+        estimates.version,
+        estimates.best_seen_height,
+        estimates.historical_first,
+        estimates.historical_best,
+        estimates.buckets.len()
+    );
+    for (name, stats) in horizons.iter() {
+        text += &format!(
+            "{:<12} decay {} scale {} max target {}\n",
+            name,
+            stats.decay,
+            stats.scale,
+            stats.max_target()
+        );
     }
+    output!(options.format, text, {
+        let mut json = serde_json::json!({
+            "version": estimates.version,
+            "best_seen_height": estimates.best_seen_height,
+            "historical_first": estimates.historical_first,
+            "historical_best": estimates.historical_best,
+            "buckets": estimates.buckets,
+        });
+        for (name, stats) in horizons.iter() {
+            json[*name] = serde_json::json!({
+                "decay": stats.decay,
+                "scale": stats.scale,
+                "max_target": stats.max_target(),
+            });
+        }
+        json
+    });
     Ok(())
 }
 
-//spravit parse dat file util script a vyhodi to Vec<Block> + statistiky (hasmap toho, ze kolko nasiel blokov a magic numberov atd.)
+//runs until stopped, blocks of other networks are left out
+fn follow(options: &Options) -> Result<(), Failure> {
+    let file = options
+        .argument
+        .parse()
+        .map_err(|_| Failure::Invalid(format!("{} is no blk file number", options.argument)))?;
+    let blocks_dir = options.blocks_dir();
+    let mut follower = BlkFollower::from_position(&blocks_dir, BlkPosition { file, offset: 0 });
+    let result = follower.follow(Duration::from_secs(1), |event| {
+        match event {
+            FollowEvent::Block {
+                file,
+                offset,
+                chain,
+                block,
+            } if chain == options.network => {
+                let hash = block.header.block_hash();
+                let txs = block.transactions.len();
+                output!(
+                    options.format,
+                    format!(
+                        "{} {} {} {} transactions\n",
+                        blk_file_name(file),
+                        offset,
+                        hash,
+                        txs
+                    ),
+                    serde_json::json!({
+                        "file": blk_file_name(file),
+                        "offset": offset,
+                        "hash": hash,
+                        "nTx": txs,
+                    })
+                );
+            }
+            FollowEvent::Block { .. } => (),
+            FollowEvent::Corrupt { file, offset, size } => eprintln!(
+                "{} {}: corrupt record of {} bytes",
+                blk_file_name(file),
+                offset,
+                size.map(|size| size.to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            ),
+        }
+        true
+    });
+    result.map_err(|e| Failure::Io(blocks_dir, e))
+}
+
+fn run(options: &Options) -> Result<(), Failure> {
+    match options.command.as_str() {
+        "help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        "parse-blk" => parse_blk(options),
+        "show-block" => show_block(options),
+        "show-tx" => show_tx(options),
+        "decode-tx" => decode_tx(options),
+        "decode-header" => decode_header(options),
+        "verify" => verify(options),
+        "follow" => follow(options),
+        "mempool" => mempool(options),
+        "peers" => peers(options),
+        "fee-estimates" => fee_estimates(options),
+        command => Err(Failure::Usage(format!("unknown command {}", command))),
+    }
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|options| run(&options));
+    if let Err(failure) = result {
+        eprintln!("error: {}", failure);
+        if let Failure::Usage(_) = failure {
+            eprint!("\n{}", USAGE);
+        }
+        process::exit(failure.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &str) -> Result<Options, Failure> {
+        parse_args(s.split_whitespace().map(String::from))
+    }
 
-//implementni marker trait BE a LE, potom mozes implementovat from trait z jedneho na druhy a nasledne implementnut display trate pre to
+    fn genesis() -> &'static [u8] {
+        include_bytes!(
+            "test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        )
+    }
+
+    fn record(block: &[u8]) -> Vec<u8> {
+        [
+            &[0xf9, 0xbe, 0xb4, 0xd9][..],
+            &(block.len() as u32).to_le_bytes(),
+            block,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = args("--network=regtest show-block 0 --datadir /tmp/btc").unwrap();
+        assert_eq!(
+            options,
+            Options {
+                network: "regtest".to_string(),
+                datadir: PathBuf::from("/tmp/btc"),
+                txindex: None,
+                format: Format::Text,
+                command: "show-block".to_string(),
+                argument: "0".to_string(),
+            }
+        );
+        assert_eq!(options.blocks_dir(), Path::new("/tmp/btc/regtest/blocks"));
+        assert_eq!(args("verify x -h").unwrap().command, "help");
+        for invalid in [
+            "",
+            "verify",
+            "verify a b",
+            "verify a --network",
+            "verify a --network signet",
+            "verify a --format xml",
+            "verify a --verbose 1",
+        ] {
+            assert_eq!(args(invalid).unwrap_err().exit_code(), 2, "{}", invalid);
+        }
+        assert_eq!(
+            BlockQuery::parse("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")
+                .unwrap(),
+            BlockQuery::Hash(parse_block(genesis()).unwrap().1.header.block_hash())
+        );
+        assert_eq!(BlockQuery::parse("7").unwrap(), BlockQuery::Height(7));
+        assert!(BlockQuery::parse("-1").is_err());
+    }
+
+    #[test]
+    fn test_verify_blocks() {
+        let block = genesis();
+        let mut bad_merkle_root = block.to_vec();
+        bad_merkle_root[36] ^= 1;
+        let data = [
+            record(block),
+            vec![0; 4],
+            record(&bad_merkle_root),
+            record(&block[..100]),
+        ]
+        .concat();
+        let (bad, checked) = verify_blocks(&data);
+        assert_eq!(checked, 3);
+        assert_eq!(bad.len(), 2);
+        assert_eq!(bad[0].offset, 8 + block.len() + 4 + 8);
+        //the changed header changes the hash too
+        assert_eq!(
+            bad[0].problems,
+            vec!["merkle root mismatch", "insufficient proof of work"]
+        );
+        assert_eq!(bad[1].hash, None);
+        assert!(bad[1].problems[0].starts_with("does not parse"));
+        assert_eq!(verify_blocks(&record(block)), (Vec::new(), 1));
+        //the blocks after a corrupt record are still checked
+        let (bad, checked) = verify_blocks(include_bytes!("test_data/blk_corrupt_size.bin"));
+        assert_eq!(checked, 3);
+        assert_eq!(bad.len(), 1);
+        assert_eq!(bad[0].offset, 8 + block.len() + 8);
+    }
+
+    #[test]
+    fn test_blocks_dir() {
+        let datadir = env::temp_dir().join(format!("parse_bitcoin_cli_{}", std::process::id()));
+        let blocks_dir = datadir.join("blocks");
+        fs::create_dir_all(&blocks_dir).unwrap();
+        fs::write(blocks_dir.join("blk00000.dat"), record(genesis())).unwrap();
+        let options = |command: &str, argument: &str| Options {
+            network: "mainnet".to_string(),
+            datadir: datadir.clone(),
+            txindex: None,
+            format: Format::Text,
+            command: command.to_string(),
+            argument: argument.to_string(),
+        };
+
+        let mut stats = BlkStats::default();
+        for path in blk_paths(&blocks_dir).unwrap() {
+            stats.add_file(&read(&path).unwrap());
+        }
+        assert_eq!(stats.blocks.blocks, 1);
+        assert_eq!(stats.blocks.txs, 1);
+        assert_eq!(stats.blocks.script_types.get("pubkey"), Some(&1));
+        assert_eq!(stats.chains.get("mainnet"), Some(&1));
+        assert!(stats.text().contains("\nscript types  pubkey 1\n"));
+
+        let hash = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let options = options("show-block", hash);
+        let (block, height) = find_block(&options, BlockQuery::parse(hash).unwrap()).unwrap();
+        assert_eq!(
+            (block.header.block_hash().to_string().as_str(), height),
+            (hash, None)
+        );
+        let (block, height) = find_block(&options, BlockQuery::Height(0)).unwrap();
+        assert_eq!(height, Some(0));
+        assert!(block_text(&block, height).contains("\nheight        0\n"));
+        assert!(block_text(&block, height).contains("\nweight        1140\n"));
+        assert_eq!(
+            find_block(&options, BlockQuery::Height(1))
+                .unwrap_err()
+                .exit_code(),
+            1
+        );
+
+        let txid = block.transactions[0].txid();
+        let (tx, block_hash) = find_transaction(&options, txid).unwrap();
+        assert_eq!((tx.txid(), block_hash), (txid, block.header.block_hash()));
+        assert!(tx_text(&tx, "mainnet").contains("\n  0 50.00000000 pubkey\n"));
+        let regtest = Options {
+            network: "regtest".to_string(),
+            ..options
+        };
+        assert_eq!(find_transaction(&regtest, txid).unwrap_err().exit_code(), 3);
+
+        fs::remove_dir_all(&datadir).unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+//the fee rate buckets of one horizon of the estimator, fee rates are in satoshis per 1000 virtual bytes
+#[derive(Debug, PartialEq, Clone)]
+pub struct TxConfirmStats {
+    pub decay: f64,
+    //the number of blocks in a period
+    pub scale: u32,
+    //per bucket, the moving average of the fee rates and of the number of transactions
+    pub fee_rate_avg: Vec<f64>,
+    pub tx_count_avg: Vec<f64>,
+    //per period and bucket, the transactions confirmed within the period and those that failed to
+    pub confirmed_avg: Vec<Vec<f64>>,
+    pub failed_avg: Vec<Vec<f64>>,
+}
+
+impl TxConfirmStats {
+    //the largest confirmation target in blocks the horizon tracks
+    pub fn max_target(&self) -> u32 {
+        self.confirmed_avg.len() as u32 * self.scale
+    }
+}
+
+//the state of the fee estimator as bitcoind writes it to fee_estimates.dat
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeeEstimates {
+    //the lowest client version able to read the file and the version that wrote it
+    pub version_required: i32,
+    pub version: i32,
+    pub best_seen_height: u32,
+    //the blocks the estimates were made over
+    pub historical_first: u32,
+    pub historical_best: u32,
+    //the upper fee rate bounds of the buckets
+    pub buckets: Vec<f64>,
+    pub medium: TxConfirmStats,
+    pub short: TxConfirmStats,
+    pub long: TxConfirmStats,
+}
@@ -0,0 +1,48 @@
+use crate::types::{Hash256, Transaction};
+
+//mempool.dat of bitcoind before 28.0, and of later versions run with -persistmempoolv1
+pub const MEMPOOL_DUMP_VERSION_NO_XOR_KEY: u64 = 1;
+//the rest of the file after the key is xored with it
+pub const MEMPOOL_DUMP_VERSION: u64 = 2;
+
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub transaction: Transaction,
+    //when the transaction entered the mempool, in seconds since the epoch
+    pub time: i64,
+    //the fee prioritisetransaction added, in satoshis
+    pub fee_delta: i64,
+}
+
+//the mempool as bitcoind writes it to mempool.dat on shutdown
+#[derive(Debug, Clone)]
+pub struct MempoolDump {
+    pub version: u64,
+    pub xor_key: Option<Vec<u8>>,
+    pub entries: Vec<MempoolEntry>,
+    //fee deltas of transactions that are not in the mempool
+    pub deltas: Vec<(Hash256, i64)>,
+    //the transactions not yet seen announced back by a peer, missing in files of bitcoind before 0.21
+    pub unbroadcast: Vec<Hash256>,
+}
+
+impl MempoolDump {
+    pub fn entry(&self, txid: &Hash256) -> Option<&MempoolEntry> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.transaction.txid.0 == txid)
+    }
+
+    pub fn total_vsize(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.transaction.vsize())
+            .sum()
+    }
+
+    //the span of the entry times, None for an empty mempool
+    pub fn time_range(&self) -> Option<(i64, i64)> {
+        let times = self.entries.iter().map(|entry| entry.time);
+        Some((times.clone().min()?, times.max()?))
+    }
+}
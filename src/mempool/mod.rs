@@ -0,0 +1,6 @@
+mod mempool_dump;
+pub use self::mempool_dump::{
+    MempoolDump, MempoolEntry, MEMPOOL_DUMP_VERSION, MEMPOOL_DUMP_VERSION_NO_XOR_KEY,
+};
+mod fee_estimates;
+pub use self::fee_estimates::{FeeEstimates, TxConfirmStats};
@@ -0,0 +1,57 @@
+use crate::{p2p::AddrV2, types::Hash256};
+
+//the addrman formats of peers.dat, addresses are stored as in addrv2 messages from FORMAT_BIP155 on
+pub const ADDRMAN_FORMAT_DETERMINISTIC: u8 = 1;
+pub const ADDRMAN_FORMAT_ASMAP: u8 = 2;
+pub const ADDRMAN_FORMAT_BIP155: u8 = 3;
+pub const ADDRMAN_FORMAT_MULTIPORT: u8 = 4;
+//added to the lowest format able to read a file, for versions that did not know it
+pub const ADDRMAN_INCOMPATIBILITY_BASE: u8 = 32;
+
+pub const ADDRMAN_NEW_BUCKET_COUNT: usize = 1024;
+pub const ADDRMAN_TRIED_BUCKET_COUNT: usize = 256;
+pub const ADDRMAN_BUCKET_SIZE: usize = 64;
+
+//an address known to addrman, addresses of files before FORMAT_BIP155 are converted to IPv4 or IPv6
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddrInfo {
+    pub address: AddrV2,
+    //the network id and address of the peer that told about it
+    pub source: (u8, Vec<u8>),
+    //in seconds since the epoch, 0 if no connection ever succeeded
+    pub last_success: i64,
+    pub attempts: i32,
+}
+
+//the address manager as bitcoind writes it to peers.dat
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddrMan {
+    pub format: u8,
+    //the lowest format able to read the file, without the incompatibility base
+    pub lowest_compatible: u8,
+    pub key: Hash256,
+    pub new: Vec<AddrInfo>,
+    pub tried: Vec<AddrInfo>,
+    //the indices into new of the entries in each new bucket
+    pub new_buckets: Vec<Vec<i32>>,
+    //None before FORMAT_ASMAP
+    pub asmap_checksum: Option<Hash256>,
+}
+
+impl AddrMan {
+    pub fn len(&self) -> usize {
+        self.new.len() + self.tried.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //the tried and new addresses of a BIP155 network id
+    pub fn addresses(&self, network: u8) -> impl Iterator<Item = &AddrInfo> {
+        self.tried
+            .iter()
+            .chain(self.new.iter())
+            .filter(move |info| info.address.network == network)
+    }
+}
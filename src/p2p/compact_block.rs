@@ -0,0 +1,308 @@
+use crate::{
+    serializers::serialize_block_header,
+    types::{Block, BlockHeader, Hash256, Transaction},
+    utils::{calculate_merkle_root, sha256, siphash_2_4},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+};
+
+//short ids are the lower 6 bytes of the siphash
+const SHORT_ID_MASK: u64 = 0xffff_ffff_ffff;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompactBlockError {
+    //a transaction index beyond the block or beyond what fits in 16 bits
+    InvalidIndex,
+    //two transactions of the block have the same short id
+    ShortIdCollision,
+    //blocktxn for another block
+    WrongBlock,
+    //blocktxn did not have one transaction for every missing index
+    TransactionCount,
+    //a short id matched the wrong transaction of the mempool
+    MerkleRootMismatch,
+}
+
+impl std::fmt::Display for CompactBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CompactBlockError::InvalidIndex => write!(f, "invalid transaction index"),
+            CompactBlockError::ShortIdCollision => write!(f, "duplicate short transaction id"),
+            CompactBlockError::WrongBlock => write!(f, "transactions of another block"),
+            CompactBlockError::TransactionCount => {
+                write!(f, "wrong number of transactions to fill the block")
+            }
+            CompactBlockError::MerkleRootMismatch => {
+                write!(f, "reconstructed block does not match its merkle root")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompactBlockError {}
+
+//the siphash key of a compact block's short ids, from the sha256 of the header and nonce
+pub fn short_id_key(header: &BlockHeader, nonce: u64) -> (u64, u64) {
+    let hash = sha256(&[&serialize_block_header(header)[..], &nonce.to_le_bytes()].concat());
+    (
+        u64::from_le_bytes(hash.0[..8].try_into().unwrap()),
+        u64::from_le_bytes(hash.0[8..16].try_into().unwrap()),
+    )
+}
+
+pub fn short_id(key: (u64, u64), wtxid: &Hash256) -> u64 {
+    siphash_2_4(key.0, key.1, &wtxid.0) & SHORT_ID_MASK
+}
+
+//index is the position in the block, on the wire it is the difference to the previous index
+#[derive(Debug, Clone)]
+pub struct PrefilledTransaction {
+    pub index: usize,
+    pub tx: Transaction,
+}
+
+//a cmpctblock message, see BIP152
+//only version 2 is supported, where short ids are computed from wtxids
+#[derive(Debug, Clone)]
+pub struct CompactBlock {
+    pub header: BlockHeader,
+    pub nonce: u64,
+    pub short_ids: Vec<u64>,
+    pub prefilled: Vec<PrefilledTransaction>,
+}
+
+impl CompactBlock {
+    //prefills the coinbase like bitcoind does
+    pub fn new(block: &Block, nonce: u64) -> CompactBlock {
+        let key = short_id_key(&block.header, nonce);
+        CompactBlock {
+            header: block.header.clone(),
+            nonce,
+            short_ids: block
+                .transactions
+                .iter()
+                .skip(1)
+                .map(|tx| short_id(key, &tx.wtxid.0))
+                .collect(),
+            prefilled: block
+                .transactions
+                .iter()
+                .take(1)
+                .map(|tx| PrefilledTransaction {
+                    index: 0,
+                    tx: tx.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn key(&self) -> (u64, u64) {
+        short_id_key(&self.header, self.nonce)
+    }
+
+    pub fn transaction_count(&self) -> usize {
+        self.short_ids.len() + self.prefilled.len()
+    }
+
+    //places the prefilled transactions and the ones of the mempool matching a short id
+    //a short id matched by several mempool transactions is left missing
+    pub fn reconstruct<'a, I>(&self, mempool: I) -> Result<PartialBlock, CompactBlockError>
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        let count = self.transaction_count();
+        if count > usize::from(u16::MAX) + 1 {
+            return Err(CompactBlockError::InvalidIndex);
+        }
+        let mut transactions = vec![None; count];
+        for prefilled in self.prefilled.iter() {
+            match transactions.get_mut(prefilled.index) {
+                Some(tx @ None) => *tx = Some(prefilled.tx.clone()),
+                _ => return Err(CompactBlockError::InvalidIndex),
+            }
+        }
+        //the positions left for the short ids, in order
+        let mut positions = HashMap::new();
+        let mut free = (0..count).filter(|i| transactions[*i].is_none());
+        for short_id in self.short_ids.iter() {
+            let position = free.next().ok_or(CompactBlockError::InvalidIndex)?;
+            if positions.insert(*short_id, position).is_some() {
+                return Err(CompactBlockError::ShortIdCollision);
+            }
+        }
+        let key = self.key();
+        let mut collisions = HashSet::new();
+        for tx in mempool {
+            if let Some(position) = positions.get(&short_id(key, &tx.wtxid.0)) {
+                match &transactions[*position] {
+                    None => transactions[*position] = Some(tx.clone()),
+                    Some(found) if found.wtxid != tx.wtxid => {
+                        collisions.insert(*position);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for position in collisions {
+            transactions[position] = None;
+        }
+        Ok(PartialBlock {
+            header: self.header.clone(),
+            transactions,
+        })
+    }
+}
+
+//a getblocktxn message, indexes are absolute and differential on the wire like prefilled ones
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockTransactionsRequest {
+    pub block_hash: Hash256,
+    pub indexes: Vec<usize>,
+}
+
+//a blocktxn message, the transactions in the order they were requested
+#[derive(Debug, Clone)]
+pub struct BlockTransactions {
+    pub block_hash: Hash256,
+    pub transactions: Vec<Transaction>,
+}
+
+//a block reconstructed from a compact block, missing the transactions not found in the mempool
+#[derive(Debug)]
+pub struct PartialBlock {
+    pub header: BlockHeader,
+    pub transactions: Vec<Option<Transaction>>,
+}
+
+impl PartialBlock {
+    pub fn missing(&self) -> Vec<usize> {
+        (0..self.transactions.len())
+            .filter(|i| self.transactions[*i].is_none())
+            .collect()
+    }
+
+    //the getblocktxn asking for the missing transactions
+    pub fn request(&self) -> BlockTransactionsRequest {
+        BlockTransactionsRequest {
+            block_hash: self.header.hash.0,
+            indexes: self.missing(),
+        }
+    }
+
+    //fills the missing transactions with the ones of blocktxn and checks the merkle root
+    pub fn fill(self, block_transactions: BlockTransactions) -> Result<Block, CompactBlockError> {
+        if block_transactions.block_hash != self.header.hash.0 {
+            return Err(CompactBlockError::WrongBlock);
+        }
+        if block_transactions.transactions.len() != self.missing().len() {
+            return Err(CompactBlockError::TransactionCount);
+        }
+        let mut received = block_transactions.transactions.into_iter();
+        let transactions: Vec<Transaction> = self
+            .transactions
+            .into_iter()
+            .map(|tx| tx.or_else(|| received.next()).unwrap())
+            .collect();
+        let merkle_root = calculate_merkle_root(transactions.iter().map(|tx| tx.txid.0).collect());
+        if merkle_root != self.header.merkle_root_hash.0 {
+            return Err(CompactBlockError::MerkleRootMismatch);
+        }
+        Ok(Block::new(self.header, transactions))
+    }
+
+    //the block when nothing is missing
+    pub fn into_block(self) -> Result<Block, CompactBlockError> {
+        let block_transactions = BlockTransactions {
+            block_hash: self.header.hash.0,
+            transactions: Vec::new(),
+        };
+        self.fill(block_transactions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::{parse_block, parse_transaction};
+
+    #[test]
+    fn test_reconstruct() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let compact = CompactBlock::new(&block, 0x0123456789abcdef);
+        assert_eq!(compact.transaction_count(), 447);
+        assert_eq!(compact.prefilled[0].index, 0);
+        //computed independently with a python siphash
+        assert_eq!(compact.short_ids[0], 0x5161d39551c6);
+
+        //everything but two transactions is in the mempool, with an unrelated one
+        let tx = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, unrelated) = parse_transaction(tx).unwrap();
+        let mut mempool: Vec<&Transaction> = block
+            .transactions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 5 && *i != 300)
+            .map(|(_, tx)| tx)
+            .collect();
+        mempool.push(&unrelated);
+        let partial = compact.reconstruct(mempool.iter().copied()).unwrap();
+        assert_eq!(partial.missing(), vec![5, 300]);
+        let request = partial.request();
+        assert_eq!(request.block_hash, block.header.hash.0);
+
+        let response = |indexes: &[usize]| BlockTransactions {
+            block_hash: block.header.hash.0,
+            transactions: indexes
+                .iter()
+                .map(|i| block.transactions[*i].clone())
+                .collect(),
+        };
+        let filled = partial.fill(response(&request.indexes)).unwrap();
+        assert_eq!(filled.transactions.len(), 447);
+        assert_eq!(filled.transactions[300].txid, block.transactions[300].txid);
+
+        let partial = compact.reconstruct(mempool.iter().copied()).unwrap();
+        assert_eq!(
+            partial.fill(response(&[5])).unwrap_err(),
+            CompactBlockError::TransactionCount
+        );
+        let partial = compact.reconstruct(mempool.iter().copied()).unwrap();
+        assert_eq!(
+            partial.fill(response(&[300, 5])).unwrap_err(),
+            CompactBlockError::MerkleRootMismatch
+        );
+        let partial = compact.reconstruct(mempool.iter().copied()).unwrap();
+        let mut wrong = response(&[5, 300]);
+        wrong.block_hash = Hash256::default();
+        assert_eq!(
+            partial.fill(wrong).unwrap_err(),
+            CompactBlockError::WrongBlock
+        );
+
+        //the whole block from the mempool
+        let partial = compact.reconstruct(block.transactions.iter()).unwrap();
+        assert!(partial.missing().is_empty());
+        assert_eq!(partial.into_block().unwrap().transactions.len(), 447);
+
+        //prefilled transactions at a taken or too large index
+        let mut invalid = compact.clone();
+        invalid.prefilled[0].index = 447;
+        assert_eq!(
+            invalid.reconstruct(mempool.iter().copied()).unwrap_err(),
+            CompactBlockError::InvalidIndex
+        );
+        let mut invalid = compact.clone();
+        invalid.short_ids[1] = invalid.short_ids[0];
+        assert_eq!(
+            invalid.reconstruct(mempool.iter().copied()).unwrap_err(),
+            CompactBlockError::ShortIdCollision
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    p2p::{BlockTransactions, BlockTransactionsRequest, CompactBlock},
+    parsers::chain_name,
+    types::{Block, BlockHeader, Hash256, Transaction},
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+//inventory types, the witness flag asks for the serialization with witnesses
+pub const MSG_TX: u32 = 1;
+pub const MSG_BLOCK: u32 = 2;
+pub const MSG_FILTERED_BLOCK: u32 = 3;
+pub const MSG_CMPCT_BLOCK: u32 = 4;
+pub const MSG_WTX: u32 = 5;
+pub const MSG_WITNESS_FLAG: u32 = 1 << 30;
+pub const MSG_WITNESS_TX: u32 = MSG_TX | MSG_WITNESS_FLAG;
+pub const MSG_WITNESS_BLOCK: u32 = MSG_BLOCK | MSG_WITNESS_FLAG;
+
+//service bits of the version message and addresses
+pub const NODE_NETWORK: u64 = 1;
+pub const NODE_BLOOM: u64 = 1 << 2;
+pub const NODE_WITNESS: u64 = 1 << 3;
+pub const NODE_COMPACT_FILTERS: u64 = 1 << 6;
+pub const NODE_NETWORK_LIMITED: u64 = 1 << 10;
+
+//network ids of BIP155 addresses
+pub const NET_IPV4: u8 = 1;
+pub const NET_IPV6: u8 = 2;
+pub const NET_TORV2: u8 = 3;
+pub const NET_TORV3: u8 = 4;
+pub const NET_I2P: u8 = 5;
+pub const NET_CJDNS: u8 = 6;
+
+//an address as in version and addr messages, IPv4 addresses are mapped into IPv6
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NetAddress {
+    pub services: u64,
+    pub ip: [u8; 16],
+    pub port: u16,
+}
+
+impl NetAddress {
+    pub fn new(services: u64, ip: IpAddr, port: u16) -> NetAddress {
+        let ip = match ip {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+            IpAddr::V6(ip) => ip.octets(),
+        };
+        NetAddress { services, ip, port }
+    }
+    pub fn ip_addr(&self) -> IpAddr {
+        let ip = Ipv6Addr::from(self.ip);
+        match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(ip),
+        }
+    }
+}
+
+//an entry of an addrv2 message, see BIP155
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddrV2 {
+    pub time: u32,
+    pub services: u64,
+    pub network: u8,
+    pub addr: Vec<u8>,
+    pub port: u16,
+}
+
+impl AddrV2 {
+    //none for networks other than IPv4 and IPv6
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        match (self.network, self.addr.len()) {
+            (NET_IPV4, 4) => Some(IpAddr::V4(Ipv4Addr::new(
+                self.addr[0],
+                self.addr[1],
+                self.addr[2],
+                self.addr[3],
+            ))),
+            (NET_IPV6, 16) => {
+                let mut ip = [0; 16];
+                ip.copy_from_slice(&self.addr);
+                Some(IpAddr::V6(Ipv6Addr::from(ip)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VersionMessage {
+    pub version: i32,
+    pub services: u64,
+    pub timestamp: i64,
+    pub receiver: NetAddress,
+    pub sender: NetAddress,
+    pub nonce: u64,
+    pub user_agent: String,
+    pub start_height: i32,
+    //missing before protocol version 70001, which means true
+    pub relay: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Inventory {
+    pub inv_type: u32,
+    pub hash: Hash256,
+}
+
+impl Inventory {
+    pub fn new(inv_type: u32, hash: Hash256) -> Inventory {
+        Inventory { inv_type, hash }
+    }
+}
+
+//getheaders and getblocks, the locator lists block hashes from the tip backwards
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockLocator {
+    pub version: u32,
+    pub hashes: Vec<Hash256>,
+    //all zeros to get as many as possible
+    pub stop_hash: Hash256,
+}
+
+#[derive(Debug)]
+pub enum NetworkMessage {
+    Version(VersionMessage),
+    Verack,
+    Inv(Vec<Inventory>),
+    GetData(Vec<Inventory>),
+    NotFound(Vec<Inventory>),
+    GetBlocks(BlockLocator),
+    GetHeaders(BlockLocator),
+    Headers(Vec<BlockHeader>),
+    Block(Block),
+    Tx(Transaction),
+    //time and address
+    Addr(Vec<(u32, NetAddress)>),
+    AddrV2(Vec<AddrV2>),
+    GetAddr,
+    Ping(u64),
+    Pong(u64),
+    SendHeaders,
+    SendCmpct { announce: bool, version: u64 },
+    CmpctBlock(CompactBlock),
+    GetBlockTxn(BlockTransactionsRequest),
+    BlockTxn(BlockTransactions),
+    //the minimum fee rate in satoshis per 1000 virtual bytes of transactions to announce
+    FeeFilter(u64),
+    SendAddrV2,
+    WtxidRelay,
+    Mempool,
+    //commands not known here, the payload is kept as it is
+    Unknown { command: String, payload: Vec<u8> },
+}
+
+impl NetworkMessage {
+    pub fn command(&self) -> &str {
+        match self {
+            NetworkMessage::Version(_) => "version",
+            NetworkMessage::Verack => "verack",
+            NetworkMessage::Inv(_) => "inv",
+            NetworkMessage::GetData(_) => "getdata",
+            NetworkMessage::NotFound(_) => "notfound",
+            NetworkMessage::GetBlocks(_) => "getblocks",
+            NetworkMessage::GetHeaders(_) => "getheaders",
+            NetworkMessage::Headers(_) => "headers",
+            NetworkMessage::Block(_) => "block",
+            NetworkMessage::Tx(_) => "tx",
+            NetworkMessage::Addr(_) => "addr",
+            NetworkMessage::AddrV2(_) => "addrv2",
+            NetworkMessage::GetAddr => "getaddr",
+            NetworkMessage::Ping(_) => "ping",
+            NetworkMessage::Pong(_) => "pong",
+            NetworkMessage::SendHeaders => "sendheaders",
+            NetworkMessage::SendCmpct { .. } => "sendcmpct",
+            NetworkMessage::CmpctBlock(_) => "cmpctblock",
+            NetworkMessage::GetBlockTxn(_) => "getblocktxn",
+            NetworkMessage::BlockTxn(_) => "blocktxn",
+            NetworkMessage::FeeFilter(_) => "feefilter",
+            NetworkMessage::SendAddrV2 => "sendaddrv2",
+            NetworkMessage::WtxidRelay => "wtxidrelay",
+            NetworkMessage::Mempool => "mempool",
+            NetworkMessage::Unknown { command, .. } => command,
+        }
+    }
+}
+
+//the envelope in front of every message
+#[derive(Debug, PartialEq, Clone)]
+pub struct MessageHeader {
+    pub magic: u32,
+    pub command: String,
+    pub length: u32,
+    //the first four bytes of the payload's hash256
+    pub checksum: [u8; 4],
+}
+
+//a message with its envelope
+#[derive(Debug)]
+pub struct Message {
+    pub magic: u32,
+    pub payload: NetworkMessage,
+}
+
+impl Message {
+    pub fn new(magic: u32, payload: NetworkMessage) -> Message {
+        Message { magic, payload }
+    }
+    //the chain the magic number belongs to, as named by parse_magic_number
+    pub fn chain(&self) -> Option<&'static str> {
+        chain_name(self.magic)
+    }
+    pub fn command(&self) -> &str {
+        self.payload.command()
+    }
+}
@@ -0,0 +1,20 @@
+mod message;
+pub use self::message::{
+    AddrV2, BlockLocator, Inventory, Message, MessageHeader, NetAddress, NetworkMessage,
+    VersionMessage, MSG_BLOCK, MSG_CMPCT_BLOCK, MSG_FILTERED_BLOCK, MSG_TX, MSG_WITNESS_BLOCK,
+    MSG_WITNESS_FLAG, MSG_WITNESS_TX, MSG_WTX, NET_CJDNS, NET_I2P, NET_IPV4, NET_IPV6, NET_TORV2,
+    NET_TORV3, NODE_BLOOM, NODE_COMPACT_FILTERS, NODE_NETWORK, NODE_NETWORK_LIMITED, NODE_WITNESS,
+};
+mod peer;
+pub use self::peer::{Peer, PROTOCOL_VERSION};
+mod compact_block;
+pub use self::compact_block::{
+    short_id, short_id_key, BlockTransactions, BlockTransactionsRequest, CompactBlock,
+    CompactBlockError, PartialBlock, PrefilledTransaction,
+};
+mod addrman;
+pub use self::addrman::{
+    AddrInfo, AddrMan, ADDRMAN_BUCKET_SIZE, ADDRMAN_FORMAT_ASMAP, ADDRMAN_FORMAT_BIP155,
+    ADDRMAN_FORMAT_DETERMINISTIC, ADDRMAN_FORMAT_MULTIPORT, ADDRMAN_INCOMPATIBILITY_BASE,
+    ADDRMAN_NEW_BUCKET_COUNT, ADDRMAN_TRIED_BUCKET_COUNT,
+};
@@ -0,0 +1,325 @@
+use crate::{
+    p2p::{
+        BlockLocator, Inventory, Message, NetAddress, NetworkMessage, VersionMessage,
+        MSG_WITNESS_BLOCK, NODE_WITNESS,
+    },
+    parsers::{chain_magic, parse_message},
+    serializers::serialize_message,
+    types::{Block, BlockHeader, Hash256},
+};
+use nom::Err;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv6Addr, TcpStream, ToSocketAddrs},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub const PROTOCOL_VERSION: i32 = 70016;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+//a synchronous connection to a node, the messages of other chains are rejected
+pub struct Peer<S: Read + Write> {
+    stream: S,
+    magic: u32,
+    //received bytes not parsed yet
+    buffer: Vec<u8>,
+}
+
+impl Peer<TcpStream> {
+    //chain as named by parse_magic_number
+    pub fn connect<A: ToSocketAddrs>(addr: A, chain: &str) -> io::Result<Peer<TcpStream>> {
+        let magic = chain_magic(chain).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown chain {}", chain),
+            )
+        })?;
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+        Ok(Peer::new(stream, magic))
+    }
+}
+
+impl<S: Read + Write> Peer<S> {
+    pub fn new(stream: S, magic: u32) -> Peer<S> {
+        Peer {
+            stream,
+            magic,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn send(&mut self, payload: NetworkMessage) -> io::Result<()> {
+        self.stream
+            .write_all(&serialize_message(&Message::new(self.magic, payload)))?;
+        self.stream.flush()
+    }
+
+    //blocks until a whole message arrived
+    pub fn receive(&mut self) -> io::Result<NetworkMessage> {
+        loop {
+            match parse_message(&self.buffer) {
+                Ok((rest, message)) => {
+                    let consumed = self.buffer.len() - rest.len();
+                    self.buffer.drain(..consumed);
+                    if message.magic != self.magic {
+                        return Err(invalid_data("message of another chain"));
+                    }
+                    return Ok(message.payload);
+                }
+                Err(Err::Incomplete(_)) => {}
+                Err(_) => return Err(invalid_data("invalid message")),
+            }
+            let mut data = [0; 65536];
+            let read = self.stream.read(&mut data)?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                ));
+            }
+            self.buffer.extend(&data[..read]);
+        }
+    }
+
+    //receives until f returns something, pings are answered and other messages skipped
+    fn receive_until<T, F>(&mut self, mut f: F) -> io::Result<T>
+    where
+        F: FnMut(NetworkMessage) -> io::Result<Option<T>>,
+    {
+        loop {
+            match self.receive()? {
+                NetworkMessage::Ping(nonce) => self.send(NetworkMessage::Pong(nonce))?,
+                message => {
+                    if let Some(result) = f(message)? {
+                        return Ok(result);
+                    }
+                }
+            }
+        }
+    }
+
+    //exchanges version and verack, returns the version message of the peer
+    pub fn handshake(&mut self, start_height: i32, user_agent: &str) -> io::Result<VersionMessage> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let unspecified = NetAddress::new(0, IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0);
+        self.send(NetworkMessage::Version(VersionMessage {
+            version: PROTOCOL_VERSION,
+            services: NODE_WITNESS,
+            timestamp: now.as_secs() as i64,
+            receiver: unspecified,
+            sender: unspecified,
+            nonce: now.as_nanos() as u64,
+            user_agent: user_agent.to_string(),
+            start_height,
+            relay: false,
+        }))?;
+        let mut version = None;
+        let mut verack = false;
+        loop {
+            match self.receive()? {
+                NetworkMessage::Version(v) => {
+                    if version.is_some() {
+                        return Err(invalid_data("duplicate version message"));
+                    }
+                    self.send(NetworkMessage::Verack)?;
+                    version = Some(v);
+                }
+                NetworkMessage::Verack => verack = true,
+                NetworkMessage::Ping(nonce) => self.send(NetworkMessage::Pong(nonce))?,
+                //feature negotiation like wtxidrelay and sendaddrv2 comes before verack
+                _ => {}
+            }
+            if let (Some(version), true) = (&version, verack) {
+                return Ok(version.clone());
+            }
+        }
+    }
+
+    //the headers following the first locator hash the peer knows, up to stop_hash or 2000
+    pub fn get_headers(
+        &mut self,
+        locator: Vec<Hash256>,
+        stop_hash: Hash256,
+    ) -> io::Result<Vec<BlockHeader>> {
+        self.send(NetworkMessage::GetHeaders(BlockLocator {
+            version: PROTOCOL_VERSION as u32,
+            hashes: locator,
+            stop_hash,
+        }))?;
+        self.receive_until(|message| match message {
+            NetworkMessage::Headers(headers) => Ok(Some(headers)),
+            _ => Ok(None),
+        })
+    }
+
+    //the blocks with their witnesses in the order of hashes, which must not repeat a hash
+    pub fn get_blocks(&mut self, hashes: &[Hash256]) -> io::Result<Vec<Block>> {
+        let mut unique = HashSet::new();
+        if !hashes.iter().all(|hash| unique.insert(hash)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "duplicate block hash",
+            ));
+        }
+        self.send(NetworkMessage::GetData(
+            hashes
+                .iter()
+                .map(|hash| Inventory::new(MSG_WITNESS_BLOCK, *hash))
+                .collect(),
+        ))?;
+        let mut blocks = HashMap::new();
+        while blocks.len() < hashes.len() {
+            let block = self.receive_until(|message| match message {
+                NetworkMessage::Block(block) => Ok(Some(block)),
+                NetworkMessage::NotFound(inventories) => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "blocks not found: {:?}",
+                        inventories.iter().map(|i| i.hash).collect::<Vec<_>>()
+                    ),
+                )),
+                _ => Ok(None),
+            })?;
+            if hashes.contains(&block.header.hash.0) {
+                blocks.insert(block.header.hash.0, block);
+            }
+        }
+        Ok(hashes
+            .iter()
+            .filter_map(|hash| blocks.remove(hash))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+    use std::{net::TcpListener, thread};
+
+    const REGTEST: u32 = 0xDAB5BFFA;
+
+    fn genesis() -> Block {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        parse_block(data).unwrap().1
+    }
+
+    //answers like a node that only has the genesis block
+    fn mock_peer(stream: TcpStream) -> io::Result<()> {
+        let mut peer = Peer::new(stream, REGTEST);
+        let version = match peer.receive()? {
+            NetworkMessage::Version(version) => version,
+            m => panic!("unexpected {:?}", m),
+        };
+        assert_eq!(version.user_agent, "/test/");
+        assert!(!version.relay);
+        peer.send(NetworkMessage::Version(VersionMessage {
+            user_agent: "/mock/".to_string(),
+            start_height: 0,
+            ..version
+        }))?;
+        peer.send(NetworkMessage::WtxidRelay)?;
+        peer.send(NetworkMessage::Verack)?;
+        loop {
+            let message = match peer.receive() {
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                message => message?,
+            };
+            match message {
+                NetworkMessage::Verack => peer.send(NetworkMessage::Ping(7))?,
+                NetworkMessage::Pong(nonce) => assert_eq!(nonce, 7),
+                NetworkMessage::GetHeaders(locator) => {
+                    let headers = if locator.hashes.contains(&genesis().header.hash.0) {
+                        Vec::new()
+                    } else {
+                        vec![genesis().header]
+                    };
+                    peer.send(NetworkMessage::Headers(headers))?;
+                }
+                NetworkMessage::GetData(inventories) => {
+                    for inventory in inventories {
+                        assert_eq!(inventory.inv_type, MSG_WITNESS_BLOCK);
+                        if inventory.hash == genesis().header.hash.0 {
+                            peer.send(NetworkMessage::Block(genesis()))?;
+                        } else {
+                            peer.send(NetworkMessage::NotFound(vec![inventory]))?;
+                        }
+                    }
+                }
+                m => panic!("unexpected {:?}", m),
+            }
+        }
+    }
+
+    #[test]
+    fn test_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mock = thread::spawn(move || mock_peer(listener.accept().unwrap().0));
+
+        assert!(Peer::connect(addr, "litecoin").is_err());
+        let mut peer = Peer::connect(addr, "regtest").unwrap();
+        let version = peer.handshake(0, "/test/").unwrap();
+        assert_eq!(version.user_agent, "/mock/");
+
+        let genesis = genesis();
+        let headers = peer
+            .get_headers(vec![Hash256([1; 32])], Hash256::default())
+            .unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].hash, genesis.header.hash);
+        let headers = peer
+            .get_headers(vec![genesis.header.hash.0], Hash256::default())
+            .unwrap();
+        assert!(headers.is_empty());
+
+        let blocks = peer.get_blocks(&[genesis.header.hash.0]).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].header.hash, genesis.header.hash);
+        assert_eq!(blocks[0].transactions[0].txid, genesis.transactions[0].txid);
+        let error = peer.get_blocks(&[Hash256([1; 32])]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        //nothing is asked for, the next request gets its own answer
+        let error = peer
+            .get_blocks(&[genesis.header.hash.0, genesis.header.hash.0])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(peer.get_blocks(&[genesis.header.hash.0]).unwrap().len(), 1);
+
+        drop(peer);
+        mock.join().unwrap().unwrap();
+    }
+
+    //needs a regtest bitcoind, e.g. PARSE_BITCOIN_REGTEST=127.0.0.1:18444
+    #[test]
+    #[ignore]
+    fn test_peer_regtest() {
+        let addr = std::env::var("PARSE_BITCOIN_REGTEST").unwrap();
+        let mut peer = Peer::connect(addr, "regtest").unwrap();
+        let version = peer.handshake(0, "/parse_bitcoin/").unwrap();
+        let mut genesis = [0; 32];
+        hex::decode_to_slice(
+            "06226e46111a0b59caaf126043eb5bbf28c34f3a5e332a1fc7b2b73cf188910f",
+            &mut genesis,
+        )
+        .unwrap();
+        let headers = peer
+            .get_headers(vec![Hash256(genesis)], Hash256::default())
+            .unwrap();
+        assert_eq!(headers.len(), version.start_height.min(2000) as usize);
+        let hashes: Vec<_> = headers.iter().take(10).map(|h| h.hash.0).collect();
+        let blocks = peer.get_blocks(&hashes).unwrap();
+        for (block, hash) in blocks.iter().zip(hashes.iter()) {
+            assert_eq!(block.header.hash.0, *hash);
+        }
+    }
+}
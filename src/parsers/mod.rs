@@ -1,5 +1,5 @@
 mod parse_magic_number;
-pub use self::parse_magic_number::parse_magic_number;
+pub use self::parse_magic_number::{chain_magic, chain_name, parse_magic_number, MAGIC_NUMBERS};
 mod parse_block_header;
 pub use self::parse_block_header::parse_block_header;
 mod parse_var_int;
@@ -14,3 +14,32 @@ mod parse_transaction;
 pub use self::parse_transaction::parse_transaction;
 mod parse_block;
 pub use self::parse_block::parse_block;
+mod parse_instruction;
+pub use self::parse_instruction::parse_instruction;
+mod parse_b128_var_int;
+pub use self::parse_b128_var_int::parse_b128_var_int;
+mod parse_undo_coin;
+pub use self::parse_undo_coin::{decompress_amount, parse_undo_coin};
+mod parse_block_undo;
+pub use self::parse_block_undo::parse_block_undo;
+mod parse_compact_block;
+pub use self::parse_compact_block::{
+    parse_block_transactions, parse_block_transactions_request, parse_compact_block,
+};
+mod parse_message;
+pub use self::parse_message::{
+    parse_addr_v2, parse_inventory, parse_message, parse_message_header, parse_messages,
+    parse_net_address, parse_payload,
+};
+mod parse_psbt;
+pub use self::parse_psbt::{
+    parse_key_source, parse_psbt, parse_psbt_output_value, parse_tap_key_source, parse_tap_tree,
+};
+mod parse_columnar;
+pub use self::parse_columnar::parse_columnar;
+mod parse_mempool;
+pub use self::parse_mempool::parse_mempool;
+mod parse_addrman;
+pub use self::parse_addrman::{parse_addrman, parse_anchors, parse_peers};
+mod parse_fee_estimates;
+pub use self::parse_fee_estimates::parse_fee_estimates;
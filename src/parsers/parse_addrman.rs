@@ -0,0 +1,300 @@
+use crate::{
+    p2p::{
+        AddrInfo, AddrMan, AddrV2, ADDRMAN_BUCKET_SIZE, ADDRMAN_FORMAT_ASMAP,
+        ADDRMAN_FORMAT_BIP155, ADDRMAN_FORMAT_DETERMINISTIC, ADDRMAN_FORMAT_MULTIPORT,
+        ADDRMAN_INCOMPATIBILITY_BASE, ADDRMAN_NEW_BUCKET_COUNT, ADDRMAN_TRIED_BUCKET_COUNT,
+        NET_IPV4, NET_IPV6,
+    },
+    parsers::{parse_addr_v2, parse_magic_number, parse_var_int},
+    types::Hash256,
+    utils::hash256,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::map,
+    error::{ErrorKind, ParseError},
+    multi::count,
+    number::complete::{be_u16, le_i32, le_i64, le_u32, le_u64, le_u8},
+    sequence::tuple,
+    Err, IResult,
+};
+
+//the version in front of every address on disk, its low bits are the client version that wrote it
+const DISK_VERSION_ADDRV2: u32 = 1 << 29;
+const DISK_VERSION_IGNORE_MASK: u32 = (1 << 19) - 1;
+const MAX_ADDRV2_SIZE: u64 = 512;
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+fn parse_hash(input: &[u8]) -> IResult<&[u8], Hash256> {
+    map(take(32u8), Hash256::new)(input)
+}
+
+//the magic number of the network, the data and the hash256 of both, as in peers.dat and anchors.dat
+fn parse_hashed_file<'a, O, F>(input: &'a [u8], parser: F) -> IResult<&'a [u8], (&'static str, O)>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    let (i, chain) = parse_magic_number(input)?;
+    let chain = match chain {
+        Some(chain) => chain,
+        None => return Err(error(input, ErrorKind::Tag)),
+    };
+    let (i, data) = parser(i)?;
+    let hashed = &input[..input.len() - i.len()];
+    let (i, checksum) = take(32u8)(i)?;
+    if hash256(hashed).0[..] != checksum[..] {
+        return Err(error(input, ErrorKind::Verify));
+    }
+    Ok((i, (chain, data)))
+}
+
+//IPv4 addresses are mapped into IPv6 in the format before BIP155
+fn from_v1(ip: &[u8]) -> (u8, Vec<u8>) {
+    match ip[..12] == [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff] {
+        true => (NET_IPV4, ip[12..].to_vec()),
+        false => (NET_IPV6, ip.to_vec()),
+    }
+}
+
+//a network id and address, as 16 bytes before BIP155
+fn parse_net_addr(bip155: bool) -> impl Fn(&[u8]) -> IResult<&[u8], (u8, Vec<u8>)> {
+    move |input: &[u8]| {
+        if !bip155 {
+            return map(take(16u8), from_v1)(input);
+        }
+        let (i, (network, size)) = tuple((le_u8, parse_var_int))(input)?;
+        if size > MAX_ADDRV2_SIZE {
+            return Err(error(input, ErrorKind::TooLarge));
+        }
+        let (i, addr) = take(size)(i)?;
+        Ok((i, (network, addr.to_vec())))
+    }
+}
+
+//an address as stored on disk, the version in front of it tells whether it is in the addrv2 format
+fn parse_disk_address(input: &[u8]) -> IResult<&[u8], AddrV2> {
+    let (i, version) = le_u32(input)?;
+    match version & !DISK_VERSION_IGNORE_MASK {
+        0 => {
+            let (i, (time, services, ip, port)) = tuple((le_u32, le_u64, take(16u8), be_u16))(i)?;
+            let (network, addr) = from_v1(ip);
+            let address = AddrV2 {
+                time,
+                services,
+                network,
+                addr,
+                port,
+            };
+            Ok((i, address))
+        }
+        DISK_VERSION_ADDRV2 => parse_addr_v2(i),
+        _ => Err(error(input, ErrorKind::Tag)),
+    }
+}
+
+fn parse_addr_info(bip155: bool) -> impl Fn(&[u8]) -> IResult<&[u8], AddrInfo> {
+    move |input: &[u8]| {
+        let (i, (address, source, last_success, attempts)) =
+            tuple((parse_disk_address, parse_net_addr(bip155), le_i64, le_i32))(input)?;
+        let info = AddrInfo {
+            address,
+            source,
+            last_success,
+            attempts,
+        };
+        Ok((i, info))
+    }
+}
+
+//the indices of a new bucket, a negative count is an empty bucket like in bitcoind
+fn parse_bucket(input: &[u8]) -> IResult<&[u8], Vec<i32>> {
+    let (mut i, size) = le_i32(input)?;
+    let mut bucket = Vec::new();
+    for _ in 0..size {
+        let (rest, index) = le_i32(i)?;
+        bucket.push(index);
+        i = rest;
+    }
+    Ok((i, bucket))
+}
+
+//the address manager, files of formats newer than ADDRMAN_FORMAT_MULTIPORT are read if they say they are compatible
+pub fn parse_addrman(input: &[u8]) -> IResult<&[u8], AddrMan> {
+    let (i, (format, compatible)) = tuple((le_u8, le_u8))(input)?;
+    let lowest_compatible = match compatible.checked_sub(ADDRMAN_INCOMPATIBILITY_BASE) {
+        Some(lowest) if lowest <= ADDRMAN_FORMAT_MULTIPORT => lowest,
+        _ => return Err(error(input, ErrorKind::Verify)),
+    };
+    let (i, (key, new_count, tried_count, mut bucket_count)) =
+        tuple((parse_hash, le_i32, le_i32, le_i32))(i)?;
+    if format >= ADDRMAN_FORMAT_DETERMINISTIC {
+        bucket_count ^= 1 << 30;
+    }
+    let max_new = (ADDRMAN_NEW_BUCKET_COUNT * ADDRMAN_BUCKET_SIZE) as i32;
+    let max_tried = (ADDRMAN_TRIED_BUCKET_COUNT * ADDRMAN_BUCKET_SIZE) as i32;
+    if !(0..=max_new).contains(&new_count) || !(0..=max_tried).contains(&tried_count) {
+        return Err(error(input, ErrorKind::TooLarge));
+    }
+    let bip155 = format >= ADDRMAN_FORMAT_BIP155;
+    let (i, new) = count(parse_addr_info(bip155), new_count as usize)(i)?;
+    let (mut i, tried) = count(parse_addr_info(bip155), tried_count as usize)(i)?;
+    let mut new_buckets = Vec::new();
+    for _ in 0..bucket_count {
+        let (rest, bucket) = parse_bucket(i)?;
+        new_buckets.push(bucket);
+        i = rest;
+    }
+    let (i, asmap_checksum) = match format >= ADDRMAN_FORMAT_ASMAP {
+        true => map(parse_hash, Some)(i)?,
+        false => (i, None),
+    };
+    let addrman = AddrMan {
+        format,
+        lowest_compatible,
+        key,
+        new,
+        tried,
+        new_buckets,
+        asmap_checksum,
+    };
+    Ok((i, addrman))
+}
+
+//peers.dat, with the name of the network it belongs to
+pub fn parse_peers(input: &[u8]) -> IResult<&[u8], (&'static str, AddrMan)> {
+    parse_hashed_file(input, parse_addrman)
+}
+
+//anchors.dat, the addresses of the block relay only peers bitcoind connects to first after a restart
+pub fn parse_anchors(input: &[u8]) -> IResult<&[u8], (&'static str, Vec<AddrV2>)> {
+    parse_hashed_file(input, |input| {
+        let (mut i, n) = parse_var_int(input)?;
+        let mut anchors = Vec::new();
+        for _ in 0..n {
+            let (rest, address) = parse_disk_address(i)?;
+            anchors.push(address);
+            i = rest;
+        }
+        Ok((i, anchors))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::p2p::NET_TORV3;
+
+    fn hashed_file(data: &[u8]) -> Vec<u8> {
+        let data = [&[0xf9, 0xbe, 0xb4, 0xd9][..], data].concat();
+        [&data[..], &hash256(&data).0[..]].concat()
+    }
+
+    //an IPv4 address in the addrv2 format, or mapped into IPv6 before it
+    fn address(bip155: bool, ip: [u8; 4], port: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        match bip155 {
+            true => {
+                data.extend_from_slice(&(DISK_VERSION_ADDRV2 | 270000).to_le_bytes());
+                data.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+                data.push(9);
+                data.extend_from_slice(&[NET_IPV4, 4]);
+            }
+            false => {
+                data.extend_from_slice(&220000u32.to_le_bytes());
+                data.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+                data.extend_from_slice(&9u64.to_le_bytes());
+                data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff]);
+            }
+        }
+        data.extend_from_slice(&ip);
+        data.extend_from_slice(&port.to_be_bytes());
+        data
+    }
+
+    fn addrman_data(format: u8) -> Vec<u8> {
+        let bip155 = format >= ADDRMAN_FORMAT_BIP155;
+        let mut data = vec![format, ADDRMAN_INCOMPATIBILITY_BASE + format.min(3)];
+        data.extend_from_slice(&[0x33; 32]);
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&(1024i32 ^ (1 << 30)).to_le_bytes());
+        for (ip, attempts) in [([1, 2, 3, 4], 3i32), ([5, 6, 7, 8], 0)].iter() {
+            data.extend(address(bip155, *ip, 8333));
+            match bip155 {
+                true => data.extend_from_slice(&[NET_IPV4, 4, 9, 9, 9, 9]),
+                false => data.extend_from_slice(&[0xfe; 16]),
+            }
+            data.extend_from_slice(&0i64.to_le_bytes());
+            data.extend_from_slice(&attempts.to_le_bytes());
+        }
+        //the new address is in the first bucket
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&[0; 4 * 1023]);
+        data.extend_from_slice(&[0x44; 32]);
+        data
+    }
+
+    #[test]
+    fn test_parse_peers() {
+        let data = hashed_file(&addrman_data(ADDRMAN_FORMAT_MULTIPORT));
+        let (rest, (chain, addrman)) = parse_peers(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(chain, "mainnet");
+        assert_eq!(addrman.format, ADDRMAN_FORMAT_MULTIPORT);
+        assert_eq!(addrman.lowest_compatible, ADDRMAN_FORMAT_BIP155);
+        assert_eq!(addrman.len(), 2);
+        let new = &addrman.new[0];
+        assert_eq!(new.address.addr, vec![1, 2, 3, 4]);
+        assert_eq!(new.address.port, 8333);
+        assert_eq!(new.address.services, 9);
+        assert_eq!(new.source, (NET_IPV4, vec![9, 9, 9, 9]));
+        assert_eq!(new.attempts, 3);
+        assert_eq!(addrman.tried[0].address.addr, vec![5, 6, 7, 8]);
+        assert_eq!(addrman.new_buckets.len(), 1024);
+        assert_eq!(addrman.new_buckets[0], vec![0]);
+        assert_eq!(addrman.asmap_checksum, Some(Hash256::new(&[0x44; 32])));
+        assert_eq!(addrman.addresses(NET_IPV4).count(), 2);
+        assert_eq!(addrman.addresses(NET_TORV3).count(), 0);
+
+        //IPv4 addresses mapped into IPv6 are IPv4 addresses
+        let data = hashed_file(&addrman_data(ADDRMAN_FORMAT_ASMAP));
+        let (_, (_, old)) = parse_peers(&data).unwrap();
+        assert_eq!(old.new[0].address, new.address);
+        assert_eq!(old.new[0].source, (NET_IPV6, vec![0xfe; 16]));
+
+        let mut corrupt = data.clone();
+        corrupt[100] ^= 1;
+        assert!(parse_peers(&corrupt).is_err());
+        assert!(parse_peers(&data[..data.len() - 1]).is_err());
+        //a format that is not compatible with any known one
+        let mut newer = addrman_data(ADDRMAN_FORMAT_MULTIPORT);
+        newer[1] = ADDRMAN_INCOMPATIBILITY_BASE + ADDRMAN_FORMAT_MULTIPORT + 1;
+        assert!(parse_peers(&hashed_file(&newer)).is_err());
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let data = [
+            vec![2],
+            address(true, [1, 2, 3, 4], 8333),
+            address(false, [5, 6, 7, 8], 8334),
+        ]
+        .concat();
+        let file = hashed_file(&data);
+        let (rest, (chain, anchors)) = parse_anchors(&file).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(chain, "mainnet");
+        assert_eq!(anchors.len(), 2);
+        assert_eq!(anchors[1].network, NET_IPV4);
+        assert_eq!(anchors[1].addr, vec![5, 6, 7, 8]);
+        assert_eq!(anchors[1].port, 8334);
+        //an unknown network magic
+        let mut other = file.clone();
+        other[0] = 0;
+        assert!(parse_anchors(&other).is_err());
+    }
+}
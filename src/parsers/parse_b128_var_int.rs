@@ -0,0 +1,66 @@
+use nom::{
+    error::{ErrorKind, ParseError},
+    number::complete::le_u8,
+    Err, IResult,
+};
+
+//bitcoind's VARINT as used in undo data and the chainstate, not to be mixed up with parse_var_int
+//base 128 big endian, one is subtracted from every byte but the last so each number has one encoding
+pub fn parse_b128_var_int(input: &[u8]) -> IResult<&[u8], u64> {
+    let mut n: u64 = 0;
+    let mut i = input;
+    loop {
+        let (rest, byte) = le_u8(i)?;
+        i = rest;
+        if n > u64::MAX >> 7 {
+            return Err(Err::Error(ParseError::from_error_kind(
+                input,
+                ErrorKind::TooLarge,
+            )));
+        }
+        n = (n << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((i, n));
+        }
+        n = match n.checked_add(1) {
+            Some(n) => n,
+            None => {
+                return Err(Err::Error(ParseError::from_error_kind(
+                    input,
+                    ErrorKind::TooLarge,
+                )))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse_b128_var_int() {
+        //the examples from bitcoind's serialize.h
+        let tests: Vec<(&[u8], u64)> = vec![
+            (&[0x00], 0),
+            (&[0x01], 1),
+            (&[0x7f], 127),
+            (&[0x80, 0x00], 128),
+            (&[0x80, 0x7f], 255),
+            (&[0x81, 0x00], 256),
+            (&[0xfe, 0x7f], 16383),
+            (&[0xff, 0x00], 16384),
+            (&[0xff, 0x7f], 16511),
+            (&[0x82, 0xfe, 0x7f], 65535),
+            (&[0x8e, 0xfe, 0xfe, 0xff, 0x00], 1 << 32),
+        ];
+        for (data, n) in tests {
+            assert_eq!(parse_b128_var_int(data), Ok((&[][..], n)));
+        }
+        assert_eq!(
+            parse_b128_var_int(&[0x80, 0x00, 0xaa]),
+            Ok((&[0xaa][..], 128))
+        );
+        assert!(parse_b128_var_int(&[0x80]).is_err());
+        assert!(parse_b128_var_int(&[0xff; 11]).is_err());
+    }
+}
@@ -49,14 +49,14 @@ mod test {
         let (_, header) = parse_block_header(data).unwrap();
         assert_eq!(header.version, 536870912);
         assert_eq!(
-            header.prev_block_hash,
+            header.prev_block_hash.0,
             Hash256::new(
                 &hex::decode("21e4e008ffdaa5382ffe57d4419641dc89c7f610906104000000000000000000")
                     .unwrap()
             )
         );
         assert_eq!(
-            header.merkle_root_hash,
+            header.merkle_root_hash.0,
             Hash256::new(
                 &hex::decode("2e13f67bd6f0944b17f385008364b9120cfc362c546a384db7066b02eb938e88")
                     .unwrap()
@@ -66,7 +66,7 @@ mod test {
         assert_eq!(header.bits, Bytes::new(&hex::decode("d0bc1517").unwrap()));
         assert_eq!(header.nonce, Bytes::new(&hex::decode("6129429F").unwrap()));
         assert_eq!(
-            header.hash,
+            header.hash.0,
             Hash256::new(
                 &hex::decode("3d99801f2d28568a14023868d903722cf890340a161502000000000000000000")
                     .unwrap()
@@ -79,14 +79,14 @@ mod test {
         let (_, header) = parse_block_header(data).unwrap();
         assert_eq!(header.version, 1073725440);
         assert_eq!(
-            header.prev_block_hash,
+            header.prev_block_hash.0,
             Hash256::new(
                 &hex::decode("66c810e611643b26ddc0bf0a4d9fc21f409d5ad9a6ac09000000000000000000")
                     .unwrap()
             )
         );
         assert_eq!(
-            header.merkle_root_hash,
+            header.merkle_root_hash.0,
             Hash256::new(
                 &hex::decode("96cf49dcc64a3f405ca144c9e61752896f5bcde78fe0089b61952d48ee0826b7")
                     .unwrap()
@@ -96,7 +96,7 @@ mod test {
         assert_eq!(header.bits, Bytes::new(&hex::decode("d2db1517").unwrap()));
         assert_eq!(header.nonce, Bytes::new(&hex::decode("9AB9308D").unwrap()));
         assert_eq!(
-            header.hash,
+            header.hash.0,
             Hash256::new(
                 &hex::decode("3f7c4501d494c48919cad4ad2bc412a787f1472f680a0b000000000000000000")
                     .unwrap()
@@ -109,14 +109,14 @@ mod test {
         let (_, header) = parse_block_header(data).unwrap();
         assert_eq!(header.version, 1);
         assert_eq!(
-            header.prev_block_hash,
+            header.prev_block_hash.0,
             Hash256::new(
                 &hex::decode("0000000000000000000000000000000000000000000000000000000000000000")
                     .unwrap()
             )
         );
         assert_eq!(
-            header.merkle_root_hash,
+            header.merkle_root_hash.0,
             Hash256::new(
                 &hex::decode("3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a")
                     .unwrap()
@@ -126,7 +126,7 @@ mod test {
         assert_eq!(header.bits, Bytes::new(&hex::decode("ffff001d").unwrap()));
         assert_eq!(header.nonce, Bytes::new(&hex::decode("1DAC2B7C").unwrap()));
         assert_eq!(
-            header.hash,
+            header.hash.0,
             Hash256::new(
                 &hex::decode("6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000")
                     .unwrap()
@@ -0,0 +1,50 @@
+use crate::{
+    parsers::{parse_undo_coin, parse_var_int},
+    utxo::BlockUndo,
+};
+use nom::IResult;
+
+//the undo data of a block, the record in rev*.dat is magic, size, this and a 32 byte checksum
+//counts are not trusted for allocations, every item takes at least one byte
+pub fn parse_block_undo(input: &[u8]) -> IResult<&[u8], BlockUndo> {
+    let (mut input, tx_count) = parse_var_int(input)?;
+    let mut tx_undos = Vec::with_capacity((tx_count as usize).min(input.len()));
+    for _ in 0..tx_count {
+        let (mut i, coin_count) = parse_var_int(input)?;
+        let mut coins = Vec::with_capacity((coin_count as usize).min(i.len()));
+        for _ in 0..coin_count {
+            let (rest, coin) = parse_undo_coin(i)?;
+            coins.push(coin);
+            i = rest;
+        }
+        tx_undos.push(coins);
+        input = i;
+    }
+    Ok((input, BlockUndo::new(tx_undos)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse_block_undo() {
+        //two transactions, spending one and two P2SH outputs
+        let script = format!("01{}", "11".repeat(20));
+        let coin = format!("8049000a{}", script);
+        let data = hex::decode(format!("0201{}02{}{}", coin, coin, coin)).unwrap();
+        let (rest, undo) = parse_block_undo(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(undo.tx_undos.len(), 2);
+        assert_eq!(undo.tx_undos[0].len(), 1);
+        assert_eq!(undo.tx_undos[1].len(), 2);
+        assert_eq!(undo.tx_undos[1][1].output.value, 1_000_000_000);
+        assert_eq!(undo.tx_undos[1][1].height, 100);
+        assert!(parse_block_undo(&data[..data.len() - 1]).is_err());
+        //counts no input could hold
+        assert!(parse_block_undo(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).is_err());
+        assert!(
+            parse_block_undo(&[0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f])
+                .is_err()
+        );
+    }
+}
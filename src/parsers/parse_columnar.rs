@@ -0,0 +1,118 @@
+use crate::{
+    export::{Column, ColumnType, TableData, TABLES},
+    serializers::COLUMNAR_MAGIC,
+};
+use nom::{
+    bytes::complete::tag,
+    combinator::map_res,
+    error::{ErrorKind, ParseError},
+    multi::{count, length_data},
+    number::complete::{le_u32, le_u64, le_u8},
+    sequence::tuple,
+    Err, IResult,
+};
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+fn parse_name(input: &[u8]) -> IResult<&[u8], &str> {
+    map_res(length_data(le_u8), std::str::from_utf8)(input)
+}
+
+fn parse_string(input: &[u8]) -> IResult<&[u8], String> {
+    let (i, s) = map_res(length_data(le_u32), std::str::from_utf8)(input)?;
+    Ok((i, s.to_string()))
+}
+
+//the columns have to be those of the table, in its order
+pub fn parse_columnar(input: &[u8]) -> IResult<&[u8], TableData> {
+    let (i, _) = tag(COLUMNAR_MAGIC)(input)?;
+    let (i, name) = parse_name(i)?;
+    let table = match TABLES.iter().find(|table| table.name() == name) {
+        Some(table) => *table,
+        None => return Err(error(i, ErrorKind::Tag)),
+    };
+    let columns = table.columns();
+    let (i, column_count) = le_u32(i)?;
+    let (i, rows) = le_u64(i)?;
+    if column_count as usize != columns.len() {
+        return Err(error(i, ErrorKind::Verify));
+    }
+    let mut i = i;
+    for (name, column_type) in columns.iter() {
+        let (rest, (column_name, type_byte)) = tuple((parse_name, le_u8))(i)?;
+        let expected = match column_type {
+            ColumnType::U64 => 0,
+            ColumnType::Str => 1,
+        };
+        if column_name != *name || type_byte != expected {
+            return Err(error(i, ErrorKind::Verify));
+        }
+        i = rest;
+    }
+    //every row takes at least four bytes of each column
+    if rows > (i.len() / 4) as u64 {
+        return Err(error(i, ErrorKind::TooLarge));
+    }
+    let mut data = TableData::new(table);
+    for column in data.columns.iter_mut() {
+        let (rest, parsed) = match column {
+            Column::U64(_) => {
+                let (rest, values) = count(le_u64, rows as usize)(i)?;
+                (rest, Column::U64(values))
+            }
+            Column::Str(_) => {
+                let (rest, values) = count(parse_string, rows as usize)(i)?;
+                (rest, Column::Str(values))
+            }
+        };
+        *column = parsed;
+        i = rest;
+    }
+    Ok((i, data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        export::{Chunk, Table},
+        parsers::parse_block,
+        serializers::serialize_columnar,
+    };
+
+    #[test]
+    fn test_parse_columnar() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let mut chunk = Chunk::new();
+        chunk.push_block(609015, &block, "mainnet");
+        for table in TABLES.iter() {
+            let data = serialize_columnar(chunk.table(*table));
+            let (rest, parsed) = parse_columnar(&data).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(&parsed, chunk.table(*table));
+            assert!(parse_columnar(&data[..data.len() - 1]).is_err());
+        }
+
+        let data = serialize_columnar(chunk.table(Table::Blocks));
+        assert_eq!(&data[8..15], b"\x06blocks");
+        //a column named differently
+        let mut renamed = data.clone();
+        renamed[28] = b'H';
+        assert_eq!(
+            parse_columnar(&renamed),
+            Err(error(&renamed[27..], ErrorKind::Verify))
+        );
+        let mut unknown = data.clone();
+        unknown[9] = b'x';
+        assert!(parse_columnar(&unknown).is_err());
+        //a row count the data cannot hold
+        let mut rows = data;
+        rows[19..27].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse_columnar(&rows).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    p2p::{BlockTransactions, BlockTransactionsRequest, CompactBlock, PrefilledTransaction},
+    parsers::{parse_block_header, parse_transaction, parse_var_int},
+    types::Hash256,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::map,
+    error::{ErrorKind, ParseError},
+    multi::count,
+    number::complete::{le_u16, le_u32, le_u64},
+    sequence::tuple,
+    Err, IResult,
+};
+
+//indexes have to fit in 16 bits like in bitcoind
+const MAX_INDEX: u64 = u16::MAX as u64;
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+//a count of items of at least min_size bytes each, which the input has to be long enough for
+fn parse_count(min_size: usize) -> impl Fn(&[u8]) -> IResult<&[u8], usize> {
+    move |input: &[u8]| {
+        let (i, n) = parse_var_int(input)?;
+        match n > (i.len() / min_size) as u64 {
+            true => Err(error(input, ErrorKind::TooLarge)),
+            false => Ok((i, n as usize)),
+        }
+    }
+}
+
+//differential indexes, each one is the difference to the previous index plus one
+fn parse_indexes<'a, O, F>(input: &'a [u8], min_size: usize, f: F) -> IResult<&'a [u8], Vec<O>>
+where
+    F: Fn(&'a [u8], usize) -> IResult<&'a [u8], O>,
+{
+    let (mut i, n) = parse_count(min_size)(input)?;
+    let mut items = Vec::with_capacity(n);
+    let mut next = 0;
+    for _ in 0..n {
+        let (rest, difference) = parse_var_int(i)?;
+        let index = next + difference.min(MAX_INDEX + 1);
+        if index > MAX_INDEX {
+            return Err(error(i, ErrorKind::TooLarge));
+        }
+        let (rest, item) = f(rest, index as usize)?;
+        items.push(item);
+        next = index + 1;
+        i = rest;
+    }
+    Ok((i, items))
+}
+
+fn parse_hash(input: &[u8]) -> IResult<&[u8], Hash256> {
+    map(take(32u8), Hash256::new)(input)
+}
+
+//6 bytes little endian
+fn parse_short_id(input: &[u8]) -> IResult<&[u8], u64> {
+    map(tuple((le_u32, le_u16)), |(low, high)| {
+        u64::from(low) | u64::from(high) << 32
+    })(input)
+}
+
+pub fn parse_compact_block(input: &[u8]) -> IResult<&[u8], CompactBlock> {
+    let (i, (header, nonce)) = tuple((parse_block_header, le_u64))(input)?;
+    let (i, n) = parse_count(6)(i)?;
+    let (i, short_ids) = count(parse_short_id, n)(i)?;
+    //the smallest transaction takes 10 bytes
+    let (i, prefilled) = parse_indexes(i, 11, |i, index| {
+        map(parse_transaction, |tx| PrefilledTransaction { index, tx })(i)
+    })?;
+    let block = CompactBlock {
+        header,
+        nonce,
+        short_ids,
+        prefilled,
+    };
+    Ok((i, block))
+}
+
+pub fn parse_block_transactions_request(input: &[u8]) -> IResult<&[u8], BlockTransactionsRequest> {
+    let (i, block_hash) = parse_hash(input)?;
+    let (i, indexes) = parse_indexes(i, 1, |i, index| Ok((i, index)))?;
+    Ok((
+        i,
+        BlockTransactionsRequest {
+            block_hash,
+            indexes,
+        },
+    ))
+}
+
+pub fn parse_block_transactions(input: &[u8]) -> IResult<&[u8], BlockTransactions> {
+    let (i, block_hash) = parse_hash(input)?;
+    let (i, n) = parse_count(10)(i)?;
+    let (i, transactions) = count(parse_transaction, n)(i)?;
+    Ok((
+        i,
+        BlockTransactions {
+            block_hash,
+            transactions,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{p2p::NetworkMessage, parsers::parse_block, serializers::serialize_payload};
+    use hex;
+
+    #[test]
+    fn test_parse_compact_block() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let mut compact = CompactBlock::new(&block, 7);
+        compact.prefilled.push(PrefilledTransaction {
+            index: 3,
+            tx: block.transactions[3].clone(),
+        });
+        compact.short_ids.remove(2);
+        let payload = serialize_payload(&NetworkMessage::CmpctBlock(compact.clone()));
+        //prefilled indexes 0 and 3 are sent as 0 and 2
+        let prefilled = 80 + 8 + 3 + 445 * 6;
+        assert_eq!(&payload[88..91], &[0xfd, 0xbd, 0x01]);
+        assert_eq!(&payload[prefilled..prefilled + 2], &[2, 0]);
+        assert_eq!(&payload[prefilled + 2 + block.transactions[0].size], &2);
+        let (rest, parsed) = parse_compact_block(&payload).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed.header.hash, block.header.hash);
+        assert_eq!(parsed.nonce, 7);
+        assert_eq!(parsed.short_ids, compact.short_ids);
+        assert_eq!(parsed.prefilled[1].index, 3);
+        assert_eq!(parsed.prefilled[1].tx.txid, block.transactions[3].txid);
+        assert_eq!(parsed.transaction_count(), 447);
+
+        //more short ids than the input has room for
+        let mut invalid = payload[..prefilled].to_vec();
+        invalid[88..91].copy_from_slice(&[0xfd, 0xff, 0xff]);
+        assert_eq!(
+            parse_compact_block(&invalid).unwrap_err(),
+            Err::Error((&invalid[88..], ErrorKind::TooLarge))
+        );
+    }
+
+    #[test]
+    fn test_parse_block_transactions_request() {
+        let hash = "06226e46111a0b59caaf126043eb5bbf28c34f3a5e332a1fc7b2b73cf188910f";
+        let data = hex::decode(format!("{}0400000b00", hash)).unwrap();
+        let (rest, request) = parse_block_transactions_request(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(request.indexes, vec![0, 1, 13, 14]);
+        assert_eq!(
+            serialize_payload(&NetworkMessage::GetBlockTxn(request)),
+            data
+        );
+
+        //indexes beyond 16 bits
+        let data = hex::decode(format!("{}02fdfffffd0000", hash)).unwrap();
+        assert_eq!(
+            parse_block_transactions_request(&data).unwrap_err(),
+            Err::Error((&data[36..], ErrorKind::TooLarge))
+        );
+        let data = hex::decode(format!("{}01ffffffffffffffffff", hash)).unwrap();
+        assert!(parse_block_transactions_request(&data).is_err());
+    }
+
+    #[test]
+    fn test_parse_block_transactions() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let payload = [&block.header.hash.to_wire_bytes()[..], &[1], &data[81..]].concat();
+        let (rest, parsed) = parse_block_transactions(&payload).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed.block_hash, block.header.hash.0);
+        assert_eq!(parsed.transactions[0].txid, block.transactions[0].txid);
+        assert_eq!(
+            serialize_payload(&NetworkMessage::BlockTxn(parsed)),
+            payload
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    mempool::{FeeEstimates, TxConfirmStats},
+    parsers::parse_var_int,
+};
+use nom::{
+    combinator::map,
+    error::{ErrorKind, ParseError},
+    number::complete::{le_i32, le_u32, le_u64},
+    sequence::tuple,
+    Err, IResult,
+};
+
+//the first version of the file format still read by bitcoind
+const FEE_ESTIMATES_VERSION: i32 = 149900;
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+//doubles are stored as the little endian bits of their IEEE 754 representation
+fn parse_double(input: &[u8]) -> IResult<&[u8], f64> {
+    map(le_u64, f64::from_bits)(input)
+}
+
+fn parse_doubles(input: &[u8]) -> IResult<&[u8], Vec<f64>> {
+    let (mut i, n) = parse_var_int(input)?;
+    if n > i.len() as u64 / 8 {
+        return Err(error(input, ErrorKind::TooLarge));
+    }
+    let mut doubles = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+        let (rest, double) = parse_double(i)?;
+        doubles.push(double);
+        i = rest;
+    }
+    Ok((i, doubles))
+}
+
+fn parse_double_table(input: &[u8]) -> IResult<&[u8], Vec<Vec<f64>>> {
+    let (mut i, n) = parse_var_int(input)?;
+    let mut table = Vec::new();
+    for _ in 0..n {
+        let (rest, row) = parse_doubles(i)?;
+        table.push(row);
+        i = rest;
+    }
+    Ok((i, table))
+}
+
+//every row of the stats has an entry for each of the buckets
+fn parse_tx_confirm_stats(buckets: usize) -> impl Fn(&[u8]) -> IResult<&[u8], TxConfirmStats> {
+    move |input: &[u8]| {
+        let (i, (decay, scale, fee_rate_avg, tx_count_avg, confirmed_avg, failed_avg)) =
+            tuple((
+                parse_double,
+                le_u32,
+                parse_doubles,
+                parse_doubles,
+                parse_double_table,
+                parse_double_table,
+            ))(input)?;
+        let rows = confirmed_avg.iter().chain(failed_avg.iter());
+        if decay <= 0.0
+            || decay >= 1.0
+            || scale == 0
+            || fee_rate_avg.len() != buckets
+            || tx_count_avg.len() != buckets
+            || confirmed_avg.is_empty()
+            || confirmed_avg.len() != failed_avg.len()
+            || rows.clone().any(|row| row.len() != buckets)
+        {
+            return Err(error(input, ErrorKind::Verify));
+        }
+        let stats = TxConfirmStats {
+            decay,
+            scale,
+            fee_rate_avg,
+            tx_count_avg,
+            confirmed_avg,
+            failed_avg,
+        };
+        Ok((i, stats))
+    }
+}
+
+//fee_estimates.dat, files written before bitcoind 0.15 are an error
+pub fn parse_fee_estimates(input: &[u8]) -> IResult<&[u8], FeeEstimates> {
+    let (i, (version_required, version)) = tuple((le_i32, le_i32))(input)?;
+    if version_required < FEE_ESTIMATES_VERSION || version < FEE_ESTIMATES_VERSION {
+        return Err(error(input, ErrorKind::Tag));
+    }
+    let (i, (best_seen_height, historical_first, historical_best, buckets)) =
+        tuple((le_u32, le_u32, le_u32, parse_doubles))(i)?;
+    if buckets.len() <= 1 {
+        return Err(error(input, ErrorKind::Verify));
+    }
+    let (i, (medium, short, long)) = tuple((
+        parse_tx_confirm_stats(buckets.len()),
+        parse_tx_confirm_stats(buckets.len()),
+        parse_tx_confirm_stats(buckets.len()),
+    ))(i)?;
+    let estimates = FeeEstimates {
+        version_required,
+        version,
+        best_seen_height,
+        historical_first,
+        historical_best,
+        buckets,
+        medium,
+        short,
+        long,
+    };
+    Ok((i, estimates))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn doubles(values: &[f64]) -> Vec<u8> {
+        let mut data = vec![values.len() as u8];
+        for value in values {
+            data.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        data
+    }
+
+    //periods rows of buckets entries
+    fn stats(decay: f64, scale: u32, periods: usize, buckets: usize) -> Vec<u8> {
+        let mut data = decay.to_bits().to_le_bytes().to_vec();
+        data.extend_from_slice(&scale.to_le_bytes());
+        data.extend(doubles(&vec![1000.0; buckets]));
+        data.extend(doubles(&vec![2.5; buckets]));
+        for _ in 0..2 {
+            data.push(periods as u8);
+            for period in 0..periods {
+                data.extend(doubles(&vec![period as f64; buckets]));
+            }
+        }
+        data
+    }
+
+    //the stats may have another number of buckets than the file
+    fn fee_estimates(buckets: usize, stats_buckets: usize) -> Vec<u8> {
+        let mut data = 149900i32.to_le_bytes().to_vec();
+        data.extend_from_slice(&270000i32.to_le_bytes());
+        for height in [800_000u32, 799_000, 800_000].iter() {
+            data.extend_from_slice(&height.to_le_bytes());
+        }
+        let bounds: Vec<_> = (0..buckets)
+            .map(|n| 1000.0 * 1.05f64.powi(n as i32))
+            .collect();
+        data.extend(doubles(&bounds));
+        data.extend(stats(0.962, 2, 12, buckets));
+        data.extend(stats(0.5, 1, 12, stats_buckets));
+        data.extend(stats(0.99931, 24, 42, buckets));
+        data
+    }
+
+    #[test]
+    fn test_parse_fee_estimates() {
+        let data = fee_estimates(5, 5);
+        let (rest, estimates) = parse_fee_estimates(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(estimates.version, 270000);
+        assert_eq!(estimates.best_seen_height, 800_000);
+        assert_eq!(estimates.historical_first, 799_000);
+        assert_eq!(estimates.buckets.len(), 5);
+        assert_eq!(estimates.buckets[1], 1050.0);
+        assert_eq!(estimates.short.max_target(), 12);
+        assert_eq!(estimates.medium.max_target(), 24);
+        assert_eq!(estimates.long.max_target(), 1008);
+        assert_eq!(estimates.long.decay, 0.99931);
+        assert_eq!(estimates.long.confirmed_avg[41], vec![41.0; 5]);
+        assert_eq!(estimates.medium.tx_count_avg, vec![2.5; 5]);
+
+        assert!(parse_fee_estimates(&data[..data.len() - 1]).is_err());
+        //an old format
+        let mut old = data.clone();
+        old[..4].copy_from_slice(&109900i32.to_le_bytes());
+        assert!(parse_fee_estimates(&old).is_err());
+        //the stats have to match the buckets
+        assert!(parse_fee_estimates(&fee_estimates(5, 4)).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    script::opcodes::{OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4},
+    types::Instruction,
+};
+use nom::{
+    bytes::complete::take,
+    multi::length_data,
+    number::complete::{le_u16, le_u32, le_u8},
+    IResult,
+};
+
+pub fn parse_instruction(input: &[u8]) -> IResult<&[u8], Instruction> {
+    let (i, opcode) = le_u8(input)?;
+    let (i, data) = match opcode {
+        0x00..=0x4b => take(opcode)(i)?,
+        OP_PUSHDATA1 => length_data(le_u8)(i)?,
+        OP_PUSHDATA2 => length_data(le_u16)(i)?,
+        OP_PUSHDATA4 => length_data(le_u32)(i)?,
+        _ => return Ok((i, Instruction::Op(opcode))),
+    };
+    Ok((i, Instruction::Push(opcode, data.into())))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Bytes;
+    #[test]
+    fn test_parse_instruction() {
+        assert_eq!(
+            parse_instruction(&[0x00, 0xac][..]),
+            Ok((&[0xac][..], Instruction::Push(0x00, Bytes::default())))
+        );
+        assert_eq!(
+            parse_instruction(&[0xac][..]),
+            Ok((&[][..], Instruction::Op(0xac)))
+        );
+        assert_eq!(
+            parse_instruction(&[0x02, 0xaa, 0xbb, 0xcc][..]),
+            Ok((
+                &[0xcc][..],
+                Instruction::Push(0x02, Bytes::new(&[0xaa, 0xbb]))
+            ))
+        );
+        assert_eq!(
+            parse_instruction(&[0x4c, 0x01, 0xaa][..]),
+            Ok((&[][..], Instruction::Push(0x4c, Bytes::new(&[0xaa]))))
+        );
+        assert_eq!(
+            parse_instruction(&[0x4d, 0x01, 0x00, 0xaa][..]),
+            Ok((&[][..], Instruction::Push(0x4d, Bytes::new(&[0xaa]))))
+        );
+        assert_eq!(
+            parse_instruction(&[0x4e, 0x01, 0x00, 0x00, 0x00, 0xaa][..]),
+            Ok((&[][..], Instruction::Push(0x4e, Bytes::new(&[0xaa]))))
+        );
+        //truncated pushes are an error
+        assert!(parse_instruction(&[0x02, 0xaa][..]).is_err());
+        assert!(parse_instruction(&[0x4d, 0x01][..]).is_err());
+        assert!(parse_instruction(&[][..]).is_err());
+    }
+}
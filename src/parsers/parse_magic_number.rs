@@ -1,16 +1,31 @@
 use nom::number::complete::le_u32;
 use nom::IResult;
 
-pub fn parse_magic_number(input: &[u8]) -> IResult<&[u8], Option<&str>> {
+//the message start bytes of blk files and p2p messages, read as a little endian u32
+pub const MAGIC_NUMBERS: [(u32, &str); 4] = [
+    (0xD9B4BEF9, "mainnet"),
+    (0xDAB5BFFA, "regtest"),
+    (0x0709110B, "testnet"),
+    (0xFEB4BEF9, "namecoin"),
+];
+
+pub fn chain_name(magic: u32) -> Option<&'static str> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(m, _)| *m == magic)
+        .map(|(_, chain)| *chain)
+}
+
+pub fn chain_magic(chain: &str) -> Option<u32> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(_, c)| *c == chain)
+        .map(|(magic, _)| *magic)
+}
+
+pub fn parse_magic_number(input: &[u8]) -> IResult<&[u8], Option<&'static str>> {
     let (i, o) = le_u32(input)?;
-    let result = match o {
-        0xD9B4BEF9 => Some("mainnet"),
-        0xDAB5BFFA => Some("regtest"),
-        0x0709110B => Some("testnet"),
-        0xFEB4BEF9 => Some("namecoin"),
-        _ => None,
-    };
-    Ok((i, result))
+    Ok((i, chain_name(o)))
 }
 
 #[cfg(test)]
@@ -39,5 +54,7 @@ mod test {
         let data = &[0xf9, 0xb4, 0xff, 0xff][..];
         let (_, chain) = parse_magic_number(data).unwrap();
         assert_eq!(chain, None);
+        assert_eq!(chain_magic("testnet"), Some(0x0709110B));
+        assert_eq!(chain_magic("signet"), None);
     }
 }
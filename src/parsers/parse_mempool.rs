@@ -0,0 +1,181 @@
+use crate::{
+    mempool::{MempoolDump, MempoolEntry, MEMPOOL_DUMP_VERSION, MEMPOOL_DUMP_VERSION_NO_XOR_KEY},
+    parsers::{parse_transaction, parse_var_int},
+    types::Hash256,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::map,
+    error::{ErrorKind, ParseError},
+    multi::length_data,
+    number::complete::{le_i64, le_u64},
+    sequence::tuple,
+    Err, IResult,
+};
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+fn parse_hash(input: &[u8]) -> IResult<&[u8], Hash256> {
+    map(take(32u8), Hash256::new)(input)
+}
+
+fn parse_entry(input: &[u8]) -> IResult<&[u8], MempoolEntry> {
+    let (i, (transaction, time, fee_delta)) = tuple((parse_transaction, le_i64, le_i64))(input)?;
+    let entry = MempoolEntry {
+        transaction,
+        time,
+        fee_delta,
+    };
+    Ok((i, entry))
+}
+
+type Contents = (Vec<MempoolEntry>, Vec<(Hash256, i64)>, Vec<Hash256>);
+
+//everything after the version and key
+//counts are not trusted for allocations, every item takes at least one byte
+fn parse_contents(input: &[u8]) -> IResult<&[u8], Contents> {
+    let (mut i, tx_count) = le_u64(input)?;
+    let mut entries = Vec::with_capacity((tx_count as usize).min(i.len()));
+    for _ in 0..tx_count {
+        let (rest, entry) = parse_entry(i)?;
+        entries.push(entry);
+        i = rest;
+    }
+    let (mut i, delta_count) = parse_var_int(i)?;
+    let mut deltas = Vec::with_capacity((delta_count as usize).min(i.len()));
+    for _ in 0..delta_count {
+        let (rest, delta) = tuple((parse_hash, le_i64))(i)?;
+        deltas.push(delta);
+        i = rest;
+    }
+    if i.is_empty() {
+        return Ok((i, (entries, deltas, Vec::new())));
+    }
+    let (mut i, unbroadcast_count) = parse_var_int(i)?;
+    let mut unbroadcast = Vec::with_capacity((unbroadcast_count as usize).min(i.len()));
+    for _ in 0..unbroadcast_count {
+        let (rest, txid) = parse_hash(i)?;
+        unbroadcast.push(txid);
+        i = rest;
+    }
+    Ok((i, (entries, deltas, unbroadcast)))
+}
+
+//mempool.dat, a file with an unknown version is an error
+pub fn parse_mempool(input: &[u8]) -> IResult<&[u8], MempoolDump> {
+    let (i, version) = le_u64(input)?;
+    let (i, xor_key, (entries, deltas, unbroadcast)) = match version {
+        MEMPOOL_DUMP_VERSION_NO_XOR_KEY => {
+            let (i, contents) = parse_contents(i)?;
+            (i, None, contents)
+        }
+        MEMPOOL_DUMP_VERSION => {
+            let (i, key) = length_data(parse_var_int)(i)?;
+            //the key is applied by the position in the file, starting after the key itself
+            let start = input.len() - i.len();
+            let plain: Vec<u8> = match key.is_empty() {
+                true => i.to_vec(),
+                false => i
+                    .iter()
+                    .enumerate()
+                    .map(|(n, b)| b ^ key[(start + n) % key.len()])
+                    .collect(),
+            };
+            //the rest and errors point into the plain copy, they are moved back into input
+            let at = |rest: &[u8]| &i[i.len() - rest.len()..];
+            let (rest, contents) = match parse_contents(&plain) {
+                Ok(parsed) => parsed,
+                Err(Err::Error((rest, kind))) => return Err(Err::Error((at(rest), kind))),
+                Err(Err::Failure((rest, kind))) => return Err(Err::Failure((at(rest), kind))),
+                Err(Err::Incomplete(needed)) => return Err(Err::Incomplete(needed)),
+            };
+            (at(rest), Some(key.to_vec()), contents)
+        }
+        _ => return Err(error(input, ErrorKind::Tag)),
+    };
+    let dump = MempoolDump {
+        version,
+        xor_key,
+        entries,
+        deltas,
+        unbroadcast,
+    };
+    Ok((i, dump))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dump(version: u64, key: &[u8], unbroadcast: bool) -> Vec<u8> {
+        let legacy = include_bytes!(
+            "../test_data/tx_c623634f506375a45ee09379d4b117d5ddb1d02eb04c257d9354cbf0055ad191.bin"
+        );
+        let segwit = include_bytes!(
+            "../test_data/tx_fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54.segwit.bin"
+        );
+        let mut contents = 2u64.to_le_bytes().to_vec();
+        contents.extend_from_slice(legacy);
+        contents.extend_from_slice(&1_600_000_000i64.to_le_bytes());
+        contents.extend_from_slice(&0i64.to_le_bytes());
+        contents.extend_from_slice(segwit);
+        contents.extend_from_slice(&1_600_000_100i64.to_le_bytes());
+        contents.extend_from_slice(&(-500i64).to_le_bytes());
+        contents.push(1);
+        contents.extend_from_slice(&[0x11; 32]);
+        contents.extend_from_slice(&10_000i64.to_le_bytes());
+        if unbroadcast {
+            contents.push(1);
+            contents.extend_from_slice(&[0x22; 32]);
+        }
+        let mut data = version.to_le_bytes().to_vec();
+        if version == MEMPOOL_DUMP_VERSION {
+            data.push(key.len() as u8);
+            data.extend_from_slice(key);
+            let start = data.len();
+            for (n, b) in contents.iter().enumerate() {
+                data.push(b ^ key[(start + n) % key.len()]);
+            }
+        } else {
+            data.extend_from_slice(&contents);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_mempool() {
+        let data = dump(MEMPOOL_DUMP_VERSION_NO_XOR_KEY, &[], false);
+        let (rest, mempool) = parse_mempool(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(mempool.xor_key, None);
+        assert_eq!(mempool.entries.len(), 2);
+        let txid = "fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54"
+            .parse()
+            .unwrap();
+        let entry = mempool.entry(&txid).unwrap();
+        assert_eq!(entry.time, 1_600_000_100);
+        assert_eq!(entry.fee_delta, -500);
+        assert!(entry.transaction.witnesses.is_some());
+        assert_eq!(mempool.deltas, vec![(Hash256::new(&[0x11; 32]), 10_000)]);
+        assert!(mempool.unbroadcast.is_empty());
+        assert_eq!(mempool.time_range(), Some((1_600_000_000, 1_600_000_100)));
+
+        let key = [0x5a, 0x01, 0xff, 0x80, 0x33, 0x00, 0x42, 0x17];
+        let data = dump(MEMPOOL_DUMP_VERSION, &key, true);
+        let (rest, xored) = parse_mempool(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(xored.xor_key, Some(key.to_vec()));
+        assert_eq!(xored.total_vsize(), mempool.total_vsize());
+        assert_eq!(xored.entries[1].transaction.txid.0, txid);
+        assert_eq!(xored.unbroadcast, vec![Hash256::new(&[0x22; 32])]);
+
+        //errors point into the file, not into the copy the key was applied to
+        match parse_mempool(&data[..data.len() - 1]) {
+            Err(Err::Error((rest, _))) => assert_eq!(rest.len(), 31),
+            result => panic!("{:?}", result.map(|_| ())),
+        }
+        assert!(parse_mempool(&dump(3, &[], false)).is_err());
+    }
+}
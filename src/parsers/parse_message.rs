@@ -0,0 +1,511 @@
+use crate::{
+    p2p::{
+        AddrV2, BlockLocator, Inventory, Message, MessageHeader, NetAddress, NetworkMessage,
+        VersionMessage,
+    },
+    parsers::{
+        parse_block, parse_block_header, parse_block_transactions,
+        parse_block_transactions_request, parse_compact_block, parse_transaction, parse_var_int,
+    },
+    types::Hash256,
+    utils::hash256,
+};
+use nom::{
+    bytes::{complete, streaming},
+    combinator::{map, rest},
+    error::{ErrorKind, ParseError},
+    multi::{count, length_data},
+    number::complete::{be_u16, le_i32, le_i64, le_u32, le_u64, le_u8},
+    sequence::tuple,
+    Err, IResult,
+};
+
+//limits bitcoind enforces, they also bound what a malformed count can make us allocate
+const MAX_PROTOCOL_MESSAGE_LENGTH: u32 = 4_000_000;
+const MAX_INV_SIZE: u64 = 50_000;
+const MAX_HEADERS_RESULTS: u64 = 2_000;
+const MAX_ADDR_TO_SEND: u64 = 1_000;
+const MAX_ADDRV2_SIZE: u64 = 512;
+const MAX_LOCATOR_SZ: u64 = 101;
+const MAX_SUBVERSION_LENGTH: u64 = 256;
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+//a count that is not larger than max
+fn parse_count(max: u64) -> impl Fn(&[u8]) -> IResult<&[u8], usize> {
+    move |input: &[u8]| {
+        let (i, n) = parse_var_int(input)?;
+        match n > max {
+            true => Err(error(input, ErrorKind::TooLarge)),
+            false => Ok((i, n as usize)),
+        }
+    }
+}
+
+fn parse_hash(input: &[u8]) -> IResult<&[u8], Hash256> {
+    map(complete::take(32u8), Hash256::new)(input)
+}
+
+pub fn parse_net_address(input: &[u8]) -> IResult<&[u8], NetAddress> {
+    let (i, (services, ip, port)) = tuple((le_u64, complete::take(16u8), be_u16))(input)?;
+    let mut address = NetAddress {
+        services,
+        ip: [0; 16],
+        port,
+    };
+    address.ip.copy_from_slice(ip);
+    Ok((i, address))
+}
+
+fn parse_version(input: &[u8]) -> IResult<&[u8], VersionMessage> {
+    let (i, (version, services, timestamp, receiver, sender, nonce)) = tuple((
+        le_i32,
+        le_u64,
+        le_i64,
+        parse_net_address,
+        parse_net_address,
+        le_u64,
+    ))(input)?;
+    let (i, user_agent) = parse_count(MAX_SUBVERSION_LENGTH)(i)?;
+    let (i, user_agent) = complete::take(user_agent)(i)?;
+    let (i, start_height) = le_i32(i)?;
+    let (i, relay) = match i.is_empty() {
+        true => (i, true),
+        false => map(le_u8, |relay| relay != 0)(i)?,
+    };
+    let version = VersionMessage {
+        version,
+        services,
+        timestamp,
+        receiver,
+        sender,
+        nonce,
+        user_agent: String::from_utf8_lossy(user_agent).into_owned(),
+        start_height,
+        relay,
+    };
+    Ok((i, version))
+}
+
+pub fn parse_inventory(input: &[u8]) -> IResult<&[u8], Inventory> {
+    map(tuple((le_u32, parse_hash)), |(inv_type, hash)| {
+        Inventory::new(inv_type, hash)
+    })(input)
+}
+
+fn parse_inventories(input: &[u8]) -> IResult<&[u8], Vec<Inventory>> {
+    let (i, n) = parse_count(MAX_INV_SIZE)(input)?;
+    count(parse_inventory, n)(i)
+}
+
+fn parse_block_locator(input: &[u8]) -> IResult<&[u8], BlockLocator> {
+    let (i, version) = le_u32(input)?;
+    let (i, n) = parse_count(MAX_LOCATOR_SZ)(i)?;
+    let (i, (hashes, stop_hash)) = tuple((count(parse_hash, n), parse_hash))(i)?;
+    let locator = BlockLocator {
+        version,
+        hashes,
+        stop_hash,
+    };
+    Ok((i, locator))
+}
+
+fn parse_headers(input: &[u8]) -> IResult<&[u8], Vec<crate::types::BlockHeader>> {
+    let (i, n) = parse_count(MAX_HEADERS_RESULTS)(input)?;
+    //every header is followed by a transaction count, which is always zero
+    count(
+        map(tuple((parse_block_header, parse_var_int)), |(header, _)| {
+            header
+        }),
+        n,
+    )(i)
+}
+
+fn parse_addr(input: &[u8]) -> IResult<&[u8], Vec<(u32, NetAddress)>> {
+    let (i, n) = parse_count(MAX_ADDR_TO_SEND)(input)?;
+    count(tuple((le_u32, parse_net_address)), n)(i)
+}
+
+pub fn parse_addr_v2(input: &[u8]) -> IResult<&[u8], AddrV2> {
+    let (i, (time, services, network)) = tuple((le_u32, parse_var_int, le_u8))(input)?;
+    let (i, addr) = length_data(parse_count(MAX_ADDRV2_SIZE))(i)?;
+    let (i, port) = be_u16(i)?;
+    let addr = AddrV2 {
+        time,
+        services,
+        network,
+        addr: addr.to_vec(),
+        port,
+    };
+    Ok((i, addr))
+}
+
+fn parse_addr_v2s(input: &[u8]) -> IResult<&[u8], Vec<AddrV2>> {
+    let (i, n) = parse_count(MAX_ADDR_TO_SEND)(input)?;
+    count(parse_addr_v2, n)(i)
+}
+
+//the payload of command, bytes after the known fields are ignored like bitcoind does
+pub fn parse_payload<'a>(command: &str, payload: &'a [u8]) -> IResult<&'a [u8], NetworkMessage> {
+    let no_payload = |message: NetworkMessage| Ok((payload, message));
+    match command {
+        "version" => map(parse_version, NetworkMessage::Version)(payload),
+        "verack" => no_payload(NetworkMessage::Verack),
+        "inv" => map(parse_inventories, NetworkMessage::Inv)(payload),
+        "getdata" => map(parse_inventories, NetworkMessage::GetData)(payload),
+        "notfound" => map(parse_inventories, NetworkMessage::NotFound)(payload),
+        "getblocks" => map(parse_block_locator, NetworkMessage::GetBlocks)(payload),
+        "getheaders" => map(parse_block_locator, NetworkMessage::GetHeaders)(payload),
+        "headers" => map(parse_headers, NetworkMessage::Headers)(payload),
+        "block" => map(parse_block, NetworkMessage::Block)(payload),
+        "tx" => map(parse_transaction, NetworkMessage::Tx)(payload),
+        "addr" => map(parse_addr, NetworkMessage::Addr)(payload),
+        "addrv2" => map(parse_addr_v2s, NetworkMessage::AddrV2)(payload),
+        "getaddr" => no_payload(NetworkMessage::GetAddr),
+        "ping" => map(le_u64, NetworkMessage::Ping)(payload),
+        "pong" => map(le_u64, NetworkMessage::Pong)(payload),
+        "sendheaders" => no_payload(NetworkMessage::SendHeaders),
+        "sendcmpct" => map(tuple((le_u8, le_u64)), |(announce, version)| {
+            NetworkMessage::SendCmpct {
+                announce: announce != 0,
+                version,
+            }
+        })(payload),
+        "cmpctblock" => map(parse_compact_block, NetworkMessage::CmpctBlock)(payload),
+        "getblocktxn" => map(
+            parse_block_transactions_request,
+            NetworkMessage::GetBlockTxn,
+        )(payload),
+        "blocktxn" => map(parse_block_transactions, NetworkMessage::BlockTxn)(payload),
+        "feefilter" => map(le_u64, NetworkMessage::FeeFilter)(payload),
+        "sendaddrv2" => no_payload(NetworkMessage::SendAddrV2),
+        "wtxidrelay" => no_payload(NetworkMessage::WtxidRelay),
+        "mempool" => no_payload(NetworkMessage::Mempool),
+        _ => map(rest, |payload: &[u8]| NetworkMessage::Unknown {
+            command: command.to_string(),
+            payload: payload.to_vec(),
+        })(payload),
+    }
+}
+
+//returns Incomplete when the input ends within the header
+pub fn parse_message_header(input: &[u8]) -> IResult<&[u8], MessageHeader> {
+    let (i, header) = streaming::take(24u8)(input)?;
+    let (_, (magic, command, length, checksum)) =
+        tuple((le_u32, complete::take(12u8), le_u32, complete::take(4u8)))(header)?;
+    //printable characters padded with zeros
+    let name_length = command.iter().position(|c| *c == 0).unwrap_or(12);
+    let (name, padding) = command.split_at(name_length);
+    if !name.iter().all(|c| (b' '..=b'~').contains(c)) || padding.iter().any(|c| *c != 0) {
+        return Err(error(input, ErrorKind::Verify));
+    }
+    if length > MAX_PROTOCOL_MESSAGE_LENGTH {
+        return Err(error(input, ErrorKind::TooLarge));
+    }
+    let mut header = MessageHeader {
+        magic,
+        command: String::from_utf8_lossy(name).into_owned(),
+        length,
+        checksum: [0; 4],
+    };
+    header.checksum.copy_from_slice(checksum);
+    Ok((i, header))
+}
+
+//a message as sent over the wire, returns Incomplete when the input ends within the message
+pub fn parse_message(input: &[u8]) -> IResult<&[u8], Message> {
+    let (i, header) = parse_message_header(input)?;
+    let (i, payload) = streaming::take(header.length)(i)?;
+    if hash256(payload).0[..4] != header.checksum {
+        return Err(error(input, ErrorKind::Verify));
+    }
+    let (_, message) = parse_payload(&header.command, payload)?;
+    Ok((i, Message::new(header.magic, message)))
+}
+
+//the complete messages of a captured stream, the rest is the start of a message cut off by the capture
+pub fn parse_messages(mut input: &[u8]) -> IResult<&[u8], Vec<Message>> {
+    let mut messages = Vec::new();
+    while !input.is_empty() {
+        match parse_message(input) {
+            Ok((i, message)) => {
+                messages.push(message);
+                input = i;
+            }
+            Err(Err::Incomplete(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((input, messages))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::p2p::{MSG_BLOCK, MSG_WITNESS_TX, NET_IPV4, NET_TORV3};
+    use hex;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn envelope(command: &str, payload: &[u8]) -> Vec<u8> {
+        let mut name = [0u8; 12];
+        name[..command.len()].copy_from_slice(command.as_bytes());
+        [
+            &0xD9B4BEF9u32.to_le_bytes()[..],
+            &name,
+            &(payload.len() as u32).to_le_bytes(),
+            &hash256(payload).0[..4],
+            payload,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_parse_message_header() {
+        //a mainnet verack
+        let data = hex::decode("f9beb4d976657261636b000000000000000000005df6e0e2").unwrap();
+        let (rest, message) = parse_message(&data).unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(message.payload, NetworkMessage::Verack));
+        assert_eq!(message.chain(), Some("mainnet"));
+        assert_eq!(message.command(), "verack");
+
+        //the input ends within the header or payload
+        assert!(matches!(
+            parse_message(&data[..20]),
+            Err(Err::Incomplete(_))
+        ));
+        let ping = envelope("ping", &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(matches!(
+            parse_message(&ping[..30]),
+            Err(Err::Incomplete(_))
+        ));
+        //wrong checksum
+        let mut data = ping.clone();
+        data[20] ^= 1;
+        assert_eq!(
+            parse_message(&data).unwrap_err(),
+            Err::Error((&data[..], ErrorKind::Verify))
+        );
+        //characters after the zero padding
+        let mut data = ping.clone();
+        data[14] = b'x';
+        assert!(parse_message_header(&data).is_err());
+        let mut data = ping;
+        data[16..20].copy_from_slice(&(MAX_PROTOCOL_MESSAGE_LENGTH + 1).to_le_bytes());
+        assert_eq!(
+            parse_message_header(&data).unwrap_err(),
+            Err::Error((&data[..], ErrorKind::TooLarge))
+        );
+    }
+
+    #[test]
+    fn test_parse_version() {
+        //the version message of a 0.7.2 node
+        let payload = hex::decode(
+            "62ea00000100000000000000\
+             11b2d05000000000\
+             010000000000000000000000000000000000ffff000000000000\
+             010000000000000000000000000000000000ffff0a000001208d\
+             3b2eb35d8ce61765\
+             0f2f5361746f7368693a302e372e322f\
+             c03e0300",
+        )
+        .unwrap();
+        let data = envelope("version", &payload);
+        let (_, message) = parse_message(&data).unwrap();
+        let version = match message.payload {
+            NetworkMessage::Version(version) => version,
+            _ => panic!("not a version message"),
+        };
+        assert_eq!(version.version, 60002);
+        assert_eq!(version.services, 1);
+        assert_eq!(version.timestamp, 1355854353);
+        assert_eq!(
+            version.sender.ip_addr(),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+        );
+        assert_eq!(version.sender.port, 8333);
+        assert_eq!(version.nonce, 0x6517e68c5db32e3b);
+        assert_eq!(version.user_agent, "/Satoshi:0.7.2/");
+        assert_eq!(version.start_height, 212672);
+        assert!(version.relay);
+
+        let (_, version) = parse_version(&[&payload[..], &[0]].concat()).unwrap();
+        assert!(!version.relay);
+        //a user agent longer than bitcoind accepts
+        let mut payload = payload;
+        payload[80] = 0xff;
+        assert!(parse_version(&payload).is_err());
+    }
+
+    #[test]
+    fn test_parse_payloads() {
+        let genesis = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let tx = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let inv = [
+            &[2][..],
+            &MSG_WITNESS_TX.to_le_bytes(),
+            &[1; 32],
+            &MSG_BLOCK.to_le_bytes(),
+            &[2; 32],
+        ]
+        .concat();
+        let data = [
+            envelope("inv", &inv),
+            envelope("getdata", &inv),
+            envelope("headers", &[&[1][..], &genesis[..80], &[0]].concat()),
+            envelope("block", genesis),
+            envelope("tx", tx),
+            envelope(
+                "getheaders",
+                &[&70016u32.to_le_bytes()[..], &[1], &[3; 32], &[0; 32]].concat(),
+            ),
+            envelope(
+                "addr",
+                &hex::decode("01e215104d010000000000000000000000000000000000ffff0a000001208d")
+                    .unwrap(),
+            ),
+            envelope(
+                "addrv2",
+                &[
+                    &hex::decode("02e215104d0901040a000001208d").unwrap()[..],
+                    &hex::decode("e215104d000420").unwrap(),
+                    &[7; 32],
+                    &[0x01, 0xbb],
+                ]
+                .concat(),
+            ),
+            envelope("ping", &7u64.to_le_bytes()),
+            envelope("pong", &7u64.to_le_bytes()),
+            envelope("sendcmpct", &[&[1][..], &2u64.to_le_bytes()].concat()),
+            envelope("feefilter", &1000u64.to_le_bytes()),
+            envelope("wtxidrelay", &[]),
+            envelope("sendaddrv2", &[]),
+            envelope("cfilter", &[1, 2, 3]),
+        ]
+        .concat();
+        //a stream cut off within the last message
+        let (rest, messages) = parse_messages(&data[..data.len() - 1]).unwrap();
+        assert_eq!(rest.len(), 24 + 3 - 1);
+        let commands: Vec<_> = messages.iter().map(|m| m.command()).collect();
+        assert_eq!(
+            commands,
+            vec![
+                "inv",
+                "getdata",
+                "headers",
+                "block",
+                "tx",
+                "getheaders",
+                "addr",
+                "addrv2",
+                "ping",
+                "pong",
+                "sendcmpct",
+                "feefilter",
+                "wtxidrelay",
+                "sendaddrv2"
+            ]
+        );
+        let (_, messages) = parse_messages(&data).unwrap();
+        let mut messages = messages.into_iter().map(|m| m.payload);
+
+        let expected_inv = vec![
+            Inventory::new(MSG_WITNESS_TX, Hash256([1; 32])),
+            Inventory::new(MSG_BLOCK, Hash256([2; 32])),
+        ];
+        match messages.next() {
+            Some(NetworkMessage::Inv(inv)) => assert_eq!(inv, expected_inv),
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::GetData(inv)) => assert_eq!(inv, expected_inv),
+            m => panic!("unexpected {:?}", m),
+        }
+        let (_, block) = parse_block(genesis).unwrap();
+        match messages.next() {
+            Some(NetworkMessage::Headers(headers)) => {
+                assert_eq!(headers.len(), 1);
+                assert_eq!(headers[0].hash, block.header.hash);
+            }
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::Block(b)) => assert_eq!(b.header.hash, block.header.hash),
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::Tx(t)) => assert_eq!(t.size, tx.len()),
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::GetHeaders(locator)) => assert_eq!(
+                locator,
+                BlockLocator {
+                    version: 70016,
+                    hashes: vec![Hash256([3; 32])],
+                    stop_hash: Hash256::default(),
+                }
+            ),
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::Addr(addr)) => assert_eq!(
+                addr,
+                vec![(
+                    1292899810,
+                    NetAddress::new(1, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8333)
+                )]
+            ),
+            m => panic!("unexpected {:?}", m),
+        }
+        match messages.next() {
+            Some(NetworkMessage::AddrV2(addr)) => {
+                assert_eq!(addr.len(), 2);
+                assert_eq!(addr[0].services, 9);
+                assert_eq!(addr[0].network, NET_IPV4);
+                assert_eq!(
+                    addr[0].ip_addr(),
+                    Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+                );
+                assert_eq!(addr[1].network, NET_TORV3);
+                assert_eq!(addr[1].addr, vec![7; 32]);
+                assert_eq!(addr[1].port, 443);
+                assert_eq!(addr[1].ip_addr(), None);
+            }
+            m => panic!("unexpected {:?}", m),
+        }
+        assert!(matches!(messages.next(), Some(NetworkMessage::Ping(7))));
+        assert!(matches!(messages.next(), Some(NetworkMessage::Pong(7))));
+        assert!(matches!(
+            messages.next(),
+            Some(NetworkMessage::SendCmpct {
+                announce: true,
+                version: 2
+            })
+        ));
+        assert!(matches!(
+            messages.next(),
+            Some(NetworkMessage::FeeFilter(1000))
+        ));
+        assert!(matches!(messages.next(), Some(NetworkMessage::WtxidRelay)));
+        assert!(matches!(messages.next(), Some(NetworkMessage::SendAddrV2)));
+        match messages.next() {
+            Some(NetworkMessage::Unknown { command, payload }) => {
+                assert_eq!(command, "cfilter");
+                assert_eq!(payload, vec![1, 2, 3]);
+            }
+            m => panic!("unexpected {:?}", m),
+        }
+
+        //more inventory than bitcoind accepts in one message
+        let inv = envelope("inv", &[0xfe, 0x51, 0xc3, 0, 0]);
+        assert!(parse_message(&inv).is_err());
+    }
+}
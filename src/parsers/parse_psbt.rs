@@ -0,0 +1,427 @@
+use crate::{
+    parsers::{parse_transaction, parse_var_int, parse_witnesses},
+    psbt::{key_types::*, KeySource, Psbt, PsbtInput, PsbtKey, PsbtMap, PsbtOutput},
+    types::{Hash256, TxOutput, TxOutputBuilder},
+};
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::map,
+    error::{ErrorKind, ParseError},
+    multi::{count, length_data},
+    number::complete::{le_u32, le_u64, le_u8},
+    sequence::tuple,
+    Err, IResult,
+};
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+//a taproot control block has at most 128 hashes after the internal key
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+fn error(input: &[u8], kind: ErrorKind) -> Err<(&[u8], ErrorKind)> {
+    Err::Error(ParseError::from_error_kind(input, kind))
+}
+
+//true if f takes exactly the whole value
+fn whole<'a, O, F>(f: F, value: &'a [u8]) -> bool
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    matches!(f(value), Ok((rest, _)) if rest.is_empty())
+}
+
+pub fn parse_key_source(input: &[u8]) -> IResult<&[u8], KeySource> {
+    let (i, fingerprint) = take(4u8)(input)?;
+    if i.len() % 4 != 0 {
+        return Err(error(input, ErrorKind::Verify));
+    }
+    let (i, path) = count(le_u32, i.len() / 4)(i)?;
+    let mut source = KeySource {
+        fingerprint: [0; 4],
+        path,
+    };
+    source.fingerprint.copy_from_slice(fingerprint);
+    Ok((i, source))
+}
+
+//the leaf hashes a taproot key is used in, followed by its key source
+pub fn parse_tap_key_source(input: &[u8]) -> IResult<&[u8], (Vec<Hash256>, KeySource)> {
+    let (i, n) = parse_var_int(input)?;
+    if n > (i.len() / 32) as u64 {
+        return Err(error(input, ErrorKind::TooLarge));
+    }
+    tuple((
+        count(map(take(32u8), Hash256::new), n as usize),
+        parse_key_source,
+    ))(i)
+}
+
+//depth, leaf version and script
+type TapLeaf = (u8, u8, Vec<u8>);
+
+//the leaves of a taproot tree in depth first order
+pub fn parse_tap_tree(mut input: &[u8]) -> IResult<&[u8], Vec<TapLeaf>> {
+    let mut leaves = Vec::new();
+    while !input.is_empty() {
+        let (i, (depth, leaf_version, script)) =
+            tuple((le_u8, le_u8, length_data(parse_var_int)))(input)?;
+        leaves.push((depth, leaf_version, script.to_vec()));
+        input = i;
+    }
+    match leaves.is_empty() {
+        true => Err(error(input, ErrorKind::Verify)),
+        false => Ok((input, leaves)),
+    }
+}
+
+pub fn parse_psbt_output_value(input: &[u8]) -> IResult<&[u8], TxOutput> {
+    map(
+        tuple((le_u64, length_data(parse_var_int))),
+        |(value, script_pub_key)| {
+            TxOutputBuilder::new()
+                .value(value)
+                .script_pub_key(script_pub_key)
+                .build()
+        },
+    )(input)
+}
+
+fn is_pubkey(key_data: &[u8]) -> bool {
+    key_data.len() == 33 || key_data.len() == 65
+}
+
+fn is_schnorr_sig(value: &[u8]) -> bool {
+    value.len() == 64 || value.len() == 65
+}
+
+fn valid_global(key: &PsbtKey, value: &[u8]) -> bool {
+    let no_key_data = key.key_data.is_empty();
+    match key.key_type {
+        PSBT_GLOBAL_UNSIGNED_TX => {
+            no_key_data
+                && match parse_transaction(value) {
+                    Ok((rest, tx)) => {
+                        rest.is_empty()
+                            && tx.witnesses.is_none()
+                            && tx.inputs.iter().all(|input| input.script_sig.len() == 0)
+                    }
+                    Err(_) => false,
+                }
+        }
+        PSBT_GLOBAL_XPUB => key.key_data.len() == 78 && whole(parse_key_source, value),
+        PSBT_GLOBAL_TX_VERSION | PSBT_GLOBAL_FALLBACK_LOCKTIME | PSBT_GLOBAL_VERSION => {
+            no_key_data && value.len() == 4
+        }
+        PSBT_GLOBAL_INPUT_COUNT | PSBT_GLOBAL_OUTPUT_COUNT => {
+            no_key_data && whole(parse_var_int, value)
+        }
+        PSBT_GLOBAL_TX_MODIFIABLE => no_key_data && value.len() == 1,
+        _ => true,
+    }
+}
+
+fn valid_input(key: &PsbtKey, value: &[u8]) -> bool {
+    let no_key_data = key.key_data.is_empty();
+    let key_length = key.key_data.len();
+    match key.key_type {
+        PSBT_IN_NON_WITNESS_UTXO => no_key_data && whole(parse_transaction, value),
+        PSBT_IN_WITNESS_UTXO => no_key_data && whole(parse_psbt_output_value, value),
+        PSBT_IN_PARTIAL_SIG => is_pubkey(&key.key_data),
+        PSBT_IN_SIGHASH_TYPE
+        | PSBT_IN_OUTPUT_INDEX
+        | PSBT_IN_SEQUENCE
+        | PSBT_IN_REQUIRED_TIME_LOCKTIME
+        | PSBT_IN_REQUIRED_HEIGHT_LOCKTIME => no_key_data && value.len() == 4,
+        PSBT_IN_REDEEM_SCRIPT
+        | PSBT_IN_WITNESS_SCRIPT
+        | PSBT_IN_FINAL_SCRIPTSIG
+        | PSBT_IN_POR_COMMITMENT => no_key_data,
+        PSBT_IN_FINAL_SCRIPTWITNESS => no_key_data && whole(parse_witnesses, value),
+        PSBT_IN_BIP32_DERIVATION => is_pubkey(&key.key_data) && whole(parse_key_source, value),
+        PSBT_IN_RIPEMD160 | PSBT_IN_HASH160 => key_length == 20,
+        PSBT_IN_SHA256 | PSBT_IN_HASH256 => key_length == 32,
+        PSBT_IN_PREVIOUS_TXID | PSBT_IN_TAP_INTERNAL_KEY | PSBT_IN_TAP_MERKLE_ROOT => {
+            no_key_data && value.len() == 32
+        }
+        PSBT_IN_TAP_KEY_SIG => no_key_data && is_schnorr_sig(value),
+        PSBT_IN_TAP_SCRIPT_SIG => key_length == 64 && is_schnorr_sig(value),
+        PSBT_IN_TAP_LEAF_SCRIPT => {
+            key_length >= 33
+                && (key_length - 33) % 32 == 0
+                && (key_length - 33) / 32 <= TAPROOT_CONTROL_MAX_NODE_COUNT
+                && !value.is_empty()
+        }
+        PSBT_IN_TAP_BIP32_DERIVATION => key_length == 32 && whole(parse_tap_key_source, value),
+        _ => true,
+    }
+}
+
+fn valid_output(key: &PsbtKey, value: &[u8]) -> bool {
+    let no_key_data = key.key_data.is_empty();
+    match key.key_type {
+        PSBT_OUT_REDEEM_SCRIPT | PSBT_OUT_WITNESS_SCRIPT | PSBT_OUT_SCRIPT => no_key_data,
+        PSBT_OUT_BIP32_DERIVATION => is_pubkey(&key.key_data) && whole(parse_key_source, value),
+        PSBT_OUT_AMOUNT => no_key_data && value.len() == 8,
+        PSBT_OUT_TAP_INTERNAL_KEY => no_key_data && value.len() == 32,
+        PSBT_OUT_TAP_TREE => no_key_data && whole(parse_tap_tree, value),
+        PSBT_OUT_TAP_BIP32_DERIVATION => {
+            key.key_data.len() == 32 && whole(parse_tap_key_source, value)
+        }
+        _ => true,
+    }
+}
+
+//entries up to the zero length separator, known fields have to be well formed
+fn parse_map(mut input: &[u8], valid: fn(&PsbtKey, &[u8]) -> bool) -> IResult<&[u8], PsbtMap> {
+    let mut psbt_map = PsbtMap::new();
+    loop {
+        let (i, key) = length_data(parse_var_int)(input)?;
+        if key.is_empty() {
+            return Ok((i, psbt_map));
+        }
+        let (key_data, key_type) = parse_var_int(key)?;
+        let (i, value) = length_data(parse_var_int)(i)?;
+        let key = PsbtKey::new(key_type, key_data);
+        if !valid(&key, value) || psbt_map.get_key(&key).is_some() {
+            return Err(error(input, ErrorKind::Verify));
+        }
+        psbt_map.0.push((key, value.to_vec()));
+        input = i;
+    }
+}
+
+//a version 0 or 2 PSBT, the fields of the other version are rejected
+pub fn parse_psbt(input: &[u8]) -> IResult<&[u8], Psbt> {
+    let (i, _) = tag(PSBT_MAGIC)(input)?;
+    let (mut i, global) = parse_map(i, valid_global)?;
+    let version = global
+        .get(PSBT_GLOBAL_VERSION)
+        .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+        .unwrap_or(0);
+    let v2_global = [
+        PSBT_GLOBAL_TX_VERSION,
+        PSBT_GLOBAL_FALLBACK_LOCKTIME,
+        PSBT_GLOBAL_INPUT_COUNT,
+        PSBT_GLOBAL_OUTPUT_COUNT,
+        PSBT_GLOBAL_TX_MODIFIABLE,
+    ];
+    let v2_input = [
+        PSBT_IN_PREVIOUS_TXID,
+        PSBT_IN_OUTPUT_INDEX,
+        PSBT_IN_SEQUENCE,
+        PSBT_IN_REQUIRED_TIME_LOCKTIME,
+        PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+    ];
+    let v2_output = [PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT];
+    let count_of = |key_type| {
+        global
+            .get(key_type)
+            .and_then(|value| parse_var_int(value).ok())
+            .map(|(_, n)| n)
+    };
+    let (input_count, output_count) = match version {
+        0 => match global.get(PSBT_GLOBAL_UNSIGNED_TX) {
+            Some(tx) if !v2_global.iter().any(|k| global.contains(*k)) => {
+                match parse_transaction(tx) {
+                    Ok((_, tx)) => (tx.inputs.len() as u64, tx.outputs.len() as u64),
+                    Err(_) => return Err(error(input, ErrorKind::Verify)),
+                }
+            }
+            _ => return Err(error(input, ErrorKind::Verify)),
+        },
+        2 => match (
+            global.contains(PSBT_GLOBAL_UNSIGNED_TX) || !global.contains(PSBT_GLOBAL_TX_VERSION),
+            count_of(PSBT_GLOBAL_INPUT_COUNT),
+            count_of(PSBT_GLOBAL_OUTPUT_COUNT),
+        ) {
+            (false, Some(inputs), Some(outputs)) => (inputs, outputs),
+            _ => return Err(error(input, ErrorKind::Verify)),
+        },
+        _ => return Err(error(input, ErrorKind::Verify)),
+    };
+    //the maps are parsed one by one, so a large count cannot allocate much
+    let mut inputs = Vec::new();
+    for _ in 0..input_count {
+        let (rest, map) = parse_map(i, valid_input)?;
+        let has = |key_type| map.contains(key_type);
+        let valid = match version {
+            0 => !v2_input.iter().any(|k| has(*k)),
+            _ => has(PSBT_IN_PREVIOUS_TXID) && has(PSBT_IN_OUTPUT_INDEX),
+        };
+        if !valid {
+            return Err(error(i, ErrorKind::Verify));
+        }
+        inputs.push(PsbtInput { map });
+        i = rest;
+    }
+    let mut outputs = Vec::new();
+    for _ in 0..output_count {
+        let (rest, map) = parse_map(i, valid_output)?;
+        let valid = match version {
+            0 => !v2_output.iter().any(|k| map.contains(*k)),
+            _ => v2_output.iter().all(|k| map.contains(*k)),
+        };
+        if !valid {
+            return Err(error(i, ErrorKind::Verify));
+        }
+        outputs.push(PsbtOutput { map });
+        i = rest;
+    }
+    let psbt = Psbt {
+        global,
+        inputs,
+        outputs,
+    };
+    Ok((i, psbt))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        parsers::parse_transaction,
+        serializers::{serialize_psbt, serialize_transaction_no_witness},
+        utils::base64_decode,
+    };
+    use hex;
+
+    //the creator vector of BIP174
+    const CREATOR: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAAAAAA=";
+
+    fn creator() -> Psbt {
+        parse_psbt(&base64_decode(CREATOR).unwrap()).unwrap().1
+    }
+
+    fn fails(psbt: &Psbt) -> bool {
+        parse_psbt(&serialize_psbt(psbt)).is_err()
+    }
+
+    #[test]
+    fn test_parse_psbt() {
+        let data = base64_decode(CREATOR).unwrap();
+        let (rest, psbt) = parse_psbt(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(psbt.version(), 0);
+        assert_eq!((psbt.inputs.len(), psbt.outputs.len()), (2, 2));
+        let tx = psbt.unsigned_tx().unwrap();
+        let mut txid = tx.txid.to_wire_bytes();
+        txid.reverse();
+        assert_eq!(
+            hex::encode(txid),
+            "82efd652d7ab1197f01a5f4d9a30cb4c68bb79ab6fec58dfa1bf112291d1617b"
+        );
+        assert_eq!(tx.outputs[0].value, 149990000);
+        assert_eq!(tx.outputs[1].value, 100000000);
+        assert_eq!(serialize_psbt(&psbt), data);
+        assert_eq!(psbt.to_base64(), CREATOR);
+        assert_eq!(Psbt::from_base64(CREATOR).unwrap(), psbt);
+    }
+
+    #[test]
+    fn test_parse_psbt_unknown() {
+        let mut psbt = creator();
+        psbt.global
+            .insert(PSBT_GLOBAL_PROPRIETARY, b"\x03abc\x00", b"value");
+        psbt.inputs[1].map.insert(0x99, b"key", &[]);
+        psbt.outputs[0].map.insert(0x0f, &[], b"out");
+        let data = serialize_psbt(&psbt);
+        let (rest, parsed) = parse_psbt(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed, psbt);
+        assert_eq!(parsed.unknown().len(), 1);
+        assert_eq!(parsed.inputs[1].unknown()[0].0.key_data, b"key".to_vec());
+        assert_eq!(parsed.outputs[0].unknown()[0].1, b"out");
+    }
+
+    #[test]
+    fn test_parse_psbt_invalid() {
+        let mut data = base64_decode(CREATOR).unwrap();
+        data[4] = 0;
+        assert!(parse_psbt(&data).is_err());
+
+        //map truncated before its separator
+        let data = base64_decode(CREATOR).unwrap();
+        assert!(parse_psbt(&data[..data.len() - 1]).is_err());
+
+        let mut psbt = creator();
+        let entry = psbt.global.0[0].clone();
+        psbt.global.0.push(entry);
+        assert!(fails(&psbt));
+
+        //the unsigned transaction cannot have script sigs
+        let tx = include_bytes!(
+            "../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.bin"
+        );
+        let (_, tx) = parse_transaction(tx).unwrap();
+        let mut psbt = creator();
+        psbt.global.insert(
+            PSBT_GLOBAL_UNSIGNED_TX,
+            &[],
+            &serialize_transaction_no_witness(&tx),
+        );
+        assert!(fails(&psbt));
+
+        let mut psbt = creator();
+        psbt.inputs[0]
+            .map
+            .insert(PSBT_IN_PARTIAL_SIG, &[2; 32], &[0x30; 71]);
+        assert!(fails(&psbt));
+        psbt.inputs[0].map.remove(PSBT_IN_PARTIAL_SIG);
+        psbt.inputs[0]
+            .map
+            .insert(PSBT_IN_PARTIAL_SIG, &[2; 33], &[0x30; 71]);
+        assert!(!fails(&psbt));
+
+        let mut psbt = creator();
+        psbt.inputs[1]
+            .map
+            .insert(PSBT_IN_SIGHASH_TYPE, &[], &[1, 0]);
+        assert!(fails(&psbt));
+    }
+
+    #[test]
+    fn test_parse_psbt_version() {
+        //version 0 cannot have the fields of version 2
+        let mut psbt = creator();
+        psbt.global
+            .insert(PSBT_GLOBAL_TX_VERSION, &[], &2u32.to_le_bytes());
+        assert!(fails(&psbt));
+        let mut psbt = creator();
+        psbt.inputs[0].map.insert(PSBT_IN_SEQUENCE, &[], &[0; 4]);
+        assert!(fails(&psbt));
+
+        //version 2 cannot have an unsigned transaction
+        let mut psbt = creator();
+        psbt.global
+            .insert(PSBT_GLOBAL_VERSION, &[], &2u32.to_le_bytes());
+        psbt.global
+            .insert(PSBT_GLOBAL_TX_VERSION, &[], &2u32.to_le_bytes());
+        psbt.global.insert(PSBT_GLOBAL_INPUT_COUNT, &[], &[0]);
+        psbt.global.insert(PSBT_GLOBAL_OUTPUT_COUNT, &[], &[0]);
+        psbt.inputs.clear();
+        psbt.outputs.clear();
+        assert!(fails(&psbt));
+        psbt.global.remove(PSBT_GLOBAL_UNSIGNED_TX);
+        assert!(!fails(&psbt));
+
+        psbt.global
+            .insert(PSBT_GLOBAL_VERSION, &[], &1u32.to_le_bytes());
+        assert!(fails(&psbt));
+    }
+
+    #[test]
+    fn test_parse_tap_tree() {
+        let data = hex::decode("01c0015102c0015202c00153").unwrap();
+        let (rest, leaves) = parse_tap_tree(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            leaves,
+            vec![
+                (1, 0xc0, vec![0x51]),
+                (2, 0xc0, vec![0x52]),
+                (2, 0xc0, vec![0x53])
+            ]
+        );
+        assert!(parse_tap_tree(&data[..data.len() - 1]).is_err());
+        assert!(parse_tap_tree(&[]).is_err());
+    }
+}
@@ -97,14 +97,14 @@ mod test {
         );
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("895bbf9e251a7e37281ad4ad2ce12abecb78cf0d1dfbbfeb47909c6079020d64")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("ab1fefe700cd871d759bd8c7f77438e4561374dd1397e17e7526d48eeb44d430")
                     .unwrap()
@@ -161,14 +161,14 @@ mod test {
         let data = include_bytes!("../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.bin");
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("b810813280f55601bb994b62ec0f13f9fd11bc82cfe73b02e79d970f46147282")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("b810813280f55601bb994b62ec0f13f9fd11bc82cfe73b02e79d970f46147282")
                     .unwrap()
@@ -203,14 +203,14 @@ mod test {
         );
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("1c24f5e4d751eda71f819e26cf5f3808865149d656359fe099f5b472ea0c2e98")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("8cbc8c76ba2a594cc1056e5446e6cbb85eb82fd573faa726caa788aaae5b1ca2")
                     .unwrap()
@@ -256,14 +256,14 @@ mod test {
         let data = include_bytes!("../test_data/tx_9e48f98e0b27e09ccabf576076c01dc6277c3961c8f616dea154f6822fb17765_large_segwit.bin");
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("6577b12f82f654a1de16f6c861397c27c61dc0766057bfca9ce0270b8ef9489e")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("fa8bf120eb70ac07e8520f56536dcc9e4c0f1f7c1db8036c202c73d3a8c6fe00")
                     .unwrap()
@@ -676,14 +676,14 @@ mod test {
         );
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("91d15a05f0cb54937d254cb02ed0b1ddd517b1d47993e05ea47563504f6323c6")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("2776bbc43da932c584c20ef47b64e1e00a767520a90ae0d20c35535be47dde89")
                     .unwrap()
@@ -763,14 +763,14 @@ mod test {
         let data = include_bytes!("../test_data/tx_d1425c41b1786b4c7464a9431c2c39bc6920a6d5e6a56295bc0b2e3274941d32.regtest.bin");
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("321d9474322e0bbc9562a5e6d5a62069bc392c1c43a964744c6b78b1415c42d1")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("0c8b5575031256eff14e91d65d86acfeb99e0ce55c7b080868e00d5224c00361")
                     .unwrap()
@@ -815,14 +815,14 @@ mod test {
         let data = include_bytes!("../test_data/tx_de06af29a80be52bb5f4b6c86998dcfdf0f9e7f66a1ebb7e9d20d65cc6785d8c.native_witness.bin");
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("8c5d78c65cd6209d7ebb1e6af6e7f9f0fddc9869c8b6f4b52be50ba829af06de")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("0d7a81fb2c608a663c0b3491d1fdd93edff7d99f5583a7789b61b964d5730bb5")
                     .unwrap()
@@ -869,14 +869,14 @@ mod test {
         );
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("a59c2f30cd1bc4b02b96adc78ddacd4650c2a9efbc8e0acf4a62c64b948137e7")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("32031301ed7082726712efb99523a493a7b722dbda2e28eb2034a0168639a04a")
                     .unwrap()
@@ -966,14 +966,14 @@ mod test {
         let data = include_bytes!("../test_data/tx_fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54.segwit.bin");
         let (_, tx) = parse_transaction(data).unwrap();
         assert_eq!(
-            tx.txid,
+            tx.txid.0,
             Hash256::new(
                 &hex::decode("54bea622d987ad81b8f40d4032ac493d46b38b7b7c5d6adfa43e6df2e12d04fb")
                     .unwrap()
             )
         );
         assert_eq!(
-            tx.wtxid,
+            tx.wtxid.0,
             Hash256::new(
                 &hex::decode("99f6715bb7e45c894833ddf39556a6abe088daae36ae676266d9df7bac2c62f4")
                     .unwrap()
@@ -13,7 +13,9 @@ use nom::{
 pub fn parse_tx_inputs(input: &[u8]) -> IResult<&[u8], (Vec<TxInput>, usize)> {
     let len_start = input.len();
     let (mut input, in_count) = parse_var_int(input)?;
-    let mut vec: Vec<TxInput> = Vec::with_capacity(in_count as usize);
+    //the count is not trusted for the allocation, an input takes at least 41 bytes
+    let capacity = in_count.min(input.len() as u64 / 41) as usize;
+    let mut vec: Vec<TxInput> = Vec::with_capacity(capacity);
     for _ in 0..in_count {
         let (i, (previous_tx_hash, vout, script_sig, sequence)) =
             tuple((take(32u32), le_u32, length_data(parse_var_int), le_u32))(input)?;
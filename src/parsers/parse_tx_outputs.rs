@@ -12,7 +12,9 @@ use nom::{
 pub fn parse_tx_outputs(input: &[u8]) -> IResult<&[u8], (Vec<TxOutput>, usize)> {
     let len_start = input.len();
     let (mut input, out_count) = parse_var_int(input)?;
-    let mut vec: Vec<TxOutput> = Vec::with_capacity(out_count as usize);
+    //the count is not trusted for the allocation, an output takes at least 9 bytes
+    let capacity = out_count.min(input.len() as u64 / 9) as usize;
+    let mut vec: Vec<TxOutput> = Vec::with_capacity(capacity);
     for _ in 0..out_count {
         let (i, (value, script_pub_key)) = tuple((le_u64, length_data(parse_var_int)))(input)?;
         input = i;
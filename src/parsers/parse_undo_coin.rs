@@ -0,0 +1,150 @@
+use crate::{
+    parsers::parse_b128_var_int, script::opcodes::*, types::TxOutput, utils::decompress_pubkey,
+    utxo::Coin,
+};
+use nom::{
+    bytes::complete::take,
+    error::{ErrorKind, ParseError},
+    Err, IResult,
+};
+
+const SPECIAL_SCRIPTS: u64 = 6;
+const MAX_SCRIPT_SIZE: u64 = 10_000;
+
+//inverse of bitcoind's CompressAmount, which takes advantage of amounts being round numbers
+pub fn decompress_amount(x: u64) -> u64 {
+    if x == 0 {
+        return 0;
+    }
+    let mut x = x - 1;
+    //the exponent is stored in the lowest decimal digit
+    let mut e = x % 10;
+    x /= 10;
+    let mut n = match e < 9 {
+        true => {
+            let d = (x % 9) + 1;
+            x /= 9;
+            x * 10 + d
+        }
+        false => x + 1,
+    };
+    //bitcoind's uint64 arithmetic, a corrupt amount wraps around
+    while e > 0 {
+        n = n.wrapping_mul(10);
+        e -= 1;
+    }
+    n
+}
+
+fn decompress_script(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    let (i, size) = parse_b128_var_int(input)?;
+    let script = match size {
+        0 => {
+            let (i, hash) = take(20u8)(i)?;
+            let script = [
+                &[OP_DUP, OP_HASH160, 20][..],
+                hash,
+                &[OP_EQUALVERIFY, OP_CHECKSIG],
+            ];
+            (i, script.concat())
+        }
+        1 => {
+            let (i, hash) = take(20u8)(i)?;
+            (i, [&[OP_HASH160, 20][..], hash, &[OP_EQUAL]].concat())
+        }
+        2 | 3 => {
+            let (i, x) = take(32u8)(i)?;
+            (i, [&[33, size as u8][..], x, &[OP_CHECKSIG]].concat())
+        }
+        //uncompressed keys are stored compressed
+        4 | 5 => {
+            let (i, x) = take(32u8)(i)?;
+            let pubkey = [&[size as u8 - 2][..], x].concat();
+            let pubkey = match decompress_pubkey(&pubkey) {
+                Some(pubkey) => pubkey,
+                None => {
+                    return Err(Err::Error(ParseError::from_error_kind(
+                        input,
+                        ErrorKind::Verify,
+                    )))
+                }
+            };
+            (i, [&[65][..], &pubkey, &[OP_CHECKSIG]].concat())
+        }
+        //oversized scripts are unspendable and replaced by OP_RETURN
+        _ if size - SPECIAL_SCRIPTS > MAX_SCRIPT_SIZE => {
+            let (i, _) = take(size - SPECIAL_SCRIPTS)(i)?;
+            (i, vec![OP_RETURN])
+        }
+        _ => {
+            let (i, script) = take(size - SPECIAL_SCRIPTS)(i)?;
+            (i, script.to_vec())
+        }
+    };
+    Ok(script)
+}
+
+//an output spent by a transaction as stored in rev*.dat
+pub fn parse_undo_coin(input: &[u8]) -> IResult<&[u8], Coin> {
+    let (i, code) = parse_b128_var_int(input)?;
+    let height = (code >> 1) as u32;
+    //a leftover of the transaction version once stored here
+    let (i, _) = match height > 0 {
+        true => parse_b128_var_int(i)?,
+        false => (i, 0),
+    };
+    let (i, amount) = parse_b128_var_int(i)?;
+    let (i, script_pub_key) = decompress_script(i)?;
+    let output = TxOutput::new(decompress_amount(amount), &script_pub_key);
+    Ok((i, Coin::new(output, height, code & 1 == 1)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Bytes;
+    #[test]
+    fn test_decompress_amount() {
+        assert_eq!(decompress_amount(0), 0);
+        assert_eq!(decompress_amount(1), 1);
+        assert_eq!(decompress_amount(9), 100_000_000);
+        assert_eq!(decompress_amount(50), 5_000_000_000);
+        assert_eq!(decompress_amount(29), 300_000_000);
+        assert_eq!(decompress_amount(10000), 1_000_000_000_000);
+        decompress_amount(u64::MAX);
+        decompress_amount(u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_parse_undo_coin() {
+        //coinbase at height 100, 50 BTC to a P2PKH script
+        let hash = "62e907b15cbf27d5425399ebf6f0fb50ebb88f18";
+        let data = hex::decode(format!("8049003200{}", hash)).unwrap();
+        let (rest, coin) = parse_undo_coin(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(coin.height, 100);
+        assert!(coin.is_coinbase);
+        assert_eq!(coin.output.value, 5_000_000_000);
+        let Bytes(script_pub_key) = &coin.output.script_pub_key;
+        assert_eq!(hex::encode(script_pub_key), format!("76a914{}88ac", hash));
+
+        //the genesis output, an uncompressed key, at height 0 without the version
+        let x = "678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb6";
+        let data = hex::decode(format!("003205{}", x)).unwrap();
+        let (_, coin) = parse_undo_coin(&data).unwrap();
+        assert_eq!(coin.height, 0);
+        assert!(!coin.is_coinbase);
+        let Bytes(script_pub_key) = &coin.output.script_pub_key;
+        assert_eq!(hex::encode(script_pub_key), "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac");
+
+        //a P2WPKH script is stored as is
+        let data = hex::decode(format!("0300cd101c0014{}", hash)).unwrap();
+        let (_, coin) = parse_undo_coin(&data).unwrap();
+        assert_eq!(coin.height, 1);
+        assert_eq!(coin.output.value, 1_000_000_000_000);
+        let Bytes(script_pub_key) = &coin.output.script_pub_key;
+        assert_eq!(hex::encode(script_pub_key), format!("0014{}", hash));
+
+        assert!(parse_undo_coin(&data[..data.len() - 1]).is_err());
+    }
+}
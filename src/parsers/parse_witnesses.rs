@@ -1,6 +1,6 @@
 use crate::{
     parsers::parse_var_int,
-    types::Witness
+    types::{Bytes, Witness}
 };
 use nom::{
     multi::length_data,
@@ -16,7 +16,8 @@ pub fn parse_witnesses(input: &[u8]) -> IResult<&[u8], (Vec<Witness>, usize)> {
     } else {
         for _ in 0..witness_count {
             let (i, witness) = length_data(parse_var_int)(input)?;
-            vec.push(witness.into());
+            //an empty item is kept apart from the missing item standing for an empty stack
+            vec.push(Witness(Some(Bytes::new(witness))));
             input = i;
         }
     }
@@ -52,4 +53,20 @@ mod test {
         test_witness!(&witnesses[2], "304402200da46260a1a6b6e7fe0e23372adcf7e9569c9f27501728a5d61ab4a3c74732b302200790fb7ce382c742b8e23f53c302b19a33cba9d68a83f33974b971511e2c712e01");
         test_witness!(&witnesses[3], "5221026c8f72b9e63db63907115e65d4da86eaae595b22fdc85ec75301bb4adbf203582103806535be3e3920e5eedee92de5714188fd6a784f2bf7b04f87de0b9c3ae1ecdb21024b23bfdce2afcae7e28c42f7f79aa100f22931712c52d7414a526ba494d44a2553ae");
     }
+    #[test]
+    fn test_parse_empty_witnesses() {
+        //an empty stack
+        let (_, (witnesses, size)) = parse_witnesses(&[0x00]).unwrap();
+        assert_eq!(size, 1);
+        assert_eq!(witnesses, vec![Witness(None)]);
+        //a stack of a single empty item
+        let (_, (witnesses, size)) = parse_witnesses(&[0x01, 0x00]).unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(witnesses, vec![Witness(Some(Bytes::new(&[])))]);
+        let (_, (witnesses, _)) = parse_witnesses(&[0x02, 0x00, 0x01, 0xab]).unwrap();
+        assert_eq!(
+            witnesses,
+            vec![Witness(Some(Bytes::new(&[]))), Witness(Some(Bytes::new(&[0xab])))]
+        );
+    }
 }
@@ -0,0 +1,33 @@
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PsbtError {
+    InvalidBase64,
+    //not a well formed version 0 or 2 PSBT
+    InvalidPsbt,
+    //the unsigned transaction of a creator has script sigs or witnesses
+    SignedTransaction,
+    //PSBTs of different transactions cannot be combined
+    DifferentTransaction,
+    //the inputs require both a time and a height based lock time
+    LockTime,
+    //the input with this index
+    MissingUtxo(usize),
+    CannotFinalize(usize),
+    NotFinalized(usize),
+}
+
+impl std::fmt::Display for PsbtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PsbtError::InvalidBase64 => write!(f, "invalid base64"),
+            PsbtError::InvalidPsbt => write!(f, "invalid PSBT"),
+            PsbtError::SignedTransaction => write!(f, "the transaction is already signed"),
+            PsbtError::DifferentTransaction => write!(f, "PSBTs of different transactions"),
+            PsbtError::LockTime => write!(f, "inputs with incompatible lock times"),
+            PsbtError::MissingUtxo(i) => write!(f, "the output spent by input {} is unknown", i),
+            PsbtError::CannotFinalize(i) => write!(f, "input {} cannot be finalized", i),
+            PsbtError::NotFinalized(i) => write!(f, "input {} is not finalized", i),
+        }
+    }
+}
+
+impl std::error::Error for PsbtError {}
@@ -0,0 +1,198 @@
+use crate::{
+    parsers::{parse_key_source, parse_psbt, parse_transaction, parse_var_int},
+    psbt::{
+        input::u32_value, key_types::*, KeySource, PsbtError, PsbtInput, PsbtKey, PsbtMap,
+        PsbtOutput,
+    },
+    serializers::{serialize_psbt, serialize_transaction},
+    types::{Transaction, TransactionBuilder, TxInput, TxOutput, Witness},
+    utils::{base64_decode, base64_encode},
+};
+
+const KNOWN: [u64; 8] = [
+    PSBT_GLOBAL_UNSIGNED_TX,
+    PSBT_GLOBAL_XPUB,
+    PSBT_GLOBAL_TX_VERSION,
+    PSBT_GLOBAL_FALLBACK_LOCKTIME,
+    PSBT_GLOBAL_INPUT_COUNT,
+    PSBT_GLOBAL_OUTPUT_COUNT,
+    PSBT_GLOBAL_TX_MODIFIABLE,
+    PSBT_GLOBAL_VERSION,
+];
+
+//a transaction from its parts, with txid, wtxid and size computed
+pub(crate) fn build_transaction(
+    version: u32,
+    mut inputs: Vec<TxInput>,
+    mut outputs: Vec<TxOutput>,
+    witnesses: Option<Vec<Vec<Witness>>>,
+    lock_time: u32,
+) -> Transaction {
+    let tx = TransactionBuilder::new()
+        .version(version)
+        .inputs(&mut inputs)
+        .outputs(&mut outputs)
+        .witnesses(witnesses)
+        .lock_time(lock_time)
+        .build();
+    parse_transaction(&serialize_transaction(&tx)).unwrap().1
+}
+
+//a partially signed transaction, see BIP174 and BIP370
+#[derive(Debug, PartialEq, Clone)]
+pub struct Psbt {
+    pub global: PsbtMap,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+}
+
+impl Psbt {
+    //the creator, a version 0 PSBT of a transaction without script sigs and witnesses
+    pub fn from_unsigned_tx(tx: &Transaction) -> Result<Psbt, PsbtError> {
+        if tx.witnesses.is_some() || tx.inputs.iter().any(|i| i.script_sig.len() > 0) {
+            return Err(PsbtError::SignedTransaction);
+        }
+        let mut global = PsbtMap::new();
+        global.insert(PSBT_GLOBAL_UNSIGNED_TX, &[], &serialize_transaction(tx));
+        Ok(Psbt {
+            global,
+            inputs: vec![PsbtInput::new(); tx.inputs.len()],
+            outputs: vec![PsbtOutput::new(); tx.outputs.len()],
+        })
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Psbt, PsbtError> {
+        match parse_psbt(data) {
+            Ok(([], psbt)) => Ok(psbt),
+            _ => Err(PsbtError::InvalidPsbt),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize_psbt(self)
+    }
+
+    pub fn from_base64(s: &str) -> Result<Psbt, PsbtError> {
+        Psbt::from_bytes(&base64_decode(s.trim()).ok_or(PsbtError::InvalidBase64)?)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.to_bytes())
+    }
+
+    pub fn version(&self) -> u32 {
+        u32_value(self.global.get(PSBT_GLOBAL_VERSION)).unwrap_or(0)
+    }
+
+    pub fn tx_version(&self) -> Option<u32> {
+        u32_value(self.global.get(PSBT_GLOBAL_TX_VERSION))
+    }
+
+    pub fn fallback_locktime(&self) -> Option<u32> {
+        u32_value(self.global.get(PSBT_GLOBAL_FALLBACK_LOCKTIME))
+    }
+
+    pub fn input_count(&self) -> Option<u64> {
+        let value = self.global.get(PSBT_GLOBAL_INPUT_COUNT)?;
+        parse_var_int(value).ok().map(|(_, n)| n)
+    }
+
+    pub fn output_count(&self) -> Option<u64> {
+        let value = self.global.get(PSBT_GLOBAL_OUTPUT_COUNT)?;
+        parse_var_int(value).ok().map(|(_, n)| n)
+    }
+
+    //the bit field telling which parts of the transaction can still be changed
+    pub fn tx_modifiable(&self) -> Option<u8> {
+        self.global.get(PSBT_GLOBAL_TX_MODIFIABLE).map(|v| v[0])
+    }
+
+    //serialized extended public key and its key source
+    pub fn xpubs(&self) -> Vec<(&[u8], KeySource)> {
+        self.global
+            .entries(PSBT_GLOBAL_XPUB)
+            .filter_map(|(xpub, value)| Some((xpub, parse_key_source(value).ok()?.1)))
+            .collect()
+    }
+
+    pub fn unknown(&self) -> Vec<(&PsbtKey, &[u8])> {
+        self.global.unknown(&KNOWN)
+    }
+
+    //the lock time of a version 2 PSBT, the largest one required by the inputs
+    //heights are used when the inputs allow both kinds
+    fn lock_time(&self) -> Result<u32, PsbtError> {
+        let required: Vec<_> = self
+            .inputs
+            .iter()
+            .map(|i| (i.required_time_locktime(), i.required_height_locktime()))
+            .filter(|(time, height)| time.is_some() || height.is_some())
+            .collect();
+        if required.is_empty() {
+            return Ok(self.fallback_locktime().unwrap_or(0));
+        }
+        if required.iter().all(|(_, height)| height.is_some()) {
+            return Ok(required.iter().filter_map(|r| r.1).max().unwrap());
+        }
+        if required.iter().all(|(time, _)| time.is_some()) {
+            return Ok(required.iter().filter_map(|r| r.0).max().unwrap());
+        }
+        Err(PsbtError::LockTime)
+    }
+
+    //the unsigned transaction, built from the input and output fields for version 2
+    pub fn unsigned_tx(&self) -> Result<Transaction, PsbtError> {
+        if self.version() == 0 {
+            let tx = self
+                .global
+                .get(PSBT_GLOBAL_UNSIGNED_TX)
+                .ok_or(PsbtError::InvalidPsbt)?;
+            return Ok(parse_transaction(tx).map_err(|_| PsbtError::InvalidPsbt)?.1);
+        }
+        let mut inputs = Vec::new();
+        for input in self.inputs.iter() {
+            let (txid, vout) = match (input.previous_txid(), input.output_index()) {
+                (Some(txid), Some(vout)) => (txid, vout),
+                _ => return Err(PsbtError::InvalidPsbt),
+            };
+            inputs.push(TxInput::new(
+                &txid.0,
+                vout,
+                &[],
+                input.sequence().unwrap_or(0xffffffff),
+            ));
+        }
+        let mut outputs = Vec::new();
+        for output in self.outputs.iter() {
+            match (output.amount(), output.script()) {
+                (Some(amount), Some(script)) => outputs.push(TxOutput::new(amount, script)),
+                _ => return Err(PsbtError::InvalidPsbt),
+            }
+        }
+        let version = self.tx_version().ok_or(PsbtError::InvalidPsbt)?;
+        Ok(build_transaction(
+            version,
+            inputs,
+            outputs,
+            None,
+            self.lock_time()?,
+        ))
+    }
+
+    //the output spent by an input, from its witness utxo or else its non witness utxo
+    pub fn spent_output(&self, index: usize) -> Option<TxOutput> {
+        let input = self.inputs.get(index)?;
+        if let Some(output) = input.witness_utxo() {
+            return Some(output);
+        }
+        let vout = match self.version() {
+            0 => self.unsigned_tx().ok()?.inputs.get(index)?.vout,
+            _ => input.output_index()?,
+        };
+        let mut tx = input.non_witness_utxo()?;
+        match (vout as usize) < tx.outputs.len() {
+            true => Some(tx.outputs.swap_remove(vout as usize)),
+            false => None,
+        }
+    }
+}
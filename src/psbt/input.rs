@@ -0,0 +1,210 @@
+use crate::{
+    parsers::{
+        parse_key_source, parse_psbt_output_value, parse_tap_key_source, parse_transaction,
+        parse_witnesses,
+    },
+    psbt::{key_types::*, KeySource, PsbtKey, PsbtMap},
+    serializers::{serialize_tx_output, serialize_witnesses},
+    types::{Hash256, Transaction, TxOutput, Witness},
+};
+use std::convert::TryInto;
+
+const KNOWN: [u64; 25] = [
+    PSBT_IN_NON_WITNESS_UTXO,
+    PSBT_IN_WITNESS_UTXO,
+    PSBT_IN_PARTIAL_SIG,
+    PSBT_IN_SIGHASH_TYPE,
+    PSBT_IN_REDEEM_SCRIPT,
+    PSBT_IN_WITNESS_SCRIPT,
+    PSBT_IN_BIP32_DERIVATION,
+    PSBT_IN_FINAL_SCRIPTSIG,
+    PSBT_IN_FINAL_SCRIPTWITNESS,
+    PSBT_IN_POR_COMMITMENT,
+    PSBT_IN_RIPEMD160,
+    PSBT_IN_SHA256,
+    PSBT_IN_HASH160,
+    PSBT_IN_HASH256,
+    PSBT_IN_PREVIOUS_TXID,
+    PSBT_IN_OUTPUT_INDEX,
+    PSBT_IN_SEQUENCE,
+    PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+    PSBT_IN_TAP_KEY_SIG,
+    PSBT_IN_TAP_SCRIPT_SIG,
+    PSBT_IN_TAP_LEAF_SCRIPT,
+    PSBT_IN_TAP_BIP32_DERIVATION,
+    PSBT_IN_TAP_INTERNAL_KEY,
+    PSBT_IN_TAP_MERKLE_ROOT,
+];
+
+pub(crate) fn u32_value(value: Option<&[u8]>) -> Option<u32> {
+    value
+        .and_then(|v| v.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+//the fields of an input map, values that do not decode read as missing
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PsbtInput {
+    pub map: PsbtMap,
+}
+
+impl PsbtInput {
+    pub fn new() -> PsbtInput {
+        PsbtInput::default()
+    }
+
+    pub fn non_witness_utxo(&self) -> Option<Transaction> {
+        let value = self.map.get(PSBT_IN_NON_WITNESS_UTXO)?;
+        parse_transaction(value).ok().map(|(_, tx)| tx)
+    }
+
+    pub fn set_non_witness_utxo(&mut self, tx: &[u8]) {
+        self.map.insert(PSBT_IN_NON_WITNESS_UTXO, &[], tx);
+    }
+
+    pub fn witness_utxo(&self) -> Option<TxOutput> {
+        let value = self.map.get(PSBT_IN_WITNESS_UTXO)?;
+        parse_psbt_output_value(value)
+            .ok()
+            .map(|(_, output)| output)
+    }
+
+    pub fn set_witness_utxo(&mut self, output: &TxOutput) {
+        self.map
+            .insert(PSBT_IN_WITNESS_UTXO, &[], &serialize_tx_output(output));
+    }
+
+    //public key and signature with the sighash byte
+    pub fn partial_sigs(&self) -> Vec<(&[u8], &[u8])> {
+        self.map.entries(PSBT_IN_PARTIAL_SIG).collect()
+    }
+
+    pub fn add_partial_sig(&mut self, pubkey: &[u8], sig: &[u8]) {
+        self.map.insert(PSBT_IN_PARTIAL_SIG, pubkey, sig);
+    }
+
+    pub fn sighash_type(&self) -> Option<u32> {
+        u32_value(self.map.get(PSBT_IN_SIGHASH_TYPE))
+    }
+
+    pub fn redeem_script(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_IN_REDEEM_SCRIPT)
+    }
+
+    pub fn set_redeem_script(&mut self, script: &[u8]) {
+        self.map.insert(PSBT_IN_REDEEM_SCRIPT, &[], script);
+    }
+
+    pub fn witness_script(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_IN_WITNESS_SCRIPT)
+    }
+
+    pub fn set_witness_script(&mut self, script: &[u8]) {
+        self.map.insert(PSBT_IN_WITNESS_SCRIPT, &[], script);
+    }
+
+    pub fn bip32_derivations(&self) -> Vec<(&[u8], KeySource)> {
+        self.map
+            .entries(PSBT_IN_BIP32_DERIVATION)
+            .filter_map(|(pubkey, value)| Some((pubkey, parse_key_source(value).ok()?.1)))
+            .collect()
+    }
+
+    pub fn final_script_sig(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_IN_FINAL_SCRIPTSIG)
+    }
+
+    pub fn final_script_witness(&self) -> Option<Vec<Witness>> {
+        let value = self.map.get(PSBT_IN_FINAL_SCRIPTWITNESS)?;
+        parse_witnesses(value)
+            .ok()
+            .map(|(_, (witnesses, _))| witnesses)
+    }
+
+    pub fn set_final_script_witness(&mut self, witnesses: &[Witness]) {
+        self.map.insert(
+            PSBT_IN_FINAL_SCRIPTWITNESS,
+            &[],
+            &serialize_witnesses(witnesses),
+        );
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.map.contains(PSBT_IN_FINAL_SCRIPTSIG) || self.map.contains(PSBT_IN_FINAL_SCRIPTWITNESS)
+    }
+
+    //hash and preimage for each of ripemd160, sha256, hash160 and hash256 by key type
+    pub fn preimages(&self, key_type: u64) -> Vec<(&[u8], &[u8])> {
+        self.map.entries(key_type).collect()
+    }
+
+    //the version 2 fields locating the spent output
+    pub fn previous_txid(&self) -> Option<Hash256> {
+        self.map.get(PSBT_IN_PREVIOUS_TXID).map(Hash256::new)
+    }
+
+    pub fn output_index(&self) -> Option<u32> {
+        u32_value(self.map.get(PSBT_IN_OUTPUT_INDEX))
+    }
+
+    pub fn sequence(&self) -> Option<u32> {
+        u32_value(self.map.get(PSBT_IN_SEQUENCE))
+    }
+
+    pub fn required_time_locktime(&self) -> Option<u32> {
+        u32_value(self.map.get(PSBT_IN_REQUIRED_TIME_LOCKTIME))
+    }
+
+    pub fn required_height_locktime(&self) -> Option<u32> {
+        u32_value(self.map.get(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME))
+    }
+
+    pub fn tap_key_sig(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_IN_TAP_KEY_SIG)
+    }
+
+    //x-only public key, leaf hash and signature
+    pub fn tap_script_sigs(&self) -> Vec<(&[u8], Hash256, &[u8])> {
+        self.map
+            .entries(PSBT_IN_TAP_SCRIPT_SIG)
+            .filter(|(key, _)| key.len() == 64)
+            .map(|(key, sig)| (&key[..32], Hash256::new(&key[32..]), sig))
+            .collect()
+    }
+
+    //control block, script and leaf version
+    pub fn tap_leaf_scripts(&self) -> Vec<(&[u8], &[u8], u8)> {
+        self.map
+            .entries(PSBT_IN_TAP_LEAF_SCRIPT)
+            .filter_map(|(control_block, value)| {
+                let (leaf_version, script) = value.split_last()?;
+                Some((control_block, script, *leaf_version))
+            })
+            .collect()
+    }
+
+    //x-only public key, the leaf hashes it is used in and its key source
+    pub fn tap_bip32_derivations(&self) -> Vec<(&[u8], Vec<Hash256>, KeySource)> {
+        self.map
+            .entries(PSBT_IN_TAP_BIP32_DERIVATION)
+            .filter_map(|(key, value)| {
+                let (_, (leaf_hashes, source)) = parse_tap_key_source(value).ok()?;
+                Some((key, leaf_hashes, source))
+            })
+            .collect()
+    }
+
+    pub fn tap_internal_key(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_IN_TAP_INTERNAL_KEY)
+    }
+
+    pub fn tap_merkle_root(&self) -> Option<Hash256> {
+        self.map.get(PSBT_IN_TAP_MERKLE_ROOT).map(Hash256::new)
+    }
+
+    //proprietary entries and the ones of key types not defined by BIP174 and BIP370
+    pub fn unknown(&self) -> Vec<(&PsbtKey, &[u8])> {
+        self.map.unknown(&KNOWN)
+    }
+}
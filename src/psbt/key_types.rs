@@ -0,0 +1,51 @@
+//key types of the PSBT maps, names as in BIP174 and BIP370
+
+//global map
+pub const PSBT_GLOBAL_UNSIGNED_TX: u64 = 0x00;
+pub const PSBT_GLOBAL_XPUB: u64 = 0x01;
+pub const PSBT_GLOBAL_TX_VERSION: u64 = 0x02;
+pub const PSBT_GLOBAL_FALLBACK_LOCKTIME: u64 = 0x03;
+pub const PSBT_GLOBAL_INPUT_COUNT: u64 = 0x04;
+pub const PSBT_GLOBAL_OUTPUT_COUNT: u64 = 0x05;
+pub const PSBT_GLOBAL_TX_MODIFIABLE: u64 = 0x06;
+pub const PSBT_GLOBAL_VERSION: u64 = 0xfb;
+pub const PSBT_GLOBAL_PROPRIETARY: u64 = 0xfc;
+
+//input maps
+pub const PSBT_IN_NON_WITNESS_UTXO: u64 = 0x00;
+pub const PSBT_IN_WITNESS_UTXO: u64 = 0x01;
+pub const PSBT_IN_PARTIAL_SIG: u64 = 0x02;
+pub const PSBT_IN_SIGHASH_TYPE: u64 = 0x03;
+pub const PSBT_IN_REDEEM_SCRIPT: u64 = 0x04;
+pub const PSBT_IN_WITNESS_SCRIPT: u64 = 0x05;
+pub const PSBT_IN_BIP32_DERIVATION: u64 = 0x06;
+pub const PSBT_IN_FINAL_SCRIPTSIG: u64 = 0x07;
+pub const PSBT_IN_FINAL_SCRIPTWITNESS: u64 = 0x08;
+pub const PSBT_IN_POR_COMMITMENT: u64 = 0x09;
+pub const PSBT_IN_RIPEMD160: u64 = 0x0a;
+pub const PSBT_IN_SHA256: u64 = 0x0b;
+pub const PSBT_IN_HASH160: u64 = 0x0c;
+pub const PSBT_IN_HASH256: u64 = 0x0d;
+pub const PSBT_IN_PREVIOUS_TXID: u64 = 0x0e;
+pub const PSBT_IN_OUTPUT_INDEX: u64 = 0x0f;
+pub const PSBT_IN_SEQUENCE: u64 = 0x10;
+pub const PSBT_IN_REQUIRED_TIME_LOCKTIME: u64 = 0x11;
+pub const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u64 = 0x12;
+pub const PSBT_IN_TAP_KEY_SIG: u64 = 0x13;
+pub const PSBT_IN_TAP_SCRIPT_SIG: u64 = 0x14;
+pub const PSBT_IN_TAP_LEAF_SCRIPT: u64 = 0x15;
+pub const PSBT_IN_TAP_BIP32_DERIVATION: u64 = 0x16;
+pub const PSBT_IN_TAP_INTERNAL_KEY: u64 = 0x17;
+pub const PSBT_IN_TAP_MERKLE_ROOT: u64 = 0x18;
+pub const PSBT_IN_PROPRIETARY: u64 = 0xfc;
+
+//output maps
+pub const PSBT_OUT_REDEEM_SCRIPT: u64 = 0x00;
+pub const PSBT_OUT_WITNESS_SCRIPT: u64 = 0x01;
+pub const PSBT_OUT_BIP32_DERIVATION: u64 = 0x02;
+pub const PSBT_OUT_AMOUNT: u64 = 0x03;
+pub const PSBT_OUT_SCRIPT: u64 = 0x04;
+pub const PSBT_OUT_TAP_INTERNAL_KEY: u64 = 0x05;
+pub const PSBT_OUT_TAP_TREE: u64 = 0x06;
+pub const PSBT_OUT_TAP_BIP32_DERIVATION: u64 = 0x07;
+pub const PSBT_OUT_PROPRIETARY: u64 = 0xfc;
@@ -0,0 +1,99 @@
+//the key of a map entry, key data is empty for fields that appear once per map
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct PsbtKey {
+    pub key_type: u64,
+    pub key_data: Vec<u8>,
+}
+
+impl PsbtKey {
+    pub fn new(key_type: u64, key_data: &[u8]) -> PsbtKey {
+        PsbtKey {
+            key_type,
+            key_data: key_data.to_vec(),
+        }
+    }
+}
+
+//the entries of a global, input or output map in the order they are serialized, keys are unique
+//the typed accessors of Psbt, PsbtInput and PsbtOutput read from here, unknown entries are kept as they are
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PsbtMap(pub Vec<(PsbtKey, Vec<u8>)>);
+
+impl PsbtMap {
+    pub fn new() -> PsbtMap {
+        PsbtMap(Vec::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get_key(&self, key: &PsbtKey) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| &value[..])
+    }
+
+    //the value of a field without key data
+    pub fn get(&self, key_type: u64) -> Option<&[u8]> {
+        self.get_key(&PsbtKey::new(key_type, &[]))
+    }
+
+    pub fn contains(&self, key_type: u64) -> bool {
+        self.0.iter().any(|(key, _)| key.key_type == key_type)
+    }
+
+    //key data and value of every entry of key_type
+    pub fn entries(&self, key_type: u64) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key.key_type == key_type)
+            .map(|(key, value)| (&key.key_data[..], &value[..]))
+    }
+
+    //replaces the value of an existing key in place, returns the value replaced
+    pub fn insert(&mut self, key_type: u64, key_data: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let key = PsbtKey::new(key_type, key_data);
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(std::mem::replace(v, value.to_vec())),
+            None => {
+                self.0.push((key, value.to_vec()));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key_type: u64) {
+        self.0.retain(|(key, _)| key.key_type != key_type)
+    }
+
+    //the entries whose key type is not in known
+    pub fn unknown(&self, known: &[u64]) -> Vec<(&PsbtKey, &[u8])> {
+        self.0
+            .iter()
+            .filter(|(key, _)| !known.contains(&key.key_type))
+            .map(|(key, value)| (key, &value[..]))
+            .collect()
+    }
+
+    //adds the entries of other, the values already in this map are kept
+    pub fn merge(&mut self, other: PsbtMap) {
+        for (key, value) in other.0 {
+            if self.get_key(&key).is_none() {
+                self.0.push((key, value));
+            }
+        }
+    }
+}
+
+//the master key fingerprint and derivation path of a BIP32 key
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeySource {
+    pub fingerprint: [u8; 4],
+    pub path: Vec<u32>,
+}
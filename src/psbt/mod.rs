@@ -0,0 +1,12 @@
+pub mod key_types;
+mod map;
+pub use self::map::{KeySource, PsbtKey, PsbtMap};
+mod error;
+pub use self::error::PsbtError;
+mod input;
+pub use self::input::PsbtInput;
+mod output;
+pub use self::output::PsbtOutput;
+mod global;
+pub use self::global::Psbt;
+mod roles;
@@ -0,0 +1,78 @@
+use crate::{
+    parsers::{parse_key_source, parse_tap_key_source, parse_tap_tree},
+    psbt::{key_types::*, KeySource, PsbtKey, PsbtMap},
+    types::Hash256,
+};
+use std::convert::TryInto;
+
+const KNOWN: [u64; 8] = [
+    PSBT_OUT_REDEEM_SCRIPT,
+    PSBT_OUT_WITNESS_SCRIPT,
+    PSBT_OUT_BIP32_DERIVATION,
+    PSBT_OUT_AMOUNT,
+    PSBT_OUT_SCRIPT,
+    PSBT_OUT_TAP_INTERNAL_KEY,
+    PSBT_OUT_TAP_TREE,
+    PSBT_OUT_TAP_BIP32_DERIVATION,
+];
+
+//the fields of an output map, values that do not decode read as missing
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PsbtOutput {
+    pub map: PsbtMap,
+}
+
+impl PsbtOutput {
+    pub fn new() -> PsbtOutput {
+        PsbtOutput::default()
+    }
+
+    pub fn redeem_script(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_OUT_REDEEM_SCRIPT)
+    }
+
+    pub fn witness_script(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_OUT_WITNESS_SCRIPT)
+    }
+
+    pub fn bip32_derivations(&self) -> Vec<(&[u8], KeySource)> {
+        self.map
+            .entries(PSBT_OUT_BIP32_DERIVATION)
+            .filter_map(|(pubkey, value)| Some((pubkey, parse_key_source(value).ok()?.1)))
+            .collect()
+    }
+
+    //the version 2 fields of the output
+    pub fn amount(&self) -> Option<u64> {
+        let value = self.map.get(PSBT_OUT_AMOUNT)?;
+        value.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    pub fn script(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_OUT_SCRIPT)
+    }
+
+    pub fn tap_internal_key(&self) -> Option<&[u8]> {
+        self.map.get(PSBT_OUT_TAP_INTERNAL_KEY)
+    }
+
+    //depth, leaf version and script of every leaf
+    pub fn tap_tree(&self) -> Option<Vec<(u8, u8, Vec<u8>)>> {
+        let value = self.map.get(PSBT_OUT_TAP_TREE)?;
+        parse_tap_tree(value).ok().map(|(_, leaves)| leaves)
+    }
+
+    pub fn tap_bip32_derivations(&self) -> Vec<(&[u8], Vec<Hash256>, KeySource)> {
+        self.map
+            .entries(PSBT_OUT_TAP_BIP32_DERIVATION)
+            .filter_map(|(key, value)| {
+                let (_, (leaf_hashes, source)) = parse_tap_key_source(value).ok()?;
+                Some((key, leaf_hashes, source))
+            })
+            .collect()
+    }
+
+    pub fn unknown(&self) -> Vec<(&PsbtKey, &[u8])> {
+        self.map.unknown(&KNOWN)
+    }
+}
@@ -0,0 +1,484 @@
+use crate::{
+    parsers::parse_instruction,
+    psbt::{global::build_transaction, key_types::*, Psbt, PsbtError, PsbtInput},
+    script::{is_pay_to_script_hash, opcodes::*, push_data_script, witness_program},
+    types::{Instruction, Transaction, Witness},
+    utils::{hash160, sha256},
+};
+
+//fields the finalizer keeps, the spent output and the version 2 fields that make up the transaction
+const KEPT_BY_FINALIZER: [u64; 9] = [
+    PSBT_IN_NON_WITNESS_UTXO,
+    PSBT_IN_WITNESS_UTXO,
+    PSBT_IN_FINAL_SCRIPTSIG,
+    PSBT_IN_FINAL_SCRIPTWITNESS,
+    PSBT_IN_PREVIOUS_TXID,
+    PSBT_IN_OUTPUT_INDEX,
+    PSBT_IN_SEQUENCE,
+    PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+];
+
+fn instructions(script: &[u8]) -> Option<Vec<Instruction>> {
+    let mut input = script;
+    let mut instructions = Vec::new();
+    while !input.is_empty() {
+        let (i, instruction) = parse_instruction(input).ok()?;
+        instructions.push(instruction);
+        input = i;
+    }
+    Some(instructions)
+}
+
+fn small_int(opcode: u8) -> Option<usize> {
+    match opcode {
+        OP_1..=OP_16 => Some((opcode - OP_1 + 1) as usize),
+        _ => None,
+    }
+}
+
+//the stack satisfying a pay to pubkey, pay to pubkey hash or bare multisig script with the partial signatures
+fn solve(script: &[u8], input: &PsbtInput) -> Option<Vec<Vec<u8>>> {
+    let sigs = input.partial_sigs();
+    let sig_of = |pubkey: &[u8]| {
+        sigs.iter()
+            .find(|(key, _)| *key == pubkey)
+            .map(|(_, sig)| sig.to_vec())
+    };
+    let instructions = instructions(script)?;
+    match &instructions[..] {
+        [Instruction::Push(_, pubkey), Instruction::Op(OP_CHECKSIG)] => {
+            Some(vec![sig_of(&pubkey.0)?])
+        }
+        [Instruction::Op(OP_DUP), Instruction::Op(OP_HASH160), Instruction::Push(_, hash), Instruction::Op(OP_EQUALVERIFY), Instruction::Op(OP_CHECKSIG)] =>
+        {
+            let (pubkey, sig) = sigs
+                .iter()
+                .find(|(key, _)| hash160(key)[..] == hash.0[..])?;
+            Some(vec![sig.to_vec(), pubkey.to_vec()])
+        }
+        [Instruction::Op(m), keys @ .., Instruction::Op(n), Instruction::Op(OP_CHECKMULTISIG)] => {
+            let (m, n) = (small_int(*m)?, small_int(*n)?);
+            if keys.len() != n {
+                return None;
+            }
+            //the dummy element, then the signatures in the order of their keys
+            let mut stack = vec![Vec::new()];
+            for key in keys {
+                if let Some(sig) = sig_of(key.push_data()?) {
+                    if stack.len() <= m {
+                        stack.push(sig);
+                    }
+                }
+            }
+            match stack.len() == m + 1 {
+                true => Some(stack),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn script_sig(stack: &[Vec<u8>]) -> Vec<u8> {
+    stack
+        .iter()
+        .flat_map(|item| push_data_script(item))
+        .collect()
+}
+
+fn witness(stack: Vec<Vec<u8>>) -> Vec<Witness> {
+    stack.iter().map(|item| Witness::new(item)).collect()
+}
+
+//the witness spending a segwit v0 or taproot program
+fn solve_witness(version: u8, program: &[u8], input: &PsbtInput) -> Option<Vec<Witness>> {
+    match (version, program.len()) {
+        (0, 20) => {
+            let (pubkey, sig) = input
+                .partial_sigs()
+                .into_iter()
+                .find(|(key, _)| hash160(key)[..] == program[..])?;
+            Some(witness(vec![sig.to_vec(), pubkey.to_vec()]))
+        }
+        (0, 32) => {
+            let witness_script = input.witness_script()?;
+            if sha256(witness_script).0[..] != program[..] {
+                return None;
+            }
+            let mut stack = solve(witness_script, input)?;
+            stack.push(witness_script.to_vec());
+            Some(witness(stack))
+        }
+        (1, 32) => {
+            if let Some(sig) = input.tap_key_sig() {
+                return Some(witness(vec![sig.to_vec()]));
+            }
+            //a script path spend of a leaf checking a single signature
+            let sigs = input.tap_script_sigs();
+            input
+                .tap_leaf_scripts()
+                .into_iter()
+                .find_map(|(control_block, script, _)| {
+                    let (_, _, sig) = sigs.iter().find(|(key, _, _)| {
+                        script.len() == 34
+                            && script[0] == 32
+                            && script[33] == OP_CHECKSIG
+                            && script[1..33] == key[..]
+                    })?;
+                    Some(witness(vec![
+                        sig.to_vec(),
+                        script.to_vec(),
+                        control_block.to_vec(),
+                    ]))
+                })
+        }
+        _ => None,
+    }
+}
+
+impl Psbt {
+    //the combiner, adds the fields of other, which has to be for the same transaction
+    pub fn combine(&mut self, other: Psbt) -> Result<(), PsbtError> {
+        if self.unsigned_tx()?.txid != other.unsigned_tx()?.txid
+            || self.inputs.len() != other.inputs.len()
+            || self.outputs.len() != other.outputs.len()
+        {
+            return Err(PsbtError::DifferentTransaction);
+        }
+        self.global.merge(other.global);
+        for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+            input.map.merge(other.map);
+        }
+        for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+            output.map.merge(other.map);
+        }
+        Ok(())
+    }
+
+    //the finalizer, builds script sigs and witnesses from the partial signatures and scripts
+    //handles pay to pubkey (hash), multisig, their P2SH and P2WSH wrappings, P2WPKH and taproot
+    //signatures are not checked, inputs already finalized are left alone
+    pub fn finalize(&mut self) -> Result<(), PsbtError> {
+        for index in 0..self.inputs.len() {
+            if self.inputs[index].is_finalized() {
+                continue;
+            }
+            let script = self
+                .spent_output(index)
+                .ok_or(PsbtError::MissingUtxo(index))?
+                .script_pub_key
+                .0;
+            let input = &self.inputs[index];
+            let cannot_finalize = PsbtError::CannotFinalize(index);
+            let (script, redeem_push) = match is_pay_to_script_hash(&script) {
+                true => {
+                    let redeem_script = input.redeem_script().ok_or(cannot_finalize)?;
+                    if hash160(redeem_script)[..] != script[2..22] {
+                        return Err(cannot_finalize);
+                    }
+                    (redeem_script.to_vec(), push_data_script(redeem_script))
+                }
+                false => (script, Vec::new()),
+            };
+            let (script_sig, witness) = match witness_program(&script) {
+                Some((version, program)) => {
+                    let witness = solve_witness(version, program, input).ok_or(cannot_finalize)?;
+                    (redeem_push, Some(witness))
+                }
+                None => {
+                    let stack = solve(&script, input).ok_or(cannot_finalize)?;
+                    ([script_sig(&stack), redeem_push].concat(), None)
+                }
+            };
+            let input = &mut self.inputs[index];
+            input.map.0.retain(|(key, _)| {
+                !key_is_known(key.key_type) || KEPT_BY_FINALIZER.contains(&key.key_type)
+            });
+            if !script_sig.is_empty() {
+                input.map.insert(PSBT_IN_FINAL_SCRIPTSIG, &[], &script_sig);
+            }
+            if let Some(witness) = witness {
+                input.set_final_script_witness(&witness);
+            }
+        }
+        Ok(())
+    }
+
+    //the extractor, the signed transaction once all inputs are finalized
+    pub fn extract(&self) -> Result<Transaction, PsbtError> {
+        let tx = self.unsigned_tx()?;
+        let mut inputs = tx.inputs;
+        let mut witnesses = Vec::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            if !input.is_finalized() {
+                return Err(PsbtError::NotFinalized(index));
+            }
+            if let Some(script_sig) = input.final_script_sig() {
+                inputs[index].script_sig = script_sig.into();
+            }
+            witnesses.push(input.final_script_witness().unwrap_or_default());
+        }
+        let has_witness = witnesses.iter().any(|w| !w.is_empty());
+        let witnesses = match has_witness {
+            true => Some(
+                witnesses
+                    .into_iter()
+                    .map(|w| match w.is_empty() {
+                        true => vec![Witness::empty()],
+                        false => w,
+                    })
+                    .collect(),
+            ),
+            false => None,
+        };
+        Ok(build_transaction(
+            tx.version,
+            inputs,
+            tx.outputs,
+            witnesses,
+            tx.lock_time,
+        ))
+    }
+}
+
+fn key_is_known(key_type: u64) -> bool {
+    key_type <= PSBT_IN_TAP_MERKLE_ROOT
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        parsers::parse_transaction,
+        psbt::PsbtMap,
+        serializers::serialize_transaction,
+        types::{Bytes, TxInput, TxOutput},
+    };
+
+    //the creator vector of BIP174
+    const CREATOR: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAAAAAA=";
+
+    fn unsigned(tx: &Transaction) -> Transaction {
+        let inputs = tx
+            .inputs
+            .iter()
+            .map(|input| TxInput {
+                script_sig: Bytes::default(),
+                ..input.clone()
+            })
+            .collect();
+        build_transaction(tx.version, inputs, tx.outputs.clone(), None, tx.lock_time)
+    }
+
+    fn witness_items(tx: &Transaction, index: usize) -> Vec<Vec<u8>> {
+        tx.witnesses.as_ref().unwrap()[index]
+            .iter()
+            .map(|w| w.0.as_ref().map(|b| b.0.clone()).unwrap_or_default())
+            .collect()
+    }
+
+    fn script_sig_items(tx: &Transaction, index: usize) -> Vec<Vec<u8>> {
+        instructions(&tx.inputs[index].script_sig.0)
+            .unwrap()
+            .iter()
+            .map(|i| i.push_data().unwrap().to_vec())
+            .collect()
+    }
+
+    fn p2pkh(pubkey: &[u8]) -> Vec<u8> {
+        [
+            &[OP_DUP, OP_HASH160, 20][..],
+            &hash160(pubkey),
+            &[OP_EQUALVERIFY, OP_CHECKSIG],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_finalize_p2sh_p2wpkh() {
+        let data = include_bytes!(
+            "../test_data/tx_fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54.segwit.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        let mut psbt = Psbt::from_unsigned_tx(&unsigned(&tx)).unwrap();
+        for index in 0..tx.inputs.len() {
+            let redeem_script = script_sig_items(&tx, index).remove(0);
+            let spk = [&[OP_HASH160, 20][..], &hash160(&redeem_script), &[OP_EQUAL]].concat();
+            let witness = witness_items(&tx, index);
+            let input = &mut psbt.inputs[index];
+            input.set_witness_utxo(&TxOutput::new(0, &spk));
+            input.set_redeem_script(&redeem_script);
+            input.add_partial_sig(&witness[1], &witness[0]);
+        }
+        assert_eq!(psbt.extract().unwrap_err(), PsbtError::NotFinalized(0));
+        psbt.finalize().unwrap();
+        assert!(psbt.inputs[0].redeem_script().is_none());
+        assert!(psbt.inputs[0].witness_utxo().is_some());
+        let extracted = psbt.extract().unwrap();
+        assert_eq!(extracted.txid, tx.txid);
+        assert_eq!(serialize_transaction(&extracted), data.to_vec());
+    }
+
+    #[test]
+    fn test_finalize_p2pkh() {
+        let data = include_bytes!(
+            "../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        let mut psbt = Psbt::from_unsigned_tx(&unsigned(&tx)).unwrap();
+        assert_eq!(psbt.finalize().unwrap_err(), PsbtError::MissingUtxo(0));
+        let items = script_sig_items(&tx, 0);
+        psbt.inputs[0].set_witness_utxo(&TxOutput::new(0, &p2pkh(&items[1])));
+        assert_eq!(psbt.finalize().unwrap_err(), PsbtError::CannotFinalize(0));
+        psbt.inputs[0].add_partial_sig(&items[1], &items[0]);
+        psbt.finalize().unwrap();
+        let extracted = psbt.extract().unwrap();
+        assert!(extracted.witnesses.is_none());
+        assert_eq!(serialize_transaction(&extracted), data.to_vec());
+    }
+
+    #[test]
+    fn test_finalize_p2wsh_multisig() {
+        let data = include_bytes!(
+            "../test_data/tx_de06af29a80be52bb5f4b6c86998dcfdf0f9e7f66a1ebb7e9d20d65cc6785d8c.native_witness.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        let mut psbt = Psbt::from_unsigned_tx(&unsigned(&tx)).unwrap();
+        let mut signed = Vec::new();
+        for index in 0..tx.inputs.len() {
+            let witness = witness_items(&tx, index);
+            let witness_script = witness.last().unwrap().clone();
+            let keys: Vec<_> = instructions(&witness_script).unwrap()[1..4]
+                .iter()
+                .map(|i| i.push_data().unwrap().to_vec())
+                .collect();
+            let spk = [&[OP_0, 32][..], &sha256(&witness_script).0].concat();
+            //the first two keys of the 2-of-3 sign, each signer in its own PSBT
+            let mut other = psbt.clone();
+            let input = &mut psbt.inputs[index];
+            input.set_witness_utxo(&TxOutput::new(0, &spk));
+            input.set_witness_script(&witness_script);
+            input.add_partial_sig(&keys[0], &witness[1]);
+            other.inputs[index].add_partial_sig(&keys[1], &witness[2]);
+            signed.push(other);
+        }
+        assert_eq!(
+            psbt.clone().finalize().unwrap_err(),
+            PsbtError::CannotFinalize(0)
+        );
+        for other in signed {
+            psbt.combine(other).unwrap();
+        }
+        psbt.finalize().unwrap();
+        assert_eq!(
+            serialize_transaction(&psbt.extract().unwrap()),
+            data.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_combine_different() {
+        let mut psbt = Psbt::from_base64(CREATOR).unwrap();
+        let mut tx = psbt.unsigned_tx().unwrap();
+        tx.lock_time = 1;
+        let other = Psbt::from_unsigned_tx(&unsigned(&tx)).unwrap();
+        assert_eq!(
+            psbt.combine(other).unwrap_err(),
+            PsbtError::DifferentTransaction
+        );
+    }
+
+    #[test]
+    fn test_finalize_taproot() {
+        let mut psbt = Psbt::from_base64(CREATOR).unwrap();
+        let key = [0x11; 32];
+        let spk = [&[OP_1, 32][..], &key].concat();
+        psbt.inputs[0].set_witness_utxo(&TxOutput::new(0, &spk));
+        psbt.inputs[0]
+            .map
+            .insert(PSBT_IN_TAP_KEY_SIG, &[], &[0x22; 64]);
+        //a script path spend of a leaf checking the signature of key
+        let script = [&[32][..], &key, &[OP_CHECKSIG]].concat();
+        let control_block = [&[0xc0][..], &[0x33; 32]].concat();
+        psbt.inputs[1].set_witness_utxo(&TxOutput::new(0, &spk));
+        psbt.inputs[1].map.insert(
+            PSBT_IN_TAP_LEAF_SCRIPT,
+            &control_block,
+            &[&script[..], &[0xc0]].concat(),
+        );
+        psbt.inputs[1].map.insert(
+            PSBT_IN_TAP_SCRIPT_SIG,
+            &[&key[..], &[0x44; 32]].concat(),
+            &[0x55; 65],
+        );
+        psbt.finalize().unwrap();
+        let tx = psbt.extract().unwrap();
+        assert_eq!(witness_items(&tx, 0), vec![vec![0x22; 64]]);
+        assert_eq!(
+            witness_items(&tx, 1),
+            vec![vec![0x55; 65], script, control_block]
+        );
+        assert!(tx.inputs.iter().all(|i| i.script_sig.len() == 0));
+    }
+
+    #[test]
+    fn test_version_2() {
+        let v0 = Psbt::from_base64(CREATOR).unwrap();
+        let tx = v0.unsigned_tx().unwrap();
+        let mut psbt = Psbt {
+            global: PsbtMap::new(),
+            inputs: vec![PsbtInput::new(); 2],
+            outputs: vec![Default::default(); 2],
+        };
+        psbt.global
+            .insert(PSBT_GLOBAL_TX_VERSION, &[], &tx.version.to_le_bytes());
+        psbt.global.insert(PSBT_GLOBAL_INPUT_COUNT, &[], &[2]);
+        psbt.global.insert(PSBT_GLOBAL_OUTPUT_COUNT, &[], &[2]);
+        psbt.global
+            .insert(PSBT_GLOBAL_VERSION, &[], &2u32.to_le_bytes());
+        for (input, tx_input) in psbt.inputs.iter_mut().zip(tx.inputs.iter()) {
+            let map = &mut input.map;
+            map.insert(PSBT_IN_PREVIOUS_TXID, &[], &tx_input.previous_tx_hash.0);
+            map.insert(PSBT_IN_OUTPUT_INDEX, &[], &tx_input.vout.to_le_bytes());
+        }
+        for (output, tx_output) in psbt.outputs.iter_mut().zip(tx.outputs.iter()) {
+            let map = &mut output.map;
+            map.insert(PSBT_OUT_AMOUNT, &[], &tx_output.value.to_le_bytes());
+            map.insert(PSBT_OUT_SCRIPT, &[], &tx_output.script_pub_key.0);
+        }
+        let psbt = Psbt::from_bytes(&psbt.to_bytes()).unwrap();
+        assert_eq!(psbt.version(), 2);
+        assert_eq!(psbt.unsigned_tx().unwrap().txid, tx.txid);
+
+        //the largest required lock time, heights when both kinds are allowed
+        let mut locked = psbt.clone();
+        locked
+            .global
+            .insert(PSBT_GLOBAL_FALLBACK_LOCKTIME, &[], &7u32.to_le_bytes());
+        assert_eq!(locked.unsigned_tx().unwrap().lock_time, 7);
+        let height = PSBT_IN_REQUIRED_HEIGHT_LOCKTIME;
+        let time = PSBT_IN_REQUIRED_TIME_LOCKTIME;
+        locked.inputs[0]
+            .map
+            .insert(height, &[], &100u32.to_le_bytes());
+        locked.inputs[0]
+            .map
+            .insert(time, &[], &600000000u32.to_le_bytes());
+        locked.inputs[1]
+            .map
+            .insert(height, &[], &200u32.to_le_bytes());
+        assert_eq!(locked.unsigned_tx().unwrap().lock_time, 200);
+        locked.inputs[1].map.remove(height);
+        locked.inputs[1]
+            .map
+            .insert(time, &[], &500000001u32.to_le_bytes());
+        assert_eq!(locked.unsigned_tx().unwrap().lock_time, 600000000);
+        locked.inputs[0].map.remove(time);
+        assert_eq!(locked.unsigned_tx().unwrap_err(), PsbtError::LockTime);
+        assert_eq!(
+            psbt.clone().combine(locked).unwrap_err(),
+            PsbtError::LockTime
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    parsers::parse_instruction,
+    script::{
+        decode_script_num, is_pay_to_script_hash, is_push_only, is_valid_signature_encoding,
+        opcodes::*, witness_program,
+    },
+    types::Instruction,
+};
+
+fn sighash_name(sighash_type: u8) -> Option<&'static str> {
+    match sighash_type {
+        0x01 => Some("ALL"),
+        0x02 => Some("NONE"),
+        0x03 => Some("SINGLE"),
+        0x81 => Some("ALL|ANYONECANPAY"),
+        0x82 => Some("NONE|ANYONECANPAY"),
+        0x83 => Some("SINGLE|ANYONECANPAY"),
+        _ => None,
+    }
+}
+
+//the script as bitcoind prints it, pushes of up to 4 bytes as numbers and the rest as hex
+//sighash_decode prints signatures with their sighash type, as bitcoind does for script sigs
+pub fn script_to_asm(script: &[u8], sighash_decode: bool) -> String {
+    let unspendable = script.first() == Some(&OP_RETURN) || script.len() > 10000;
+    let mut words = Vec::new();
+    let mut input = script;
+    while !input.is_empty() {
+        let (i, instruction) = match parse_instruction(input) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                words.push("[error]".to_string());
+                break;
+            }
+        };
+        input = i;
+        let data = match instruction {
+            Instruction::Push(_, data) => data.0,
+            Instruction::Op(opcode) => {
+                words.push(opcode_name(opcode).unwrap_or("OP_UNKNOWN").to_string());
+                continue;
+            }
+        };
+        if data.len() <= 4 {
+            words.push(decode_script_num(&data, false, 4).unwrap().to_string());
+            continue;
+        }
+        let sighash = match sighash_decode && !unspendable && is_valid_signature_encoding(&data) {
+            true => sighash_name(data[data.len() - 1]),
+            false => None,
+        };
+        words.push(match sighash {
+            Some(name) => format!("{}[{}]", hex::encode(&data[..data.len() - 1]), name),
+            None => hex::encode(&data),
+        });
+    }
+    words.join(" ")
+}
+
+fn is_pubkey(data: &[u8]) -> bool {
+    match data.first() {
+        Some(2) | Some(3) => data.len() == 33,
+        Some(4) | Some(6) | Some(7) => data.len() == 65,
+        _ => false,
+    }
+}
+
+fn multisig_type(script: &[u8]) -> Option<&'static str> {
+    let mut instructions = Vec::new();
+    let mut input = script;
+    while !input.is_empty() {
+        let (i, instruction) = parse_instruction(input).ok()?;
+        instructions.push(instruction);
+        input = i;
+    }
+    match &instructions[..] {
+        [Instruction::Op(m @ OP_1..=OP_16), keys @ .., Instruction::Op(n @ OP_1..=OP_16), Instruction::Op(OP_CHECKMULTISIG)]
+            if usize::from(n - OP_1 + 1) == keys.len()
+                && m <= n
+                && keys
+                    .iter()
+                    .all(|key| key.push_data().is_some_and(is_pubkey)) =>
+        {
+            Some("multisig")
+        }
+        _ => None,
+    }
+}
+
+//the output type names of bitcoind, nonstandard for scripts it does not recognize
+pub fn script_type(script: &[u8]) -> &'static str {
+    if is_pay_to_script_hash(script) {
+        return "scripthash";
+    }
+    if let Some((version, program)) = witness_program(script) {
+        return match (version, program.len()) {
+            (0, 20) => "witness_v0_keyhash",
+            (0, 32) => "witness_v0_scripthash",
+            (0, _) => "nonstandard",
+            (1, 32) => "witness_v1_taproot",
+            (1, 2) if program == [0x4e, 0x73] => "anchor",
+            _ => "witness_unknown",
+        };
+    }
+    match script {
+        [OP_RETURN, rest @ ..] if is_push_only(rest) => "nulldata",
+        [33, key @ .., OP_CHECKSIG] | [65, key @ .., OP_CHECKSIG]
+            if usize::from(script[0]) == key.len() && is_pubkey(key) =>
+        {
+            "pubkey"
+        }
+        [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            "pubkeyhash"
+        }
+        _ => multisig_type(script).unwrap_or("nonstandard"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_script_to_asm() {
+        let script = hex::decode("76a9141b6517e189434cf8f18cc38ceb88c8fdce25b8f188ac").unwrap();
+        assert_eq!(
+            script_to_asm(&script, false),
+            "OP_DUP OP_HASH160 1b6517e189434cf8f18cc38ceb88c8fdce25b8f1 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+        assert_eq!(script_type(&script), "pubkeyhash");
+        //a BIP34 height and a truncated push
+        assert_eq!(
+            script_to_asm(&[3, 0x40, 0x0d, 0x03, 0x02, 0xff], false),
+            "200000 [error]"
+        );
+        assert_eq!(
+            script_to_asm(&[OP_0, OP_1NEGATE, 0xfe], false),
+            "0 -1 OP_UNKNOWN"
+        );
+        let sig = hex::decode("3044022045031c1ad4005f367481fc6145875b6911f0fea6ed0f60c3d14086b32da7520d022024de965e6ca7d33d096245dd52ce619ae275ed9760ca84380dbc68908d52595181").unwrap();
+        let script = [&[sig.len() as u8][..], &sig].concat();
+        assert_eq!(
+            script_to_asm(&script, true),
+            format!("{}[ALL|ANYONECANPAY]", hex::encode(&sig[..sig.len() - 1]))
+        );
+        assert_eq!(script_to_asm(&script, false), hex::encode(&sig));
+        let script = [&[OP_RETURN][..], &script].concat();
+        assert_eq!(script_type(&script), "nulldata");
+        assert_eq!(
+            script_to_asm(&script, true),
+            format!("OP_RETURN {}", hex::encode(&sig))
+        );
+    }
+
+    #[test]
+    fn test_script_type() {
+        let key = [&[2u8][..], &[0x11; 32]].concat();
+        let p2pk = [&[33u8][..], &key, &[OP_CHECKSIG]].concat();
+        assert_eq!(script_type(&p2pk), "pubkey");
+        let multisig = [
+            &[OP_1, 33][..],
+            &key,
+            &[33],
+            &key,
+            &[OP_2, OP_CHECKMULTISIG],
+        ]
+        .concat();
+        assert_eq!(script_type(&multisig), "multisig");
+        let multisig = [&[OP_1, 33][..], &key, &[OP_2, OP_CHECKMULTISIG]].concat();
+        assert_eq!(script_type(&multisig), "nonstandard");
+        let p2tr = [&[OP_1, 32][..], &[0; 32]].concat();
+        assert_eq!(script_type(&p2tr), "witness_v1_taproot");
+        assert_eq!(script_type(&[OP_1, 2, 0x4e, 0x73]), "anchor");
+        assert_eq!(script_type(&[OP_2, 2, 0x4e, 0x73]), "witness_unknown");
+        assert_eq!(script_type(&[OP_0, 2, 0x4e, 0x73]), "nonstandard");
+        assert_eq!(script_type(&[OP_RETURN, OP_CHECKSIG]), "nonstandard");
+    }
+}
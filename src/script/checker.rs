@@ -0,0 +1,258 @@
+use crate::{
+    script::ScriptError,
+    types::{Hash256, Transaction, TxOutput},
+    utils::{SighashCache, SighashError, SIGHASH_DEFAULT},
+};
+use std::cell::RefCell;
+
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+const SEQUENCE_FINAL: u32 = 0xffffffff;
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SigVersion {
+    Base,
+    WitnessV0,
+    //key path spends
+    Taproot,
+    //script path spends with leaf version 0xc0
+    Tapscript,
+}
+
+//state of a taproot spend the signature message commits to
+#[derive(Debug, Clone)]
+pub struct ExecutionData {
+    pub annex: Option<Vec<u8>>,
+    pub tapleaf_hash: Option<Hash256>,
+    //opcode position of the last executed OP_CODESEPARATOR, 0xffffffff if none
+    pub codeseparator_pos: u32,
+    pub validation_weight_left: i64,
+}
+
+impl std::default::Default for ExecutionData {
+    fn default() -> ExecutionData {
+        ExecutionData {
+            annex: None,
+            tapleaf_hash: None,
+            codeseparator_pos: 0xffffffff,
+            validation_weight_left: 0,
+        }
+    }
+}
+
+//everything the interpreter needs to know about the spending transaction
+//the default implementations fail every check, as in bitcoind's BaseSignatureChecker
+pub trait SignatureChecker {
+    //sig still carries its hash type byte
+    fn check_ecdsa_signature(
+        &self,
+        _sig: &[u8],
+        _pubkey: &[u8],
+        _script_code: &[u8],
+        _sig_version: SigVersion,
+    ) -> bool {
+        false
+    }
+    fn check_schnorr_signature(
+        &self,
+        _sig: &[u8],
+        _pubkey: &[u8],
+        _sig_version: SigVersion,
+        _exec_data: &ExecutionData,
+    ) -> Result<(), ScriptError> {
+        Err(ScriptError::SchnorrSig)
+    }
+    fn check_lock_time(&self, _lock_time: i64) -> bool {
+        false
+    }
+    fn check_sequence(&self, _sequence: i64) -> bool {
+        false
+    }
+    //whether output_key is internal_key tweaked with merkle_root, see BIP341
+    fn check_tap_tweak(
+        &self,
+        _internal_key: &[u8],
+        _merkle_root: &Hash256,
+        _output_key: &[u8],
+        _parity: bool,
+    ) -> bool {
+        false
+    }
+}
+
+//the elliptic curve operations, kept apart so the interpreter does not depend on a secp256k1 implementation
+pub trait SignatureVerifier {
+    //DER signature without the hash type byte, serialized public key
+    fn verify_ecdsa(&self, msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool;
+    //64 byte BIP340 signature, 32 byte x-only public key
+    fn verify_schnorr(&self, msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool;
+    fn verify_tap_tweak(
+        &self,
+        internal_key: &[u8],
+        merkle_root: &Hash256,
+        output_key: &[u8],
+        parity: bool,
+    ) -> bool;
+}
+
+//checks signatures of one input of a transaction
+pub struct TransactionSignatureChecker<'a, V: SignatureVerifier> {
+    tx: &'a Transaction,
+    input_index: usize,
+    amount: u64,
+    //the outputs spent by all inputs, only needed for taproot
+    prevouts: Option<&'a [TxOutput]>,
+    verifier: V,
+    cache: RefCell<SighashCache<'a>>,
+}
+
+impl<'a, V: SignatureVerifier> TransactionSignatureChecker<'a, V> {
+    pub fn new(tx: &'a Transaction, input_index: usize, amount: u64, verifier: V) -> Self {
+        TransactionSignatureChecker {
+            tx,
+            input_index,
+            amount,
+            prevouts: None,
+            verifier,
+            cache: RefCell::new(SighashCache::new(tx)),
+        }
+    }
+    pub fn with_prevouts(
+        tx: &'a Transaction,
+        input_index: usize,
+        prevouts: &'a [TxOutput],
+        verifier: V,
+    ) -> Self {
+        let amount = prevouts.get(input_index).map(|p| p.value).unwrap_or(0);
+        TransactionSignatureChecker {
+            prevouts: Some(prevouts),
+            ..TransactionSignatureChecker::new(tx, input_index, amount, verifier)
+        }
+    }
+}
+
+impl<'a, V: SignatureVerifier> SignatureChecker for TransactionSignatureChecker<'a, V> {
+    fn check_ecdsa_signature(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &[u8],
+        sig_version: SigVersion,
+    ) -> bool {
+        let (sighash_type, sig) = match sig.split_last() {
+            Some((sighash_type, sig)) => (u32::from(*sighash_type), sig),
+            None => return false,
+        };
+        let sighash = match sig_version {
+            SigVersion::Base => {
+                Ok(self
+                    .cache
+                    .borrow()
+                    .legacy_sighash(self.input_index, script_code, sighash_type))
+            }
+            SigVersion::WitnessV0 => self.cache.borrow_mut().segwit_v0_sighash(
+                self.input_index,
+                script_code,
+                self.amount,
+                sighash_type,
+            ),
+            _ => return false,
+        };
+        match sighash {
+            Ok(sighash) => self.verifier.verify_ecdsa(&sighash, sig, pubkey),
+            Err(_) => false,
+        }
+    }
+
+    fn check_schnorr_signature(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8],
+        sig_version: SigVersion,
+        exec_data: &ExecutionData,
+    ) -> Result<(), ScriptError> {
+        let (sig, sighash_type) = match sig.len() {
+            64 => (sig, SIGHASH_DEFAULT),
+            //an explicit SIGHASH_DEFAULT would make the signature malleable
+            65 if sig[64] == 0 => return Err(ScriptError::SchnorrSigHashtype),
+            65 => (&sig[..64], u32::from(sig[64])),
+            _ => return Err(ScriptError::SchnorrSigSize),
+        };
+        let prevouts = self.prevouts.ok_or(ScriptError::SighashError)?;
+        let leaf = match sig_version {
+            SigVersion::Taproot => None,
+            SigVersion::Tapscript => Some((
+                exec_data.tapleaf_hash.ok_or(ScriptError::SighashError)?,
+                exec_data.codeseparator_pos,
+            )),
+            _ => return Err(ScriptError::SighashError),
+        };
+        let annex = exec_data.annex.as_deref();
+        let mut cache = self.cache.borrow_mut();
+        let sighash = match leaf {
+            Some((leaf_hash, codesep_pos)) => cache.taproot_sighash_with_codesep(
+                self.input_index,
+                prevouts,
+                annex,
+                leaf_hash,
+                codesep_pos,
+                sighash_type,
+            ),
+            None => cache.taproot_sighash(self.input_index, prevouts, annex, None, sighash_type),
+        };
+        let sighash = match sighash {
+            Ok(sighash) => sighash,
+            Err(SighashError::InvalidSighashType(_))
+            | Err(SighashError::SingleWithoutOutput(_)) => {
+                return Err(ScriptError::SchnorrSigHashtype)
+            }
+            Err(_) => return Err(ScriptError::SighashError),
+        };
+        match self.verifier.verify_schnorr(&sighash, sig, pubkey) {
+            true => Ok(()),
+            false => Err(ScriptError::SchnorrSig),
+        }
+    }
+
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        let tx_lock_time = i64::from(self.tx.lock_time);
+        //block height and block time locks can not be compared
+        if (tx_lock_time < LOCKTIME_THRESHOLD) != (lock_time < LOCKTIME_THRESHOLD) {
+            return false;
+        }
+        if lock_time > tx_lock_time {
+            return false;
+        }
+        //a final input would disable the transaction's lock time
+        self.tx.inputs[self.input_index].sequence != SEQUENCE_FINAL
+    }
+
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let tx_sequence = self.tx.inputs[self.input_index].sequence;
+        //relative lock times are only enforced from version 2 on (BIP68)
+        if self.tx.version < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return false;
+        }
+        let mask = i64::from(SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK);
+        let tx_sequence_masked = i64::from(tx_sequence) & mask;
+        let sequence_masked = sequence & mask;
+        let type_flag = i64::from(SEQUENCE_LOCKTIME_TYPE_FLAG);
+        if (tx_sequence_masked < type_flag) != (sequence_masked < type_flag) {
+            return false;
+        }
+        sequence_masked <= tx_sequence_masked
+    }
+
+    fn check_tap_tweak(
+        &self,
+        internal_key: &[u8],
+        merkle_root: &Hash256,
+        output_key: &[u8],
+        parity: bool,
+    ) -> bool {
+        self.verifier
+            .verify_tap_tweak(internal_key, merkle_root, output_key, parity)
+    }
+}
@@ -0,0 +1,135 @@
+//reasons a script fails, following bitcoind's ScriptError_t
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScriptError {
+    EvalFalse,
+    OpReturn,
+
+    //size limits
+    ScriptSize,
+    PushSize,
+    OpCount,
+    StackSize,
+    SigCount,
+    PubkeyCount,
+
+    //failed verify operations
+    Verify,
+    EqualVerify,
+    CheckMultisigVerify,
+    CheckSigVerify,
+    NumEqualVerify,
+
+    //logical/format/canonical errors
+    BadOpcode,
+    DisabledOpcode,
+    InvalidStackOperation,
+    InvalidAltstackOperation,
+    UnbalancedConditional,
+    //script numbers too long or not minimally encoded
+    ScriptNumOverflow,
+    ScriptNumMinimal,
+
+    //CHECKLOCKTIMEVERIFY and CHECKSEQUENCEVERIFY
+    NegativeLocktime,
+    UnsatisfiedLocktime,
+
+    //malleability
+    SigHashtype,
+    SigDer,
+    SigHighS,
+    SigNullDummy,
+    SigPushOnly,
+    MinimalIf,
+    SigNullFail,
+
+    //segregated witness
+    WitnessProgramWrongLength,
+    WitnessProgramWitnessEmpty,
+    WitnessProgramMismatch,
+    WitnessMalleated,
+    WitnessMalleatedP2sh,
+    WitnessUnexpected,
+    WitnessPubkeyType,
+    CleanStack,
+
+    //taproot
+    SchnorrSigSize,
+    SchnorrSigHashtype,
+    SchnorrSig,
+    PubkeyType,
+    TaprootWrongControlSize,
+    TapscriptValidationWeight,
+    TapscriptCheckMultisig,
+    TapscriptMinimalIf,
+
+    //the signature checker could not compute a signature hash
+    SighashError,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let msg = match self {
+            ScriptError::EvalFalse => {
+                "script evaluated without error but finished with a false/empty top stack element"
+            }
+            ScriptError::OpReturn => "OP_RETURN was encountered",
+            ScriptError::ScriptSize => "script is too big",
+            ScriptError::PushSize => "push value size limit exceeded",
+            ScriptError::OpCount => "operation limit exceeded",
+            ScriptError::StackSize => "stack size limit exceeded",
+            ScriptError::SigCount => "signature count negative or greater than pubkey count",
+            ScriptError::PubkeyCount => "pubkey count negative or limit exceeded",
+            ScriptError::Verify => "script failed an OP_VERIFY operation",
+            ScriptError::EqualVerify => "script failed an OP_EQUALVERIFY operation",
+            ScriptError::CheckMultisigVerify => "script failed an OP_CHECKMULTISIGVERIFY operation",
+            ScriptError::CheckSigVerify => "script failed an OP_CHECKSIGVERIFY operation",
+            ScriptError::NumEqualVerify => "script failed an OP_NUMEQUALVERIFY operation",
+            ScriptError::BadOpcode => "opcode missing or not understood",
+            ScriptError::DisabledOpcode => "attempted to use a disabled opcode",
+            ScriptError::InvalidStackOperation => "operation not valid with the current stack size",
+            ScriptError::InvalidAltstackOperation => {
+                "operation not valid with the current altstack size"
+            }
+            ScriptError::UnbalancedConditional => "invalid OP_IF construction",
+            ScriptError::ScriptNumOverflow => "script number overflow",
+            ScriptError::ScriptNumMinimal => "non-minimally encoded script number",
+            ScriptError::NegativeLocktime => "negative locktime",
+            ScriptError::UnsatisfiedLocktime => "locktime requirement not satisfied",
+            ScriptError::SigHashtype => "signature hash type missing or not understood",
+            ScriptError::SigDer => "non-canonical DER signature",
+            ScriptError::SigHighS => "non-canonical signature: S value is unnecessarily high",
+            ScriptError::SigNullDummy => "dummy CHECKMULTISIG argument must be zero",
+            ScriptError::SigPushOnly => "only push operators allowed in signatures",
+            ScriptError::MinimalIf => "OP_IF/NOTIF argument must be minimal",
+            ScriptError::SigNullFail => {
+                "signature must be zero for failed CHECK(MULTI)SIG operation"
+            }
+            ScriptError::WitnessProgramWrongLength => "witness program has incorrect length",
+            ScriptError::WitnessProgramWitnessEmpty => {
+                "witness program was passed an empty witness"
+            }
+            ScriptError::WitnessProgramMismatch => "witness program hash mismatch",
+            ScriptError::WitnessMalleated => "witness requires empty scriptSig",
+            ScriptError::WitnessMalleatedP2sh => "witness requires only-redeemscript scriptSig",
+            ScriptError::WitnessUnexpected => "witness provided for non-witness script",
+            ScriptError::WitnessPubkeyType => "using non-compressed keys in segwit",
+            ScriptError::CleanStack => "stack size must be exactly one after execution",
+            ScriptError::SchnorrSigSize => "invalid Schnorr signature size",
+            ScriptError::SchnorrSigHashtype => "invalid Schnorr signature hash type",
+            ScriptError::SchnorrSig => "invalid Schnorr signature",
+            ScriptError::PubkeyType => "public key is neither compressed or uncompressed",
+            ScriptError::TaprootWrongControlSize => "invalid Taproot control block size",
+            ScriptError::TapscriptValidationWeight => {
+                "too much signature validation relative to witness weight"
+            }
+            ScriptError::TapscriptCheckMultisig => {
+                "OP_CHECKMULTISIG(VERIFY) is not available in tapscript"
+            }
+            ScriptError::TapscriptMinimalIf => "OP_IF/NOTIF argument must be minimal in tapscript",
+            ScriptError::SighashError => "signature hash could not be computed",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ScriptError {}
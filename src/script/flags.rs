@@ -0,0 +1,31 @@
+//script verification flags, bit positions as in bitcoind so flag sets can be shared
+pub const VERIFY_NONE: u32 = 0;
+//BIP16
+pub const VERIFY_P2SH: u32 = 1 << 0;
+//defined signature hash types and compressed or uncompressed public keys
+pub const VERIFY_STRICTENC: u32 = 1 << 1;
+//BIP66 strict DER signatures
+pub const VERIFY_DERSIG: u32 = 1 << 2;
+//BIP62 s in the lower half of the curve order
+pub const VERIFY_LOW_S: u32 = 1 << 3;
+//BIP147 the CHECKMULTISIG dummy element must be empty
+pub const VERIFY_NULLDUMMY: u32 = 1 << 4;
+//BIP65
+pub const VERIFY_CHECKLOCKTIMEVERIFY: u32 = 1 << 9;
+//BIP112
+pub const VERIFY_CHECKSEQUENCEVERIFY: u32 = 1 << 10;
+//BIP141 and BIP143
+pub const VERIFY_WITNESS: u32 = 1 << 11;
+//BIP146 a failing CHECK(MULTI)SIG must have empty signatures
+pub const VERIFY_NULLFAIL: u32 = 1 << 14;
+//BIP341 and BIP342
+pub const VERIFY_TAPROOT: u32 = 1 << 17;
+
+//all consensus rules enforced today, STRICTENC, LOW_S and NULLFAIL are only bitcoind's relay policy
+pub const VERIFY_CONSENSUS: u32 = VERIFY_P2SH
+    | VERIFY_DERSIG
+    | VERIFY_NULLDUMMY
+    | VERIFY_CHECKLOCKTIMEVERIFY
+    | VERIFY_CHECKSEQUENCEVERIFY
+    | VERIFY_WITNESS
+    | VERIFY_TAPROOT;
@@ -0,0 +1,1546 @@
+use crate::{
+    parsers::parse_instruction,
+    script::{
+        checker::{ExecutionData, SigVersion, SignatureChecker},
+        flags::*,
+        num::{cast_to_bool, decode_script_num, encode_script_num},
+        opcodes::*,
+        ScriptError,
+    },
+    serializers::serialize_var_int,
+    types::{Hash256, Instruction},
+    utils::{hash160, hash256, ripemd160, sha256, tap_branch_hash, tap_leaf_hash},
+};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+pub const MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+pub const MAX_STACK_SIZE: usize = 1000;
+
+const ANNEX_TAG: u8 = 0x50;
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+pub type Stack = Vec<Vec<u8>>;
+
+fn top(stack: &[Vec<u8>], depth: usize) -> Result<&Vec<u8>, ScriptError> {
+    match stack.len().checked_sub(depth) {
+        Some(index) => Ok(&stack[index]),
+        None => Err(ScriptError::InvalidStackOperation),
+    }
+}
+
+fn pop(stack: &mut Stack) -> Result<Vec<u8>, ScriptError> {
+    stack.pop().ok_or(ScriptError::InvalidStackOperation)
+}
+
+fn require(stack: &[Vec<u8>], n: usize) -> Result<(), ScriptError> {
+    match stack.len() < n {
+        true => Err(ScriptError::InvalidStackOperation),
+        false => Ok(()),
+    }
+}
+
+fn encode_bool(value: bool) -> Vec<u8> {
+    match value {
+        true => vec![1],
+        false => Vec::new(),
+    }
+}
+
+fn is_disabled(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        OP_CAT
+            | OP_SUBSTR
+            | OP_LEFT
+            | OP_RIGHT
+            | OP_INVERT
+            | OP_AND
+            | OP_OR
+            | OP_XOR
+            | OP_2MUL
+            | OP_2DIV
+            | OP_MUL
+            | OP_DIV
+            | OP_MOD
+            | OP_LSHIFT
+            | OP_RSHIFT
+    )
+}
+
+//opcodes that make a tapscript succeed unconditionally, reserved for soft forks (BIP342)
+pub fn is_op_success(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254
+    )
+}
+
+//the push of data as done by the shortest encoding
+pub fn push_data_script(data: &[u8]) -> Vec<u8> {
+    let len = data.len();
+    let prefix = match len {
+        0..=0x4b => vec![len as u8],
+        0x4c..=0xff => vec![OP_PUSHDATA1, len as u8],
+        0x100..=0xffff => [&[OP_PUSHDATA2][..], &(len as u16).to_le_bytes()].concat(),
+        _ => [&[OP_PUSHDATA4][..], &(len as u32).to_le_bytes()].concat(),
+    };
+    [&prefix[..], data].concat()
+}
+
+//removes every push of sig found at an opcode boundary, the legacy scriptCode quirk
+fn find_and_delete(script: &[u8], sig: &[u8]) -> Vec<u8> {
+    let pattern = push_data_script(sig);
+    let mut result = Vec::with_capacity(script.len());
+    let mut pos = 0;
+    let mut copied_to = 0;
+    loop {
+        result.extend(&script[copied_to..pos]);
+        while script.len() - pos >= pattern.len() && script[pos..].starts_with(&pattern) {
+            pos += pattern.len();
+        }
+        copied_to = pos;
+        match parse_instruction(&script[pos..]) {
+            Ok((rest, _)) => pos = script.len() - rest.len(),
+            Err(_) => break,
+        }
+    }
+    result.extend(&script[copied_to..]);
+    result
+}
+
+//strict DER as required by BIP66, including the trailing hash type byte
+pub fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    let len = sig.len();
+    if !(9..=73).contains(&len) || sig[0] != 0x30 || sig[1] as usize != len - 3 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= len {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != len {
+        return false;
+    }
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+    true
+}
+
+//s of a strict DER signature is above half the curve order, an s not below the order is left to fail
+fn is_high_s(sig: &[u8]) -> bool {
+    const ORDER: [u8; 32] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ];
+    const HALF_ORDER: [u8; 32] = [
+        0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b,
+        0x20, 0xa0,
+    ];
+    let len_r = sig[3] as usize;
+    let s = &sig[len_r + 6..sig.len() - 1];
+    let s = &s[s.iter().take_while(|b| **b == 0).count()..];
+    if s.len() > 32 {
+        return false;
+    }
+    let mut padded = [0; 32];
+    padded[32 - s.len()..].copy_from_slice(s);
+    padded > HALF_ORDER && padded < ORDER
+}
+
+fn check_signature_encoding(sig: &[u8], flags: u32) -> Result<(), ScriptError> {
+    //an empty signature is allowed to make CHECK(MULTI)SIG fail
+    if sig.is_empty() {
+        return Ok(());
+    }
+    if flags & (VERIFY_DERSIG | VERIFY_LOW_S | VERIFY_STRICTENC) != 0
+        && !is_valid_signature_encoding(sig)
+    {
+        return Err(ScriptError::SigDer);
+    }
+    if flags & VERIFY_LOW_S != 0 && is_high_s(sig) {
+        return Err(ScriptError::SigHighS);
+    }
+    //ALL, NONE or SINGLE, with or without ANYONECANPAY
+    if flags & VERIFY_STRICTENC != 0 && !(1..=3).contains(&(sig[sig.len() - 1] & !0x80)) {
+        return Err(ScriptError::SigHashtype);
+    }
+    Ok(())
+}
+
+fn check_pubkey_encoding(pubkey: &[u8], flags: u32) -> Result<(), ScriptError> {
+    let valid = match pubkey.first() {
+        Some(0x02) | Some(0x03) => pubkey.len() == 33,
+        Some(0x04) => pubkey.len() == 65,
+        _ => false,
+    };
+    match flags & VERIFY_STRICTENC != 0 && !valid {
+        true => Err(ScriptError::PubkeyType),
+        false => Ok(()),
+    }
+}
+
+pub fn is_push_only(script: &[u8]) -> bool {
+    let mut input = script;
+    while !input.is_empty() {
+        match parse_instruction(input) {
+            Ok((i, instruction)) if instruction.opcode() <= OP_16 => input = i,
+            _ => return false,
+        }
+    }
+    true
+}
+
+pub fn is_pay_to_script_hash(script: &[u8]) -> bool {
+    script.len() == 23 && script[0] == OP_HASH160 && script[1] == 0x14 && script[22] == OP_EQUAL
+}
+
+//(version, program) if the script is a BIP141 witness program
+pub fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if script.len() < 4 || script.len() > 42 {
+        return None;
+    }
+    let version = match script[0] {
+        OP_0 => 0,
+        op @ OP_1..=OP_16 => op - OP_1 + 1,
+        _ => return None,
+    };
+    match script[1] as usize + 2 == script.len() {
+        true => Some((version, &script[2..])),
+        false => None,
+    }
+}
+
+fn eval_checksig<C: SignatureChecker + ?Sized>(
+    sig: &[u8],
+    pubkey: &[u8],
+    script_code: &[u8],
+    flags: u32,
+    checker: &C,
+    sig_version: SigVersion,
+    exec_data: &mut ExecutionData,
+) -> Result<bool, ScriptError> {
+    match sig_version {
+        SigVersion::Base | SigVersion::WitnessV0 => {
+            let script_code = match sig_version {
+                SigVersion::Base => find_and_delete(script_code, sig),
+                _ => script_code.to_vec(),
+            };
+            check_signature_encoding(sig, flags)?;
+            check_pubkey_encoding(pubkey, flags)?;
+            let success = checker.check_ecdsa_signature(sig, pubkey, &script_code, sig_version);
+            if !success && flags & VERIFY_NULLFAIL != 0 && !sig.is_empty() {
+                return Err(ScriptError::SigNullFail);
+            }
+            Ok(success)
+        }
+        _ => {
+            let success = !sig.is_empty();
+            if success {
+                exec_data.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+                if exec_data.validation_weight_left < 0 {
+                    return Err(ScriptError::TapscriptValidationWeight);
+                }
+            }
+            if pubkey.is_empty() {
+                return Err(ScriptError::PubkeyType);
+            }
+            //unknown public key types are left for future soft forks and always succeed
+            if pubkey.len() == 32 && success {
+                checker.check_schnorr_signature(sig, pubkey, sig_version, exec_data)?;
+            }
+            Ok(success)
+        }
+    }
+}
+
+//runs script on stack, the equivalent of bitcoind's EvalScript
+pub fn eval_script<C: SignatureChecker + ?Sized>(
+    stack: &mut Stack,
+    script: &[u8],
+    flags: u32,
+    checker: &C,
+    sig_version: SigVersion,
+    exec_data: &mut ExecutionData,
+) -> Result<(), ScriptError> {
+    let legacy = sig_version == SigVersion::Base || sig_version == SigVersion::WitnessV0;
+    if legacy && script.len() > MAX_SCRIPT_SIZE {
+        return Err(ScriptError::ScriptSize);
+    }
+    let mut alt_stack: Stack = Vec::new();
+    let mut exec_stack: Vec<bool> = Vec::new();
+    let mut op_count = 0;
+    //scriptCode starts after the last executed OP_CODESEPARATOR
+    let mut code_start = 0;
+    let mut opcode_pos: u32 = 0;
+    exec_data.codeseparator_pos = 0xffffffff;
+    let mut input = script;
+
+    while !input.is_empty() {
+        let executing = exec_stack.iter().all(|e| *e);
+        let (rest, instruction) = parse_instruction(input).map_err(|_| ScriptError::BadOpcode)?;
+        input = rest;
+        let pc = script.len() - input.len();
+        let opcode = instruction.opcode();
+
+        if let Instruction::Push(_, data) = &instruction {
+            if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(ScriptError::PushSize);
+            }
+        }
+        if legacy && opcode > OP_16 {
+            op_count += 1;
+            if op_count > MAX_OPS_PER_SCRIPT {
+                return Err(ScriptError::OpCount);
+            }
+        }
+        //disabled opcodes fail even in an unexecuted branch
+        if is_disabled(opcode) {
+            return Err(ScriptError::DisabledOpcode);
+        }
+
+        match instruction {
+            Instruction::Push(_, data) => {
+                if executing {
+                    stack.push(data.0);
+                }
+            }
+            Instruction::Op(opcode) if executing || (OP_IF..=OP_ENDIF).contains(&opcode) => {
+                match opcode {
+                    OP_1NEGATE | OP_1..=OP_16 => {
+                        stack.push(encode_script_num(i64::from(opcode) - i64::from(OP_1 - 1)))
+                    }
+                    OP_NOP | OP_NOP1 | OP_NOP4..=OP_NOP10 => (),
+                    OP_CHECKLOCKTIMEVERIFY => {
+                        if flags & VERIFY_CHECKLOCKTIMEVERIFY != 0 {
+                            //5 bytes as lock times go up to 2^32-1
+                            let lock_time = decode_script_num(top(stack, 1)?, false, 5)?;
+                            if lock_time < 0 {
+                                return Err(ScriptError::NegativeLocktime);
+                            }
+                            if !checker.check_lock_time(lock_time) {
+                                return Err(ScriptError::UnsatisfiedLocktime);
+                            }
+                        }
+                    }
+                    OP_CHECKSEQUENCEVERIFY => {
+                        if flags & VERIFY_CHECKSEQUENCEVERIFY != 0 {
+                            let sequence = decode_script_num(top(stack, 1)?, false, 5)?;
+                            if sequence < 0 {
+                                return Err(ScriptError::NegativeLocktime);
+                            }
+                            //the disable flag makes it a NOP
+                            if sequence & (1 << 31) == 0 && !checker.check_sequence(sequence) {
+                                return Err(ScriptError::UnsatisfiedLocktime);
+                            }
+                        }
+                    }
+                    OP_IF | OP_NOTIF => {
+                        let mut value = false;
+                        if executing {
+                            let condition =
+                                pop(stack).map_err(|_| ScriptError::UnbalancedConditional)?;
+                            if sig_version == SigVersion::Tapscript
+                                && (condition.len() > 1
+                                    || (condition.len() == 1 && condition[0] != 1))
+                            {
+                                return Err(ScriptError::TapscriptMinimalIf);
+                            }
+                            value = cast_to_bool(&condition) == (opcode == OP_IF);
+                        }
+                        exec_stack.push(value);
+                    }
+                    OP_ELSE => match exec_stack.last_mut() {
+                        Some(value) => *value = !*value,
+                        None => return Err(ScriptError::UnbalancedConditional),
+                    },
+                    OP_ENDIF => {
+                        exec_stack.pop().ok_or(ScriptError::UnbalancedConditional)?;
+                    }
+                    OP_VERIFY => match cast_to_bool(top(stack, 1)?) {
+                        true => {
+                            stack.pop();
+                        }
+                        false => return Err(ScriptError::Verify),
+                    },
+                    OP_RETURN => return Err(ScriptError::OpReturn),
+
+                    OP_TOALTSTACK => alt_stack.push(pop(stack)?),
+                    OP_FROMALTSTACK => stack.push(
+                        alt_stack
+                            .pop()
+                            .ok_or(ScriptError::InvalidAltstackOperation)?,
+                    ),
+                    OP_2DROP => {
+                        require(stack, 2)?;
+                        stack.truncate(stack.len() - 2);
+                    }
+                    OP_2DUP => {
+                        let (a, b) = (top(stack, 2)?.clone(), top(stack, 1)?.clone());
+                        stack.push(a);
+                        stack.push(b);
+                    }
+                    OP_3DUP => {
+                        require(stack, 3)?;
+                        let start = stack.len() - 3;
+                        let items = stack[start..].to_vec();
+                        stack.extend(items);
+                    }
+                    OP_2OVER => {
+                        require(stack, 4)?;
+                        let start = stack.len() - 4;
+                        let items = stack[start..start + 2].to_vec();
+                        stack.extend(items);
+                    }
+                    OP_2ROT => {
+                        require(stack, 6)?;
+                        let start = stack.len() - 6;
+                        let items: Vec<_> = stack.drain(start..start + 2).collect();
+                        stack.extend(items);
+                    }
+                    OP_2SWAP => {
+                        require(stack, 4)?;
+                        let len = stack.len();
+                        stack.swap(len - 4, len - 2);
+                        stack.swap(len - 3, len - 1);
+                    }
+                    OP_IFDUP => {
+                        let item = top(stack, 1)?.clone();
+                        if cast_to_bool(&item) {
+                            stack.push(item);
+                        }
+                    }
+                    OP_DEPTH => stack.push(encode_script_num(stack.len() as i64)),
+                    OP_DROP => {
+                        pop(stack)?;
+                    }
+                    OP_DUP => {
+                        let item = top(stack, 1)?.clone();
+                        stack.push(item);
+                    }
+                    OP_NIP => {
+                        require(stack, 2)?;
+                        let len = stack.len();
+                        stack.remove(len - 2);
+                    }
+                    OP_OVER => {
+                        let item = top(stack, 2)?.clone();
+                        stack.push(item);
+                    }
+                    OP_PICK | OP_ROLL => {
+                        require(stack, 2)?;
+                        let n = decode_script_num(&pop(stack)?, false, 4)?;
+                        if n < 0 || n as usize >= stack.len() {
+                            return Err(ScriptError::InvalidStackOperation);
+                        }
+                        let index = stack.len() - 1 - n as usize;
+                        let item = match opcode {
+                            OP_ROLL => stack.remove(index),
+                            _ => stack[index].clone(),
+                        };
+                        stack.push(item);
+                    }
+                    OP_ROT => {
+                        require(stack, 3)?;
+                        let len = stack.len();
+                        stack.swap(len - 3, len - 2);
+                        stack.swap(len - 2, len - 1);
+                    }
+                    OP_SWAP => {
+                        require(stack, 2)?;
+                        let len = stack.len();
+                        stack.swap(len - 2, len - 1);
+                    }
+                    OP_TUCK => {
+                        let item = top(stack, 1)?.clone();
+                        require(stack, 2)?;
+                        let len = stack.len();
+                        stack.insert(len - 2, item);
+                    }
+                    OP_SIZE => {
+                        let size = top(stack, 1)?.len();
+                        stack.push(encode_script_num(size as i64));
+                    }
+
+                    OP_EQUAL | OP_EQUALVERIFY => {
+                        require(stack, 2)?;
+                        let equal = pop(stack)? == pop(stack)?;
+                        match opcode {
+                            OP_EQUALVERIFY if !equal => return Err(ScriptError::EqualVerify),
+                            OP_EQUALVERIFY => (),
+                            _ => stack.push(encode_bool(equal)),
+                        }
+                    }
+
+                    OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+                        let n = decode_script_num(top(stack, 1)?, false, 4)?;
+                        let result = match opcode {
+                            OP_1ADD => n + 1,
+                            OP_1SUB => n - 1,
+                            OP_NEGATE => -n,
+                            OP_ABS => n.abs(),
+                            OP_NOT => (n == 0) as i64,
+                            _ => (n != 0) as i64,
+                        };
+                        stack.pop();
+                        stack.push(encode_script_num(result));
+                    }
+                    OP_ADD
+                    | OP_SUB
+                    | OP_BOOLAND
+                    | OP_BOOLOR
+                    | OP_NUMEQUAL
+                    | OP_NUMEQUALVERIFY
+                    | OP_NUMNOTEQUAL
+                    | OP_LESSTHAN
+                    | OP_GREATERTHAN
+                    | OP_LESSTHANOREQUAL
+                    | OP_GREATERTHANOREQUAL
+                    | OP_MIN
+                    | OP_MAX => {
+                        let a = decode_script_num(top(stack, 2)?, false, 4)?;
+                        let b = decode_script_num(top(stack, 1)?, false, 4)?;
+                        let result = match opcode {
+                            OP_ADD => a + b,
+                            OP_SUB => a - b,
+                            OP_BOOLAND => (a != 0 && b != 0) as i64,
+                            OP_BOOLOR => (a != 0 || b != 0) as i64,
+                            OP_NUMEQUAL | OP_NUMEQUALVERIFY => (a == b) as i64,
+                            OP_NUMNOTEQUAL => (a != b) as i64,
+                            OP_LESSTHAN => (a < b) as i64,
+                            OP_GREATERTHAN => (a > b) as i64,
+                            OP_LESSTHANOREQUAL => (a <= b) as i64,
+                            OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                            OP_MIN => a.min(b),
+                            _ => a.max(b),
+                        };
+                        stack.truncate(stack.len() - 2);
+                        if opcode == OP_NUMEQUALVERIFY {
+                            if result == 0 {
+                                return Err(ScriptError::NumEqualVerify);
+                            }
+                        } else {
+                            stack.push(encode_script_num(result));
+                        }
+                    }
+                    OP_WITHIN => {
+                        let x = decode_script_num(top(stack, 3)?, false, 4)?;
+                        let min = decode_script_num(top(stack, 2)?, false, 4)?;
+                        let max = decode_script_num(top(stack, 1)?, false, 4)?;
+                        stack.truncate(stack.len() - 3);
+                        stack.push(encode_bool(min <= x && x < max));
+                    }
+
+                    OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => {
+                        let item = pop(stack)?;
+                        let hash = match opcode {
+                            OP_RIPEMD160 => ripemd160(&item).to_vec(),
+                            OP_SHA1 => digest(&SHA1_FOR_LEGACY_USE_ONLY, &item).as_ref().to_vec(),
+                            OP_SHA256 => sha256(&item).as_ref().to_vec(),
+                            OP_HASH160 => hash160(&item).to_vec(),
+                            _ => hash256(&item).as_ref().to_vec(),
+                        };
+                        stack.push(hash);
+                    }
+                    OP_CODESEPARATOR => {
+                        code_start = pc;
+                        exec_data.codeseparator_pos = opcode_pos;
+                    }
+                    OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                        require(stack, 2)?;
+                        let pubkey = pop(stack)?;
+                        let sig = pop(stack)?;
+                        let success = eval_checksig(
+                            &sig,
+                            &pubkey,
+                            &script[code_start..],
+                            flags,
+                            checker,
+                            sig_version,
+                            exec_data,
+                        )?;
+                        match opcode {
+                            OP_CHECKSIGVERIFY if !success => {
+                                return Err(ScriptError::CheckSigVerify)
+                            }
+                            OP_CHECKSIGVERIFY => (),
+                            _ => stack.push(encode_bool(success)),
+                        }
+                    }
+                    OP_CHECKSIGADD => {
+                        if legacy {
+                            return Err(ScriptError::BadOpcode);
+                        }
+                        require(stack, 3)?;
+                        let pubkey = pop(stack)?;
+                        let n = decode_script_num(&pop(stack)?, false, 4)?;
+                        let sig = pop(stack)?;
+                        let success = eval_checksig(
+                            &sig,
+                            &pubkey,
+                            &script[code_start..],
+                            flags,
+                            checker,
+                            sig_version,
+                            exec_data,
+                        )?;
+                        stack.push(encode_script_num(n + success as i64));
+                    }
+                    OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                        if sig_version == SigVersion::Tapscript {
+                            return Err(ScriptError::TapscriptCheckMultisig);
+                        }
+                        let mut i = 1;
+                        let mut keys_count = decode_script_num(top(stack, i)?, false, 4)?;
+                        if !(0..=MAX_PUBKEYS_PER_MULTISIG).contains(&keys_count) {
+                            return Err(ScriptError::PubkeyCount);
+                        }
+                        op_count += keys_count as usize;
+                        if op_count > MAX_OPS_PER_SCRIPT {
+                            return Err(ScriptError::OpCount);
+                        }
+                        i += 1;
+                        let mut key_index = i;
+                        i += keys_count as usize;
+                        let mut sigs_count = decode_script_num(top(stack, i)?, false, 4)?;
+                        if sigs_count < 0 || sigs_count > keys_count {
+                            return Err(ScriptError::SigCount);
+                        }
+                        i += 1;
+                        let mut sig_index = i;
+                        let first_sig = i;
+                        i += sigs_count as usize;
+                        require(stack, i)?;
+
+                        let mut script_code = script[code_start..].to_vec();
+                        if sig_version == SigVersion::Base {
+                            for k in 0..sigs_count as usize {
+                                script_code = find_and_delete(
+                                    &script_code,
+                                    &stack[stack.len() - sig_index - k],
+                                );
+                            }
+                        }
+                        let mut success = true;
+                        while success && sigs_count > 0 {
+                            let sig = &stack[stack.len() - sig_index];
+                            let pubkey = &stack[stack.len() - key_index];
+                            check_signature_encoding(sig, flags)?;
+                            check_pubkey_encoding(pubkey, flags)?;
+                            if checker.check_ecdsa_signature(sig, pubkey, &script_code, sig_version)
+                            {
+                                sig_index += 1;
+                                sigs_count -= 1;
+                            }
+                            key_index += 1;
+                            keys_count -= 1;
+                            //more signatures left than keys means it can not succeed anymore
+                            if sigs_count > keys_count {
+                                success = false;
+                            }
+                        }
+                        //the signatures are right below the keys and the two counts
+                        if !success && flags & VERIFY_NULLFAIL != 0 {
+                            let sigs = &stack[stack.len() - (i - 1)..stack.len() - (first_sig - 1)];
+                            if sigs.iter().any(|sig| !sig.is_empty()) {
+                                return Err(ScriptError::SigNullFail);
+                            }
+                        }
+                        //everything but the dummy element
+                        stack.truncate(stack.len() - (i - 1));
+                        //the dummy element consumed by an off by one bug, BIP147 requires it to be empty
+                        let dummy = pop(stack)?;
+                        if flags & VERIFY_NULLDUMMY != 0 && !dummy.is_empty() {
+                            return Err(ScriptError::SigNullDummy);
+                        }
+                        match opcode {
+                            OP_CHECKMULTISIGVERIFY if !success => {
+                                return Err(ScriptError::CheckMultisigVerify)
+                            }
+                            OP_CHECKMULTISIGVERIFY => (),
+                            _ => stack.push(encode_bool(success)),
+                        }
+                    }
+                    _ => return Err(ScriptError::BadOpcode),
+                }
+            }
+            Instruction::Op(_) => (),
+        }
+
+        if stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            return Err(ScriptError::StackSize);
+        }
+        opcode_pos += 1;
+    }
+    match exec_stack.is_empty() {
+        true => Ok(()),
+        false => Err(ScriptError::UnbalancedConditional),
+    }
+}
+
+fn execute_witness_script<C: SignatureChecker + ?Sized>(
+    mut stack: Stack,
+    script: &[u8],
+    flags: u32,
+    checker: &C,
+    sig_version: SigVersion,
+    exec_data: &mut ExecutionData,
+) -> Result<(), ScriptError> {
+    if sig_version == SigVersion::Tapscript {
+        let mut input = script;
+        while !input.is_empty() {
+            let (rest, instruction) =
+                parse_instruction(input).map_err(|_| ScriptError::BadOpcode)?;
+            if let Instruction::Op(opcode) = instruction {
+                if is_op_success(opcode) {
+                    return Ok(());
+                }
+            }
+            input = rest;
+        }
+        if stack.len() > MAX_STACK_SIZE {
+            return Err(ScriptError::StackSize);
+        }
+    }
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(ScriptError::PushSize);
+    }
+    eval_script(&mut stack, script, flags, checker, sig_version, exec_data)?;
+    //witness scripts have to leave exactly one true element
+    if stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+    match cast_to_bool(&stack[0]) {
+        true => Ok(()),
+        false => Err(ScriptError::EvalFalse),
+    }
+}
+
+fn witness_stack_size(witness: &[Vec<u8>]) -> usize {
+    witness
+        .iter()
+        .map(|item| serialize_var_int(item.len() as u64).len() + item.len())
+        .sum::<usize>()
+        + serialize_var_int(witness.len() as u64).len()
+}
+
+fn verify_witness_program<C: SignatureChecker + ?Sized>(
+    witness: &[Vec<u8>],
+    version: u8,
+    program: &[u8],
+    flags: u32,
+    checker: &C,
+    is_p2sh: bool,
+) -> Result<(), ScriptError> {
+    let mut stack = witness.to_vec();
+    let mut exec_data = ExecutionData::default();
+    match (version, program.len()) {
+        (0, 32) => {
+            let script = stack.pop().ok_or(ScriptError::WitnessProgramWitnessEmpty)?;
+            if sha256(&script).as_ref() != program {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+            execute_witness_script(
+                stack,
+                &script,
+                flags,
+                checker,
+                SigVersion::WitnessV0,
+                &mut exec_data,
+            )
+        }
+        (0, 20) => {
+            if stack.len() != 2 {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+            let script = [
+                &[OP_DUP, OP_HASH160, 0x14][..],
+                program,
+                &[OP_EQUALVERIFY, OP_CHECKSIG],
+            ]
+            .concat();
+            execute_witness_script(
+                stack,
+                &script,
+                flags,
+                checker,
+                SigVersion::WitnessV0,
+                &mut exec_data,
+            )
+        }
+        (0, _) => Err(ScriptError::WitnessProgramWrongLength),
+        (1, 32) if !is_p2sh => {
+            if flags & VERIFY_TAPROOT == 0 {
+                return Ok(());
+            }
+            if stack.is_empty() {
+                return Err(ScriptError::WitnessProgramWitnessEmpty);
+            }
+            if stack.len() >= 2 && stack[stack.len() - 1].first() == Some(&ANNEX_TAG) {
+                exec_data.annex = stack.pop();
+            }
+            if stack.len() == 1 {
+                //key path spend
+                return checker.check_schnorr_signature(
+                    &stack[0],
+                    program,
+                    SigVersion::Taproot,
+                    &exec_data,
+                );
+            }
+            let control = pop(&mut stack)?;
+            let script = pop(&mut stack)?;
+            if control.len() < TAPROOT_CONTROL_BASE_SIZE
+                || control.len()
+                    > TAPROOT_CONTROL_BASE_SIZE
+                        + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT
+                || (control.len() - TAPROOT_CONTROL_BASE_SIZE) % TAPROOT_CONTROL_NODE_SIZE != 0
+            {
+                return Err(ScriptError::TaprootWrongControlSize);
+            }
+            let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+            let leaf_hash = tap_leaf_hash(leaf_version, &script);
+            let merkle_root = control[TAPROOT_CONTROL_BASE_SIZE..]
+                .chunks(TAPROOT_CONTROL_NODE_SIZE)
+                .fold(leaf_hash, |k, node| {
+                    tap_branch_hash(&k, &Hash256::new(node))
+                });
+            if !checker.check_tap_tweak(
+                &control[1..TAPROOT_CONTROL_BASE_SIZE],
+                &merkle_root,
+                program,
+                control[0] & 1 == 1,
+            ) {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+            exec_data.tapleaf_hash = Some(leaf_hash);
+            match leaf_version {
+                TAPROOT_LEAF_TAPSCRIPT => {
+                    exec_data.validation_weight_left =
+                        witness_stack_size(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+                    execute_witness_script(
+                        stack,
+                        &script,
+                        flags,
+                        checker,
+                        SigVersion::Tapscript,
+                        &mut exec_data,
+                    )
+                }
+                //unknown leaf versions are left for future soft forks
+                _ => Ok(()),
+            }
+        }
+        //unknown witness versions are left for future soft forks
+        _ => Ok(()),
+    }
+}
+
+//verifies that script_sig and witness satisfy script_pub_key, the equivalent of bitcoind's VerifyScript
+pub fn verify_script<C: SignatureChecker + ?Sized>(
+    script_sig: &[u8],
+    script_pub_key: &[u8],
+    witness: &[Vec<u8>],
+    flags: u32,
+    checker: &C,
+) -> Result<(), ScriptError> {
+    let mut had_witness = false;
+    let mut stack = Vec::new();
+    let mut exec_data = ExecutionData::default();
+    eval_script(
+        &mut stack,
+        script_sig,
+        flags,
+        checker,
+        SigVersion::Base,
+        &mut exec_data,
+    )?;
+    let stack_copy = match flags & VERIFY_P2SH != 0 {
+        true => stack.clone(),
+        false => Vec::new(),
+    };
+    eval_script(
+        &mut stack,
+        script_pub_key,
+        flags,
+        checker,
+        SigVersion::Base,
+        &mut exec_data,
+    )?;
+    match stack.last() {
+        Some(item) if cast_to_bool(item) => (),
+        _ => return Err(ScriptError::EvalFalse),
+    }
+
+    if flags & VERIFY_WITNESS != 0 {
+        if let Some((version, program)) = witness_program(script_pub_key) {
+            had_witness = true;
+            if !script_sig.is_empty() {
+                return Err(ScriptError::WitnessMalleated);
+            }
+            verify_witness_program(witness, version, program, flags, checker, false)?;
+        }
+    }
+
+    if flags & VERIFY_P2SH != 0 && is_pay_to_script_hash(script_pub_key) {
+        if !is_push_only(script_sig) {
+            return Err(ScriptError::SigPushOnly);
+        }
+        let mut stack = stack_copy;
+        //the stack can not be empty here, an empty stack failed the hash comparison above
+        let redeem_script = pop(&mut stack)?;
+        eval_script(
+            &mut stack,
+            &redeem_script,
+            flags,
+            checker,
+            SigVersion::Base,
+            &mut exec_data,
+        )?;
+        match stack.last() {
+            Some(item) if cast_to_bool(item) => (),
+            _ => return Err(ScriptError::EvalFalse),
+        }
+        if flags & VERIFY_WITNESS != 0 {
+            if let Some((version, program)) = witness_program(&redeem_script) {
+                had_witness = true;
+                //the scriptSig has to be exactly the push of the redeem script
+                if script_sig != &push_data_script(&redeem_script)[..] {
+                    return Err(ScriptError::WitnessMalleatedP2sh);
+                }
+                verify_witness_program(witness, version, program, flags, checker, true)?;
+            }
+        }
+    }
+
+    if flags & VERIFY_WITNESS != 0 && !had_witness && !witness.is_empty() {
+        return Err(ScriptError::WitnessUnexpected);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        script::{opcodes::opcode_name, TransactionSignatureChecker},
+        serializers::serialize_transaction_no_witness,
+        types::{Transaction, TransactionBuilder, TxInput, TxOutput},
+    };
+
+    //accepts an ECDSA signature that is the public key followed by SIGHASH_ALL,
+    //any 64 byte Schnorr signature and a tap tweak committing to merkle_root
+    struct MockChecker {
+        lock_time: i64,
+        merkle_root: Option<Hash256>,
+    }
+
+    impl SignatureChecker for MockChecker {
+        fn check_ecdsa_signature(
+            &self,
+            sig: &[u8],
+            pubkey: &[u8],
+            _: &[u8],
+            _: SigVersion,
+        ) -> bool {
+            sig == &[pubkey, &[1]].concat()[..]
+        }
+        fn check_schnorr_signature(
+            &self,
+            sig: &[u8],
+            _: &[u8],
+            _: SigVersion,
+            _: &ExecutionData,
+        ) -> Result<(), ScriptError> {
+            match sig.len() {
+                64 => Ok(()),
+                _ => Err(ScriptError::SchnorrSigSize),
+            }
+        }
+        fn check_lock_time(&self, lock_time: i64) -> bool {
+            lock_time <= self.lock_time
+        }
+        fn check_tap_tweak(&self, _: &[u8], merkle_root: &Hash256, _: &[u8], _: bool) -> bool {
+            self.merkle_root.as_ref() == Some(merkle_root)
+        }
+    }
+
+    const CHECKER: MockChecker = MockChecker {
+        lock_time: 100,
+        merkle_root: None,
+    };
+
+    //the script notation of bitcoind's script_tests.json
+    fn asm(text: &str) -> Vec<u8> {
+        let mut script = Vec::new();
+        for token in text.split_whitespace() {
+            if let Ok(n) = token.parse::<i64>() {
+                match n {
+                    0 => script.push(OP_0),
+                    -1 | 1..=16 => script.push((i64::from(OP_1) - 1 + n) as u8),
+                    _ => script.extend(push_data_script(&encode_script_num(n))),
+                }
+            } else if let Some(hex) = token.strip_prefix("0x") {
+                script.extend(hex::decode(hex).unwrap());
+            } else if token.starts_with('\'') {
+                script.extend(push_data_script(token.trim_matches('\'').as_bytes()));
+            } else {
+                let name = format!("OP_{}", token.trim_start_matches("OP_"));
+                let opcode = (0..=255u8).find(|op| opcode_name(*op) == Some(&name[..]));
+                script.push(opcode.unwrap_or_else(|| panic!("unknown opcode {}", token)));
+            }
+        }
+        script
+    }
+
+    fn flags(text: &str) -> u32 {
+        text.split(',')
+            .filter(|flag| !flag.is_empty())
+            .map(|flag| match flag {
+                "P2SH" => VERIFY_P2SH,
+                "STRICTENC" => VERIFY_STRICTENC,
+                "DERSIG" => VERIFY_DERSIG,
+                "LOW_S" => VERIFY_LOW_S,
+                "NULLDUMMY" => VERIFY_NULLDUMMY,
+                "CHECKLOCKTIMEVERIFY" => VERIFY_CHECKLOCKTIMEVERIFY,
+                "CHECKSEQUENCEVERIFY" => VERIFY_CHECKSEQUENCEVERIFY,
+                "WITNESS" => VERIFY_WITNESS,
+                "NULLFAIL" => VERIFY_NULLFAIL,
+                "TAPROOT" => VERIFY_TAPROOT,
+                _ => panic!("unknown flag {}", flag),
+            })
+            .fold(VERIFY_NONE, |a, b| a | b)
+    }
+
+    #[test]
+    fn test_script_tests() {
+        //[scriptSig, scriptPubKey, flags, expected]
+        let tests: Vec<(&str, &str, &str, Result<(), ScriptError>)> = vec![
+            ("", "DEPTH 0 EQUAL", "P2SH", Ok(())),
+            ("1 2", "2 EQUALVERIFY 1 EQUAL", "P2SH", Ok(())),
+            ("0x4c01 0x07", "7 EQUAL", "", Ok(())),
+            ("1", "IF 1 ELSE 0 ENDIF", "", Ok(())),
+            ("0", "IF 1 ELSE 0 ENDIF", "", Err(ScriptError::EvalFalse)),
+            ("0", "NOTIF 1 ELSE 0 ENDIF", "", Ok(())),
+            ("1", "IF", "", Err(ScriptError::UnbalancedConditional)),
+            ("1", "ENDIF", "", Err(ScriptError::UnbalancedConditional)),
+            ("", "IF 1 ENDIF", "", Err(ScriptError::UnbalancedConditional)),
+            ("1", "0 IF CAT ENDIF", "", Err(ScriptError::DisabledOpcode)),
+            ("1", "0 IF VER ENDIF", "", Ok(())),
+            ("1", "0 IF VERIF ENDIF", "", Err(ScriptError::BadOpcode)),
+            ("1", "VER", "", Err(ScriptError::BadOpcode)),
+            ("1", "RETURN", "", Err(ScriptError::OpReturn)),
+            ("1", "0 VERIFY", "", Err(ScriptError::Verify)),
+            ("", "0x02 0x0100 1ADD 2 EQUAL", "", Ok(())),
+            ("", "0x05 0x0000000000 1ADD", "", Err(ScriptError::ScriptNumOverflow)),
+            ("", "2147483647 1ADD 2147483648 EQUAL", "", Ok(())),
+            ("", "-1 ABS 1 NUMEQUAL", "", Ok(())),
+            ("", "1 2 SUB -1 NUMEQUAL", "", Ok(())),
+            ("", "5 3 MIN 7 MAX 7 NUMEQUALVERIFY 1", "", Ok(())),
+            ("", "1 2 NUMEQUALVERIFY 1", "", Err(ScriptError::NumEqualVerify)),
+            ("", "0 0 1 WITHIN", "", Ok(())),
+            ("", "1 0 1 WITHIN NOT", "", Ok(())),
+            ("1 2 3", "ROT 1 EQUALVERIFY 3 EQUALVERIFY 2 EQUAL", "", Ok(())),
+            ("1 2 3 4", "2SWAP 2 EQUALVERIFY 1 EQUALVERIFY 4 EQUALVERIFY 3 EQUAL", "", Ok(())),
+            (
+                "1 2 3 4 5 6",
+                "2ROT 2 EQUALVERIFY 1 EQUALVERIFY 6 EQUALVERIFY 5 EQUALVERIFY 4 EQUALVERIFY 3 EQUAL",
+                "",
+                Ok(()),
+            ),
+            ("1 2", "3DUP", "", Err(ScriptError::InvalidStackOperation)),
+            ("0 1 2", "2 PICK 0 EQUALVERIFY DEPTH 3 EQUAL", "", Ok(())),
+            ("0 1 2", "2 ROLL 0 EQUALVERIFY DEPTH 2 EQUAL", "", Ok(())),
+            ("0 1", "2 PICK", "", Err(ScriptError::InvalidStackOperation)),
+            ("1 2", "TUCK DEPTH 3 EQUALVERIFY 2 EQUALVERIFY 1 EQUALVERIFY 2 EQUAL", "", Ok(())),
+            ("'abc'", "SIZE 3 EQUALVERIFY 'abc' EQUAL", "", Ok(())),
+            ("1", "TOALTSTACK FROMALTSTACK", "", Ok(())),
+            ("1", "FROMALTSTACK", "", Err(ScriptError::InvalidAltstackOperation)),
+            ("", "DROP 1", "", Err(ScriptError::InvalidStackOperation)),
+            (
+                "'abc'",
+                "SHA256 0x20 0xba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad EQUAL",
+                "",
+                Ok(()),
+            ),
+            ("''", "RIPEMD160 0x14 0x9c1185a5c5e9fc54612808977ee8f548b2258d31 EQUAL", "", Ok(())),
+            ("''", "SHA1 0x14 0xda39a3ee5e6b4b0d3255bfef95601890afd80709 EQUAL", "", Ok(())),
+            ("''", "HASH160 0x14 0xb472a266d0bd89c13706a4132ccfb16f7c3b9fcb EQUAL", "", Ok(())),
+            (
+                "''",
+                "HASH256 0x20 0x5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456 EQUAL",
+                "",
+                Ok(()),
+            ),
+            ("0", "0 0 CHECKMULTISIG", "NULLDUMMY", Ok(())),
+            ("1", "0 0 CHECKMULTISIG", "", Ok(())),
+            ("1", "0 0 CHECKMULTISIG", "NULLDUMMY", Err(ScriptError::SigNullDummy)),
+            ("0", "0 21 CHECKMULTISIG", "", Err(ScriptError::PubkeyCount)),
+            ("0 0", "2 0 CHECKMULTISIG", "", Err(ScriptError::SigCount)),
+            ("0x03 0x020201", "0x02 0x0202 CHECKSIG", "", Ok(())),
+            ("0x03 0x020202", "0x02 0x0202 CHECKSIG", "", Err(ScriptError::EvalFalse)),
+            ("0x03 0x020202", "0x02 0x0202 CHECKSIG", "DERSIG", Err(ScriptError::SigDer)),
+            ("0", "0x02 0x0202 CHECKSIG NOT", "DERSIG", Ok(())),
+            ("0x03 0x020202", "0x02 0x0202 CHECKSIGVERIFY 1", "", Err(ScriptError::CheckSigVerify)),
+            (
+                "0 0x03 0x0a0a01 0x03 0x0c0c01",
+                "2 0x02 0x0a0a 0x02 0x0b0b 0x02 0x0c0c 3 CHECKMULTISIG",
+                "NULLDUMMY",
+                Ok(()),
+            ),
+            //signatures have to be in the order of the public keys
+            (
+                "0 0x03 0x0c0c01 0x03 0x0a0a01",
+                "2 0x02 0x0a0a 0x02 0x0b0b 0x02 0x0c0c 3 CHECKMULTISIG",
+                "NULLDUMMY",
+                Err(ScriptError::EvalFalse),
+            ),
+            //STRICTENC and LOW_S require DER as well
+            ("0x03 0x020201", "0x02 0x0202 CHECKSIG", "STRICTENC", Err(ScriptError::SigDer)),
+            ("0x03 0x020201", "0x02 0x0202 CHECKSIG", "LOW_S", Err(ScriptError::SigDer)),
+            //a failing signature has to be empty with NULLFAIL, in CHECKMULTISIG all of them
+            ("0x03 0x0a0a02", "0x02 0x0a0a CHECKSIG NOT", "NULLFAIL", Err(ScriptError::SigNullFail)),
+            ("0", "0x02 0x0a0a CHECKSIG NOT", "NULLFAIL", Ok(())),
+            (
+                "0 0 0x03 0x0c0c02",
+                "2 0x02 0x0a0a 0x02 0x0c0c 2 CHECKMULTISIG NOT",
+                "NULLFAIL",
+                Err(ScriptError::SigNullFail),
+            ),
+            ("0 0 0", "2 0x02 0x0a0a 0x02 0x0c0c 2 CHECKMULTISIG NOT", "NULLFAIL", Ok(())),
+            ("0", "100 CHECKLOCKTIMEVERIFY DROP", "CHECKLOCKTIMEVERIFY", Err(ScriptError::EvalFalse)),
+            ("1", "100 CHECKLOCKTIMEVERIFY", "CHECKLOCKTIMEVERIFY", Ok(())),
+            ("1", "101 CHECKLOCKTIMEVERIFY", "CHECKLOCKTIMEVERIFY", Err(ScriptError::UnsatisfiedLocktime)),
+            ("1", "101 CHECKLOCKTIMEVERIFY", "", Ok(())),
+            ("1", "-1 CHECKLOCKTIMEVERIFY", "CHECKLOCKTIMEVERIFY", Err(ScriptError::NegativeLocktime)),
+            ("1", "CHECKLOCKTIMEVERIFY", "CHECKLOCKTIMEVERIFY", Ok(())),
+            ("", "CHECKLOCKTIMEVERIFY 1", "CHECKLOCKTIMEVERIFY", Err(ScriptError::InvalidStackOperation)),
+            ("1", "0x05 0x0000008000 CHECKSEQUENCEVERIFY", "CHECKSEQUENCEVERIFY", Ok(())),
+            ("1", "1 CHECKSEQUENCEVERIFY", "CHECKSEQUENCEVERIFY", Err(ScriptError::UnsatisfiedLocktime)),
+            ("1", "CHECKSIGADD", "", Err(ScriptError::BadOpcode)),
+            ("0x01 0x51", "HASH160 0x14 0xda1745e9b549bd0bfa1a569971c77eba30cd5a4b EQUAL", "P2SH", Ok(())),
+            ("0x01 0x00", "HASH160 0x14 0x9f7fd096d37ed2c0e3f7f0cfc924beef4ffceb68 EQUAL", "P2SH", Err(ScriptError::EvalFalse)),
+            ("0x01 0x00", "HASH160 0x14 0x9f7fd096d37ed2c0e3f7f0cfc924beef4ffceb68 EQUAL", "", Ok(())),
+            ("NOP 0x01 0x51", "HASH160 0x14 0xda1745e9b549bd0bfa1a569971c77eba30cd5a4b EQUAL", "P2SH", Err(ScriptError::SigPushOnly)),
+            ("NOP 0x01 0x51", "HASH160 0x14 0xda1745e9b549bd0bfa1a569971c77eba30cd5a4b EQUAL", "", Ok(())),
+        ];
+        for (script_sig, script_pub_key, test_flags, expected) in tests {
+            assert_eq!(
+                verify_script(
+                    &asm(script_sig),
+                    &asm(script_pub_key),
+                    &[],
+                    flags(test_flags),
+                    &CHECKER
+                ),
+                expected,
+                "[\"{}\", \"{}\", \"{}\"]",
+                script_sig,
+                script_pub_key,
+                test_flags
+            );
+        }
+    }
+
+    //policy flags the interpreter leaves to the caller, they can only make a script fail
+    const POLICY_FLAGS: [&str; 11] = [
+        "MINIMALDATA",
+        "SIGPUSHONLY",
+        "CLEANSTACK",
+        "MINIMALIF",
+        "WITNESS_PUBKEYTYPE",
+        "CONST_SCRIPTCODE",
+        "DISCOURAGE_UPGRADABLE_NOPS",
+        "DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM",
+        "DISCOURAGE_UPGRADABLE_TAPROOT_VERSION",
+        "DISCOURAGE_OP_SUCCESS",
+        "DISCOURAGE_UPGRADABLE_PUBKEYTYPE",
+    ];
+
+    //bitcoind's name of the result, numbers out of range or not minimally encoded are an UNKNOWN_ERROR
+    fn error_name(result: Result<(), ScriptError>) -> &'static str {
+        match result {
+            Ok(()) => "OK",
+            Err(error) => match error {
+                ScriptError::EvalFalse => "EVAL_FALSE",
+                ScriptError::OpReturn => "OP_RETURN",
+                ScriptError::ScriptSize => "SCRIPT_SIZE",
+                ScriptError::PushSize => "PUSH_SIZE",
+                ScriptError::OpCount => "OP_COUNT",
+                ScriptError::StackSize => "STACK_SIZE",
+                ScriptError::SigCount => "SIG_COUNT",
+                ScriptError::PubkeyCount => "PUBKEY_COUNT",
+                ScriptError::Verify => "VERIFY",
+                ScriptError::EqualVerify => "EQUALVERIFY",
+                ScriptError::CheckMultisigVerify => "CHECKMULTISIGVERIFY",
+                ScriptError::CheckSigVerify => "CHECKSIGVERIFY",
+                ScriptError::NumEqualVerify => "NUMEQUALVERIFY",
+                ScriptError::BadOpcode => "BAD_OPCODE",
+                ScriptError::DisabledOpcode => "DISABLED_OPCODE",
+                ScriptError::InvalidStackOperation => "INVALID_STACK_OPERATION",
+                ScriptError::InvalidAltstackOperation => "INVALID_ALTSTACK_OPERATION",
+                ScriptError::UnbalancedConditional => "UNBALANCED_CONDITIONAL",
+                ScriptError::ScriptNumOverflow | ScriptError::ScriptNumMinimal => "UNKNOWN_ERROR",
+                ScriptError::NegativeLocktime => "NEGATIVE_LOCKTIME",
+                ScriptError::UnsatisfiedLocktime => "UNSATISFIED_LOCKTIME",
+                ScriptError::SigHashtype => "SIG_HASHTYPE",
+                ScriptError::SigDer => "SIG_DER",
+                ScriptError::SigHighS => "SIG_HIGH_S",
+                ScriptError::SigNullDummy => "SIG_NULLDUMMY",
+                ScriptError::SigPushOnly => "SIG_PUSHONLY",
+                ScriptError::MinimalIf => "MINIMALIF",
+                ScriptError::SigNullFail => "NULLFAIL",
+                ScriptError::WitnessProgramWrongLength => "WITNESS_PROGRAM_WRONG_LENGTH",
+                ScriptError::WitnessProgramWitnessEmpty => "WITNESS_PROGRAM_WITNESS_EMPTY",
+                ScriptError::WitnessProgramMismatch => "WITNESS_PROGRAM_MISMATCH",
+                ScriptError::WitnessMalleated => "WITNESS_MALLEATED",
+                ScriptError::WitnessMalleatedP2sh => "WITNESS_MALLEATED_P2SH",
+                ScriptError::WitnessUnexpected => "WITNESS_UNEXPECTED",
+                ScriptError::WitnessPubkeyType => "WITNESS_PUBKEYTYPE",
+                ScriptError::CleanStack => "CLEANSTACK",
+                ScriptError::SchnorrSigSize => "SCHNORR_SIG_SIZE",
+                ScriptError::SchnorrSigHashtype => "SCHNORR_SIG_HASHTYPE",
+                ScriptError::SchnorrSig => "SCHNORR_SIG",
+                ScriptError::PubkeyType => "PUBKEYTYPE",
+                ScriptError::TaprootWrongControlSize => "TAPROOT_WRONG_CONTROL_SIZE",
+                ScriptError::TapscriptValidationWeight => "TAPSCRIPT_VALIDATION_WEIGHT",
+                ScriptError::TapscriptCheckMultisig => "TAPSCRIPT_CHECKMULTISIG",
+                ScriptError::TapscriptMinimalIf => "TAPSCRIPT_MINIMALIF",
+                ScriptError::SighashError => "UNKNOWN_ERROR",
+            },
+        }
+    }
+
+    //fails every signature and remembers if one was checked,
+    //the outcome of such a script depends on the signature without the secp256k1 feature
+    #[cfg(not(feature = "secp256k1"))]
+    struct NoSignatures<'a>(&'a std::cell::Cell<bool>);
+
+    #[cfg(not(feature = "secp256k1"))]
+    impl<'a> crate::script::SignatureVerifier for NoSignatures<'a> {
+        fn verify_ecdsa(&self, _: &Hash256, _: &[u8], _: &[u8]) -> bool {
+            self.0.set(true);
+            false
+        }
+        fn verify_schnorr(&self, _: &Hash256, _: &[u8], _: &[u8]) -> bool {
+            false
+        }
+        fn verify_tap_tweak(&self, _: &[u8], _: &Hash256, _: &[u8], _: bool) -> bool {
+            false
+        }
+    }
+
+    //the transaction spending the output of script_pub_key, as bitcoind's tests build them
+    fn spending_tx(script_sig: &[u8], script_pub_key: &[u8], amount: u64) -> Transaction {
+        let coinbase = TxInput::new(&[0; 32], 0xffffffff, &[OP_0, OP_0], 0xffffffff);
+        let credit = TransactionBuilder::new()
+            .version(1)
+            .input(coinbase)
+            .output(TxOutput::new(amount, script_pub_key))
+            .build();
+        let txid = hash256(&serialize_transaction_no_witness(&credit));
+        TransactionBuilder::new()
+            .version(1)
+            .input(TxInput::new(&txid.0, 0, script_sig, 0xffffffff))
+            .output(TxOutput::new(amount, &[]))
+            .build()
+    }
+
+    #[test]
+    fn test_script_tests_json() {
+        let tests: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../test_data/script_tests.json")).unwrap();
+        let mut checked = 0;
+        for test in tests.iter().map(|test| test.as_array().unwrap()) {
+            //the witness and the amount come first if the test has them
+            let (witness, amount, test) = match test[0].as_array() {
+                Some(witness) => {
+                    let (amount, items) = witness.split_last().unwrap();
+                    let items: Vec<_> = items
+                        .iter()
+                        .map(|item| hex::decode(item.as_str().unwrap()).unwrap())
+                        .collect();
+                    let amount = (amount.as_f64().unwrap() * 100_000_000.0).round() as u64;
+                    (items, amount, &test[1..])
+                }
+                None => (Vec::new(), 0, &test[..]),
+            };
+            //comments
+            if test.len() < 4 {
+                continue;
+            }
+            let field = |n: usize| test[n].as_str().unwrap();
+            let (script_sig, script_pub_key, expected) = (field(0), field(1), field(3));
+            let (policy, consensus): (Vec<_>, Vec<_>) = field(2)
+                .split(',')
+                .partition(|flag| POLICY_FLAGS.contains(flag));
+            if !policy.is_empty() && expected != "OK" {
+                continue;
+            }
+            let (script_sig, script_pub_key) = (asm(script_sig), asm(script_pub_key));
+            let tx = spending_tx(&script_sig, &script_pub_key, amount);
+            let verify = |verifier| {
+                let checker = TransactionSignatureChecker::new(&tx, 0, amount, verifier);
+                let result = verify_script(
+                    &script_sig,
+                    &script_pub_key,
+                    &witness,
+                    flags(&consensus.join(",")),
+                    &checker,
+                );
+                error_name(result)
+            };
+            #[cfg(feature = "secp256k1")]
+            let result = verify(crate::secp256k1::Secp256k1Verifier);
+            #[cfg(not(feature = "secp256k1"))]
+            let result = {
+                let checked = std::cell::Cell::new(false);
+                let result = verify(NoSignatures(&checked));
+                if result != expected && checked.get() {
+                    continue;
+                }
+                result
+            };
+            assert_eq!(result, expected, "{:?}", test);
+            checked += 1;
+        }
+        assert!(checked > 250);
+    }
+
+    #[test]
+    fn test_limits() {
+        let verify =
+            |script_pub_key: &[u8]| verify_script(&[], script_pub_key, &[], VERIFY_NONE, &CHECKER);
+        let mut script = vec![OP_1];
+        script.extend(vec![OP_NOP; MAX_OPS_PER_SCRIPT]);
+        assert_eq!(verify(&script), Ok(()));
+        script.push(OP_NOP);
+        assert_eq!(verify(&script), Err(ScriptError::OpCount));
+
+        let script = push_data_script(&[1; MAX_SCRIPT_ELEMENT_SIZE]);
+        assert_eq!(verify(&script), Ok(()));
+        let script = push_data_script(&[1; MAX_SCRIPT_ELEMENT_SIZE + 1]);
+        assert_eq!(verify(&script), Err(ScriptError::PushSize));
+
+        let script = vec![OP_1; MAX_STACK_SIZE];
+        assert_eq!(verify(&script), Ok(()));
+        let script = vec![OP_1; MAX_STACK_SIZE + 1];
+        assert_eq!(verify(&script), Err(ScriptError::StackSize));
+
+        let script = [&[OP_1][..], &[OP_0; MAX_SCRIPT_SIZE]].concat();
+        assert_eq!(verify(&script), Err(ScriptError::ScriptSize));
+    }
+
+    #[test]
+    fn test_witness() {
+        let flags = VERIFY_P2SH | VERIFY_WITNESS;
+        let witness_script = asm("1");
+        let p2wsh = [&[OP_0, 0x20][..], sha256(&witness_script).as_ref()].concat();
+        let witness = vec![witness_script.clone()];
+        let verify = |script_sig: &[u8], script_pub_key: &[u8], witness: &[Vec<u8>]| {
+            verify_script(script_sig, script_pub_key, witness, flags, &CHECKER)
+        };
+        assert_eq!(verify(&[], &p2wsh, &witness), Ok(()));
+        assert_eq!(
+            verify(&[], &p2wsh, &[asm("2")]),
+            Err(ScriptError::WitnessProgramMismatch)
+        );
+        assert_eq!(
+            verify(&[], &p2wsh, &[]),
+            Err(ScriptError::WitnessProgramWitnessEmpty)
+        );
+        assert_eq!(
+            verify(&[], &p2wsh, &[vec![1], witness_script.clone()]),
+            Err(ScriptError::CleanStack)
+        );
+        assert_eq!(
+            verify(&asm("0"), &p2wsh, &witness),
+            Err(ScriptError::WitnessMalleated)
+        );
+        //without the WITNESS flag a witness program is anyone can spend
+        assert_eq!(
+            verify_script(&[], &p2wsh, &[], VERIFY_P2SH, &CHECKER),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&asm("1"), &[], &[vec![1]]),
+            Err(ScriptError::WitnessUnexpected)
+        );
+
+        let pubkey = vec![2; 33];
+        let p2wpkh = [&[OP_0, 0x14][..], &hash160(&pubkey)].concat();
+        let sig = [&pubkey[..], &[1]].concat();
+        assert_eq!(verify(&[], &p2wpkh, &[sig.clone(), pubkey.clone()]), Ok(()));
+        assert_eq!(
+            verify(&[], &p2wpkh, &[vec![1], pubkey.clone()]),
+            Err(ScriptError::EvalFalse)
+        );
+        assert_eq!(
+            verify(&[], &p2wpkh, &[pubkey]),
+            Err(ScriptError::WitnessProgramMismatch)
+        );
+        assert_eq!(
+            verify(&[], &[&[OP_0, 0x13][..], &[1; 19]].concat(), &[vec![1]]),
+            Err(ScriptError::WitnessProgramWrongLength)
+        );
+        //unknown witness versions
+        assert_eq!(
+            verify(&[], &[&[OP_2, 0x20][..], &[1; 32]].concat(), &[vec![]]),
+            Ok(())
+        );
+
+        //P2SH-P2WSH
+        let p2sh = [&[OP_HASH160, 0x14][..], &hash160(&p2wsh), &[OP_EQUAL]].concat();
+        let script_sig = push_data_script(&p2wsh);
+        assert_eq!(verify(&script_sig, &p2sh, &witness), Ok(()));
+        let script_sig = [&[OP_0][..], &push_data_script(&p2wsh)].concat();
+        assert_eq!(
+            verify(&script_sig, &p2sh, &witness),
+            Err(ScriptError::WitnessMalleatedP2sh)
+        );
+    }
+
+    #[test]
+    fn test_taproot() {
+        let flags = VERIFY_CONSENSUS;
+        let output_key = [7; 32];
+        let p2tr = [&[OP_1, 0x20][..], &output_key].concat();
+        let control = |leaf_version: u8| [&[leaf_version][..], &[9; 32]].concat();
+        let verify = |witness: &[Vec<u8>], merkle_root: Option<Hash256>| {
+            let checker = MockChecker {
+                lock_time: 0,
+                merkle_root,
+            };
+            verify_script(&[], &p2tr, witness, flags, &checker)
+        };
+
+        //key path
+        assert_eq!(verify(&[vec![1; 64]], None), Ok(()));
+        assert_eq!(verify(&[vec![1; 64], vec![ANNEX_TAG]], None), Ok(()));
+        assert_eq!(
+            verify(&[vec![1; 63]], None),
+            Err(ScriptError::SchnorrSigSize)
+        );
+        assert_eq!(
+            verify(&[], None),
+            Err(ScriptError::WitnessProgramWitnessEmpty)
+        );
+        assert_eq!(
+            verify_script(&[], &p2tr, &[], flags & !VERIFY_TAPROOT, &CHECKER),
+            Ok(())
+        );
+
+        //script path
+        let run = |stack: &[Vec<u8>], script: &str| {
+            let script = asm(script);
+            let mut witness = stack.to_vec();
+            witness.push(script.clone());
+            witness.push(control(TAPROOT_LEAF_TAPSCRIPT));
+            verify(
+                &witness,
+                Some(tap_leaf_hash(TAPROOT_LEAF_TAPSCRIPT, &script)),
+            )
+        };
+        assert_eq!(run(&[], "1"), Ok(()));
+        assert_eq!(run(&[], "0"), Err(ScriptError::EvalFalse));
+        assert_eq!(run(&[vec![1]], "IF 1 ENDIF"), Ok(()));
+        assert_eq!(
+            run(&[vec![2]], "IF 1 ENDIF"),
+            Err(ScriptError::TapscriptMinimalIf)
+        );
+        assert_eq!(
+            run(&[], "0 0 0 CHECKMULTISIG"),
+            Err(ScriptError::TapscriptCheckMultisig)
+        );
+        //OP_SUCCESS makes the script succeed before anything is executed
+        assert_eq!(run(&[], "RETURN 0x50"), Ok(()));
+        let pubkey = "0x20 0x0707070707070707070707070707070707070707070707070707070707070707";
+        assert_eq!(run(&[vec![1; 64]], &format!("{} CHECKSIG", pubkey)), Ok(()));
+        assert_eq!(run(&[vec![]], &format!("{} CHECKSIG NOT", pubkey)), Ok(()));
+        assert_eq!(
+            run(&[vec![1; 63]], &format!("{} CHECKSIG", pubkey)),
+            Err(ScriptError::SchnorrSigSize)
+        );
+        assert_eq!(
+            run(&[vec![1; 64]], "0 CHECKSIG"),
+            Err(ScriptError::PubkeyType)
+        );
+        //unknown public key types succeed
+        assert_eq!(run(&[vec![1; 64]], "0x01 0x07 CHECKSIG"), Ok(()));
+        assert_eq!(
+            run(
+                &[vec![1; 64], vec![1; 64]],
+                &format!("{} CHECKSIG {} CHECKSIGADD 2 EQUAL", pubkey, pubkey)
+            ),
+            Ok(())
+        );
+        //every signature has to be paid for with 50 witness bytes
+        let script = |n| format!("DUP {} CHECKSIGVERIFY ", pubkey).repeat(n) + pubkey + " CHECKSIG";
+        assert_eq!(run(&[vec![1; 64]], &script(2)), Ok(()));
+        assert_eq!(
+            run(&[vec![1; 64]], &script(20)),
+            Err(ScriptError::TapscriptValidationWeight)
+        );
+
+        let script = asm("0");
+        let merkle_root = Some(tap_leaf_hash(0xc2, &script));
+        assert_eq!(
+            verify(&[script.clone(), control(0xc2)], merkle_root),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&[script.clone(), control(0xc2)], None),
+            Err(ScriptError::WitnessProgramMismatch)
+        );
+        assert_eq!(
+            verify(&[script, control(0xc2)[..32].to_vec()], merkle_root),
+            Err(ScriptError::TaprootWrongControlSize)
+        );
+    }
+
+    #[test]
+    fn test_find_and_delete() {
+        assert_eq!(find_and_delete(&[0x01, 0x02, OP_1], &[0x02]), vec![OP_1]);
+        assert_eq!(find_and_delete(&[0x01, 0x02, 0x01, 0x02], &[0x02]), Vec::<u8>::new());
+        //only matches at instruction boundaries
+        assert_eq!(
+            find_and_delete(&[0x02, 0x01, 0x02], &[0x02]),
+            vec![0x02, 0x01, 0x02]
+        );
+        assert_eq!(find_and_delete(&[OP_1, 0x01], &[0x02]), vec![OP_1, 0x01]);
+    }
+
+    #[test]
+    fn test_is_valid_signature_encoding() {
+        let sig = hex::decode("304402200da46260a1a6b6e7fe0e23372adcf7e9569c9f27501728a5d61ab4a3c74732b302200790fb7ce382c742b8e23f53c302b19a33cba9d68a83f33974b971511e2c712e01").unwrap();
+        assert!(is_valid_signature_encoding(&sig));
+        assert!(!is_valid_signature_encoding(&sig[..sig.len() - 2]));
+        let mut padded = sig.clone();
+        padded[4] = 0x80;
+        assert!(!is_valid_signature_encoding(&padded));
+    }
+}
@@ -0,0 +1,17 @@
+mod error;
+pub mod flags;
+pub mod opcodes;
+pub use self::error::ScriptError;
+mod num;
+pub use self::num::{cast_to_bool, decode_script_num, encode_script_num};
+mod checker;
+pub use self::checker::{
+    ExecutionData, SigVersion, SignatureChecker, SignatureVerifier, TransactionSignatureChecker,
+};
+mod interpreter;
+pub use self::interpreter::{
+    eval_script, is_op_success, is_pay_to_script_hash, is_push_only, is_valid_signature_encoding,
+    push_data_script, verify_script, witness_program, Stack,
+};
+mod asm;
+pub use self::asm::{script_to_asm, script_type};
@@ -0,0 +1,111 @@
+use crate::script::ScriptError;
+
+//numbers on the stack are little endian sign-magnitude, the sign being the top bit of the last byte
+pub fn decode_script_num(
+    bytes: &[u8],
+    require_minimal: bool,
+    max_size: usize,
+) -> Result<i64, ScriptError> {
+    if bytes.len() > max_size {
+        return Err(ScriptError::ScriptNumOverflow);
+    }
+    if require_minimal && !bytes.is_empty() {
+        //the last byte may only be 0x00 or 0x80 if the byte before it needs its top bit
+        let last = bytes[bytes.len() - 1];
+        if last & 0x7f == 0 && (bytes.len() <= 1 || bytes[bytes.len() - 2] & 0x80 == 0) {
+            return Err(ScriptError::ScriptNumMinimal);
+        }
+    }
+    let mut result: i64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        result |= i64::from(*byte) << (8 * i);
+    }
+    match bytes.last() {
+        Some(last) if last & 0x80 != 0 => Ok(-(result & !(0x80i64 << (8 * (bytes.len() - 1))))),
+        _ => Ok(result),
+    }
+}
+
+pub fn encode_script_num(value: i64) -> Vec<u8> {
+    if value == 0 {
+        return Vec::new();
+    }
+    let negative = value < 0;
+    let mut abs = value.unsigned_abs();
+    let mut vec = Vec::new();
+    while abs > 0 {
+        vec.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    //an extra byte is needed if the top bit is already taken by the magnitude
+    if vec[vec.len() - 1] & 0x80 != 0 {
+        vec.push(if negative { 0x80 } else { 0x00 });
+    } else if negative {
+        let last = vec.len() - 1;
+        vec[last] |= 0x80;
+    }
+    vec
+}
+
+pub fn cast_to_bool(bytes: &[u8]) -> bool {
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte != 0 {
+            //negative zero is still false
+            return !(i == bytes.len() - 1 && *byte == 0x80);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_script_num() {
+        for (value, bytes) in &[
+            (0, &[][..]),
+            (1, &[0x01][..]),
+            (-1, &[0x81][..]),
+            (127, &[0x7f][..]),
+            (128, &[0x80, 0x00][..]),
+            (-128, &[0x80, 0x80][..]),
+            (255, &[0xff, 0x00][..]),
+            (256, &[0x00, 0x01][..]),
+            (-255, &[0xff, 0x80][..]),
+            (0x7fffffff, &[0xff, 0xff, 0xff, 0x7f][..]),
+            (-0x7fffffff, &[0xff, 0xff, 0xff, 0xff][..]),
+        ] {
+            assert_eq!(encode_script_num(*value), bytes.to_vec());
+            assert_eq!(decode_script_num(bytes, true, 4), Ok(*value));
+        }
+        assert_eq!(decode_script_num(&[0x00], false, 4), Ok(0));
+        assert_eq!(decode_script_num(&[0x80], false, 4), Ok(0));
+        assert_eq!(decode_script_num(&[0x01, 0x00], false, 4), Ok(1));
+        assert_eq!(
+            decode_script_num(&[0x00], true, 4),
+            Err(ScriptError::ScriptNumMinimal)
+        );
+        assert_eq!(
+            decode_script_num(&[0x01, 0x00], true, 4),
+            Err(ScriptError::ScriptNumMinimal)
+        );
+        assert_eq!(
+            decode_script_num(&[0x01, 0x80], true, 4),
+            Err(ScriptError::ScriptNumMinimal)
+        );
+        assert_eq!(
+            decode_script_num(&[0x01, 0x02, 0x03, 0x04, 0x05], false, 4),
+            Err(ScriptError::ScriptNumOverflow)
+        );
+        assert_eq!(
+            decode_script_num(&[0x01, 0x02, 0x03, 0x04, 0x05], false, 5),
+            Ok(0x0504030201)
+        );
+
+        assert!(!cast_to_bool(&[]));
+        assert!(!cast_to_bool(&[0x00, 0x00]));
+        assert!(!cast_to_bool(&[0x00, 0x80]));
+        assert!(cast_to_bool(&[0x80, 0x00]));
+        assert!(cast_to_bool(&[0x01]));
+    }
+}
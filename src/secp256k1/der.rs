@@ -0,0 +1,153 @@
+//r and s of an ECDSA signature as 32 byte big endian numbers
+pub type RawSignature = ([u8; 32], [u8; 32]);
+
+fn to_scalar_bytes(int: &[u8]) -> Option<[u8; 32]> {
+    let mut int = int;
+    while !int.is_empty() && int[0] == 0 {
+        int = &int[1..];
+    }
+    if int.len() > 32 {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    bytes[32 - int.len()..].copy_from_slice(int);
+    Some(bytes)
+}
+
+//DER as required by BIP66, sig without the hash type byte
+pub fn parse_der_strict(sig: &[u8]) -> Option<RawSignature> {
+    let len = sig.len();
+    if !(8..=72).contains(&len) || sig[0] != 0x30 || sig[1] as usize != len - 2 {
+        return None;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= len {
+        return None;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 6 != len {
+        return None;
+    }
+    //integers have to be positive and without unnecessary padding
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return None;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return None;
+    }
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return None;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return None;
+    }
+    let r = to_scalar_bytes(&sig[4..4 + len_r])?;
+    let s = to_scalar_bytes(&sig[6 + len_r..])?;
+    Some((r, s))
+}
+
+//reads a DER length, long forms included
+fn parse_length(sig: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut len = *sig.get(*pos)? as usize;
+    *pos += 1;
+    if len & 0x80 != 0 {
+        let mut len_bytes = len - 0x80;
+        if len_bytes > sig.len() - *pos {
+            return None;
+        }
+        while len_bytes > 0 && sig[*pos] == 0 {
+            *pos += 1;
+            len_bytes -= 1;
+        }
+        if len_bytes >= std::mem::size_of::<usize>() {
+            return None;
+        }
+        len = 0;
+        while len_bytes > 0 {
+            len = (len << 8) + sig[*pos] as usize;
+            *pos += 1;
+            len_bytes -= 1;
+        }
+    }
+    Some(len)
+}
+
+fn parse_integer<'a>(sig: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    if *sig.get(*pos)? != 0x02 {
+        return None;
+    }
+    *pos += 1;
+    let len = parse_length(sig, pos)?;
+    if len > sig.len() - *pos {
+        return None;
+    }
+    *pos += len;
+    Some(&sig[*pos - len..*pos])
+}
+
+//the encodings accepted by OpenSSL before BIP66, as bitcoind's ecdsa_signature_parse_der_lax
+//numbers not fitting in 32 bytes give a zero signature, which never verifies
+pub fn parse_der_lax(sig: &[u8]) -> Option<RawSignature> {
+    let mut pos = 0;
+    if *sig.first()? != 0x30 {
+        return None;
+    }
+    pos += 1;
+    //the sequence length is not checked at all
+    let len = *sig.get(pos)? as usize;
+    pos += 1;
+    if len & 0x80 != 0 {
+        if len - 0x80 > sig.len() - pos {
+            return None;
+        }
+        pos += len - 0x80;
+    }
+    let r = parse_integer(sig, &mut pos)?;
+    let s = parse_integer(sig, &mut pos)?;
+    match (to_scalar_bytes(r), to_scalar_bytes(s)) {
+        (Some(r), Some(s)) => Some((r, s)),
+        _ => Some(([0; 32], [0; 32])),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse_der() {
+        let sig = hex::decode("304402200da46260a1a6b6e7fe0e23372adcf7e9569c9f27501728a5d61ab4a3c74732b302200790fb7ce382c742b8e23f53c302b19a33cba9d68a83f33974b971511e2c712e").unwrap();
+        let (r, s) = parse_der_strict(&sig).unwrap();
+        assert_eq!(
+            hex::encode(r),
+            "0da46260a1a6b6e7fe0e23372adcf7e9569c9f27501728a5d61ab4a3c74732b3"
+        );
+        assert_eq!(
+            hex::encode(s),
+            "0790fb7ce382c742b8e23f53c302b19a33cba9d68a83f33974b971511e2c712e"
+        );
+        assert_eq!(parse_der_lax(&sig), Some((r, s)));
+
+        //unnecessary padding of r, a long form length and trailing garbage are only accepted by the lax parser
+        let padded = [&hex::decode("3045022100").unwrap()[..], &sig[4..]].concat();
+        let long_form = [&hex::decode("30814402").unwrap()[..], &sig[3..]].concat();
+        let trailing = [&sig[..], &[0]].concat();
+        for sig in [padded, long_form, trailing].iter() {
+            assert_eq!(parse_der_strict(sig), None);
+            assert_eq!(parse_der_lax(sig), Some((r, s)));
+        }
+
+        //a negative r
+        let negative = [&sig[..4], &[0x8d], &sig[5..]].concat();
+        assert_eq!(parse_der_strict(&negative), None);
+        assert!(parse_der_lax(&negative).is_some());
+
+        assert_eq!(parse_der_strict(&sig[..sig.len() - 1]), None);
+        assert_eq!(parse_der_lax(&sig[..sig.len() - 1]), None);
+        assert_eq!(parse_der_lax(&[]), None);
+
+        //an r too big for 32 bytes
+        let overflow = [&[0x30, 0x27, 0x02, 0x22][..], &[1; 34], &[0x02, 0x01, 0x01]].concat();
+        assert_eq!(parse_der_strict(&overflow), None);
+        assert_eq!(parse_der_lax(&overflow), Some(([0; 32], [0; 32])));
+    }
+}
@@ -0,0 +1,6 @@
+mod der;
+pub use self::der::{parse_der_lax, parse_der_strict, RawSignature};
+mod verify;
+pub use self::verify::{
+    verify_ecdsa, verify_ecdsa_lax, verify_schnorr, verify_tap_tweak, Secp256k1Verifier,
+};
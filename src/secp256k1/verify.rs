@@ -0,0 +1,271 @@
+use crate::{
+    script::SignatureVerifier,
+    secp256k1::{parse_der_lax, parse_der_strict, RawSignature},
+    types::Hash256,
+    utils::tagged_hash,
+};
+use k256::{
+    ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey},
+    elliptic_curve::{point::AffineCoordinates, PrimeField},
+    schnorr, ProjectivePoint, Scalar,
+};
+use std::convert::TryFrom;
+
+//compressed, uncompressed and hybrid public keys, as accepted by bitcoind
+fn parse_public_key(pubkey: &[u8]) -> Option<VerifyingKey> {
+    match pubkey.first() {
+        Some(0x06) | Some(0x07) if pubkey.len() == 65 => {
+            //hybrid keys carry the parity of y in the prefix as well
+            if pubkey[0] & 1 != pubkey[64] & 1 {
+                return None;
+            }
+            let uncompressed = [&[0x04][..], &pubkey[1..]].concat();
+            VerifyingKey::from_sec1_bytes(&uncompressed).ok()
+        }
+        _ => VerifyingKey::from_sec1_bytes(pubkey).ok(),
+    }
+}
+
+fn verify_raw_ecdsa(msg: &Hash256, sig: RawSignature, pubkey: &[u8]) -> bool {
+    let (r, s) = sig;
+    let sig = match Signature::from_scalars(r, s) {
+        Ok(sig) => sig,
+        Err(_) => return false,
+    };
+    //high s values are valid by consensus, only the verification code requires low s
+    let sig = sig.normalize_s().unwrap_or(sig);
+    match parse_public_key(pubkey) {
+        Some(key) => key.verify_prehash(msg.as_ref(), &sig).is_ok(),
+        None => false,
+    }
+}
+
+//msg is the 32 byte digest that was signed, sig is DER encoded without the hash type byte
+pub fn verify_ecdsa(msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool {
+    match parse_der_strict(sig) {
+        Some(sig) => verify_raw_ecdsa(msg, sig, pubkey),
+        None => false,
+    }
+}
+
+//the same, accepting the DER violations found in signatures before BIP66
+pub fn verify_ecdsa_lax(msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool {
+    match parse_der_lax(sig) {
+        Some(sig) => verify_raw_ecdsa(msg, sig, pubkey),
+        None => false,
+    }
+}
+
+//BIP340, 64 byte signature and 32 byte x-only public key
+pub fn verify_schnorr(msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool {
+    let key = match schnorr::VerifyingKey::from_bytes(pubkey) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    match schnorr::Signature::try_from(sig) {
+        Ok(sig) => key.verify_raw(msg.as_ref(), &sig).is_ok(),
+        Err(_) => false,
+    }
+}
+
+//BIP341, whether output_key with y parity is internal_key tweaked with merkle_root
+//merkle_root is None for outputs without a script tree
+pub fn verify_tap_tweak(
+    internal_key: &[u8],
+    merkle_root: Option<&Hash256>,
+    output_key: &[u8],
+    parity: bool,
+) -> bool {
+    let key = match schnorr::VerifyingKey::from_bytes(internal_key) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    let tweak = match merkle_root {
+        Some(merkle_root) => {
+            tagged_hash("TapTweak", &[internal_key, merkle_root.as_ref()].concat())
+        }
+        None => tagged_hash("TapTweak", internal_key),
+    };
+    let tweak: Option<Scalar> = Scalar::from_repr(tweak.0.into()).into();
+    let tweak = match tweak {
+        Some(tweak) => tweak,
+        None => return false,
+    };
+    let point =
+        (ProjectivePoint::from(*key.as_affine()) + ProjectivePoint::GENERATOR * tweak).to_affine();
+    point.x().as_slice() == output_key && bool::from(point.y_is_odd()) == parity
+}
+
+//verifies signatures the way consensus does, DER is parsed laxly as the DERSIG flag is checked by the interpreter
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Secp256k1Verifier;
+
+impl SignatureVerifier for Secp256k1Verifier {
+    fn verify_ecdsa(&self, msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool {
+        verify_ecdsa_lax(msg, sig, pubkey)
+    }
+    fn verify_schnorr(&self, msg: &Hash256, sig: &[u8], pubkey: &[u8]) -> bool {
+        verify_schnorr(msg, sig, pubkey)
+    }
+    fn verify_tap_tweak(
+        &self,
+        internal_key: &[u8],
+        merkle_root: &Hash256,
+        output_key: &[u8],
+        parity: bool,
+    ) -> bool {
+        verify_tap_tweak(internal_key, Some(merkle_root), output_key, parity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        parsers::{parse_block, parse_instruction, parse_transaction},
+        script::{flags::VERIFY_CONSENSUS, opcodes::*, verify_script, TransactionSignatureChecker},
+        types::{Bytes, Instruction},
+        utils::hash160,
+    };
+
+    fn hash(hex: &str) -> Hash256 {
+        Hash256::new(&hex::decode(hex).unwrap())
+    }
+
+    #[test]
+    fn test_verify_ecdsa() {
+        let data = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        //input 1 spends a P2PKH output, its scriptSig is <sig> <pubkey>
+        let Bytes(script_sig) = &tx.inputs[1].script_sig;
+        let (i, sig) = parse_instruction(script_sig).unwrap();
+        let (_, pubkey) = parse_instruction(i).unwrap();
+        let (sig, pubkey) = (sig.push_data().unwrap(), pubkey.push_data().unwrap());
+        let (sighash_type, sig) = sig.split_last().unwrap();
+        let script_code = [
+            &[OP_DUP, OP_HASH160, 0x14][..],
+            &hash160(pubkey),
+            &[OP_EQUALVERIFY, OP_CHECKSIG],
+        ]
+        .concat();
+        let msg = tx.legacy_sighash(1, &script_code, u32::from(*sighash_type));
+        assert!(verify_ecdsa(&msg, sig, pubkey));
+        assert!(verify_ecdsa_lax(&msg, sig, pubkey));
+        assert!(!verify_ecdsa(
+            &tx.legacy_sighash(2, &script_code, 1),
+            sig,
+            pubkey
+        ));
+        assert!(!verify_ecdsa(&msg, sig, &pubkey[1..]));
+
+        //padding r with a zero is only accepted by the lax parser
+        let padded = [&[0x30, sig[1] + 1, 0x02, sig[3] + 1, 0x00][..], &sig[4..]].concat();
+        assert!(!verify_ecdsa(&msg, &padded, pubkey));
+        assert!(verify_ecdsa_lax(&msg, &padded, pubkey));
+
+        //s and n - s are both valid
+        let (r, s) = parse_der_strict(sig).unwrap();
+        let signature = Signature::from_scalars(r, s).unwrap();
+        let negated = Signature::from_scalars(r, -*signature.s()).unwrap();
+        assert!(verify_ecdsa(&msg, negated.to_der().as_bytes(), pubkey));
+    }
+
+    #[test]
+    fn test_verify_block() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let mut verified = 0;
+        for tx in block.transactions.iter().skip(1) {
+            for (index, input) in tx.inputs.iter().enumerate() {
+                //only P2PKH spends can be verified without the previous outputs
+                let Bytes(script_sig) = &input.script_sig;
+                let pushes: Vec<_> = match parse_instruction(script_sig) {
+                    Ok((i, Instruction::Push(_, sig))) => match parse_instruction(i) {
+                        Ok(([], Instruction::Push(_, pubkey))) => vec![sig, pubkey],
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                let Bytes(pubkey) = &pushes[1];
+                if pubkey.len() != 33 && pubkey.len() != 65 {
+                    continue;
+                }
+                let script_pub_key = [
+                    &[OP_DUP, OP_HASH160, 0x14][..],
+                    &hash160(pubkey),
+                    &[OP_EQUALVERIFY, OP_CHECKSIG],
+                ]
+                .concat();
+                let checker = TransactionSignatureChecker::new(tx, index, 0, Secp256k1Verifier);
+                assert_eq!(
+                    verify_script(script_sig, &script_pub_key, &[], VERIFY_CONSENSUS, &checker),
+                    Ok(()),
+                    "{:?}:{}",
+                    tx.txid,
+                    index
+                );
+                verified += 1;
+            }
+        }
+        assert!(verified > 100);
+    }
+
+    #[test]
+    fn test_verify_schnorr() {
+        //BIP340 test vectors 0 and 1
+        let pubkey =
+            hex::decode("f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9")
+                .unwrap();
+        let sig = hex::decode("e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0").unwrap();
+        assert!(verify_schnorr(&Hash256::default(), &sig, &pubkey));
+        let pubkey =
+            hex::decode("dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659")
+                .unwrap();
+        let sig = hex::decode("6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a").unwrap();
+        let msg = hash("243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89");
+        assert!(verify_schnorr(&msg, &sig, &pubkey));
+
+        assert!(!verify_schnorr(&Hash256::default(), &sig, &pubkey));
+        let mut flipped = sig.clone();
+        flipped[63] ^= 1;
+        assert!(!verify_schnorr(&msg, &flipped, &pubkey));
+        assert!(!verify_schnorr(&msg, &sig[..63], &pubkey));
+        //BIP340 test vector 5, the public key is not on the curve
+        let pubkey =
+            hex::decode("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34")
+                .unwrap();
+        assert!(!verify_schnorr(&msg, &sig, &pubkey));
+    }
+
+    #[test]
+    fn test_verify_tap_tweak() {
+        //BIP341 wallet test vectors 0 and 1
+        let internal_key =
+            hex::decode("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d")
+                .unwrap();
+        let output_key =
+            hex::decode("53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343")
+                .unwrap();
+        assert!(verify_tap_tweak(&internal_key, None, &output_key, true));
+        assert!(!verify_tap_tweak(&internal_key, None, &output_key, false));
+
+        let internal_key =
+            hex::decode("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+                .unwrap();
+        let output_key =
+            hex::decode("147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3")
+                .unwrap();
+        let merkle_root = hash("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21");
+        assert!(verify_tap_tweak(
+            &internal_key,
+            Some(&merkle_root),
+            &output_key,
+            true
+        ));
+        assert!(!verify_tap_tweak(&internal_key, None, &output_key, true));
+    }
+}
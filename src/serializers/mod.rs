@@ -0,0 +1,23 @@
+mod serialize_var_int;
+pub use self::serialize_var_int::serialize_var_int;
+mod serialize_tx_inputs;
+pub use self::serialize_tx_inputs::{serialize_tx_input, serialize_tx_inputs};
+mod serialize_tx_outputs;
+pub use self::serialize_tx_outputs::{serialize_tx_output, serialize_tx_outputs};
+mod serialize_witnesses;
+pub use self::serialize_witnesses::serialize_witnesses;
+mod serialize_transaction;
+pub use self::serialize_transaction::{serialize_transaction, serialize_transaction_no_witness};
+mod serialize_block;
+pub use self::serialize_block::{serialize_block, serialize_block_header};
+mod serialize_message;
+pub use self::serialize_message::{
+    serialize_addr_v2, serialize_block_transactions, serialize_block_transactions_request,
+    serialize_compact_block, serialize_message, serialize_net_address, serialize_payload,
+};
+mod serialize_psbt;
+pub use self::serialize_psbt::{
+    serialize_key_source, serialize_psbt, serialize_psbt_map, serialize_tap_key_source,
+};
+mod serialize_columnar;
+pub use self::serialize_columnar::{serialize_columnar, COLUMNAR_MAGIC};
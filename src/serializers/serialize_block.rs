@@ -0,0 +1,41 @@
+use crate::{
+    serializers::{serialize_transaction, serialize_var_int},
+    types::{Block, BlockHeader},
+};
+
+//the 80 bytes hashed into the block hash
+pub fn serialize_block_header(header: &BlockHeader) -> Vec<u8> {
+    [
+        &header.version.to_le_bytes()[..],
+        &header.prev_block_hash.to_wire_bytes(),
+        &header.merkle_root_hash.to_wire_bytes(),
+        &header.time.to_le_bytes(),
+        &header.bits.0,
+        &header.nonce.0,
+    ]
+    .concat()
+}
+
+pub fn serialize_block(block: &Block) -> Vec<u8> {
+    let mut vec = serialize_block_header(&block.header);
+    vec.extend(serialize_var_int(block.transactions.len() as u64));
+    for tx in block.transactions.iter() {
+        vec.extend(serialize_transaction(tx));
+    }
+    vec
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+    #[test]
+    fn test_serialize_block() {
+        let data = &include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        )[..];
+        let (_, block) = parse_block(data).unwrap();
+        assert_eq!(serialize_block_header(&block.header), &data[..80]);
+        assert_eq!(serialize_block(&block), data);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::export::{Column, ColumnType, TableData};
+
+//the columnar export file, all numbers little endian:
+//magic, u8 length and name of the table, u32 column count, u64 row count,
+//u8 length, name and u8 type (0 u64, 1 string) of each column,
+//then each column in turn, u64 values or the u32 length and bytes of each string
+pub const COLUMNAR_MAGIC: &[u8] = b"PBCOLS\x00\x01";
+
+fn serialize_name(vec: &mut Vec<u8>, name: &str) {
+    vec.push(name.len() as u8);
+    vec.extend(name.as_bytes());
+}
+
+pub fn serialize_columnar(data: &TableData) -> Vec<u8> {
+    let mut vec = COLUMNAR_MAGIC.to_vec();
+    serialize_name(&mut vec, data.table.name());
+    let columns = data.table.columns();
+    vec.extend(&(columns.len() as u32).to_le_bytes());
+    vec.extend(&(data.rows() as u64).to_le_bytes());
+    for (name, column_type) in columns.iter() {
+        serialize_name(&mut vec, name);
+        vec.push(match column_type {
+            ColumnType::U64 => 0,
+            ColumnType::Str => 1,
+        });
+    }
+    for column in data.columns.iter() {
+        match column {
+            Column::U64(values) => values.iter().for_each(|v| vec.extend(&v.to_le_bytes())),
+            Column::Str(values) => {
+                for s in values.iter() {
+                    vec.extend(&(s.len() as u32).to_le_bytes());
+                    vec.extend(s.as_bytes());
+                }
+            }
+        }
+    }
+    vec
+}
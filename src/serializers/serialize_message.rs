@@ -0,0 +1,286 @@
+use crate::{
+    p2p::{
+        AddrV2, BlockLocator, BlockTransactions, BlockTransactionsRequest, CompactBlock, Inventory,
+        Message, NetAddress, NetworkMessage, VersionMessage,
+    },
+    serializers::{
+        serialize_block, serialize_block_header, serialize_transaction, serialize_var_int,
+    },
+    utils::hash256,
+};
+
+pub fn serialize_net_address(address: &NetAddress) -> Vec<u8> {
+    [
+        &address.services.to_le_bytes()[..],
+        &address.ip,
+        &address.port.to_be_bytes(),
+    ]
+    .concat()
+}
+
+fn serialize_version(version: &VersionMessage) -> Vec<u8> {
+    [
+        &version.version.to_le_bytes()[..],
+        &version.services.to_le_bytes(),
+        &version.timestamp.to_le_bytes(),
+        &serialize_net_address(&version.receiver),
+        &serialize_net_address(&version.sender),
+        &version.nonce.to_le_bytes(),
+        &serialize_var_int(version.user_agent.len() as u64),
+        version.user_agent.as_bytes(),
+        &version.start_height.to_le_bytes(),
+        &[version.relay as u8],
+    ]
+    .concat()
+}
+
+fn serialize_inventories(inventories: &[Inventory]) -> Vec<u8> {
+    let mut vec = serialize_var_int(inventories.len() as u64);
+    for inventory in inventories {
+        vec.extend(&inventory.inv_type.to_le_bytes());
+        vec.extend(&inventory.hash.0);
+    }
+    vec
+}
+
+fn serialize_block_locator(locator: &BlockLocator) -> Vec<u8> {
+    let mut vec = locator.version.to_le_bytes().to_vec();
+    vec.extend(serialize_var_int(locator.hashes.len() as u64));
+    for hash in locator.hashes.iter() {
+        vec.extend(&hash.0);
+    }
+    vec.extend(&locator.stop_hash.0);
+    vec
+}
+
+pub fn serialize_addr_v2(addr: &AddrV2) -> Vec<u8> {
+    [
+        &addr.time.to_le_bytes()[..],
+        &serialize_var_int(addr.services),
+        &[addr.network],
+        &serialize_var_int(addr.addr.len() as u64),
+        &addr.addr,
+        &addr.port.to_be_bytes(),
+    ]
+    .concat()
+}
+
+//each index as the difference to the previous index plus one
+fn serialize_indexes<I: Iterator<Item = (usize, Vec<u8>)>>(count: usize, items: I) -> Vec<u8> {
+    let mut vec = serialize_var_int(count as u64);
+    let mut next = 0;
+    for (index, item) in items {
+        vec.extend(serialize_var_int((index - next) as u64));
+        vec.extend(item);
+        next = index + 1;
+    }
+    vec
+}
+
+pub fn serialize_compact_block(block: &CompactBlock) -> Vec<u8> {
+    let mut vec = serialize_block_header(&block.header);
+    vec.extend(&block.nonce.to_le_bytes());
+    vec.extend(serialize_var_int(block.short_ids.len() as u64));
+    for short_id in block.short_ids.iter() {
+        vec.extend(&short_id.to_le_bytes()[..6]);
+    }
+    vec.extend(serialize_indexes(
+        block.prefilled.len(),
+        block
+            .prefilled
+            .iter()
+            .map(|prefilled| (prefilled.index, serialize_transaction(&prefilled.tx))),
+    ));
+    vec
+}
+
+pub fn serialize_block_transactions_request(request: &BlockTransactionsRequest) -> Vec<u8> {
+    let mut vec = request.block_hash.0.to_vec();
+    vec.extend(serialize_indexes(
+        request.indexes.len(),
+        request.indexes.iter().map(|index| (*index, Vec::new())),
+    ));
+    vec
+}
+
+pub fn serialize_block_transactions(transactions: &BlockTransactions) -> Vec<u8> {
+    let mut vec = transactions.block_hash.0.to_vec();
+    vec.extend(serialize_var_int(transactions.transactions.len() as u64));
+    for tx in transactions.transactions.iter() {
+        vec.extend(serialize_transaction(tx));
+    }
+    vec
+}
+
+pub fn serialize_payload(message: &NetworkMessage) -> Vec<u8> {
+    match message {
+        NetworkMessage::Version(version) => serialize_version(version),
+        NetworkMessage::Inv(inventories)
+        | NetworkMessage::GetData(inventories)
+        | NetworkMessage::NotFound(inventories) => serialize_inventories(inventories),
+        NetworkMessage::GetBlocks(locator) | NetworkMessage::GetHeaders(locator) => {
+            serialize_block_locator(locator)
+        }
+        NetworkMessage::Headers(headers) => {
+            let mut vec = serialize_var_int(headers.len() as u64);
+            for header in headers {
+                vec.extend(serialize_block_header(header));
+                vec.push(0);
+            }
+            vec
+        }
+        NetworkMessage::Block(block) => serialize_block(block),
+        NetworkMessage::Tx(tx) => serialize_transaction(tx),
+        NetworkMessage::Addr(addresses) => {
+            let mut vec = serialize_var_int(addresses.len() as u64);
+            for (time, address) in addresses {
+                vec.extend(&time.to_le_bytes());
+                vec.extend(serialize_net_address(address));
+            }
+            vec
+        }
+        NetworkMessage::AddrV2(addresses) => {
+            let mut vec = serialize_var_int(addresses.len() as u64);
+            for address in addresses {
+                vec.extend(serialize_addr_v2(address));
+            }
+            vec
+        }
+        NetworkMessage::Ping(nonce) | NetworkMessage::Pong(nonce) => nonce.to_le_bytes().to_vec(),
+        NetworkMessage::SendCmpct { announce, version } => {
+            [&[*announce as u8][..], &version.to_le_bytes()].concat()
+        }
+        NetworkMessage::CmpctBlock(block) => serialize_compact_block(block),
+        NetworkMessage::GetBlockTxn(request) => serialize_block_transactions_request(request),
+        NetworkMessage::BlockTxn(transactions) => serialize_block_transactions(transactions),
+        NetworkMessage::FeeFilter(fee_rate) => fee_rate.to_le_bytes().to_vec(),
+        NetworkMessage::Unknown { payload, .. } => payload.clone(),
+        NetworkMessage::Verack
+        | NetworkMessage::GetAddr
+        | NetworkMessage::SendHeaders
+        | NetworkMessage::SendAddrV2
+        | NetworkMessage::WtxidRelay
+        | NetworkMessage::Mempool => Vec::new(),
+    }
+}
+
+//the envelope followed by the payload
+pub fn serialize_message(message: &Message) -> Vec<u8> {
+    let payload = serialize_payload(&message.payload);
+    let mut command = [0u8; 12];
+    let name = message.command().as_bytes();
+    let length = name.len().min(12);
+    command[..length].copy_from_slice(&name[..length]);
+    [
+        &message.magic.to_le_bytes()[..],
+        &command,
+        &(payload.len() as u32).to_le_bytes(),
+        &hash256(&payload).0[..4],
+        &payload,
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        p2p::{MSG_WITNESS_BLOCK, NET_TORV3, NODE_NETWORK, NODE_WITNESS},
+        parsers::{parse_block, parse_message, parse_transaction},
+        types::Hash256,
+    };
+    use hex;
+    use std::net::{IpAddr, Ipv6Addr};
+
+    fn round_trip(payload: NetworkMessage) -> (Vec<u8>, Message) {
+        let data = serialize_message(&Message::new(0xDAB5BFFA, payload));
+        let (rest, message) = parse_message(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(message.chain(), Some("regtest"));
+        assert_eq!(serialize_message(&message), data);
+        (data, message)
+    }
+
+    #[test]
+    fn test_serialize_message() {
+        let (data, _) = round_trip(NetworkMessage::Verack);
+        assert_eq!(
+            hex::encode(data),
+            "fabfb5da76657261636b000000000000000000005df6e0e2"
+        );
+
+        let address = NetAddress::new(
+            NODE_NETWORK | NODE_WITNESS,
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            18444,
+        );
+        let version = VersionMessage {
+            version: 70016,
+            services: NODE_WITNESS,
+            timestamp: 1600000000,
+            receiver: address,
+            sender: NetAddress::new(0, IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+            nonce: 42,
+            user_agent: "/parse_bitcoin/".to_string(),
+            start_height: 100,
+            relay: false,
+        };
+        match round_trip(NetworkMessage::Version(version.clone()))
+            .1
+            .payload
+        {
+            NetworkMessage::Version(v) => assert_eq!(v, version),
+            m => panic!("unexpected {:?}", m),
+        }
+
+        let genesis = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(genesis).unwrap();
+        let hash = block.header.hash.0;
+        let (data, _) = round_trip(NetworkMessage::Block(block));
+        assert_eq!(&data[24..], &genesis[..]);
+        let (_, block) = parse_block(genesis).unwrap();
+        round_trip(NetworkMessage::Headers(vec![block.header]));
+
+        let tx = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, tx) = parse_transaction(tx).unwrap();
+        round_trip(NetworkMessage::Tx(tx));
+
+        for payload in vec![
+            NetworkMessage::GetData(vec![Inventory::new(MSG_WITNESS_BLOCK, hash)]),
+            NetworkMessage::GetHeaders(BlockLocator {
+                version: 70016,
+                hashes: vec![hash, Hash256([1; 32])],
+                stop_hash: Hash256::default(),
+            }),
+            NetworkMessage::Addr(vec![(1600000000, address)]),
+            NetworkMessage::AddrV2(vec![AddrV2 {
+                time: 1600000000,
+                services: NODE_NETWORK,
+                network: NET_TORV3,
+                addr: vec![9; 32],
+                port: 8333,
+            }]),
+            NetworkMessage::Ping(7),
+            NetworkMessage::SendCmpct {
+                announce: false,
+                version: 2,
+            },
+            NetworkMessage::FeeFilter(1000),
+            NetworkMessage::SendHeaders,
+            NetworkMessage::Unknown {
+                command: "cfilter".to_string(),
+                payload: vec![1, 2, 3],
+            },
+        ] {
+            let command = payload.command().to_string();
+            let expected = serialize_payload(&payload);
+            let (_, message) = round_trip(payload);
+            assert_eq!(message.command(), command);
+            assert_eq!(serialize_payload(&message.payload), expected);
+        }
+    }
+}
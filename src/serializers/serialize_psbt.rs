@@ -0,0 +1,48 @@
+use crate::{
+    psbt::{KeySource, Psbt, PsbtMap},
+    serializers::serialize_var_int,
+    types::Hash256,
+};
+
+pub fn serialize_key_source(source: &KeySource) -> Vec<u8> {
+    let mut vec = source.fingerprint.to_vec();
+    for index in source.path.iter() {
+        vec.extend(&index.to_le_bytes());
+    }
+    vec
+}
+
+pub fn serialize_tap_key_source(leaf_hashes: &[Hash256], source: &KeySource) -> Vec<u8> {
+    let mut vec = serialize_var_int(leaf_hashes.len() as u64);
+    for hash in leaf_hashes {
+        vec.extend(&hash.0);
+    }
+    vec.extend(serialize_key_source(source));
+    vec
+}
+
+//the entries in key order followed by the separator
+pub fn serialize_psbt_map(psbt_map: &PsbtMap) -> Vec<u8> {
+    let mut vec = Vec::new();
+    for (key, value) in psbt_map.0.iter() {
+        let key = [&serialize_var_int(key.key_type)[..], &key.key_data].concat();
+        vec.extend(serialize_var_int(key.len() as u64));
+        vec.extend(key);
+        vec.extend(serialize_var_int(value.len() as u64));
+        vec.extend(value);
+    }
+    vec.push(0);
+    vec
+}
+
+pub fn serialize_psbt(psbt: &Psbt) -> Vec<u8> {
+    let mut vec = b"psbt\xff".to_vec();
+    vec.extend(serialize_psbt_map(&psbt.global));
+    for input in psbt.inputs.iter() {
+        vec.extend(serialize_psbt_map(&input.map));
+    }
+    for output in psbt.outputs.iter() {
+        vec.extend(serialize_psbt_map(&output.map));
+    }
+    vec
+}
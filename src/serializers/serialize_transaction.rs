@@ -0,0 +1,66 @@
+use crate::{
+    serializers::{serialize_tx_inputs, serialize_tx_outputs, serialize_witnesses},
+    types::Transaction,
+};
+
+//serializes with the segwit marker and witnesses if the transaction has them
+pub fn serialize_transaction(tx: &Transaction) -> Vec<u8> {
+    match &tx.witnesses {
+        Some(witnesses) => {
+            let mut vec = [
+                &tx.version.to_le_bytes()[..],
+                &[0x00, 0x01],
+                &serialize_tx_inputs(&tx.inputs),
+                &serialize_tx_outputs(&tx.outputs),
+            ]
+            .concat();
+            for witness in witnesses {
+                vec.extend(serialize_witnesses(witness));
+            }
+            vec.extend(&tx.lock_time.to_le_bytes());
+            vec
+        }
+        None => serialize_transaction_no_witness(tx),
+    }
+}
+
+//the legacy serialization, the one hashed into the txid
+pub fn serialize_transaction_no_witness(tx: &Transaction) -> Vec<u8> {
+    [
+        &tx.version.to_le_bytes()[..],
+        &serialize_tx_inputs(&tx.inputs),
+        &serialize_tx_outputs(&tx.outputs),
+        &tx.lock_time.to_le_bytes(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_transaction;
+    use crate::utils::hash256;
+    #[test]
+    fn test_serialize_transaction() {
+        let data = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        assert_eq!(serialize_transaction(&tx), &data[..]);
+        assert_eq!(hash256(&serialize_transaction_no_witness(&tx)), tx.txid.0);
+
+        let data = include_bytes!(
+            "../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        assert_eq!(serialize_transaction(&tx), &data[..]);
+        assert_eq!(serialize_transaction_no_witness(&tx), &data[..]);
+
+        let data = include_bytes!(
+            "../test_data/tx_9e48f98e0b27e09ccabf576076c01dc6277c3961c8f616dea154f6822fb17765_large_segwit.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        assert_eq!(serialize_transaction(&tx), &data[..]);
+        assert_eq!(hash256(&serialize_transaction(&tx)), tx.wtxid.0);
+    }
+}
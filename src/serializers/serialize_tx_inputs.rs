@@ -0,0 +1,33 @@
+use crate::{serializers::serialize_var_int, types::TxInput};
+
+pub fn serialize_tx_input(input: &TxInput) -> Vec<u8> {
+    let script_sig = &input.script_sig.0;
+    [
+        input.previous_tx_hash.as_ref(),
+        &input.vout.to_le_bytes(),
+        &serialize_var_int(script_sig.len() as u64),
+        script_sig,
+        &input.sequence.to_le_bytes(),
+    ]
+    .concat()
+}
+
+pub fn serialize_tx_inputs(inputs: &[TxInput]) -> Vec<u8> {
+    let mut vec = serialize_var_int(inputs.len() as u64);
+    for input in inputs {
+        vec.extend(serialize_tx_input(input));
+    }
+    vec
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_tx_inputs;
+    #[test]
+    fn test_serialize_tx_inputs() {
+        let data = include_bytes!("../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.input.bin");
+        let (_, (inputs, _)) = parse_tx_inputs(data).unwrap();
+        assert_eq!(serialize_tx_inputs(&inputs), &data[..]);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{serializers::serialize_var_int, types::TxOutput};
+
+pub fn serialize_tx_output(output: &TxOutput) -> Vec<u8> {
+    let script_pub_key = &output.script_pub_key.0;
+    [
+        &output.value.to_le_bytes(),
+        &serialize_var_int(script_pub_key.len() as u64)[..],
+        script_pub_key,
+    ]
+    .concat()
+}
+
+pub fn serialize_tx_outputs(outputs: &[TxOutput]) -> Vec<u8> {
+    let mut vec = serialize_var_int(outputs.len() as u64);
+    for output in outputs {
+        vec.extend(serialize_tx_output(output));
+    }
+    vec
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_tx_outputs;
+    #[test]
+    fn test_serialize_tx_outputs() {
+        let data = include_bytes!("../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.output.bin");
+        let (_, (outputs, _)) = parse_tx_outputs(data).unwrap();
+        assert_eq!(serialize_tx_outputs(&outputs), &data[..]);
+    }
+}
@@ -0,0 +1,40 @@
+pub fn serialize_var_int(n: u64) -> Vec<u8> {
+    match n {
+        0..=0xFC => vec![n as u8],
+        0xFD..=0xFFFF => [&[0xFD][..], &(n as u16).to_le_bytes()].concat(),
+        0x10000..=0xFFFF_FFFF => [&[0xFE][..], &(n as u32).to_le_bytes()].concat(),
+        _ => [&[0xFF][..], &n.to_le_bytes()].concat(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_var_int;
+    #[test]
+    fn test_serialize_var_int() {
+        assert_eq!(serialize_var_int(0xFA), vec![0xFA]);
+        assert_eq!(serialize_var_int(0xFD), vec![0xFD, 0xFD, 0x00]);
+        assert_eq!(serialize_var_int(0xBBAA), vec![0xFD, 0xAA, 0xBB]);
+        assert_eq!(
+            serialize_var_int(0xDDCCBBAA),
+            vec![0xFE, 0xAA, 0xBB, 0xCC, 0xDD]
+        );
+        assert_eq!(
+            serialize_var_int(0x0910FFEEDDCCBBAA),
+            vec![0xFF, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x10, 0x09]
+        );
+        for n in &[
+            0,
+            1,
+            0xFC,
+            0xFD,
+            0xFFFF,
+            0x10000,
+            0xFFFF_FFFF,
+            0x1_0000_0000,
+        ] {
+            assert_eq!(parse_var_int(&serialize_var_int(*n)), Ok((&[][..], *n)));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    serializers::serialize_var_int,
+    types::{Bytes, Witness},
+};
+
+//the witness stack of a single input, the item count is written as parsed
+//parse_witnesses turns an empty stack into [Witness(None)], so that is written back as an empty stack,
+//an empty item it parses as Witness(Some) and a missing one elsewhere is written as an empty item
+pub fn serialize_witnesses(witnesses: &[Witness]) -> Vec<u8> {
+    if let [Witness(None)] = witnesses {
+        return serialize_var_int(0);
+    }
+    let mut vec = serialize_var_int(witnesses.len() as u64);
+    for witness in witnesses {
+        match witness {
+            Witness(Some(Bytes(bytes))) => {
+                vec.extend(serialize_var_int(bytes.len() as u64));
+                vec.extend(bytes);
+            }
+            Witness(None) => vec.extend(serialize_var_int(0)),
+        }
+    }
+    vec
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_witnesses;
+    #[test]
+    fn test_serialize_witnesses() {
+        let data = include_bytes!("../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.witnesses.bin");
+        let (_, (witnesses, _)) = parse_witnesses(data).unwrap();
+        assert_eq!(serialize_witnesses(&witnesses), &data[..]);
+        assert_eq!(serialize_witnesses(&[Witness::empty()]), vec![0x00]);
+        //an empty stack, a single empty item and empty items around others
+        for data in ["00", "0100", "020001ab", "03000000"].iter() {
+            let data = hex::decode(data).unwrap();
+            let (_, (witnesses, _)) = parse_witnesses(&data).unwrap();
+            assert_eq!(serialize_witnesses(&witnesses), data);
+        }
+        assert_eq!(
+            serialize_witnesses(&[Witness::empty(), Witness::empty()]),
+            vec![0x02, 0x00, 0x00]
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{
+    script::{opcodes::OP_RETURN, script_type},
+    types::Block,
+    utxo::{BlockPrevouts, FeeError, PrevoutProvider},
+};
+use std::collections::BTreeMap;
+
+//the fee and weight of every transaction but the coinbase
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Fees(pub Vec<(u64, usize)>);
+
+impl Fees {
+    pub fn total(&self) -> u64 {
+        self.0.iter().map(|(fee, _)| fee).sum()
+    }
+
+    pub fn avg(&self) -> u64 {
+        match self.0.len() {
+            0 => 0,
+            n => self.total() / n as u64,
+        }
+    }
+
+    pub fn min(&self) -> u64 {
+        self.0.iter().map(|(fee, _)| *fee).min().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> u64 {
+        self.0.iter().map(|(fee, _)| *fee).max().unwrap_or(0)
+    }
+
+    //the mean of the two middle fees for an even number of transactions
+    pub fn median(&self) -> u64 {
+        let mut fees: Vec<_> = self.0.iter().map(|(fee, _)| *fee).collect();
+        fees.sort_unstable();
+        let n = fees.len();
+        match n {
+            0 => 0,
+            _ if n % 2 == 0 => (fees[n / 2 - 1] + fees[n / 2]) / 2,
+            _ => fees[n / 2],
+        }
+    }
+
+    //satoshis per virtual byte, rounded down like bitcoind does
+    fn rates(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        self.0.iter().map(|(fee, weight)| match weight {
+            0 => (0, 0),
+            _ => (fee * 4 / *weight as u64, *weight),
+        })
+    }
+
+    pub fn min_rate(&self) -> u64 {
+        self.rates().map(|(rate, _)| rate).min().unwrap_or(0)
+    }
+
+    pub fn max_rate(&self) -> u64 {
+        self.rates().map(|(rate, _)| rate).max().unwrap_or(0)
+    }
+
+    //the total fee over the total weight, not the mean of the rates
+    pub fn avg_rate(&self) -> u64 {
+        match self.0.iter().map(|(_, weight)| *weight as u64).sum() {
+            0 => 0,
+            weight => self.total() * 4 / weight,
+        }
+    }
+
+    //the fee rates at the 10th, 25th, 50th, 75th and 90th percentile of the weight
+    pub fn rate_percentiles(&self) -> [u64; 5] {
+        let mut rates: Vec<_> = self.rates().collect();
+        rates.sort_unstable();
+        let total_weight = rates.iter().map(|(_, weight)| *weight).sum::<usize>() as f64;
+        let thresholds = [
+            total_weight / 10.0,
+            total_weight / 4.0,
+            total_weight / 2.0,
+            total_weight * 3.0 / 4.0,
+            total_weight * 9.0 / 10.0,
+        ];
+        let mut percentiles = [0; 5];
+        let mut next = 0;
+        let mut cumulative = 0;
+        for (rate, weight) in rates.iter() {
+            cumulative += weight;
+            while next < 5 && cumulative as f64 >= thresholds[next] {
+                percentiles[next] = *rate;
+                next += 1;
+            }
+        }
+        if let Some((rate, _)) = rates.last() {
+            percentiles[next..].iter_mut().for_each(|p| *p = *rate);
+        }
+        percentiles
+    }
+}
+
+//the statistics of bitcoind's getblockstats, for one block or added up over a range of blocks
+//sizes, weights, input counts, output values and fees leave out the coinbase transactions
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BlockStats {
+    pub blocks: usize,
+    //the lowest and highest height of the blocks with a known height
+    pub heights: Option<(u32, u32)>,
+    //including the coinbase transactions
+    pub txs: usize,
+    pub ins: usize,
+    //including the outputs of the coinbase transactions
+    pub outs: usize,
+    pub total_out: u64,
+    pub total_size: usize,
+    pub total_weight: usize,
+    pub min_tx_size: Option<usize>,
+    pub max_tx_size: usize,
+    //transactions with witness data
+    pub swtxs: usize,
+    pub swtotal_size: usize,
+    pub swtotal_weight: usize,
+    //the output script types by the names of bitcoind, coinbase outputs included
+    pub script_types: BTreeMap<&'static str, usize>,
+    //outputs whose script starts with OP_RETURN, like the witness commitment of the coinbase
+    pub op_returns: usize,
+    //known only when the prevouts of all blocks were given
+    pub fees: Option<Fees>,
+}
+
+impl BlockStats {
+    pub fn new(block: &Block, height: Option<u32>) -> BlockStats {
+        let mut stats = BlockStats {
+            blocks: 1,
+            heights: height.map(|height| (height, height)),
+            txs: block.transactions.len(),
+            ..BlockStats::default()
+        };
+        for tx in block.transactions.iter() {
+            stats.outs += tx.outputs.len();
+            for output in tx.outputs.iter() {
+                let script = &output.script_pub_key.0;
+                *stats.script_types.entry(script_type(script)).or_insert(0) += 1;
+                if script.first() == Some(&OP_RETURN) {
+                    stats.op_returns += 1;
+                }
+            }
+            if tx.is_coinbase() {
+                continue;
+            }
+            let weight = tx.weight();
+            stats.ins += tx.inputs.len();
+            stats.total_out += tx.output_value();
+            stats.total_size += tx.size;
+            stats.total_weight += weight;
+            stats.min_tx_size = Some(stats.min_tx_size.map_or(tx.size, |min| min.min(tx.size)));
+            stats.max_tx_size = stats.max_tx_size.max(tx.size);
+            if tx.size != tx.stripped_size() {
+                stats.swtxs += 1;
+                stats.swtotal_size += tx.size;
+                stats.swtotal_weight += weight;
+            }
+        }
+        stats
+    }
+
+    //provider has to know the outputs spent by the block, outputs created in the block are looked up in it
+    pub fn with_fees<P: PrevoutProvider>(
+        block: &Block,
+        height: Option<u32>,
+        provider: &P,
+    ) -> Result<BlockStats, FeeError> {
+        let provider = BlockPrevouts::new(&block.transactions, provider);
+        let mut fees = Vec::new();
+        for tx in block.transactions.iter().skip(1) {
+            fees.push((tx.fee(&provider)?, tx.weight()));
+        }
+        Ok(BlockStats {
+            fees: Some(Fees(fees)),
+            ..BlockStats::new(block, height)
+        })
+    }
+
+    //adds the blocks of other to the range, the fees stay known only if they are known for both
+    pub fn add(&mut self, other: &BlockStats) {
+        self.fees = match (self.blocks, self.fees.take(), &other.fees) {
+            (0, _, fees) => fees.clone(),
+            (_, Some(mut fees), Some(other)) => {
+                fees.0.extend(other.0.iter().cloned());
+                Some(fees)
+            }
+            _ => None,
+        };
+        self.blocks += other.blocks;
+        self.heights = match (self.heights, other.heights) {
+            (Some((low, high)), Some((other_low, other_high))) => {
+                Some((low.min(other_low), high.max(other_high)))
+            }
+            (heights, None) | (None, heights) => heights,
+        };
+        self.txs += other.txs;
+        self.ins += other.ins;
+        self.outs += other.outs;
+        self.total_out += other.total_out;
+        self.total_size += other.total_size;
+        self.total_weight += other.total_weight;
+        self.min_tx_size = match (self.min_tx_size, other.min_tx_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (size, None) | (None, size) => size,
+        };
+        self.max_tx_size = self.max_tx_size.max(other.max_tx_size);
+        self.swtxs += other.swtxs;
+        self.swtotal_size += other.swtotal_size;
+        self.swtotal_weight += other.swtotal_weight;
+        for (script_type, n) in other.script_types.iter() {
+            *self.script_types.entry(script_type).or_insert(0) += n;
+        }
+        self.op_returns += other.op_returns;
+    }
+
+    pub fn aggregate<'a, I: IntoIterator<Item = &'a BlockStats>>(stats: I) -> BlockStats {
+        let mut range = BlockStats::default();
+        for stats in stats {
+            range.add(stats);
+        }
+        range
+    }
+
+    //transactions but the coinbases
+    fn spending_txs(&self) -> usize {
+        self.txs - self.blocks
+    }
+
+    pub fn avg_tx_size(&self) -> usize {
+        self.total_size
+            .checked_div(self.spending_txs())
+            .unwrap_or(0)
+    }
+
+    pub fn avg_tx_weight(&self) -> usize {
+        self.total_weight
+            .checked_div(self.spending_txs())
+            .unwrap_or(0)
+    }
+
+    //the share of transactions with witness data, of those that are no coinbase
+    pub fn segwit_share(&self) -> f64 {
+        match self.spending_txs() {
+            0 => 0.0,
+            n => self.swtxs as f64 / n as f64,
+        }
+    }
+
+    //the growth of the number of unspent outputs, unspendable outputs included as bitcoind does
+    pub fn utxo_increase(&self) -> i64 {
+        self.outs as i64 - self.ins as i64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        parsers::parse_block,
+        types::{OutPoint, TxOutput},
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_block_stats() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let stats = BlockStats::new(&block, Some(609015));
+        let spending = &block.transactions[1..];
+        assert_eq!(stats.txs, block.transactions.len());
+        assert_eq!(
+            stats.ins,
+            spending.iter().map(|tx| tx.inputs.len()).sum::<usize>()
+        );
+        assert_eq!(stats.outs, stats.script_types.values().sum::<usize>());
+        //the block weight of getblock is the header, the transaction count and all transactions
+        let coinbase = &block.transactions[0];
+        assert_eq!(
+            stats.total_weight + coinbase.weight() + (80 + 3) * 4,
+            529414
+        );
+        assert_eq!(stats.avg_tx_size(), stats.total_size / spending.len());
+        assert!(stats.swtxs > 0 && stats.swtxs < spending.len());
+        assert!(stats.segwit_share() > 0.0 && stats.segwit_share() < 1.0);
+        assert!(stats.swtotal_weight < stats.total_weight);
+        assert!(stats.min_tx_size.unwrap() <= stats.avg_tx_size());
+        assert!(stats.max_tx_size >= stats.avg_tx_size());
+        //the witness commitment
+        assert!(stats.op_returns >= 1);
+        assert_eq!(stats.fees, None);
+
+        //every output spent from an earlier block is made worth 1 BTC more than it pays out
+        let mut prevouts = HashMap::new();
+        for tx in spending.iter() {
+            let value = 100_000_000 + tx.output_value() / tx.inputs.len() as u64 + 1;
+            for input in tx.inputs.iter() {
+                prevouts.insert(input.out_point(), TxOutput::new(value, &[]));
+            }
+        }
+        //outputs created in the block are found in it
+        let created: Vec<OutPoint> = spending
+            .iter()
+            .flat_map(|tx| tx.inputs.iter().map(|input| input.out_point()))
+            .filter(|out_point| spending.iter().any(|tx| tx.txid.0 == out_point.txid))
+            .collect();
+        for out_point in created.iter() {
+            prevouts.remove(out_point);
+        }
+        let stats = BlockStats::with_fees(&block, Some(609015), &prevouts).unwrap();
+        let fees = stats.fees.as_ref().unwrap();
+        assert_eq!(fees.0.len(), spending.len());
+        assert_eq!(fees.total(), block.total_fees(&prevouts).unwrap());
+        let percentiles = fees.rate_percentiles();
+        assert!(percentiles.windows(2).all(|p| p[0] <= p[1]));
+        assert!(fees.min_rate() <= percentiles[0] && percentiles[4] <= fees.max_rate());
+
+        prevouts.remove(&spending[0].inputs[0].out_point());
+        assert!(matches!(
+            BlockStats::with_fees(&block, None, &prevouts),
+            Err(FeeError::MissingPrevout(_))
+        ));
+    }
+
+    #[test]
+    fn test_fees() {
+        let fees = Fees(vec![(1000, 400), (100, 800), (1000, 2000), (0, 400)]);
+        assert_eq!(fees.total(), 2100);
+        assert_eq!(fees.avg(), 525);
+        assert_eq!((fees.min(), fees.max()), (0, 1000));
+        assert_eq!(fees.median(), 550);
+        //rates of 10, 0.5, 2 and 0 sat/vB, rounded down
+        assert_eq!((fees.min_rate(), fees.max_rate()), (0, 10));
+        assert_eq!(fees.avg_rate(), 2100 * 4 / 3600);
+        //the weight sorted by rate is 400 and 800 at 0, 2000 at 2 and 400 at 10
+        assert_eq!(fees.rate_percentiles(), [0, 0, 2, 2, 10]);
+        assert_eq!(Fees(vec![(3, 4), (1, 4), (2, 4)]).median(), 2);
+        assert_eq!(Fees::default().rate_percentiles(), [0; 5]);
+        assert_eq!(Fees::default().median(), 0);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, genesis) = parse_block(data).unwrap();
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let genesis_stats = BlockStats::with_fees(&genesis, Some(0), &HashMap::new()).unwrap();
+        assert_eq!(genesis_stats.fees, Some(Fees::default()));
+        assert_eq!(genesis_stats.avg_tx_size(), 0);
+        assert_eq!(genesis_stats.min_tx_size, None);
+        assert_eq!(genesis_stats.script_types.get("pubkey"), Some(&1));
+        let block_stats = BlockStats::new(&block, Some(609015));
+
+        let range = BlockStats::aggregate(&[genesis_stats.clone(), block_stats.clone()]);
+        assert_eq!(range.blocks, 2);
+        assert_eq!(range.heights, Some((0, 609015)));
+        assert_eq!(range.txs, 1 + block.transactions.len());
+        assert_eq!(range.total_size, block_stats.total_size);
+        assert_eq!(range.avg_tx_size(), block_stats.avg_tx_size());
+        assert_eq!(range.utxo_increase(), block_stats.utxo_increase() + 1);
+        assert_eq!(
+            range.script_types.get("pubkey").copied().unwrap_or(0),
+            block_stats.script_types.get("pubkey").copied().unwrap_or(0) + 1
+        );
+        //the fees of the second block are not known
+        assert_eq!(range.fees, None);
+        let range = BlockStats::aggregate(&[genesis_stats.clone(), genesis_stats]);
+        assert_eq!(range.fees, Some(Fees::default()));
+        assert_eq!(BlockStats::aggregate(&[]), BlockStats::default());
+    }
+}
@@ -0,0 +1,2 @@
+mod block_stats;
+pub use self::block_stats::{BlockStats, Fees};
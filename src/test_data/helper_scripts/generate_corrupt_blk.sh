@@ -0,0 +1,44 @@
+#!/bin/bash
+
+#writes blk files with corrupt records made from the genesis block to the test_data dir
+#blk_corrupt_tx_count.bin: genesis, genesis claiming 2 transactions, genesis
+#blk_corrupt_tx_count_overflow.bin: genesis, the genesis header with a transaction count of 2^63-1, genesis
+#blk_corrupt_size.bin: genesis, genesis with a size 85 bytes short, genesis
+#blk_corrupt_truncated.bin: genesis, genesis with a size past the end of the file, genesis, the first 142 bytes of genesis
+
+test_data_dir="../"
+genesis="${test_data_dir}blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+magic='\xf9\xbe\xb4\xd9'
+
+function record(){
+  local size=$1
+  printf "$magic"
+  printf "\\x$(printf %02x $((size & 255)))\\x$(printf %02x $((size >> 8 & 255)))\\x$(printf %02x $((size >> 16 & 255)))\\x$(printf %02x $((size >> 24 & 255)))"
+}
+
+size=$(stat -c %s $genesis)
+
+{
+  record $size; cat $genesis
+  record $size; head -c 80 $genesis; printf '\x02'; tail -c +82 $genesis
+  record $size; cat $genesis
+} > ${test_data_dir}blk_corrupt_tx_count.bin
+
+{
+  record $size; cat $genesis
+  record 89; head -c 80 $genesis; printf '\xff\xff\xff\xff\xff\xff\xff\xff\x7f'
+  record $size; cat $genesis
+} > ${test_data_dir}blk_corrupt_tx_count_overflow.bin
+
+{
+  record $size; cat $genesis
+  record $((size - 85)); cat $genesis
+  record $size; cat $genesis
+} > ${test_data_dir}blk_corrupt_size.bin
+
+{
+  record $size; cat $genesis
+  record 100000; cat $genesis
+  record $size; cat $genesis
+  record $size; head -c 142 $genesis
+} > ${test_data_dir}blk_corrupt_truncated.bin
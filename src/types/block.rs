@@ -1,4 +1,10 @@
-use crate::types::{BlockHeader, Transaction};
+use crate::{
+    parsers::parse_block,
+    serializers::serialize_block,
+    types::{BlockHeader, Transaction},
+    utils::{parse_hex, HexError},
+    utxo::{BlockPrevouts, FeeError, PrevoutProvider},
+};
 
 #[derive(Debug)]
 pub struct Block {
@@ -13,6 +19,22 @@ impl Block {
             transactions: t,
         }
     }
+    //the hex of getblock with verbosity 0
+    pub fn from_hex(s: &str) -> Result<Block, HexError> {
+        parse_hex(s, parse_block)
+    }
+    pub fn to_hex(&self) -> String {
+        hex::encode(serialize_block(self))
+    }
+    //provider has to know the outputs spent by the block, outputs created in the block are looked up in it
+    pub fn total_fees<P: PrevoutProvider>(&self, provider: &P) -> Result<u64, FeeError> {
+        let provider = BlockPrevouts::new(&self.transactions, provider);
+        let mut fees = 0;
+        for tx in self.transactions.iter().skip(1) {
+            fees += tx.fee(&provider)?;
+        }
+        Ok(fees)
+    }
 }
 
 impl std::default::Default for Block {
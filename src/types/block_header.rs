@@ -1,18 +1,22 @@
+use crate::parsers::parse_block_header;
+use crate::serializers::serialize_block_header;
 use crate::types::Bytes;
 use crate::types::Hash256;
+use crate::types::{BlockHash, MerkleRoot};
+use crate::utils::{parse_hex, HexError};
 use chrono::prelude::*;
 use std::convert::TryInto;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BlockHeader {
     pub version: u32,
-    pub prev_block_hash: Hash256,
-    pub merkle_root_hash: Hash256,
+    pub prev_block_hash: BlockHash,
+    pub merkle_root_hash: MerkleRoot,
     pub time_str: String,
     pub time: u32,
     pub bits: Bytes,
     pub nonce: Bytes,
-    pub hash: Hash256,
+    pub hash: BlockHash,
 }
 
 impl BlockHeader {
@@ -27,15 +31,111 @@ impl BlockHeader {
     ) -> BlockHeader {
         BlockHeader {
             version: v,
-            prev_block_hash: Hash256::new(pbh),
-            merkle_root_hash: Hash256::new(mrh),
+            prev_block_hash: BlockHash(Hash256::new(pbh)),
+            merkle_root_hash: MerkleRoot(Hash256::new(mrh)),
             time: t,
             time_str: chrono::Utc
                 .timestamp(t.try_into().unwrap(), 0u32)
                 .to_rfc2822(),
             bits: Bytes::new(b),
             nonce: Bytes::new(n),
-            hash: h,
+            hash: BlockHash(h),
+        }
+    }
+    //the hex of getblockheader with verbose false
+    pub fn from_hex(s: &str) -> Result<BlockHeader, HexError> {
+        parse_hex(s, parse_block_header)
+    }
+    pub fn to_hex(&self) -> String {
+        hex::encode(serialize_block_header(self))
+    }
+    pub fn block_hash(&self) -> BlockHash {
+        self.hash
+    }
+    //all zeros for the genesis block
+    pub fn prev_block_hash(&self) -> BlockHash {
+        self.prev_block_hash
+    }
+    pub fn merkle_root(&self) -> MerkleRoot {
+        self.merkle_root_hash
+    }
+    //bits as the number bitcoind prints in hex
+    pub fn compact_target(&self) -> u32 {
+        let mut bits = [0; 4];
+        let len = self.bits.len().min(4);
+        bits[..len].copy_from_slice(&self.bits.0[..len]);
+        u32::from_le_bytes(bits)
+    }
+    //the target of difficulty 1 divided by the target of the block, computed like bitcoind does
+    pub fn difficulty(&self) -> f64 {
+        let bits = self.compact_target();
+        let mut shift = (bits >> 24) & 0xff;
+        let mut difficulty = f64::from(0x0000ffff) / f64::from(bits & 0x00ffffff);
+        while shift < 29 {
+            difficulty *= 256.0;
+            shift += 1;
+        }
+        while shift > 29 {
+            difficulty /= 256.0;
+            shift -= 1;
+        }
+        difficulty
+    }
+    //the target encoded by bits, in the wire order of hashes
+    //None for a negative, zero or overflowing target, which bitcoind rejects
+    pub fn target(&self) -> Option<Hash256> {
+        let bits = self.compact_target();
+        let exponent = (bits >> 24) as usize;
+        let mantissa = bits & 0x007fffff;
+        if mantissa == 0 || bits & 0x00800000 != 0 {
+            return None;
+        }
+        let mut target = [0; 32];
+        if exponent <= 3 {
+            let mantissa = mantissa >> (8 * (3 - exponent));
+            target[..4].copy_from_slice(&mantissa.to_le_bytes());
+            return match mantissa {
+                0 => None,
+                _ => Some(Hash256(target)),
+            };
+        }
+        for (i, byte) in mantissa.to_le_bytes()[..3].iter().enumerate() {
+            match target.get_mut(exponent - 3 + i) {
+                Some(b) => *b = *byte,
+                None if *byte != 0 => return None,
+                None => (),
+            }
+        }
+        Some(Hash256(target))
+    }
+    //the expected number of hashes for the target, 2^256 / (target + 1) like bitcoind's GetBlockProof
+    //0 for an invalid target, targets with an exponent of 19 or less, far below any network's, saturate
+    pub fn work(&self) -> u128 {
+        if self.target().is_none() {
+            return 0;
+        }
+        //the target is mantissa * 2^shift
+        let bits = self.compact_target();
+        let mantissa = u128::from(bits & 0x007fffff);
+        let shift = 8 * ((bits >> 24) as usize).saturating_sub(3);
+        if shift <= 128 {
+            return u128::MAX;
+        }
+        //2^256 = quotient * (target + 1) + remainder * 2^shift - quotient,
+        //so 2^256 / (target + 1) is the quotient, one less if the remainder is 0
+        let quotient = (1 << (256 - shift)) / mantissa;
+        let remainder = (1 << (256 - shift)) % mantissa;
+        match remainder {
+            0 => quotient - 1,
+            _ => quotient,
+        }
+    }
+    //the hash, read as a little endian number, is not above the target
+    //the proof of work limit of the network is not checked
+    pub fn check_proof_of_work(&self) -> bool {
+        match self.target() {
+            Some(target) => (self.hash.0).0.iter().rev().le(target.0.iter().rev()),
+            None => false,
         }
     }
 }
@@ -44,13 +144,13 @@ impl std::default::Default for BlockHeader {
     fn default() -> BlockHeader {
         BlockHeader {
             version: 0,
-            prev_block_hash: Hash256::default(),
-            merkle_root_hash: Hash256::default(),
+            prev_block_hash: BlockHash::default(),
+            merkle_root_hash: MerkleRoot::default(),
             time_str: String::new(),
             time: 0,
             bits: Bytes::default(),
             nonce: Bytes::default(),
-            hash: Hash256::default(),
+            hash: BlockHash::default(),
         }
     }
 }
@@ -70,11 +170,11 @@ impl BlockHeaderBuilder {
         self
     }
     pub fn prev_block_hash<H: Into<Hash256>>(&mut self, hash: H) -> &mut Self {
-        self.blkh.prev_block_hash = hash.into();
+        self.blkh.prev_block_hash = BlockHash(hash.into());
         self
     }
     pub fn merkle_root_hash<H: Into<Hash256>>(&mut self, hash: H) -> &mut Self {
-        self.blkh.merkle_root_hash = hash.into();
+        self.blkh.merkle_root_hash = MerkleRoot(hash.into());
         self
     }
     pub fn time(&mut self, time: u32) -> &mut Self {
@@ -95,7 +195,7 @@ impl BlockHeaderBuilder {
         self
     }
     pub fn hash<H: Into<Hash256>>(&mut self, hash: H) -> &mut Self {
-        self.blkh.hash = hash.into();
+        self.blkh.hash = BlockHash(hash.into());
         self
     }
     pub fn build(&self) -> BlockHeader {
@@ -111,3 +211,55 @@ impl BlockHeaderBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_proof_of_work() {
+        let hex = include_str!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.hex"
+        );
+        let mut header = BlockHeader::from_hex(&hex.trim()[..160]).unwrap();
+        assert_eq!(
+            header.target().unwrap().to_string(),
+            "00000000ffff0000000000000000000000000000000000000000000000000000"
+        );
+        assert!(header.check_proof_of_work());
+        (header.hash.0).0[31] = 1;
+        assert!(!header.check_proof_of_work());
+
+        let target = |bits: u32| {
+            let header = BlockHeader {
+                bits: Bytes::new(&bits.to_le_bytes()),
+                ..BlockHeader::default()
+            };
+            header.target().map(|target| target.to_string())
+        };
+        let regtest = "7fffff0000000000000000000000000000000000000000000000000000000000";
+        assert_eq!(target(0x207fffff).unwrap(), regtest);
+        assert_eq!(target(0x03123456).unwrap(), format!("{:0>64}", "123456"));
+        assert_eq!(target(0x02123456).unwrap(), format!("{:0>64}", "1234"));
+        assert_eq!(target(0x01003456), None);
+        assert_eq!(target(0x04923456), None);
+        assert_eq!(target(0x22000001).unwrap(), format!("{:0<64}", "01"));
+        assert_eq!(target(0x22000100), None);
+        assert_eq!(target(0), None);
+
+        //the chain work bitcoind reports for a block on mainnet, testnet and regtest
+        let work = |bits: u32| {
+            let header = BlockHeader {
+                bits: Bytes::new(&bits.to_le_bytes()),
+                ..BlockHeader::default()
+            };
+            header.work()
+        };
+        assert_eq!(work(0x1d00ffff), 0x100010001);
+        assert_eq!(work(0x207fffff), 2);
+        assert_eq!(work(0x22000001), 255);
+        assert_eq!(work(0x170331db), 0x5021ab2578ee9fc3005e);
+        assert_eq!(work(0x03123456), u128::MAX);
+        assert_eq!(work(0), 0);
+    }
+}
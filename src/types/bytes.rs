@@ -1,3 +1,5 @@
+use crate::utils::{decode_hex, HexError};
+
 #[derive(Clone, PartialEq)]
 pub struct Bytes(pub Vec<u8>);
 
@@ -26,6 +28,21 @@ impl std::fmt::Debug for Bytes {
     }
 }
 
+//lowercase hex in wire order
+impl std::fmt::Display for Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Bytes(bytes) = self;
+        write!(f, "{}", hex::encode(bytes))
+    }
+}
+
+impl std::str::FromStr for Bytes {
+    type Err = HexError;
+    fn from_str(s: &str) -> Result<Bytes, HexError> {
+        decode_hex(s).map(Bytes)
+    }
+}
+
 impl std::convert::From<&[u8]> for Bytes {
     fn from(slice: &[u8]) -> Bytes {
         Bytes(Vec::from(slice))
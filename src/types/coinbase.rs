@@ -0,0 +1,177 @@
+use crate::types::{Bytes, Transaction, Witness};
+
+//known pool tags as found in the coinbase scriptSig, (tag, pool name)
+const DEFAULT_POOL_TAGS: &[(&str, &str)] = &[
+    ("/poolin.com", "Poolin"),
+    ("/bytepool.com/", "BytePool"),
+    ("/ViaBTC/", "ViaBTC"),
+    ("/BTC.COM/", "BTC.com"),
+    ("/slush/", "SlushPool"),
+    ("/AntPool/", "AntPool"),
+    ("Mined by AntPool", "AntPool"),
+    ("/F2Pool/", "F2Pool"),
+    ("/Huobi/", "Huobi.pool"),
+    ("/HuoBi/", "Huobi.pool"),
+    ("/1THash&58COIN/", "1THash&58COIN"),
+    ("/Bitfury/", "BitFury"),
+    ("/BitClub Network/", "BitClub Network"),
+    ("/Foundry USA Pool", "Foundry USA"),
+    ("/Binance/", "Binance Pool"),
+    ("/mined by luxor/", "Luxor"),
+    ("/MARA Pool", "MARA Pool"),
+];
+
+//configurable table of scriptSig tags used to recognise mining pools
+#[derive(Debug, Clone)]
+pub struct PoolTags(pub Vec<(Bytes, String)>);
+
+impl PoolTags {
+    pub fn new() -> PoolTags {
+        PoolTags(Vec::new())
+    }
+    pub fn add<B: Into<Bytes>>(&mut self, tag: B, pool: &str) -> &mut Self {
+        let PoolTags(tags) = self;
+        tags.push((tag.into(), pool.to_string()));
+        self
+    }
+    //the first tag found anywhere in the script wins
+    pub fn find(&self, script: &[u8]) -> Option<&str> {
+        let PoolTags(tags) = self;
+        tags.iter()
+            .find(|(Bytes(tag), _)| {
+                !tag.is_empty() && script.windows(tag.len()).any(|w| w == &tag[..])
+            })
+            .map(|(_, pool)| pool.as_str())
+    }
+}
+
+impl std::default::Default for PoolTags {
+    fn default() -> PoolTags {
+        let mut tags = PoolTags::new();
+        for (tag, pool) in DEFAULT_POOL_TAGS {
+            tags.add(tag.as_bytes(), pool);
+        }
+        tags
+    }
+}
+
+//read only view over a coinbase transaction
+#[derive(Debug, Clone, Copy)]
+pub struct Coinbase<'a> {
+    tx: &'a Transaction,
+}
+
+impl<'a> Coinbase<'a> {
+    //returns None if the transaction is not a coinbase
+    pub fn new(tx: &'a Transaction) -> Option<Coinbase<'a>> {
+        match tx.is_coinbase() {
+            true => Some(Coinbase { tx }),
+            false => None,
+        }
+    }
+    pub fn transaction(&self) -> &'a Transaction {
+        self.tx
+    }
+    pub fn script_sig(&self) -> &'a [u8] {
+        let Bytes(script) = &self.tx.inputs[0].script_sig;
+        &script[..]
+    }
+    //BIP34 height, the first push of the scriptSig
+    //only meaningful for blocks with version >= 2 mined after BIP34 activation (227931 on mainnet)
+    pub fn height(&self) -> Option<u64> {
+        self.height_push().map(|(height, _)| height)
+    }
+    //extranonce and any arbitrary data following the height push
+    pub fn extra_data(&self) -> &'a [u8] {
+        let script = self.script_sig();
+        match self.height_push() {
+            Some((_, len)) => &script[len..],
+            None => script,
+        }
+    }
+    pub fn pool_tag<'t>(&self, tags: &'t PoolTags) -> Option<&'t str> {
+        tags.find(self.script_sig())
+    }
+    //subsidy + fees, as paid out by the coinbase outputs
+    pub fn total_reward(&self) -> u64 {
+        self.tx.outputs.iter().map(|o| o.value).sum()
+    }
+    //BIP141 witness reserved value, the single 32 byte item of the coinbase witness
+    pub fn witness_reserved_value(&self) -> Option<&'a [u8]> {
+        let witnesses = self.tx.witnesses.as_ref()?;
+        match &witnesses.first()?[..] {
+            [Witness(Some(Bytes(value)))] if value.len() == 32 => Some(&value[..]),
+            _ => None,
+        }
+    }
+    //(height, bytes taken by the push including the opcode)
+    fn height_push(&self) -> Option<(u64, usize)> {
+        let script = self.script_sig();
+        match *script.first()? {
+            0x00 => Some((0, 1)),
+            op @ 0x51..=0x60 => Some(((op - 0x50).into(), 1)),
+            len @ 0x01..=0x08 => {
+                let len = len as usize;
+                let bytes = script.get(1..1 + len)?;
+                //script numbers are sign-magnitude, a negative height is not a height
+                if bytes[len - 1] & 0x80 != 0 {
+                    return None;
+                }
+                let height = bytes
+                    .iter()
+                    .rev()
+                    .fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+                Some((height, 1 + len))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+    #[test]
+    fn test_coinbase() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        assert!(block.transactions[0].is_coinbase());
+        assert!(!block.transactions[1].is_coinbase());
+        assert!(block.transactions[1].coinbase().is_none());
+        let coinbase = block.transactions[0].coinbase().unwrap();
+        assert_eq!(coinbase.height(), Some(609015));
+        assert_eq!(coinbase.extra_data(), &coinbase.script_sig()[4..]);
+        assert_eq!(coinbase.pool_tag(&PoolTags::default()), Some("Poolin"));
+        assert_eq!(coinbase.pool_tag(&PoolTags::new()), None);
+        let mut tags = PoolTags::new();
+        tags.add(&b"poolin"[..], "custom");
+        assert_eq!(coinbase.pool_tag(&tags), Some("custom"));
+        assert_eq!(
+            coinbase.total_reward(),
+            block.transactions[0].outputs.iter().map(|o| o.value).sum::<u64>()
+        );
+        assert_eq!(coinbase.witness_reserved_value(), Some(&[0u8; 32][..]));
+
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000b0a682f47f187a712c42badd4ca1989c494d401457c3f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let coinbase = block.transactions[0].coinbase().unwrap();
+        assert_eq!(coinbase.height(), Some(607786));
+        assert_eq!(coinbase.pool_tag(&PoolTags::default()), Some("BytePool"));
+
+        //genesis predates BIP34, the first push is the bits field
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let coinbase = block.transactions[0].coinbase().unwrap();
+        assert_eq!(coinbase.height(), Some(0x1d00ffff));
+        assert_eq!(coinbase.total_reward(), 50_0000_0000);
+        assert_eq!(coinbase.witness_reserved_value(), None);
+        assert_eq!(coinbase.pool_tag(&PoolTags::default()), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::utils::{decode_hex, HexError};
 use std::{
     cmp::PartialEq,
     convert::{AsRef, From},
@@ -5,7 +6,7 @@ use std::{
 };
 
 //warning LE on wire, keeping format!
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
 pub struct Hash256(pub [u8; 32]);
 
 impl Hash256 {
@@ -35,6 +36,30 @@ impl std::fmt::Debug for Hash256 {
     }
 }
 
+//lowercase and reversed, as bitcoind prints hashes
+impl std::fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let Hash256(hash) = self;
+        for byte in hash.iter().rev() {
+            write!(f, "{:02x}", byte)?
+        }
+        Ok(())
+    }
+}
+
+//parses the reversed order Display prints
+impl std::str::FromStr for Hash256 {
+    type Err = HexError;
+    fn from_str(s: &str) -> Result<Hash256, HexError> {
+        let mut bytes = decode_hex(s)?;
+        if bytes.len() != 32 {
+            return Err(HexError::InvalidLength(bytes.len()));
+        }
+        bytes.reverse();
+        Ok(Hash256::new(&bytes))
+    }
+}
+
 impl Default for Hash256 {
     fn default() -> Hash256 {
         Hash256::new(&[0u8; 32][..])
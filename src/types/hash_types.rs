@@ -0,0 +1,120 @@
+use crate::{types::Hash256, utils::HexError};
+
+//a Hash256 whose meaning is part of its type
+//Display, Debug and FromStr use the reversed order bitcoind prints, the wire order needs from_wire_bytes and to_wire_bytes
+macro_rules! hash_newtype {
+    ($name:ident) => {
+        #[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone, Default)]
+        pub struct $name(pub Hash256);
+
+        impl $name {
+            pub fn from_wire_bytes(bytes: [u8; 32]) -> $name {
+                $name(Hash256(bytes))
+            }
+            pub fn to_wire_bytes(&self) -> [u8; 32] {
+                (self.0).0
+            }
+            pub fn is_zero(&self) -> bool {
+                self.0.is_zero()
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.0)
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = HexError;
+            fn from_str(s: &str) -> Result<$name, HexError> {
+                s.parse().map($name)
+            }
+        }
+
+        impl From<Hash256> for $name {
+            fn from(hash: Hash256) -> $name {
+                $name(hash)
+            }
+        }
+
+        impl From<$name> for Hash256 {
+            fn from(hash: $name) -> Hash256 {
+                hash.0
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+    };
+}
+
+hash_newtype!(Txid);
+hash_newtype!(Wtxid);
+hash_newtype!(BlockHash);
+hash_newtype!(MerkleRoot);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parsers::parse_block, types::Bytes};
+
+    #[test]
+    fn test_hash_types() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let hash = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let block_hash: BlockHash = hash.parse().unwrap();
+        assert_eq!(block.header.block_hash(), block_hash);
+        assert_eq!(block.header.hash, block_hash);
+        assert_eq!(block.header.prev_block_hash(), BlockHash::default());
+        assert_eq!(block_hash.to_string(), hash);
+        assert_eq!(format!("{:?}", block_hash), format!("BlockHash({})", hash));
+        assert_eq!(block_hash.to_wire_bytes()[31], 0);
+        assert_eq!(
+            BlockHash::from_wire_bytes(block_hash.to_wire_bytes()),
+            block_hash
+        );
+
+        let merkle_root = block.header.merkle_root();
+        let txid = block.transactions[0].txid();
+        assert_eq!(merkle_root, block.header.merkle_root_hash);
+        assert_eq!(txid, block.transactions[0].txid);
+        assert_eq!(merkle_root.0, txid.0);
+        assert_eq!(
+            txid.to_string(),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        //without witnesses both ids are the same hash
+        assert_eq!(block.transactions[0].wtxid.0, Hash256::from(txid));
+        assert_eq!(block.transactions[0].wtxid().0, txid.0);
+        assert_eq!(
+            block.transactions[0].inputs[0].previous_txid(),
+            Txid::default()
+        );
+
+        assert_eq!(
+            "4A5E1E4BAAB89F3A32518A88C31BC87F618F76673E2CC77AB2127B7AFDEDA33B".parse(),
+            Ok(txid)
+        );
+        assert_eq!("4a5e".parse::<Txid>(), Err(HexError::InvalidLength(2)));
+        assert_eq!("4a5".parse::<Txid>(), Err(HexError::OddLength));
+
+        //bytes keep the wire order
+        let bits = &block.header.bits;
+        assert_eq!(bits.to_string(), "ffff001d");
+        assert_eq!("ffff001d".parse::<Bytes>(), Ok(bits.clone()));
+    }
+}
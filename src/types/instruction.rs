@@ -0,0 +1,23 @@
+use crate::types::Bytes;
+
+//a single decoded script element
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    //data push, keeping the opcode used so non-minimal pushes can be told apart (OP_0 included)
+    Push(u8, Bytes),
+    Op(u8),
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Push(opcode, _) | Instruction::Op(opcode) => *opcode,
+        }
+    }
+    pub fn push_data(&self) -> Option<&[u8]> {
+        match self {
+            Instruction::Push(_, Bytes(data)) => Some(&data[..]),
+            Instruction::Op(_) => None,
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//the JSON of bitcoind's getblock with verbosity 2, getblockheader and decoderawtransaction
+//fields that need the chain, like confirmations, height or chainwork, are left out
+//addresses are those of mainnet
+use crate::{
+    script::{script_to_asm, script_type},
+    serializers::{serialize_block, serialize_transaction},
+    types::{Block, BlockHeader, Hash256, Transaction, TxInput, TxOutput, Witness},
+    utils::script_to_address,
+};
+use serde::ser::{Error, Serialize, SerializeMap, Serializer};
+
+impl Serialize for Hash256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for Witness {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0 {
+            Some(item) => serializer.serialize_str(&hex::encode(&item.0)),
+            None => serializer.serialize_str(""),
+        }
+    }
+}
+
+struct Script<'a> {
+    script: &'a [u8],
+    sighash_decode: bool,
+}
+
+impl Serialize for Script<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let is_script_sig = self.sighash_decode;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("asm", &script_to_asm(self.script, self.sighash_decode))?;
+        map.serialize_entry("hex", &hex::encode(self.script))?;
+        if !is_script_sig {
+            if let Some(address) = script_to_address(self.script, "mainnet") {
+                map.serialize_entry("address", &address)?;
+            }
+            map.serialize_entry("type", script_type(self.script))?;
+        }
+        map.end()
+    }
+}
+
+//an input with its witness stack, which is serialized in the input by bitcoind
+struct Vin<'a> {
+    input: &'a TxInput,
+    witness: Option<&'a [Witness]>,
+}
+
+impl Serialize for Vin<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let input = self.input;
+        let mut map = serializer.serialize_map(None)?;
+        if input.is_coinbase() {
+            map.serialize_entry("coinbase", &hex::encode(&input.script_sig.0))?;
+        } else {
+            map.serialize_entry("txid", &input.previous_txid())?;
+            map.serialize_entry("vout", &input.vout)?;
+            let script = Script {
+                script: &input.script_sig.0,
+                sighash_decode: true,
+            };
+            map.serialize_entry("scriptSig", &script)?;
+        }
+        //an empty stack is parsed as a single missing item
+        match self.witness {
+            Some([Witness(None)]) | Some([]) | None => (),
+            Some(witness) => map.serialize_entry("txinwitness", witness)?,
+        }
+        map.serialize_entry("sequence", &input.sequence)?;
+        map.end()
+    }
+}
+
+impl Serialize for TxInput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Vin {
+            input: self,
+            witness: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+//bitcoind prints amounts with 8 decimals, they are built from the sats so a float never rounds them
+struct Amount(u64);
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let number: serde_json::Number =
+            format!("{}.{:08}", self.0 / 100_000_000, self.0 % 100_000_000)
+                .parse()
+                .map_err(S::Error::custom)?;
+        number.serialize(serializer)
+    }
+}
+
+struct Vout<'a> {
+    output: &'a TxOutput,
+    n: Option<usize>,
+}
+
+impl Serialize for Vout<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("value", &Amount(self.output.value))?;
+        if let Some(n) = self.n {
+            map.serialize_entry("n", &n)?;
+        }
+        let script = Script {
+            script: &self.output.script_pub_key.0,
+            sighash_decode: false,
+        };
+        map.serialize_entry("scriptPubKey", &script)?;
+        map.end()
+    }
+}
+
+impl Serialize for TxOutput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Vout {
+            output: self,
+            n: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let vin: Vec<_> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| Vin {
+                input,
+                witness: self
+                    .witnesses
+                    .as_ref()
+                    .and_then(|w| w.get(index))
+                    .map(|w| &w[..]),
+            })
+            .collect();
+        let vout: Vec<_> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(n, output)| Vout { output, n: Some(n) })
+            .collect();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("txid", &self.txid())?;
+        map.serialize_entry("hash", &self.wtxid())?;
+        map.serialize_entry("version", &(self.version as i32))?;
+        map.serialize_entry("size", &self.size)?;
+        map.serialize_entry("vsize", &self.vsize())?;
+        map.serialize_entry("weight", &self.weight())?;
+        map.serialize_entry("locktime", &self.lock_time)?;
+        map.serialize_entry("vin", &vin)?;
+        map.serialize_entry("vout", &vout)?;
+        map.serialize_entry("hex", &hex::encode(serialize_transaction(self)))?;
+        map.end()
+    }
+}
+
+fn serialize_header_fields<M: SerializeMap>(
+    map: &mut M,
+    header: &BlockHeader,
+) -> Result<(), M::Error> {
+    map.serialize_entry("version", &(header.version as i32))?;
+    map.serialize_entry("versionHex", &format!("{:08x}", header.version))?;
+    map.serialize_entry("merkleroot", &header.merkle_root())
+}
+
+fn serialize_target_fields<M: SerializeMap>(
+    map: &mut M,
+    header: &BlockHeader,
+) -> Result<(), M::Error> {
+    let mut nonce = [0; 4];
+    nonce.copy_from_slice(&header.nonce.0);
+    map.serialize_entry("time", &header.time)?;
+    map.serialize_entry("nonce", &u32::from_le_bytes(nonce))?;
+    map.serialize_entry("bits", &format!("{:08x}", header.compact_target()))?;
+    map.serialize_entry("difficulty", &header.difficulty())
+}
+
+impl Serialize for BlockHeader {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("hash", &self.block_hash())?;
+        serialize_header_fields(&mut map, self)?;
+        serialize_target_fields(&mut map, self)?;
+        if !self.prev_block_hash.is_zero() {
+            map.serialize_entry("previousblockhash", &self.prev_block_hash())?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Block {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let size = serialize_block(self).len();
+        let witness_size: usize = self
+            .transactions
+            .iter()
+            .map(|tx| tx.size - tx.stripped_size())
+            .sum();
+        let stripped_size = size - witness_size;
+        let header = &self.header;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("hash", &header.block_hash())?;
+        map.serialize_entry("strippedsize", &stripped_size)?;
+        map.serialize_entry("size", &size)?;
+        map.serialize_entry("weight", &(stripped_size * 3 + size))?;
+        serialize_header_fields(&mut map, header)?;
+        map.serialize_entry("tx", &self.transactions)?;
+        serialize_target_fields(&mut map, header)?;
+        map.serialize_entry("nTx", &self.transactions.len())?;
+        if !header.prev_block_hash.is_zero() {
+            map.serialize_entry("previousblockhash", &header.prev_block_hash())?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        parsers::parse_transaction,
+        types::{Block, Transaction},
+    };
+    use serde_json::{json, Value};
+
+    //the JSON of an .rpc file, after its comment line
+    fn rpc(text: &str) -> Value {
+        let start = text.find('{').unwrap();
+        serde_json::from_str(&text[start..]).unwrap()
+    }
+
+    //fields of the fixtures that need the chain, and reqSigs and addresses of older bitcoind
+    const CHAIN_FIELDS: [&str; 11] = [
+        "confirmations",
+        "in_active_chain",
+        "height",
+        "chainwork",
+        "mediantime",
+        "nextblockhash",
+        "blockhash",
+        "blocktime",
+        "time",
+        "reqSigs",
+        "addresses",
+    ];
+
+    //ours and the fixture have the same fields with the same values, but for CHAIN_FIELDS
+    //older bitcoind printed an addresses array instead of address
+    fn assert_matches(ours: &Value, core: &Value, path: &str) {
+        match (ours, core) {
+            (Value::Object(ours), Value::Object(core)) => {
+                for key in core.keys() {
+                    assert!(
+                        ours.contains_key(key) || CHAIN_FIELDS.contains(&key.as_str()),
+                        "{}.{} is missing in ours",
+                        path,
+                        key
+                    );
+                }
+                for (key, value) in ours.iter() {
+                    let path = format!("{}.{}", path, key);
+                    match (key.as_str(), core.get(key)) {
+                        ("address", None) => {
+                            assert_eq!(&core["addresses"], &json!([value]), "{}", path)
+                        }
+                        (_, Some(core)) => assert_matches(value, core, &path),
+                        (_, None) => panic!("{} is missing in the fixture", path),
+                    }
+                }
+            }
+            (Value::Array(ours), Value::Array(core)) => {
+                assert_eq!(ours.len(), core.len(), "{}", path);
+                for (i, (ours, core)) in ours.iter().zip(core).enumerate() {
+                    assert_matches(ours, core, &format!("{}[{}]", path, i));
+                }
+            }
+            //amounts are printed the way bitcoind does
+            (Value::Number(ours), Value::Number(core)) if path.ends_with(".value") => {
+                assert_eq!(ours.to_string(), core.to_string(), "{}", path)
+            }
+            //bitcoind prints whole numbers without a fraction
+            (Value::Number(ours), Value::Number(core)) => {
+                assert_eq!(ours.as_f64(), core.as_f64(), "{}", path)
+            }
+            //getblock with verbosity 1 lists txids
+            (Value::Object(ours), Value::String(txid)) => {
+                assert_eq!(&ours["txid"], txid, "{}", path)
+            }
+            _ => assert_eq!(ours, core, "{}", path),
+        }
+    }
+
+    #[test]
+    fn test_transaction_json() {
+        let fixtures: [(&[u8], &str); 4] = [
+            (
+                include_bytes!("../test_data/tx_de06af29a80be52bb5f4b6c86998dcfdf0f9e7f66a1ebb7e9d20d65cc6785d8c.native_witness.bin"),
+                include_str!("../test_data/tx_de06af29a80be52bb5f4b6c86998dcfdf0f9e7f66a1ebb7e9d20d65cc6785d8c.native_witness.rpc"),
+            ),
+            (
+                include_bytes!("../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.bin"),
+                include_str!("../test_data/tx_827214460f979de7023be7cf82bc11fdf9130fec624b99bb0156f580328110b8.pre_segwit.rpc"),
+            ),
+            (
+                include_bytes!("../test_data/tx_9e48f98e0b27e09ccabf576076c01dc6277c3961c8f616dea154f6822fb17765_large_segwit.bin"),
+                include_str!("../test_data/tx_9e48f98e0b27e09ccabf576076c01dc6277c3961c8f616dea154f6822fb17765_large_segwit.rpc"),
+            ),
+            (
+                include_bytes!("../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"),
+                include_str!("../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.rpc"),
+            ),
+        ];
+        for (data, text) in fixtures.iter() {
+            let (_, tx) = parse_transaction(data).unwrap();
+            let json = serde_json::to_value(&tx).unwrap();
+            assert_matches(&json, &rpc(text), "tx");
+        }
+    }
+
+    #[test]
+    fn test_block_json() {
+        let hex = include_str!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.hex"
+        );
+        let block = Block::from_hex(hex).unwrap();
+        let json = serde_json::to_value(&block).unwrap();
+        let core = rpc(include_str!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.rpc"
+        ));
+        assert_matches(&json, &core, "block");
+        assert!(json.get("previousblockhash").is_none());
+        let coinbase = &json["tx"][0]["vin"][0];
+        assert_eq!(coinbase["coinbase"].as_str().unwrap().len(), 77 * 2);
+        assert!(coinbase.get("txid").is_none());
+        assert_eq!(json["tx"][0]["vout"][0]["value"].to_string(), "50.00000000");
+        assert_eq!(json["tx"][0]["vout"][0]["scriptPubKey"]["type"], "pubkey");
+
+        let header = serde_json::to_value(&block.header).unwrap();
+        //getblockheader prints the fields of getblock but those of the block itself
+        let mut core_header = core.clone();
+        for key in ["strippedsize", "size", "weight", "tx", "nTx"].iter() {
+            core_header.as_object_mut().unwrap().remove(*key);
+        }
+        assert_matches(&header, &core_header, "header");
+        let tx: Transaction = block.transactions[0].clone();
+        assert_eq!(serde_json::to_value(&tx).unwrap(), json["tx"][0]);
+    }
+}
@@ -1,10 +1,16 @@
 mod hash256;
 pub use self::hash256::Hash256;
+mod hash_types;
+pub use self::hash_types::{BlockHash, MerkleRoot, Txid, Wtxid};
 mod bytes;
 pub use self::bytes::Bytes;
+mod instruction;
+pub use self::instruction::Instruction;
 mod block_header;
 pub use self::block_header::BlockHeader;
 pub use self::block_header::BlockHeaderBuilder;
+mod out_point;
+pub use self::out_point::OutPoint;
 mod tx_input;
 pub use self::tx_input::TxInput;
 pub use self::tx_input::TxInputBuilder;
@@ -18,3 +24,8 @@ pub use self::transaction::Transaction;
 pub use self::transaction::TransactionBuilder;
 mod block;
 pub use self::block::Block;
+mod coinbase;
+pub use self::coinbase::Coinbase;
+pub use self::coinbase::PoolTags;
+#[cfg(feature = "serde")]
+mod json;
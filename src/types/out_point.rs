@@ -0,0 +1,14 @@
+use crate::types::Hash256;
+
+//an output of a transaction, ordered by txid in wire order and then vout like bitcoind's coin database
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub struct OutPoint {
+    pub txid: Hash256,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: Hash256, vout: u32) -> OutPoint {
+        OutPoint { txid, vout }
+    }
+}
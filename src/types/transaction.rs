@@ -1,14 +1,20 @@
-use crate::types::{Hash256, TxInput, TxOutput, Witness};
+use crate::{
+    parsers::parse_transaction,
+    serializers::{serialize_transaction, serialize_transaction_no_witness},
+    types::{Coinbase, Hash256, TxInput, TxOutput, Txid, Witness, Wtxid},
+    utils::{parse_hex, HexError, SighashCache, SighashError},
+    utxo::{FeeError, PrevoutProvider},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Transaction {
     pub version: u32,
     pub inputs: Vec<TxInput>,
     pub outputs: Vec<TxOutput>,
     pub witnesses: Option<Vec<Vec<Witness>>>,
     pub lock_time: u32,
-    pub txid: Hash256,
-    pub wtxid: Hash256,
+    pub txid: Txid,
+    pub wtxid: Wtxid,
     pub size: usize,
 }
 
@@ -29,11 +35,106 @@ impl Transaction {
             outputs,
             witnesses,
             lock_time,
-            txid,
-            wtxid,
+            txid: Txid(txid),
+            wtxid: Wtxid(wtxid),
             size,
         }
     }
+    //the hex of getrawtransaction or decoderawtransaction, with or without witnesses
+    pub fn from_hex(s: &str) -> Result<Transaction, HexError> {
+        parse_hex(s, parse_transaction)
+    }
+    pub fn to_hex(&self) -> String {
+        hex::encode(serialize_transaction(self))
+    }
+    pub fn txid(&self) -> Txid {
+        self.txid
+    }
+    //the same as the txid for a transaction without witnesses
+    pub fn wtxid(&self) -> Wtxid {
+        self.wtxid
+    }
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].is_coinbase()
+    }
+    pub fn coinbase(&self) -> Option<Coinbase<'_>> {
+        Coinbase::new(self)
+    }
+    //size without the witnesses, as seen by nodes from before segwit
+    pub fn stripped_size(&self) -> usize {
+        serialize_transaction_no_witness(self).len()
+    }
+    pub fn weight(&self) -> usize {
+        self.stripped_size() * 3 + self.size
+    }
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+    pub fn output_value(&self) -> u64 {
+        self.outputs.iter().map(|output| output.value).sum()
+    }
+    pub fn input_value<P: PrevoutProvider>(&self, provider: &P) -> Result<u64, FeeError> {
+        let mut value = 0;
+        for input in self.inputs.iter() {
+            let out_point = input.out_point();
+            match provider.prevout(&out_point)? {
+                Some(prevout) => value += prevout.value,
+                None => return Err(FeeError::MissingPrevout(out_point)),
+            }
+        }
+        Ok(value)
+    }
+    //coinbase transactions pay no fee
+    pub fn fee<P: PrevoutProvider>(&self, provider: &P) -> Result<u64, FeeError> {
+        if self.is_coinbase() {
+            return Ok(0);
+        }
+        let inputs = self.input_value(provider)?;
+        let outputs = self.output_value();
+        match inputs.checked_sub(outputs) {
+            Some(fee) => Ok(fee),
+            None => Err(FeeError::NegativeFee { inputs, outputs }),
+        }
+    }
+    //satoshis per virtual byte
+    pub fn fee_rate<P: PrevoutProvider>(&self, provider: &P) -> Result<f64, FeeError> {
+        Ok(self.fee(provider)? as f64 / self.vsize() as f64)
+    }
+    pub fn legacy_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        sighash_type: u32,
+    ) -> Hash256 {
+        SighashCache::new(self).legacy_sighash(input_index, script_code, sighash_type)
+    }
+    //use a SighashCache directly when signing several inputs of the same transaction
+    pub fn segwit_v0_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        amount: u64,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        SighashCache::new(self).segwit_v0_sighash(input_index, script_code, amount, sighash_type)
+    }
+    //prevouts are the outputs spent by each of the inputs, in order
+    pub fn taproot_sighash(
+        &self,
+        input_index: usize,
+        prevouts: &[TxOutput],
+        annex: Option<&[u8]>,
+        leaf_hash: Option<Hash256>,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        SighashCache::new(self).taproot_sighash(
+            input_index,
+            prevouts,
+            annex,
+            leaf_hash,
+            sighash_type,
+        )
+    }
 }
 
 impl std::default::Default for Transaction {
@@ -44,8 +145,8 @@ impl std::default::Default for Transaction {
             outputs: Vec::new(),
             witnesses: None,
             lock_time: 0,
-            txid: Hash256::default(),
-            wtxid: Hash256::default(),
+            txid: Txid::default(),
+            wtxid: Wtxid::default(),
             size: 0,
         }
     }
@@ -90,11 +191,11 @@ impl TransactionBuilder {
         self
     }
     pub fn txid<H: Into<Hash256>>(&mut self, hash: H) -> &mut Self {
-        self.tx.txid = hash.into();
+        self.tx.txid = Txid(hash.into());
         self
     }
     pub fn wtxid<H: Into<Hash256>>(&mut self, hash: H) -> &mut Self {
-        self.tx.wtxid = hash.into();
+        self.tx.wtxid = Wtxid(hash.into());
         self
     }
     pub fn size(&mut self, size: usize) -> &mut Self {
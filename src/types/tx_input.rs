@@ -1,4 +1,4 @@
-use crate::types::{Bytes, Hash256};
+use crate::types::{Bytes, Hash256, OutPoint, Txid};
 
 #[derive(Debug, Clone)]
 pub struct TxInput {
@@ -17,6 +17,16 @@ impl TxInput {
             sequence: seq,
         }
     }
+    //coinbase inputs spend the null outpoint
+    pub fn is_coinbase(&self) -> bool {
+        self.previous_tx_hash.is_zero() && self.vout == 0xffffffff
+    }
+    pub fn previous_txid(&self) -> Txid {
+        Txid(self.previous_tx_hash)
+    }
+    pub fn out_point(&self) -> OutPoint {
+        OutPoint::new(self.previous_tx_hash, self.vout)
+    }
 }
 
 impl std::default::Default for TxInput {
@@ -0,0 +1,177 @@
+use crate::{
+    script::opcodes::{
+        OP_0, OP_1, OP_16, OP_CHECKSIG, OP_DUP, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160,
+    },
+    utils::{base58check_decode, base58check_encode, segwit_decode, segwit_encode},
+};
+
+//address prefixes of the chains parse_magic_number knows: P2PKH version, P2SH version, segwit hrp
+const NETWORKS: [(&str, u8, u8, &str); 4] = [
+    ("mainnet", 0x00, 0x05, "bc"),
+    ("testnet", 0x6f, 0xc4, "tb"),
+    ("regtest", 0x6f, 0xc4, "bcrt"),
+    ("namecoin", 0x34, 0x0d, "nc"),
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AddressError {
+    //neither valid base58check nor valid bech32/bech32m
+    InvalidEncoding,
+    //a version byte or human readable part no known chain uses
+    UnknownPrefix,
+    InvalidLength,
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AddressError::InvalidEncoding => write!(f, "invalid address encoding or checksum"),
+            AddressError::UnknownPrefix => write!(f, "address of an unknown network"),
+            AddressError::InvalidLength => write!(f, "invalid address payload length"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+fn p2pkh(hash: &[u8]) -> Vec<u8> {
+    [
+        &[OP_DUP, OP_HASH160, 20][..],
+        hash,
+        &[OP_EQUALVERIFY, OP_CHECKSIG],
+    ]
+    .concat()
+}
+
+fn p2sh(hash: &[u8]) -> Vec<u8> {
+    [&[OP_HASH160, 20][..], hash, &[OP_EQUAL]].concat()
+}
+
+fn witness(version: u8, program: &[u8]) -> Vec<u8> {
+    let version = match version {
+        0 => OP_0,
+        v => OP_1 + v - 1,
+    };
+    [&[version, program.len() as u8][..], program].concat()
+}
+
+//the scriptPubKey an address pays to, for any of the known chains
+pub fn address_to_script(address: &str) -> Result<Vec<u8>, AddressError> {
+    if let Some((hrp, version, program)) = segwit_decode(address) {
+        return match NETWORKS.iter().any(|n| n.3 == hrp) {
+            true => Ok(witness(version, &program)),
+            false => Err(AddressError::UnknownPrefix),
+        };
+    }
+    let payload = base58check_decode(address).ok_or(AddressError::InvalidEncoding)?;
+    if payload.len() != 21 {
+        return Err(AddressError::InvalidLength);
+    }
+    let (version, hash) = (payload[0], &payload[1..]);
+    if NETWORKS.iter().any(|n| n.1 == version) {
+        Ok(p2pkh(hash))
+    } else if NETWORKS.iter().any(|n| n.2 == version) {
+        Ok(p2sh(hash))
+    } else {
+        Err(AddressError::UnknownPrefix)
+    }
+}
+
+//the address of a standard scriptPubKey on chain, none for bare multisig, pay to pubkey and nonstandard scripts
+pub fn script_to_address(script: &[u8], chain: &str) -> Option<String> {
+    let (_, pubkey_version, script_version, hrp) = NETWORKS.iter().find(|n| n.0 == chain)?;
+    match script {
+        [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            Some(base58check_encode(&[&[*pubkey_version][..], hash].concat()))
+        }
+        [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+            Some(base58check_encode(&[&[*script_version][..], hash].concat()))
+        }
+        [version, length, program @ ..]
+            if (*version == OP_0 || (OP_1..=OP_16).contains(version))
+                && usize::from(*length) == program.len()
+                && (2..=40).contains(&program.len()) =>
+        {
+            let version = match *version {
+                OP_0 => 0,
+                v => v - OP_1 + 1,
+            };
+            if version == 0 && program.len() != 20 && program.len() != 32 {
+                return None;
+            }
+            Some(segwit_encode(hrp, version, program))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex;
+    #[test]
+    fn test_address_to_script() {
+        for (address, script, chain) in [
+            (
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                "mainnet",
+            ),
+            (
+                "367f4YWz1VCFaqBqwbTrzwi2b1h2U3w1AF",
+                "a91430897cc6c9d69f6a2c2f1c651d51f22219f1a4f687",
+                "mainnet",
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                "mainnet",
+            ),
+            (
+                "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                "regtest",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                "testnet",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "mainnet",
+            ),
+        ]
+        .iter()
+        {
+            let script = hex::decode(script).unwrap();
+            assert_eq!(address_to_script(address), Ok(script.clone()));
+            assert_eq!(script_to_address(&script, chain).as_deref(), Some(*address));
+        }
+
+        assert_eq!(
+            address_to_script("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            Err(AddressError::InvalidEncoding)
+        );
+        //a bech32 address of a chain without a known prefix
+        assert_eq!(
+            address_to_script(&segwit_encode("xy", 0, &[0; 20])),
+            Err(AddressError::UnknownPrefix)
+        );
+        assert_eq!(
+            address_to_script(&base58check_encode(&[0; 20])),
+            Err(AddressError::InvalidLength)
+        );
+        //pay to pubkey has no address
+        let p2pk = hex::decode("4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac").unwrap();
+        assert_eq!(script_to_address(&p2pk, "mainnet"), None);
+        assert_eq!(
+            script_to_address(
+                &hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+                "unknown"
+            ),
+            None
+        );
+    }
+}
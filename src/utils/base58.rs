@@ -0,0 +1,99 @@
+use crate::utils::hash256;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+//the payload followed by the first four bytes of its hash256
+pub fn base58check_encode(payload: &[u8]) -> String {
+    let checksum = hash256(payload);
+    let data = [payload, &checksum.0[..4]].concat();
+    //digits in base 58, least significant first
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in data.iter() {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    //every leading zero byte is written as a leading 1
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n(b'1', zeros)
+        .chain(digits.iter().rev().map(|d| ALPHABET[*d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+//none if the string has characters outside of the alphabet or the checksum does not match
+pub fn base58check_decode(s: &str) -> Option<Vec<u8>> {
+    //bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.bytes() {
+        let mut carry = ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    let data: Vec<u8> = std::iter::repeat_n(0, zeros)
+        .chain(bytes.into_iter().rev())
+        .collect();
+    if data.len() < 4 {
+        return None;
+    }
+    let (payload, checksum) = data.split_at(data.len() - 4);
+    match hash256(payload).0[..4] == *checksum {
+        true => Some(payload.to_vec()),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex;
+    #[test]
+    fn test_base58check() {
+        //the address paid by the genesis coinbase
+        let payload = hex::decode("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert_eq!(
+            base58check_encode(&payload),
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+        );
+        assert_eq!(
+            base58check_decode("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"),
+            Some(payload)
+        );
+        //a changed character breaks the checksum
+        assert_eq!(
+            base58check_decode("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            None
+        );
+        //0, O, I and l are not part of the alphabet
+        assert_eq!(
+            base58check_decode("1A1zP1eP5QGefi2DMPTfTL5SLmv7Div0Na"),
+            None
+        );
+        assert_eq!(base58check_decode(""), None);
+
+        let payload = hex::decode("0530897cc6c9d69f6a2c2f1c651d51f22219f1a4f6").unwrap();
+        assert_eq!(
+            base58check_encode(&payload),
+            "367f4YWz1VCFaqBqwbTrzwi2b1h2U3w1AF"
+        );
+        assert_eq!(
+            base58check_decode(&base58check_encode(&[0, 0, 1])),
+            Some(vec![0, 0, 1])
+        );
+    }
+}
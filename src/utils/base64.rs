@@ -0,0 +1,79 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+//the standard alphabet with padding, as used for PSBTs
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from(group[0]) << 16 | u32::from(group[1]) << 8 | u32::from(group[2]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(char::from(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize])),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+//none unless the length is a multiple of 4 with only the padding needed and no bits set in it
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 4 != 0 {
+        return None;
+    }
+    let padding = s.iter().rev().take_while(|c| **c == b'=').count();
+    if padding > 2 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(s.len() / 4 * 3);
+    for (n, chunk) in s.chunks(4).enumerate() {
+        let last = n == s.len() / 4 - 1;
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = match ALPHABET.iter().position(|a| a == c) {
+                Some(value) => value as u32,
+                None if last && *c == b'=' && i >= 4 - padding => 0,
+                None => return None,
+            };
+            bits = bits << 6 | value;
+        }
+        let bytes = bits.to_be_bytes();
+        let length = if last { 3 - padding } else { 3 };
+        //the bits of the last character not used by the bytes have to be zero
+        if bits & ((1 << (8 * (3 - length))) - 1) != 0 {
+            return None;
+        }
+        decoded.extend(&bytes[1..1 + length]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        //the RFC 4648 vectors
+        for (data, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ]
+        .iter()
+        {
+            assert_eq!(base64_encode(data.as_bytes()), *encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), data.as_bytes());
+        }
+        assert_eq!(base64_decode("/+8="), Some(vec![0xff, 0xef]));
+        for invalid in ["Zg=", "Zg", "Z===", "Zh==", "Zm9v!A==", "Zg==Zm9v", "Z=g="].iter() {
+            assert_eq!(base64_decode(invalid), None, "{}", invalid);
+        }
+    }
+}
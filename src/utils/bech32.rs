@@ -0,0 +1,188 @@
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const MAX_LENGTH: usize = 90;
+
+//BIP173 checksums are used by witness version 0, BIP350 bech32m by all later versions
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Bech32Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Bech32Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Bech32Variant::Bech32 => 1,
+            Bech32Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 31))
+}
+
+//regroups bits, when not padding leftover bits have to be zero padding of less than from bits
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let max = (1u32 << to) - 1;
+    let mut result = Vec::new();
+    for value in data {
+        acc = (acc << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return None;
+    }
+    Some(result)
+}
+
+fn encode(hrp: &str, data: &[u8], variant: Bech32Variant) -> String {
+    let values = hrp_expand(hrp)
+        .chain(data.iter().copied())
+        .chain([0; 6].iter().copied());
+    let checksum = polymod(values) ^ variant.constant();
+    let checksum = (0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8);
+    let data: String = data
+        .iter()
+        .copied()
+        .chain(checksum)
+        .map(|d| char::from(CHARSET[d as usize]))
+        .collect();
+    format!("{}1{}", hrp, data)
+}
+
+//returns the lowercase human readable part and the 5 bit values without the checksum
+fn decode(s: &str) -> Option<(String, Vec<u8>, Bech32Variant)> {
+    if s.len() > MAX_LENGTH || !s.bytes().all(|c| (33..=126).contains(&c)) {
+        return None;
+    }
+    //either all upper or all lower case
+    if s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1')?;
+    if separator == 0 || separator + 7 > s.len() {
+        return None;
+    }
+    let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+    let data = data
+        .bytes()
+        .map(|c| CHARSET.iter().position(|d| *d == c).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let checksum = polymod(hrp_expand(hrp).chain(data.iter().copied()));
+    let variant = match checksum {
+        c if c == Bech32Variant::Bech32.constant() => Bech32Variant::Bech32,
+        c if c == Bech32Variant::Bech32m.constant() => Bech32Variant::Bech32m,
+        _ => return None,
+    };
+    Some((hrp.to_string(), data[..data.len() - 6].to_vec(), variant))
+}
+
+//the address of a witness program, see BIP173 and BIP350
+pub fn segwit_encode(hrp: &str, version: u8, program: &[u8]) -> String {
+    let variant = match version {
+        0 => Bech32Variant::Bech32,
+        _ => Bech32Variant::Bech32m,
+    };
+    let mut data = vec![version];
+    data.extend(convert_bits(program, 8, 5, true).unwrap_or_default());
+    encode(hrp, &data, variant)
+}
+
+//returns the human readable part, the witness version and the witness program
+pub fn segwit_decode(s: &str) -> Option<(String, u8, Vec<u8>)> {
+    let (hrp, data, variant) = decode(s)?;
+    let (version, data) = data.split_first()?;
+    let program = convert_bits(data, 5, 8, false)?;
+    let valid = match version {
+        0 => variant == Bech32Variant::Bech32 && (program.len() == 20 || program.len() == 32),
+        1..=16 => variant == Bech32Variant::Bech32m && (2..=40).contains(&program.len()),
+        _ => false,
+    };
+    match valid {
+        true => Some((hrp, *version, program)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex;
+    #[test]
+    fn test_segwit_address() {
+        //valid addresses from BIP173 and BIP350
+        let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        assert_eq!(
+            segwit_encode("bc", 0, &program),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            segwit_decode("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"),
+            Some(("bc".to_string(), 0, program))
+        );
+        let program =
+            hex::decode("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262")
+                .unwrap();
+        assert_eq!(
+            segwit_decode("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"),
+            Some(("tb".to_string(), 0, program))
+        );
+        let program =
+            hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        let address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        assert_eq!(segwit_encode("bc", 1, &program), address);
+        assert_eq!(segwit_decode(address), Some(("bc".to_string(), 1, program)));
+
+        //invalid addresses from BIP173 and BIP350
+        for address in [
+            //mixed case
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7",
+            //invalid checksum
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+            //version 0 with a bech32m checksum
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            //version 1 with a bech32 checksum
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            //invalid program length
+            "bc1rw5uspcuh",
+            //zero padding of more than 4 bits
+            "bc1zw508d6qejxtdg4y5r3zarqfsj7",
+            //empty data
+            "bc1gmk9yu",
+        ]
+        .iter()
+        {
+            assert_eq!(segwit_decode(address), None, "{}", address);
+        }
+    }
+}
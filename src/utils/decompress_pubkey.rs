@@ -0,0 +1,149 @@
+//just enough secp256k1 field arithmetic to recover y from x, numbers are little endian u64 limbs
+type FieldElement = [u64; 4];
+
+const P: FieldElement = [
+    0xFFFFFFFEFFFFFC2F,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+//2^256 - p, as 2^256 = 0x1000003d1 mod p
+const C: u128 = 0x1000003D1;
+//(p + 1) / 4, as p = 3 mod 4 the square root of a is a^((p + 1) / 4)
+const SQRT_EXPONENT: FieldElement = [
+    0xFFFFFFFFBFFFFF0C,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0x3FFFFFFFFFFFFFFF,
+];
+
+fn add_c(a: &mut FieldElement, mut carry: u128) -> u128 {
+    for limb in a.iter_mut() {
+        let v = *limb as u128 + carry;
+        *limb = v as u64;
+        carry = v >> 64;
+    }
+    carry
+}
+
+fn is_at_least_p(a: &FieldElement) -> bool {
+    a[1..] == P[1..] && a[0] >= P[0]
+}
+
+fn mul(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut t = [0u64; 8];
+    for i in 0..4 {
+        let mut carry: u128 = 0;
+        for j in 0..4 {
+            let v = t[i + j] as u128 + a[i] as u128 * b[j] as u128 + carry;
+            t[i + j] = v as u64;
+            carry = v >> 64;
+        }
+        t[i + 4] = carry as u64;
+    }
+    //fold the upper half onto the lower one twice, multiplying it by 2^256 mod p
+    let mut r = [0u64; 4];
+    let mut carry: u128 = 0;
+    for i in 0..4 {
+        let v = t[i] as u128 + t[i + 4] as u128 * C + carry;
+        r[i] = v as u64;
+        carry = v >> 64;
+    }
+    while carry > 0 {
+        carry = add_c(&mut r, carry * C);
+    }
+    if is_at_least_p(&r) {
+        add_c(&mut r, C);
+    }
+    r
+}
+
+fn pow(a: &FieldElement, exponent: &FieldElement) -> FieldElement {
+    let mut result = [1, 0, 0, 0];
+    for limb in exponent.iter().rev() {
+        for bit in (0..64).rev() {
+            result = mul(&result, &result);
+            if limb >> bit & 1 == 1 {
+                result = mul(&result, a);
+            }
+        }
+    }
+    result
+}
+
+fn from_be_bytes(bytes: &[u8]) -> FieldElement {
+    let mut a = [0u64; 4];
+    for (i, limb) in a.iter_mut().enumerate() {
+        let mut limb_bytes = [0u8; 8];
+        limb_bytes.copy_from_slice(&bytes[24 - 8 * i..32 - 8 * i]);
+        *limb = u64::from_be_bytes(limb_bytes);
+    }
+    a
+}
+
+fn to_be_bytes(a: &FieldElement) -> Vec<u8> {
+    a.iter().rev().flat_map(|limb| limb.to_be_bytes()).collect()
+}
+
+//turns a 33 byte compressed public key into the 65 byte uncompressed one
+//None if the key is not on the curve
+pub fn decompress_pubkey(pubkey: &[u8]) -> Option<Vec<u8>> {
+    if pubkey.len() != 33 || (pubkey[0] != 0x02 && pubkey[0] != 0x03) {
+        return None;
+    }
+    let x = from_be_bytes(&pubkey[1..]);
+    if is_at_least_p(&x) {
+        return None;
+    }
+    //y^2 = x^3 + 7
+    let mut y_squared = mul(&mul(&x, &x), &x);
+    add_c(&mut y_squared, 7);
+    if is_at_least_p(&y_squared) {
+        add_c(&mut y_squared, C);
+    }
+    let mut y = pow(&y_squared, &SQRT_EXPONENT);
+    if mul(&y, &y) != y_squared {
+        return None;
+    }
+    if (y[0] & 1) as u8 != pubkey[0] & 1 {
+        //p - y, never zero as 7 is not a cube
+        let mut borrow = 0u128;
+        for i in 0..4 {
+            let v = (P[i] as u128).wrapping_sub(y[i] as u128 + borrow);
+            y[i] = v as u64;
+            borrow = (v >> 127) & 1;
+        }
+    }
+    Some([&[0x04][..], &pubkey[1..], &to_be_bytes(&y)].concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_decompress_pubkey() {
+        //the key of the genesis coinbase output
+        let pubkey = "04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f";
+        let compressed = hex::decode(format!("03{}", &pubkey[2..66])).unwrap();
+        assert_eq!(hex::encode(decompress_pubkey(&compressed).unwrap()), pubkey);
+        let compressed = hex::decode(format!("02{}", &pubkey[2..66])).unwrap();
+        let uncompressed = decompress_pubkey(&compressed).unwrap();
+        assert_eq!(uncompressed[..33], hex::decode(pubkey).unwrap()[..33]);
+        assert_eq!(uncompressed[64] & 1, 0);
+
+        //the generator point
+        let generator = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        let compressed = hex::decode(format!("02{}", &generator[2..66])).unwrap();
+        assert_eq!(
+            hex::encode(decompress_pubkey(&compressed).unwrap()),
+            generator
+        );
+
+        //x = 5 is not on the curve, 5^3 + 7 is not a square
+        let mut compressed = vec![0x02; 33];
+        compressed[1..].copy_from_slice(&[0; 32]);
+        compressed[32] = 5;
+        assert_eq!(decompress_pubkey(&compressed), None);
+        assert_eq!(decompress_pubkey(&[0x04; 33]), None);
+    }
+}
@@ -1,27 +1,40 @@
-use crate::parsers::parse_magic_number;
-use nom::combinator::peek;
-use nom::number::complete::le_u8;
-use nom::IResult;
-
-pub fn find_block_start(mut input: &[u8]) -> IResult<&[u8], Option<&str>> {
-    //move per byte untill magic number is found
-    loop {
-        match peek(parse_magic_number)(input)?.1 {
-            Some(_) => {
-                break;
-            }
-            None => {
-                input = le_u8(input)?.0;
-            }
+use crate::parsers::chain_name;
+use memchr::memchr3;
+use nom::{error::ErrorKind, Err, IResult};
+
+//the first bytes of the magic numbers in MAGIC_NUMBERS, searched for all at once
+const FIRST_BYTES: [u8; 3] = [0xf9, 0xfa, 0x0b];
+
+//finds the next known magic number, returning the rest after it, its chain and how many bytes came before it
+//memchr only stops at a byte a magic number starts with, so long zero runs are skipped many bytes at a time
+pub fn scan_block_start(input: &[u8]) -> IResult<&[u8], (Option<&str>, usize)> {
+    let [a, b, c] = FIRST_BYTES;
+    let mut position = 0;
+    while let Some(found) = memchr3(a, b, c, &input[position..]) {
+        let start = position + found;
+        let magic = match input.get(start..start + 4) {
+            Some(magic) => u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]),
+            None => break,
         };
+        if let Some(chain) = chain_name(magic) {
+            return Ok((&input[start + 4..], (Some(chain), start)));
+        }
+        position = start + 1;
     }
-    let (input, chain) = parse_magic_number(input)?;
+    //like reading a magic number from the last bytes, too few for one
+    let rest = &input[input.len().saturating_sub(3)..];
+    Err(Err::Error((rest, ErrorKind::Eof)))
+}
+
+pub fn find_block_start(input: &[u8]) -> IResult<&[u8], Option<&str>> {
+    let (input, (chain, _)) = scan_block_start(input)?;
     Ok((input, chain))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::parsers::MAGIC_NUMBERS;
     use hex;
     #[test]
     fn test_find_block_start() {
@@ -49,4 +62,23 @@ mod test {
         let data = &hex::decode("010101001010100709110Baabbccddeeff").unwrap();
         assert_eq!(true, find_block_start(data).is_err());
     }
+
+    #[test]
+    fn test_scan_block_start() {
+        for (magic, _) in MAGIC_NUMBERS.iter() {
+            assert!(FIRST_BYTES.contains(&magic.to_le_bytes()[0]));
+        }
+        //the zero padding of a preallocated file
+        let mut data = vec![0; 1 << 20];
+        data.extend_from_slice(&[0xf9, 0xbe, 0xb4, 0xf9, 0xbe, 0xb4, 0xd9, 0xaa]);
+        let (rest, (chain, skipped)) = scan_block_start(&data).unwrap();
+        assert_eq!(rest, &[0xaa][..]);
+        assert_eq!(chain, Some("mainnet"));
+        assert_eq!(skipped, (1 << 20) + 3);
+        let (_, (_, skipped)) = scan_block_start(&[0xfa, 0xbf, 0xb5, 0xda]).unwrap();
+        assert_eq!(skipped, 0);
+        assert!(scan_block_start(&data[..1 << 20]).is_err());
+        assert!(scan_block_start(&[0xf9, 0xbe, 0xb4]).is_err());
+        assert!(scan_block_start(&[]).is_err());
+    }
 }
@@ -0,0 +1,34 @@
+use crate::utils::sha256;
+use ripemd::{Digest, Ripemd160};
+
+pub fn ripemd160(input: &[u8]) -> [u8; 20] {
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(&Ripemd160::digest(input));
+    hash
+}
+
+//ripemd160(sha256(data)), used for public key and script hashes
+pub fn hash160(input: &[u8]) -> [u8; 20] {
+    ripemd160(sha256(input).as_ref())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex;
+    #[test]
+    fn test_hash160() {
+        assert_eq!(
+            hex::encode(ripemd160(&[])),
+            "9c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        //the pubkey and pubkey hash of the BIP143 P2WPKH example
+        let pubkey =
+            hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")
+                .unwrap();
+        assert_eq!(
+            hex::encode(hash160(&pubkey)),
+            "1d0f172a0ecb48aee1be1f2687d2963ae33f71a1"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use nom::{Err, IResult};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HexError {
+    OddLength,
+    //the character and its position in the string
+    InvalidCharacter(char, usize),
+    //the bytes do not parse, with the offset the parser failed at
+    Malformed(usize),
+    //the data parsed but this many bytes were left after it
+    TrailingBytes(usize),
+    //the number of bytes of a value with a fixed size
+    InvalidLength(usize),
+}
+
+impl std::fmt::Display for HexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HexError::OddLength => write!(f, "hex string of odd length"),
+            HexError::InvalidCharacter(c, i) => {
+                write!(f, "invalid hex character {:?} at position {}", c, i)
+            }
+            HexError::Malformed(offset) => write!(f, "malformed data at byte {}", offset),
+            HexError::TrailingBytes(n) => write!(f, "{} trailing bytes after the data", n),
+            HexError::InvalidLength(n) => write!(f, "invalid length of {} bytes", n),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+//surrounding whitespace is ignored, as in the output of bitcoin-cli
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, HexError> {
+    hex::decode(s.trim()).map_err(|e| match e {
+        hex::FromHexError::InvalidHexCharacter { c, index } => {
+            let start = s.len() - s.trim_start().len();
+            HexError::InvalidCharacter(c, start + index)
+        }
+        _ => HexError::OddLength,
+    })
+}
+
+//decodes s and parses it with parser, which has to take all of it
+pub fn parse_hex<T, F>(s: &str, parser: F) -> Result<T, HexError>
+where
+    F: Fn(&[u8]) -> IResult<&[u8], T>,
+{
+    let data = decode_hex(s)?;
+    match parser(&data) {
+        Ok(([], value)) => Ok(value),
+        Ok((rest, _)) => Err(HexError::TrailingBytes(rest.len())),
+        Err(Err::Error((rest, _))) | Err(Err::Failure((rest, _))) => {
+            Err(HexError::Malformed(data.len() - rest.len()))
+        }
+        Err(Err::Incomplete(_)) => Err(HexError::Malformed(data.len())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Block, BlockHeader, Transaction};
+    use nom::number::complete::le_u16;
+
+    fn parse_u16(input: &[u8]) -> IResult<&[u8], u16> {
+        le_u16(input)
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(decode_hex(" 00ff\n"), Ok(vec![0, 0xff]));
+        assert_eq!(decode_hex("0ff"), Err(HexError::OddLength));
+        assert_eq!(decode_hex(" 00fg"), Err(HexError::InvalidCharacter('g', 4)));
+        assert_eq!(parse_hex("3412", parse_u16), Ok(0x1234));
+        assert_eq!(
+            parse_hex("341200", parse_u16),
+            Err(HexError::TrailingBytes(1))
+        );
+        assert_eq!(parse_hex("34", parse_u16), Err(HexError::Malformed(0)));
+    }
+
+    #[test]
+    fn test_from_hex() {
+        let data = include_bytes!(
+            "../test_data/tx_fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54.segwit.bin"
+        );
+        let s = hex::encode(&data[..]);
+        let tx = Transaction::from_hex(&format!("{}\n", s)).unwrap();
+        assert_eq!(
+            format!("{:?}", tx.txid),
+            "Txid(fb042de1f26d3ea4df6a5d7c7b8bb3463d49ac32400df4b881ad87d922a6be54)"
+        );
+        assert_eq!(tx.to_hex(), s);
+        assert_eq!(
+            Transaction::from_hex(&format!("{}00", s)).unwrap_err(),
+            HexError::TrailingBytes(1)
+        );
+        assert_eq!(
+            Transaction::from_hex(&s[..s.len() - 1]).unwrap_err(),
+            HexError::OddLength
+        );
+        assert!(matches!(
+            Transaction::from_hex(&s[..s.len() - 2]),
+            Err(HexError::Malformed(_))
+        ));
+        //a count larger than the input is an error, not an allocation
+        assert!(matches!(
+            Transaction::from_hex("01000000ffffffffffffffff7f"),
+            Err(HexError::Malformed(_))
+        ));
+        assert!(matches!(
+            Transaction::from_hex("0100000000ffffffffffffffff7f"),
+            Err(HexError::Malformed(_))
+        ));
+
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let s = hex::encode(&data[..]);
+        let block = Block::from_hex(&s).unwrap();
+        assert_eq!(block.to_hex(), s);
+        let header = BlockHeader::from_hex(&s[..160]).unwrap();
+        assert_eq!(header.hash, block.header.hash);
+        assert_eq!(header.to_hex(), s[..160]);
+        assert_eq!(
+            BlockHeader::from_hex(&s).unwrap_err(),
+            HexError::TrailingBytes(data.len() - 80)
+        );
+        assert_eq!(
+            BlockHeader::from_hex(&s[..158]).unwrap_err(),
+            HexError::Malformed(76)
+        );
+        assert_eq!(
+            BlockHeader::from_hex(&format!("x{}", &s[1..160])).unwrap_err(),
+            HexError::InvalidCharacter('x', 0)
+        );
+    }
+}
@@ -1,6 +1,31 @@
 mod find_block_start;
-pub use find_block_start::find_block_start;
+pub use find_block_start::{find_block_start, scan_block_start};
 mod hash256;
 pub use hash256::hash256;
 mod calculate_merkle_root;
 pub use calculate_merkle_root::calculate_merkle_root;
+mod sha256;
+pub use sha256::sha256;
+mod decompress_pubkey;
+pub use decompress_pubkey::decompress_pubkey;
+mod hash160;
+pub use hash160::{hash160, ripemd160};
+mod tagged_hash;
+pub use tagged_hash::{tagged_hash, tap_branch_hash, tap_leaf_hash};
+mod sighash;
+pub use sighash::{
+    SighashCache, SighashError, SIGHASH_ALL, SIGHASH_ANYONECANPAY, SIGHASH_DEFAULT, SIGHASH_NONE,
+    SIGHASH_SINGLE,
+};
+mod base58;
+pub use base58::{base58check_decode, base58check_encode};
+mod bech32;
+pub use bech32::{segwit_decode, segwit_encode};
+mod address;
+pub use address::{address_to_script, script_to_address, AddressError};
+mod siphash;
+pub use siphash::siphash_2_4;
+mod base64;
+pub use base64::{base64_decode, base64_encode};
+mod hex;
+pub use self::hex::{decode_hex, parse_hex, HexError};
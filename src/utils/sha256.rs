@@ -0,0 +1,27 @@
+use crate::types::Hash256;
+use ring::digest::digest;
+use ring::digest::SHA256;
+
+//single sha256, used by taproot where hash256 is not
+pub fn sha256(input: &[u8]) -> Hash256 {
+    Hash256::new(digest(&SHA256, input).as_ref())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::hash256;
+    use hex;
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(&[]),
+            Hash256::new(
+                &hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+                    .unwrap()
+            )
+        );
+        let data = "this is a test\n".as_bytes();
+        assert_eq!(sha256(sha256(data).as_ref()), hash256(data));
+    }
+}
@@ -0,0 +1,753 @@
+use crate::{
+    parsers::parse_instruction,
+    script::opcodes::OP_CODESEPARATOR,
+    serializers::{serialize_transaction_no_witness, serialize_tx_output, serialize_var_int},
+    types::{Bytes, Hash256, Transaction, TxInput, TxOutput},
+    utils::{hash256, sha256, tagged_hash},
+};
+
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+//taproot only, signs the same as SIGHASH_ALL but leaves the hash type byte out of the signature
+pub const SIGHASH_DEFAULT: u32 = 0x00;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SighashError {
+    InputIndexOutOfRange(usize),
+    //taproot needs the spent output of every input
+    PrevoutsCountMismatch { inputs: usize, prevouts: usize },
+    InvalidSighashType(u32),
+    //taproot forbids SIGHASH_SINGLE without a matching output
+    SingleWithoutOutput(usize),
+}
+
+impl std::fmt::Display for SighashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SighashError::InputIndexOutOfRange(index) => {
+                write!(f, "input index {} out of range", index)
+            }
+            SighashError::PrevoutsCountMismatch { inputs, prevouts } => write!(
+                f,
+                "{} prevouts given for a transaction with {} inputs",
+                prevouts, inputs
+            ),
+            SighashError::InvalidSighashType(sighash_type) => {
+                write!(f, "invalid sighash type {:#x}", sighash_type)
+            }
+            SighashError::SingleWithoutOutput(index) => {
+                write!(f, "SIGHASH_SINGLE without an output for input {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SighashError {}
+
+//the uint256 "1" returned by the original algorithm instead of failing
+fn sighash_one() -> Hash256 {
+    let mut one = [0u8; 32];
+    one[0] = 1;
+    Hash256(one)
+}
+
+//scriptCode with all OP_CODESEPARATORs removed, an unparsable tail is kept as is
+fn strip_code_separators(script_code: &[u8]) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(script_code.len());
+    let mut input = script_code;
+    while !input.is_empty() {
+        let start = input;
+        match parse_instruction(input) {
+            Ok((i, instruction)) => {
+                if instruction.opcode() != OP_CODESEPARATOR || instruction.push_data().is_some() {
+                    vec.extend(&start[..start.len() - i.len()]);
+                }
+                input = i;
+            }
+            Err(_) => {
+                vec.extend(input);
+                break;
+            }
+        }
+    }
+    vec
+}
+
+//precomputed parts of the BIP143 and BIP341 signature messages, shared by all inputs of a transaction
+//BIP341 uses single sha256 of the same data BIP143 double hashes, so only the single hashes are kept
+pub struct SighashCache<'a> {
+    tx: &'a Transaction,
+    sha_prevouts: Option<Hash256>,
+    sha_sequences: Option<Hash256>,
+    sha_outputs: Option<Hash256>,
+    sha_amounts: Option<Hash256>,
+    sha_script_pub_keys: Option<Hash256>,
+}
+
+impl<'a> SighashCache<'a> {
+    pub fn new(tx: &'a Transaction) -> SighashCache<'a> {
+        SighashCache {
+            tx,
+            sha_prevouts: None,
+            sha_sequences: None,
+            sha_outputs: None,
+            sha_amounts: None,
+            sha_script_pub_keys: None,
+        }
+    }
+
+    //the original pre-segwit algorithm, bugs included:
+    //an out of range input or SIGHASH_SINGLE without a matching output signs the value 1
+    pub fn legacy_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        sighash_type: u32,
+    ) -> Hash256 {
+        let tx = self.tx;
+        let base_type = sighash_type & 0x1f;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        if input_index >= tx.inputs.len()
+            || (base_type == SIGHASH_SINGLE && input_index >= tx.outputs.len())
+        {
+            return sighash_one();
+        }
+        let script_code = strip_code_separators(script_code);
+        let signed_input = |index: usize, input: &TxInput| {
+            let (script_sig, sequence) = match index == input_index {
+                true => (Bytes::new(&script_code), input.sequence),
+                false if base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE => {
+                    (Bytes::default(), 0)
+                }
+                false => (Bytes::default(), input.sequence),
+            };
+            TxInput {
+                script_sig,
+                sequence,
+                ..input.clone()
+            }
+        };
+        let inputs = match anyone_can_pay {
+            true => vec![signed_input(input_index, &tx.inputs[input_index])],
+            false => tx
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(index, input)| signed_input(index, input))
+                .collect(),
+        };
+        let outputs = match base_type {
+            SIGHASH_NONE => Vec::new(),
+            //outputs before the signed one are blanked to value -1 and an empty script
+            SIGHASH_SINGLE => (0..=input_index)
+                .map(|index| match index == input_index {
+                    true => tx.outputs[index].clone(),
+                    false => TxOutput::new(u64::MAX, &[]),
+                })
+                .collect(),
+            _ => tx.outputs.clone(),
+        };
+        let stripped = Transaction {
+            version: tx.version,
+            inputs,
+            outputs,
+            witnesses: None,
+            lock_time: tx.lock_time,
+            ..Transaction::default()
+        };
+        hash256(
+            &[
+                &serialize_transaction_no_witness(&stripped)[..],
+                &sighash_type.to_le_bytes(),
+            ]
+            .concat(),
+        )
+    }
+
+    //BIP143, script_code is given without its length prefix
+    pub fn segwit_v0_sighash(
+        &mut self,
+        input_index: usize,
+        script_code: &[u8],
+        amount: u64,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        let tx = self.tx;
+        let input = tx
+            .inputs
+            .get(input_index)
+            .ok_or(SighashError::InputIndexOutOfRange(input_index))?;
+        let base_type = sighash_type & 0x1f;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+
+        let hash_prevouts = match anyone_can_pay {
+            true => Hash256::default(),
+            false => self.hash_prevouts(),
+        };
+        let hash_sequence =
+            match anyone_can_pay || base_type == SIGHASH_SINGLE || base_type == SIGHASH_NONE {
+                true => Hash256::default(),
+                false => self.hash_sequence(),
+            };
+        let hash_outputs = match base_type {
+            SIGHASH_SINGLE if input_index < tx.outputs.len() => {
+                hash256(&serialize_tx_output(&tx.outputs[input_index]))
+            }
+            SIGHASH_SINGLE | SIGHASH_NONE => Hash256::default(),
+            _ => self.hash_outputs(),
+        };
+        Ok(hash256(
+            &[
+                &tx.version.to_le_bytes()[..],
+                hash_prevouts.as_ref(),
+                hash_sequence.as_ref(),
+                input.previous_tx_hash.as_ref(),
+                &input.vout.to_le_bytes(),
+                &serialize_var_int(script_code.len() as u64),
+                script_code,
+                &amount.to_le_bytes(),
+                &input.sequence.to_le_bytes(),
+                hash_outputs.as_ref(),
+                &tx.lock_time.to_le_bytes(),
+                &sighash_type.to_le_bytes(),
+            ]
+            .concat(),
+        ))
+    }
+
+    //BIP341 with the default codeseparator position, leaf_hash is given for script path spends
+    pub fn taproot_sighash(
+        &mut self,
+        input_index: usize,
+        prevouts: &[TxOutput],
+        annex: Option<&[u8]>,
+        leaf_hash: Option<Hash256>,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        let script_path = leaf_hash.map(|leaf_hash| (leaf_hash, 0xffffffff));
+        self.taproot_message_hash(input_index, prevouts, annex, script_path, sighash_type)
+    }
+
+    //BIP341 script path spend after an executed OP_CODESEPARATOR at opcode position codesep_pos
+    pub fn taproot_sighash_with_codesep(
+        &mut self,
+        input_index: usize,
+        prevouts: &[TxOutput],
+        annex: Option<&[u8]>,
+        leaf_hash: Hash256,
+        codesep_pos: u32,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        let script_path = Some((leaf_hash, codesep_pos));
+        self.taproot_message_hash(input_index, prevouts, annex, script_path, sighash_type)
+    }
+
+    fn taproot_message_hash(
+        &mut self,
+        input_index: usize,
+        prevouts: &[TxOutput],
+        annex: Option<&[u8]>,
+        script_path: Option<(Hash256, u32)>,
+        sighash_type: u32,
+    ) -> Result<Hash256, SighashError> {
+        let msg = self.taproot_sig_msg(input_index, prevouts, annex, script_path, sighash_type)?;
+        Ok(tagged_hash("TapSighash", &msg))
+    }
+
+    //the epoch byte, then the signature message
+    fn taproot_sig_msg(
+        &mut self,
+        input_index: usize,
+        prevouts: &[TxOutput],
+        annex: Option<&[u8]>,
+        script_path: Option<(Hash256, u32)>,
+        sighash_type: u32,
+    ) -> Result<Vec<u8>, SighashError> {
+        let tx = self.tx;
+        let input = tx
+            .inputs
+            .get(input_index)
+            .ok_or(SighashError::InputIndexOutOfRange(input_index))?;
+        if prevouts.len() != tx.inputs.len() {
+            return Err(SighashError::PrevoutsCountMismatch {
+                inputs: tx.inputs.len(),
+                prevouts: prevouts.len(),
+            });
+        }
+        match sighash_type {
+            0x00..=0x03 | 0x81..=0x83 => (),
+            _ => return Err(SighashError::InvalidSighashType(sighash_type)),
+        }
+        let base_type = sighash_type & 0x03;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+
+        let mut msg = vec![0x00, sighash_type as u8];
+        msg.extend(&tx.version.to_le_bytes());
+        msg.extend(&tx.lock_time.to_le_bytes());
+        if !anyone_can_pay {
+            msg.extend(self.sha_prevouts().as_ref());
+            msg.extend(self.sha_amounts(prevouts).as_ref());
+            msg.extend(self.sha_script_pub_keys(prevouts).as_ref());
+            msg.extend(self.sha_sequences().as_ref());
+        }
+        if base_type != SIGHASH_NONE && base_type != SIGHASH_SINGLE {
+            msg.extend(self.sha_outputs().as_ref());
+        }
+        let ext_flag = match script_path {
+            Some(_) => 1,
+            None => 0,
+        };
+        msg.push(ext_flag * 2 + annex.is_some() as u8);
+        match anyone_can_pay {
+            true => {
+                let prevout = &prevouts[input_index];
+                msg.extend(input.previous_tx_hash.as_ref());
+                msg.extend(&input.vout.to_le_bytes());
+                msg.extend(serialize_tx_output(prevout));
+                msg.extend(&input.sequence.to_le_bytes());
+            }
+            false => msg.extend(&(input_index as u32).to_le_bytes()),
+        }
+        if let Some(annex) = annex {
+            let annex = [&serialize_var_int(annex.len() as u64)[..], annex].concat();
+            msg.extend(sha256(&annex).as_ref());
+        }
+        if base_type == SIGHASH_SINGLE {
+            let output = tx
+                .outputs
+                .get(input_index)
+                .ok_or(SighashError::SingleWithoutOutput(input_index))?;
+            msg.extend(sha256(&serialize_tx_output(output)).as_ref());
+        }
+        if let Some((leaf_hash, codesep_pos)) = script_path {
+            msg.extend(leaf_hash.as_ref());
+            //key_version, always 0 for now
+            msg.push(0x00);
+            msg.extend(&codesep_pos.to_le_bytes());
+        }
+        Ok(msg)
+    }
+
+    fn sha_prevouts(&mut self) -> Hash256 {
+        let tx = self.tx;
+        *self.sha_prevouts.get_or_insert_with(|| {
+            let mut vec = Vec::with_capacity(36 * tx.inputs.len());
+            for input in tx.inputs.iter() {
+                vec.extend(input.previous_tx_hash.as_ref());
+                vec.extend(&input.vout.to_le_bytes());
+            }
+            sha256(&vec)
+        })
+    }
+
+    fn sha_sequences(&mut self) -> Hash256 {
+        let tx = self.tx;
+        *self.sha_sequences.get_or_insert_with(|| {
+            let mut vec = Vec::with_capacity(4 * tx.inputs.len());
+            for input in tx.inputs.iter() {
+                vec.extend(&input.sequence.to_le_bytes());
+            }
+            sha256(&vec)
+        })
+    }
+
+    fn sha_outputs(&mut self) -> Hash256 {
+        let tx = self.tx;
+        *self.sha_outputs.get_or_insert_with(|| {
+            let mut vec = Vec::new();
+            for output in tx.outputs.iter() {
+                vec.extend(serialize_tx_output(output));
+            }
+            sha256(&vec)
+        })
+    }
+
+    //the prevouts are not part of the cache key, a cache is meant for a single set of prevouts
+    fn sha_amounts(&mut self, prevouts: &[TxOutput]) -> Hash256 {
+        *self.sha_amounts.get_or_insert_with(|| {
+            let mut vec = Vec::with_capacity(8 * prevouts.len());
+            for prevout in prevouts {
+                vec.extend(&prevout.value.to_le_bytes());
+            }
+            sha256(&vec)
+        })
+    }
+
+    fn sha_script_pub_keys(&mut self, prevouts: &[TxOutput]) -> Hash256 {
+        *self.sha_script_pub_keys.get_or_insert_with(|| {
+            let mut vec = Vec::new();
+            for prevout in prevouts {
+                let script_pub_key = &prevout.script_pub_key.0;
+                vec.extend(serialize_var_int(script_pub_key.len() as u64));
+                vec.extend(script_pub_key);
+            }
+            sha256(&vec)
+        })
+    }
+
+    fn hash_prevouts(&mut self) -> Hash256 {
+        sha256(self.sha_prevouts().as_ref())
+    }
+
+    fn hash_sequence(&mut self) -> Hash256 {
+        sha256(self.sha_sequences().as_ref())
+    }
+
+    fn hash_outputs(&mut self) -> Hash256 {
+        sha256(self.sha_outputs().as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_transaction;
+    use hex;
+    //expected hashes are in the reversed (display) order, as in bitcoind's sighash.json
+    fn assert_legacy(
+        raw_tx: &str,
+        script: &str,
+        input_index: usize,
+        sighash_type: u32,
+        expected: &str,
+    ) {
+        let data = hex::decode(raw_tx).unwrap();
+        let (_, tx) = parse_transaction(&data).unwrap();
+        let mut expected = hex::decode(expected).unwrap();
+        expected.reverse();
+        assert_eq!(
+            tx.legacy_sighash(input_index, &hex::decode(script).unwrap(), sighash_type),
+            Hash256::new(&expected)
+        );
+    }
+    #[test]
+    fn test_legacy_sighash() {
+        let tests: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../test_data/sighash.json")).unwrap();
+        //[raw transaction, script, input index, hash type, expected hash]
+        let tests: Vec<_> = tests
+            .iter()
+            .map(|test| test.as_array().unwrap())
+            .filter(|test| test.len() == 5)
+            .collect();
+        assert!(!tests.is_empty());
+        for test in tests.iter() {
+            let field = |n: usize| test[n].as_str().unwrap();
+            let input_index = test[2].as_u64().unwrap() as usize;
+            let sighash_type = test[3].as_i64().unwrap() as u32;
+            assert_legacy(field(0), field(1), input_index, sighash_type, field(4));
+        }
+
+        //SIGHASH_SINGLE past the outputs of a transaction from sighash.json, with 2 inputs and 1 output
+        let data = hex::decode("73107cbd025c22ebc8c3e0a47b2a760739216a528de8d4dab5d45cbeb3051cebae73b01ca10200000007ab6353656a636affffffffe26816dffc670841e6a6c8c61c586da401df1261a330a6c6b3dd9f9a0789bc9e000000000800ac6552ac6aac51ffffffff0174a8f0010000000004ac52515100000000").unwrap();
+        let (_, tx) = parse_transaction(&data).unwrap();
+        let script = hex::decode("5163ac63635151ac").unwrap();
+        let one = sighash_one();
+        assert_eq!(tx.legacy_sighash(1, &script, SIGHASH_SINGLE), one);
+        assert_eq!(
+            tx.legacy_sighash(1, &script, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY),
+            one
+        );
+        assert_ne!(tx.legacy_sighash(0, &script, SIGHASH_SINGLE), one);
+        assert_ne!(tx.legacy_sighash(1, &script, SIGHASH_NONE), one);
+
+        //the unsigned transaction from the BIP143 P2WPKH example, signed the legacy way
+        let tx = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+        let script = "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac";
+        let script_with_separators = "ab76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188acab";
+        let expected = "a4a612c19dd001d604b8561f713a4e8f64413d5d5bcc472a4048bc0c823060c4";
+        assert_legacy(tx, script, 1, SIGHASH_ALL, expected);
+        assert_legacy(tx, script_with_separators, 1, SIGHASH_ALL, expected);
+        assert_legacy(
+            tx,
+            script_with_separators,
+            1,
+            SIGHASH_NONE,
+            "e289cbfaf7b86b3660a6934a1894f191c8ed05d2b79da7765fe5eb4d55cfbbff",
+        );
+        assert_legacy(
+            tx,
+            script_with_separators,
+            1,
+            SIGHASH_SINGLE,
+            "c2aeaf1559462e7bf2a21117b1e3de6f1d528c740b18efbc042fb8d68b46cd33",
+        );
+        assert_legacy(
+            tx,
+            script_with_separators,
+            0,
+            SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+            "d4410ae37e88288ad4892b9f2860a1490038c99e751279b87bd497f08fe47d4e",
+        );
+        assert_legacy(
+            tx,
+            script_with_separators,
+            1,
+            SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+            "21671d0cd5c93489b32980a020ead5aba968c08bd51b45b88d0ab61acba48cbd",
+        );
+        assert_legacy(
+            tx,
+            script_with_separators,
+            1,
+            SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+            "a6dc3752518f5408daec502f6c3ac546f102c37621404c8a491789b891775c86",
+        );
+
+        //SIGHASH_SINGLE bug, 5 inputs but only 2 outputs
+        let data = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        let one = sighash_one();
+        assert_eq!(tx.legacy_sighash(3, &[], SIGHASH_SINGLE), one);
+        assert_eq!(tx.legacy_sighash(5, &[], SIGHASH_ALL), one);
+        assert_ne!(tx.legacy_sighash(1, &[], SIGHASH_SINGLE), one);
+    }
+    #[test]
+    fn test_segwit_v0_sighash() {
+        //native P2WPKH example from BIP143
+        let data = hex::decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap();
+        let (_, tx) = parse_transaction(&data).unwrap();
+        let script_code =
+            hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap();
+        let mut cache = SighashCache::new(&tx);
+        let sighash = cache
+            .segwit_v0_sighash(1, &script_code, 600000000, SIGHASH_ALL)
+            .unwrap();
+        assert_eq!(
+            cache.hash_prevouts(),
+            Hash256::new(
+                &hex::decode("96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            cache.hash_sequence(),
+            Hash256::new(
+                &hex::decode("52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            cache.hash_outputs(),
+            Hash256::new(
+                &hex::decode("863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            sighash,
+            Hash256::new(
+                &hex::decode("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670")
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            tx.segwit_v0_sighash(1, &script_code, 600000000, SIGHASH_ALL),
+            Ok(sighash)
+        );
+        for (sighash_type, expected) in &[
+            (
+                SIGHASH_NONE,
+                "6ff11a9b87fb510a3a31af006bd3811b632f8a39d88a2bfda49cee203dcc356e",
+            ),
+            (
+                SIGHASH_SINGLE,
+                "f4fe57286dd2ca8ac0e3dfccd54c352fcdcacbed80f194e264b75d7a7c74e4ce",
+            ),
+            (
+                SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+                "fc5b6bbc855883bcfdaefb77071740ccde4929f15e6a13286584e779b2529d91",
+            ),
+            (
+                SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+                "4abb5ef58a968f8e1ab88a9fb72f2ce74b3022e65d334ac7b8aeda747515dc15",
+            ),
+            (
+                SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+                "79ff9ff708f79ce8f7a4f90d62028533a99d7340b7fb3d819dfd9a599a78e39c",
+            ),
+        ] {
+            assert_eq!(
+                cache.segwit_v0_sighash(1, &script_code, 600000000, *sighash_type),
+                Ok(Hash256::new(&hex::decode(expected).unwrap()))
+            );
+        }
+        assert_eq!(
+            cache.segwit_v0_sighash(2, &script_code, 600000000, SIGHASH_ALL),
+            Err(SighashError::InputIndexOutOfRange(2))
+        );
+
+        //P2SH-P2WPKH example from BIP143
+        let data = hex::decode("0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000").unwrap();
+        let (_, tx) = parse_transaction(&data).unwrap();
+        let script_code =
+            hex::decode("76a91479091972186c449eb1ded22b78e40d009bdf008988ac").unwrap();
+        assert_eq!(
+            tx.segwit_v0_sighash(0, &script_code, 1000000000, SIGHASH_ALL),
+            Ok(Hash256::new(
+                &hex::decode("64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6")
+                    .unwrap()
+            ))
+        );
+    }
+    fn hex_value(value: &serde_json::Value) -> Vec<u8> {
+        hex::decode(value.as_str().unwrap()).unwrap()
+    }
+
+    //BIP341 has no vectors for script path spends and annexes, their messages are the key path
+    //message with the spend type raised, the annex hash after the input data and the script path data at the end
+    fn extend_sig_msg(
+        sig_msg: &[u8],
+        sighash_type: u32,
+        prevout: &TxOutput,
+        annex: Option<&[u8]>,
+        script_path: Option<(Hash256, u32)>,
+    ) -> Vec<u8> {
+        let mut spend_type = 10;
+        if sighash_type & SIGHASH_ANYONECANPAY == 0 {
+            spend_type += 4 * 32;
+        }
+        if sighash_type & 0x03 == SIGHASH_DEFAULT || sighash_type & 0x03 == SIGHASH_ALL {
+            spend_type += 32;
+        }
+        let input_end = match sighash_type & SIGHASH_ANYONECANPAY {
+            0 => spend_type + 1 + 4,
+            _ => spend_type + 1 + 36 + serialize_tx_output(prevout).len() + 4,
+        };
+        let mut msg = sig_msg[..input_end].to_vec();
+        if let Some(annex) = annex {
+            msg[spend_type] += 1;
+            let annex = [&serialize_var_int(annex.len() as u64)[..], annex].concat();
+            msg.extend(sha256(&annex).as_ref());
+        }
+        msg.extend(&sig_msg[input_end..]);
+        if let Some((leaf_hash, codesep_pos)) = script_path {
+            msg[spend_type] += 2;
+            msg.extend(leaf_hash.as_ref());
+            msg.push(0x00);
+            msg.extend(&codesep_pos.to_le_bytes());
+        }
+        msg
+    }
+
+    #[test]
+    fn test_taproot_sighash() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../test_data/bip341_wallet_vectors.json")).unwrap();
+        let spending = &vectors["keyPathSpending"][0];
+        let data = hex_value(&spending["given"]["rawUnsignedTx"]);
+        let (_, tx) = parse_transaction(&data).unwrap();
+        let prevouts: Vec<TxOutput> = spending["given"]["utxosSpent"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|utxo| {
+                let script_pub_key = hex_value(&utxo["scriptPubKey"]);
+                TxOutput::new(utxo["amountSats"].as_u64().unwrap(), &script_pub_key)
+            })
+            .collect();
+        let mut cache = SighashCache::new(&tx);
+        for input in spending["inputSpending"].as_array().unwrap() {
+            let input_index = input["given"]["txinIndex"].as_u64().unwrap() as usize;
+            let sighash_type = input["given"]["hashType"].as_u64().unwrap() as u32;
+            let sig_msg = hex_value(&input["intermediary"]["sigMsg"]);
+            let sig_hash = Hash256::new(&hex_value(&input["intermediary"]["sigHash"]));
+            assert_eq!(
+                cache.taproot_sig_msg(input_index, &prevouts, None, None, sighash_type),
+                Ok(sig_msg.clone())
+            );
+            assert_eq!(
+                cache.taproot_sighash(input_index, &prevouts, None, None, sighash_type),
+                Ok(sig_hash)
+            );
+            assert_eq!(
+                tx.taproot_sighash(input_index, &prevouts, None, None, sighash_type),
+                Ok(sig_hash)
+            );
+
+            let prevout = &prevouts[input_index];
+            let annex = [0x50, 0x01];
+            let msg = extend_sig_msg(&sig_msg, sighash_type, prevout, Some(&annex), None);
+            assert_eq!(
+                cache.taproot_sighash(input_index, &prevouts, Some(&annex), None, sighash_type),
+                Ok(tagged_hash("TapSighash", &msg))
+            );
+            //the leaves of the script tree of the spent output
+            let spent = vectors["scriptPubKey"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|spk| {
+                    hex_value(&spk["expected"]["scriptPubKey"]) == prevout.script_pub_key.0
+                });
+            let leaf_hashes = spent
+                .and_then(|spk| spk["intermediary"]["leafHashes"].as_array())
+                .cloned()
+                .unwrap_or_default();
+            for leaf_hash in leaf_hashes.iter() {
+                let leaf_hash = Hash256::new(&hex_value(leaf_hash));
+                let script_path = Some((leaf_hash, 0xffffffff));
+                let msg = extend_sig_msg(&sig_msg, sighash_type, prevout, None, script_path);
+                assert_eq!(
+                    cache.taproot_sighash(
+                        input_index,
+                        &prevouts,
+                        None,
+                        Some(leaf_hash),
+                        sighash_type
+                    ),
+                    Ok(tagged_hash("TapSighash", &msg))
+                );
+                let script_path = Some((leaf_hash, 2));
+                let msg =
+                    extend_sig_msg(&sig_msg, sighash_type, prevout, Some(&annex), script_path);
+                assert_eq!(
+                    cache.taproot_sighash_with_codesep(
+                        input_index,
+                        &prevouts,
+                        Some(&annex),
+                        leaf_hash,
+                        2,
+                        sighash_type
+                    ),
+                    Ok(tagged_hash("TapSighash", &msg))
+                );
+            }
+        }
+        let intermediary = &spending["intermediary"];
+        let hash = |name: &str| Hash256::new(&hex_value(&intermediary[name]));
+        assert_eq!(cache.sha_prevouts(), hash("hashPrevouts"));
+        assert_eq!(cache.sha_amounts(&prevouts), hash("hashAmounts"));
+        assert_eq!(
+            cache.sha_script_pub_keys(&prevouts),
+            hash("hashScriptPubkeys")
+        );
+        assert_eq!(cache.sha_sequences(), hash("hashSequences"));
+        assert_eq!(cache.sha_outputs(), hash("hashOutputs"));
+
+        //errors
+        assert_eq!(
+            tx.taproot_sighash(9, &prevouts, None, None, SIGHASH_ALL),
+            Err(SighashError::InputIndexOutOfRange(9))
+        );
+        assert_eq!(
+            tx.taproot_sighash(0, &prevouts[1..], None, None, SIGHASH_ALL),
+            Err(SighashError::PrevoutsCountMismatch {
+                inputs: 9,
+                prevouts: 8
+            })
+        );
+        assert_eq!(
+            tx.taproot_sighash(0, &prevouts, None, None, 0x04),
+            Err(SighashError::InvalidSighashType(0x04))
+        );
+        assert_eq!(
+            tx.taproot_sighash(2, &prevouts, None, None, SIGHASH_SINGLE),
+            Err(SighashError::SingleWithoutOutput(2))
+        );
+    }
+}
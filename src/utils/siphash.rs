@@ -0,0 +1,58 @@
+//SipHash-2-4 with the key as two little endian words, as used by BIP152 and BIP158
+pub fn siphash_2_4(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    };
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        compress(&mut v, u64::from_le_bytes(word));
+    }
+    //the last word holds the remaining bytes and the length in its top byte
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_siphash_2_4() {
+        //vectors of the reference implementation, key 00 01 .. 0f and message 00 01 .. len - 1
+        let k0 = 0x0706050403020100;
+        let k1 = 0x0f0e0d0c0b0a0908;
+        let message: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash_2_4(k0, k1, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash_2_4(k0, k1, &message[..8]), 0x93f5f5799a932462);
+        assert_eq!(siphash_2_4(k0, k1, &message), 0xa129ca6149be45e5);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{serializers::serialize_var_int, types::Hash256, utils::sha256};
+
+//BIP340 tagged hash: sha256(sha256(tag) || sha256(tag) || msg)
+pub fn tagged_hash(tag: &str, msg: &[u8]) -> Hash256 {
+    let tag_hash = sha256(tag.as_bytes());
+    sha256(&[tag_hash.as_ref(), tag_hash.as_ref(), msg].concat())
+}
+
+//BIP341 leaf hash of a tapscript, leaf_version is 0xc0 for tapscript
+pub fn tap_leaf_hash(leaf_version: u8, script: &[u8]) -> Hash256 {
+    tagged_hash(
+        "TapLeaf",
+        &[
+            &[leaf_version][..],
+            &serialize_var_int(script.len() as u64),
+            script,
+        ]
+        .concat(),
+    )
+}
+
+//BIP341 branch hash, the children are sorted before hashing
+pub fn tap_branch_hash(a: &Hash256, b: &Hash256) -> Hash256 {
+    let (a, b) = match a.as_ref() <= b.as_ref() {
+        true => (a, b),
+        false => (b, a),
+    };
+    tagged_hash("TapBranch", &[a.as_ref(), b.as_ref()].concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::script_to_address;
+    use hex;
+    use serde_json::Value;
+
+    fn hex_value(value: &Value) -> Vec<u8> {
+        hex::decode(value.as_str().unwrap()).unwrap()
+    }
+
+    //the hash of a script tree, leaves are collected depth first
+    fn tree_hash(tree: &Value, leaves: &mut Vec<Hash256>) -> Hash256 {
+        match tree.as_array() {
+            Some(branches) => {
+                let left = tree_hash(&branches[0], leaves);
+                let right = tree_hash(&branches[1], leaves);
+                tap_branch_hash(&left, &right)
+            }
+            None => {
+                let leaf_version = tree["leafVersion"].as_u64().unwrap() as u8;
+                let leaf = tap_leaf_hash(leaf_version, &hex_value(&tree["script"]));
+                leaves.push(leaf);
+                leaf
+            }
+        }
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let tag_hash = sha256("TapSighash".as_bytes());
+        assert_eq!(
+            tagged_hash("TapSighash", &[0x00]),
+            sha256(&[tag_hash.as_ref(), tag_hash.as_ref(), &[0x00]].concat())
+        );
+        let leaf = Hash256([0x00; 32]);
+        let other = Hash256([0xff; 32]);
+        assert_eq!(
+            tap_branch_hash(&leaf, &other),
+            tap_branch_hash(&other, &leaf)
+        );
+    }
+
+    #[test]
+    fn test_bip341_script_pub_keys() {
+        let vectors: Value =
+            serde_json::from_str(include_str!("../test_data/bip341_wallet_vectors.json")).unwrap();
+        for test in vectors["scriptPubKey"].as_array().unwrap() {
+            let internal_key = hex_value(&test["given"]["internalPubkey"]);
+            let tree = &test["given"]["scriptTree"];
+            let intermediary = &test["intermediary"];
+            let mut leaves = Vec::new();
+            let tweak = match tree.is_null() {
+                true => tagged_hash("TapTweak", &internal_key),
+                false => {
+                    let merkle_root = tree_hash(tree, &mut leaves);
+                    assert_eq!(hex::encode(merkle_root), intermediary["merkleRoot"]);
+                    tagged_hash(
+                        "TapTweak",
+                        &[&internal_key[..], merkle_root.as_ref()].concat(),
+                    )
+                }
+            };
+            assert_eq!(hex::encode(tweak), intermediary["tweak"]);
+            let leaf_hashes: Vec<_> = leaves.iter().map(hex::encode).collect();
+            match intermediary["leafHashes"].as_array() {
+                Some(expected) => assert_eq!(&leaf_hashes, expected),
+                None => assert!(leaf_hashes.is_empty()),
+            }
+
+            //the control blocks lead from their leaf to the merkle root
+            let expected = &test["expected"];
+            let control_blocks = expected["scriptPathControlBlocks"].as_array();
+            assert_eq!(control_blocks.map_or(0, |c| c.len()), leaves.len());
+            for (leaf, control_block) in leaves.iter().zip(control_blocks.into_iter().flatten()) {
+                let control_block = hex_value(control_block);
+                assert_eq!(&control_block[1..33], &internal_key[..]);
+                let root = control_block[33..].chunks(32).fold(*leaf, |hash, node| {
+                    tap_branch_hash(&hash, &Hash256::new(node))
+                });
+                assert_eq!(hex::encode(root), intermediary["merkleRoot"]);
+            }
+
+            let script_pub_key = hex_value(&expected["scriptPubKey"]);
+            assert_eq!(
+                hex::encode(&script_pub_key[2..]),
+                intermediary["tweakedPubkey"]
+            );
+            assert_eq!(
+                script_to_address(&script_pub_key, "mainnet").unwrap(),
+                expected["bip350Address"]
+            );
+        }
+    }
+}
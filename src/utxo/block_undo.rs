@@ -0,0 +1,35 @@
+use crate::{
+    types::{Block, OutPoint, TxOutput},
+    utxo::Coin,
+};
+use std::collections::HashMap;
+
+//the outputs spent by a block as stored in rev*.dat, one entry for each transaction but the coinbase
+#[derive(Debug, Clone)]
+pub struct BlockUndo {
+    pub tx_undos: Vec<Vec<Coin>>,
+}
+
+impl BlockUndo {
+    pub fn new(tx_undos: Vec<Vec<Coin>>) -> BlockUndo {
+        BlockUndo { tx_undos }
+    }
+    //undo data does not name the outputs, they are matched to the inputs of block
+    //None if the undo data does not belong to block
+    pub fn prevouts(&self, block: &Block) -> Option<HashMap<OutPoint, TxOutput>> {
+        let txs = block.transactions.get(1..)?;
+        if txs.len() != self.tx_undos.len() {
+            return None;
+        }
+        let mut prevouts = HashMap::new();
+        for (tx, coins) in txs.iter().zip(self.tx_undos.iter()) {
+            if tx.inputs.len() != coins.len() {
+                return None;
+            }
+            for (input, coin) in tx.inputs.iter().zip(coins.iter()) {
+                prevouts.insert(input.out_point(), coin.output.clone());
+            }
+        }
+        Some(prevouts)
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{
+    parsers::parse_var_int,
+    serializers::serialize_var_int,
+    types::{Bytes, TxOutput},
+};
+use nom::{
+    multi::length_data,
+    number::complete::{le_u32, le_u64, le_u8},
+    sequence::tuple,
+    IResult,
+};
+
+//an unspent output together with where it was created
+#[derive(Debug, Clone)]
+pub struct Coin {
+    pub output: TxOutput,
+    pub height: u32,
+    pub is_coinbase: bool,
+}
+
+impl Coin {
+    pub fn new(output: TxOutput, height: u32, is_coinbase: bool) -> Coin {
+        Coin {
+            output,
+            height,
+            is_coinbase,
+        }
+    }
+    //the record format of the on disk spill
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        let Bytes(script_pub_key) = &self.output.script_pub_key;
+        [
+            &self.output.value.to_le_bytes()[..],
+            &self.height.to_le_bytes(),
+            &[self.is_coinbase as u8],
+            &serialize_var_int(script_pub_key.len() as u64),
+            script_pub_key,
+        ]
+        .concat()
+    }
+    pub(crate) fn parse(input: &[u8]) -> IResult<&[u8], Coin> {
+        let (input, (value, height, is_coinbase, script_pub_key)) =
+            tuple((le_u64, le_u32, le_u8, length_data(parse_var_int)))(input)?;
+        let output = TxOutput::new(value, script_pub_key);
+        Ok((input, Coin::new(output, height, is_coinbase != 0)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_coin_serialization() {
+        let output = TxOutput::new(
+            7357023,
+            &hex::decode("a91430897cc6c9d69f6a2c2f1c651d51f22219f1a4f687").unwrap(),
+        );
+        let coin = Coin::new(output, 609015, true);
+        let data = coin.serialize();
+        let (rest, parsed) = Coin::parse(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed.output.value, 7357023);
+        assert_eq!(parsed.output.script_pub_key, coin.output.script_pub_key);
+        assert_eq!(parsed.height, 609015);
+        assert!(parsed.is_coinbase);
+    }
+}
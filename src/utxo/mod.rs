@@ -0,0 +1,9 @@
+mod coin;
+pub use self::coin::Coin;
+mod spill;
+mod utxo_set;
+pub use self::utxo_set::{is_unspendable, UtxoIssue, UtxoSet, UtxoStats};
+mod prevout_provider;
+pub use self::prevout_provider::{BlockPrevouts, FeeError, PrevoutProvider};
+mod block_undo;
+pub use self::block_undo::BlockUndo;
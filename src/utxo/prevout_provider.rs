@@ -0,0 +1,182 @@
+use crate::{
+    types::{Hash256, OutPoint, Transaction, TxOutput},
+    utxo::UtxoSet,
+};
+use std::{collections::HashMap, io};
+
+//looks up the outputs spent by transaction inputs
+pub trait PrevoutProvider {
+    fn prevout(&self, out_point: &OutPoint) -> io::Result<Option<TxOutput>>;
+}
+
+impl PrevoutProvider for HashMap<OutPoint, TxOutput> {
+    fn prevout(&self, out_point: &OutPoint) -> io::Result<Option<TxOutput>> {
+        Ok(self.get(out_point).cloned())
+    }
+}
+
+//the set has to be in the state before the block spending the outputs was applied
+impl PrevoutProvider for UtxoSet {
+    fn prevout(&self, out_point: &OutPoint) -> io::Result<Option<TxOutput>> {
+        Ok(self.get(out_point)?.map(|coin| coin.output))
+    }
+}
+
+impl<P: PrevoutProvider + ?Sized> PrevoutProvider for &P {
+    fn prevout(&self, out_point: &OutPoint) -> io::Result<Option<TxOutput>> {
+        (**self).prevout(out_point)
+    }
+}
+
+//outputs of the transactions of a block first, as they can be spent later in the same block
+pub struct BlockPrevouts<'a, P: PrevoutProvider> {
+    transactions: HashMap<Hash256, &'a Transaction>,
+    provider: P,
+}
+
+impl<'a, P: PrevoutProvider> BlockPrevouts<'a, P> {
+    pub fn new(transactions: &'a [Transaction], provider: P) -> Self {
+        BlockPrevouts {
+            transactions: transactions.iter().map(|tx| (tx.txid.0, tx)).collect(),
+            provider,
+        }
+    }
+}
+
+impl<'a, P: PrevoutProvider> PrevoutProvider for BlockPrevouts<'a, P> {
+    fn prevout(&self, out_point: &OutPoint) -> io::Result<Option<TxOutput>> {
+        match self.transactions.get(&out_point.txid) {
+            Some(tx) => Ok(tx.outputs.get(out_point.vout as usize).cloned()),
+            None => self.provider.prevout(out_point),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FeeError {
+    MissingPrevout(OutPoint),
+    //the outputs are worth more than the inputs
+    NegativeFee { inputs: u64, outputs: u64 },
+    Io(io::Error),
+}
+
+impl std::fmt::Display for FeeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FeeError::MissingPrevout(out_point) => write!(
+                f,
+                "previous output {:?}:{} not found",
+                out_point.txid, out_point.vout
+            ),
+            FeeError::NegativeFee { inputs, outputs } => write!(
+                f,
+                "outputs worth {} are more than the inputs worth {}",
+                outputs, inputs
+            ),
+            FeeError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for FeeError {}
+
+impl From<io::Error> for FeeError {
+    fn from(err: io::Error) -> FeeError {
+        FeeError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        parsers::{parse_block, parse_transaction},
+        utxo::BlockUndo,
+        utxo::Coin,
+    };
+
+    #[test]
+    fn test_fee() {
+        let data = include_bytes!(
+            "../test_data/tx_640d0279609c9047ebbffb1d0dcf78cbbe2ae12cadd41a28377e1a259ebf5b89.bin"
+        );
+        let (_, tx) = parse_transaction(data).unwrap();
+        assert_eq!(tx.size, 1000);
+        assert_eq!(tx.weight(), 3223);
+        assert_eq!(tx.vsize(), 806);
+        assert_eq!(tx.output_value(), 7357023 + 28734702);
+
+        let mut prevouts = HashMap::new();
+        for input in tx.inputs.iter() {
+            prevouts.insert(input.out_point(), TxOutput::new(8_000_000, &[]));
+        }
+        assert_eq!(tx.fee(&prevouts).unwrap(), 40_000_000 - 36_091_725);
+        assert_eq!(tx.fee_rate(&prevouts).unwrap(), 3_908_275f64 / 806f64);
+
+        prevouts.insert(tx.inputs[0].out_point(), TxOutput::new(0, &[]));
+        match tx.fee(&prevouts) {
+            Err(FeeError::NegativeFee { inputs, outputs }) => {
+                assert_eq!((inputs, outputs), (32_000_000, 36_091_725))
+            }
+            other => panic!("{:?}", other),
+        }
+        prevouts.remove(&tx.inputs[4].out_point());
+        match tx.fee(&prevouts) {
+            Err(FeeError::MissingPrevout(out_point)) => {
+                assert_eq!(out_point, tx.inputs[4].out_point())
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let data = include_bytes!("../test_data/tx_de06af29a80be52bb5f4b6c86998dcfdf0f9e7f66a1ebb7e9d20d65cc6785d8c.native_witness.bin");
+        let (_, tx) = parse_transaction(data).unwrap();
+        assert_eq!(
+            (tx.stripped_size(), tx.weight(), tx.vsize()),
+            (128, 766, 192)
+        );
+    }
+
+    #[test]
+    fn test_total_fees() {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let created: HashMap<_, _> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| {
+                tx.outputs.iter().enumerate().map(move |(vout, output)| {
+                    (OutPoint::new(tx.txid.0, vout as u32), output.value)
+                })
+            })
+            .collect();
+        //every output spent from an earlier block is made worth 10000 BTC
+        let mut prevouts = HashMap::new();
+        let mut tx_undos = Vec::new();
+        let mut expected = 0;
+        for tx in block.transactions[1..].iter() {
+            let mut coins = Vec::new();
+            for input in tx.inputs.iter() {
+                let out_point = input.out_point();
+                let value = match created.get(&out_point) {
+                    Some(value) => *value,
+                    None => {
+                        prevouts.insert(out_point, TxOutput::new(1_000_000_000_000, &[]));
+                        1_000_000_000_000
+                    }
+                };
+                coins.push(Coin::new(TxOutput::new(value, &[]), 1, false));
+                expected += value;
+            }
+            expected -= tx.output_value();
+            tx_undos.push(coins);
+        }
+        assert_eq!(block.total_fees(&prevouts).unwrap(), expected);
+
+        let undo = BlockUndo::new(tx_undos);
+        let prevouts = undo.prevouts(&block).unwrap();
+        assert_eq!(block.total_fees(&prevouts).unwrap(), expected);
+        assert!(BlockUndo::new(Vec::new()).prevouts(&block).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{types::OutPoint, utxo::Coin};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+//coins moved out of memory, appended to a file and found through an in memory index
+//spent records stay in the file until the garbage outweighs the live records
+pub(crate) struct Spill {
+    path: PathBuf,
+    file: File,
+    index: HashMap<OutPoint, (u64, u32)>,
+    end: u64,
+    garbage: u64,
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "corrupted utxo spill record")
+}
+
+impl Spill {
+    pub fn create(path: &Path) -> io::Result<Spill> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Spill {
+            path: path.to_path_buf(),
+            file,
+            index: HashMap::new(),
+            end: 0,
+            garbage: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn contains(&self, out_point: &OutPoint) -> bool {
+        self.index.contains_key(out_point)
+    }
+
+    pub fn write(&mut self, coins: Vec<(OutPoint, Coin)>) -> io::Result<()> {
+        let mut data = Vec::new();
+        for (out_point, coin) in coins {
+            let record = coin.serialize();
+            self.index.insert(
+                out_point,
+                (self.end + data.len() as u64, record.len() as u32),
+            );
+            data.extend(record);
+        }
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&data)?;
+        self.end += data.len() as u64;
+        Ok(())
+    }
+
+    //reading goes through &File so lookups do not need exclusive access
+    fn read(&self, offset: u64, len: u32) -> io::Result<Coin> {
+        let mut data = vec![0; len as usize];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        match Coin::parse(&data) {
+            Ok((_, coin)) => Ok(coin),
+            Err(_) => Err(invalid_data()),
+        }
+    }
+
+    pub fn get(&self, out_point: &OutPoint) -> io::Result<Option<Coin>> {
+        match self.index.get(out_point) {
+            Some(&(offset, len)) => self.read(offset, len).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove(&mut self, out_point: &OutPoint) -> io::Result<Option<Coin>> {
+        let (offset, len) = match self.index.remove(out_point) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let coin = self.read(offset, len)?;
+        self.garbage += u64::from(len);
+        if self.garbage > self.end / 2 {
+            self.compact()?;
+        }
+        Ok(Some(coin))
+    }
+
+    pub fn out_points(&self) -> impl Iterator<Item = &OutPoint> {
+        self.index.keys()
+    }
+
+    //rewrites the live records to a new file, in their old order
+    fn compact(&mut self) -> io::Result<()> {
+        let mut entries: Vec<_> = self.index.iter().map(|(o, e)| (*o, *e)).collect();
+        entries.sort_unstable_by_key(|(_, (offset, _))| *offset);
+        let mut spill = Spill::create(&self.path.with_extension("compact"))?;
+        {
+            let mut writer = BufWriter::new(&spill.file);
+            for (out_point, (offset, len)) in entries {
+                let mut data = vec![0; len as usize];
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.read_exact(&mut data)?;
+                writer.write_all(&data)?;
+                spill.index.insert(out_point, (spill.end, len));
+                spill.end += u64::from(len);
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&spill.path, &self.path)?;
+        std::mem::swap(&mut self.file, &mut spill.file);
+        std::mem::swap(&mut self.index, &mut spill.index);
+        self.end = spill.end;
+        self.garbage = 0;
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        //the file is only scratch space, there is nothing to do if it is gone already
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
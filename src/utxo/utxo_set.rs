@@ -0,0 +1,374 @@
+use crate::{
+    script::opcodes::OP_RETURN,
+    serializers::serialize_tx_output,
+    types::{Block, Hash256, OutPoint, TxOutput},
+    utils::sha256,
+    utxo::{spill::Spill, Coin},
+};
+use ring::digest::{Context, SHA256};
+use std::{collections::HashMap, io, path::Path};
+
+const MAX_SCRIPT_SIZE: usize = 10_000;
+
+//the two blocks whose coinbases duplicate earlier ones, allowed by BIP30 (hashes in wire order)
+const BIP30_EXCEPTIONS: [(u32, &str); 2] = [
+    (
+        91842,
+        "eccae000e3c8e4e093936360431f3b7603c563c1ff6181390a4d0a0000000000",
+    ),
+    (
+        91880,
+        "21d77ccb4c08386a04ac0196ae10f6a1d2c2a377558ca190f143070000000000",
+    ),
+];
+
+//inconsistencies found while applying a block, the scan goes on regardless
+#[derive(Debug, PartialEq, Clone)]
+pub enum UtxoIssue {
+    //the spent output is not in the set, either unknown or already spent
+    MissingOutput {
+        txid: Hash256,
+        input_index: usize,
+        out_point: OutPoint,
+    },
+    //an output that is still unspent got created again, the old one is replaced
+    DuplicateOutput {
+        out_point: OutPoint,
+    },
+}
+
+//the summary bitcoind's gettxoutsetinfo gives with hash_type hash_serialized_3
+#[derive(Debug, PartialEq, Clone)]
+pub struct UtxoStats {
+    pub height: u32,
+    pub best_block: Hash256,
+    pub transactions: u64,
+    pub txouts: u64,
+    pub bogosize: u64,
+    pub hash_serialized: Hash256,
+    pub total_amount: u64,
+}
+
+//outputs that can never be spent are not added to the set
+pub fn is_unspendable(output: &TxOutput) -> bool {
+    let script_pub_key = &output.script_pub_key.0;
+    script_pub_key.first() == Some(&OP_RETURN) || script_pub_key.len() > MAX_SCRIPT_SIZE
+}
+
+fn is_bip30_exception(height: u32, hash: &Hash256) -> bool {
+    BIP30_EXCEPTIONS
+        .iter()
+        .any(|(h, block_hash)| *h == height && hex::encode(hash.0) == *block_hash)
+}
+
+//the unspent outputs after applying blocks in chain order
+pub struct UtxoSet {
+    coins: HashMap<OutPoint, Coin>,
+    spill: Option<Spill>,
+    max_coins: usize,
+    height: u32,
+    best_block: Hash256,
+}
+
+impl UtxoSet {
+    pub fn new() -> UtxoSet {
+        UtxoSet {
+            coins: HashMap::new(),
+            spill: None,
+            max_coins: usize::MAX,
+            height: 0,
+            best_block: Hash256::default(),
+        }
+    }
+
+    //keeps at most max_coins coins in memory, the oldest ones are moved to the file at path
+    pub fn with_spill<P: AsRef<Path>>(path: P, max_coins: usize) -> io::Result<UtxoSet> {
+        Ok(UtxoSet {
+            spill: Some(Spill::create(path.as_ref())?),
+            max_coins,
+            ..UtxoSet::new()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len() + self.spill.as_ref().map_or(0, |s| s.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn best_block(&self) -> Hash256 {
+        self.best_block
+    }
+
+    pub fn contains(&self, out_point: &OutPoint) -> bool {
+        self.coins.contains_key(out_point)
+            || self.spill.as_ref().is_some_and(|s| s.contains(out_point))
+    }
+
+    pub fn get(&self, out_point: &OutPoint) -> io::Result<Option<Coin>> {
+        if let Some(coin) = self.coins.get(out_point) {
+            return Ok(Some(coin.clone()));
+        }
+        match &self.spill {
+            Some(spill) => spill.get(out_point),
+            None => Ok(None),
+        }
+    }
+
+    fn remove(&mut self, out_point: &OutPoint) -> io::Result<Option<Coin>> {
+        if let Some(coin) = self.coins.remove(out_point) {
+            return Ok(Some(coin));
+        }
+        match &mut self.spill {
+            Some(spill) => spill.remove(out_point),
+            None => Ok(None),
+        }
+    }
+
+    //spends the inputs and adds the outputs of every transaction of a block at height
+    //errors are only returned for the spill file, inconsistencies of the chain are returned as issues
+    pub fn apply_block(&mut self, block: &Block, height: u32) -> io::Result<Vec<UtxoIssue>> {
+        let mut issues = Vec::new();
+        self.height = height;
+        self.best_block = block.header.hash.0;
+        //the genesis coinbase is not spendable
+        if height == 0 {
+            return Ok(issues);
+        }
+        let allow_overwrite = is_bip30_exception(height, &block.header.hash.0);
+        for tx in block.transactions.iter() {
+            let is_coinbase = tx.is_coinbase();
+            if !is_coinbase {
+                for (input_index, input) in tx.inputs.iter().enumerate() {
+                    let out_point = input.out_point();
+                    if self.remove(&out_point)?.is_none() {
+                        issues.push(UtxoIssue::MissingOutput {
+                            txid: tx.txid.0,
+                            input_index,
+                            out_point,
+                        });
+                    }
+                }
+            }
+            for (vout, output) in tx.outputs.iter().enumerate() {
+                if is_unspendable(output) {
+                    continue;
+                }
+                let out_point = OutPoint::new(tx.txid.0, vout as u32);
+                if self.remove(&out_point)?.is_some() && !allow_overwrite {
+                    issues.push(UtxoIssue::DuplicateOutput { out_point });
+                }
+                let coin = Coin::new(output.clone(), height, is_coinbase);
+                self.coins.insert(out_point, coin);
+            }
+        }
+        if self.coins.len() > self.max_coins {
+            self.spill()?;
+        }
+        Ok(issues)
+    }
+
+    //moves the older half of the coins in memory to disk, recent coins are the likeliest to be spent
+    fn spill(&mut self) -> io::Result<()> {
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => return Ok(()),
+        };
+        let coins = &mut self.coins;
+        let mut out_points: Vec<_> = coins.iter().map(|(o, c)| (c.height, *o)).collect();
+        out_points.sort_unstable();
+        let count = out_points.len() - self.max_coins / 2;
+        let coins = out_points[..count]
+            .iter()
+            .filter_map(|(_, o)| coins.remove(o).map(|c| (*o, c)))
+            .collect();
+        spill.write(coins)
+    }
+
+    //goes through the coins in the order of bitcoind's database, coins on disk are read one at a time
+    pub fn stats(&self) -> io::Result<UtxoStats> {
+        let mut out_points: Vec<OutPoint> = self.coins.keys().copied().collect();
+        if let Some(spill) = &self.spill {
+            out_points.extend(spill.out_points());
+        }
+        out_points.sort_unstable();
+
+        let mut stats = UtxoStats {
+            height: self.height,
+            best_block: self.best_block,
+            transactions: 0,
+            txouts: out_points.len() as u64,
+            bogosize: 0,
+            hash_serialized: Hash256::default(),
+            total_amount: 0,
+        };
+        let mut context = Context::new(&SHA256);
+        let mut last_txid = None;
+        for out_point in out_points {
+            let coin = match self.get(&out_point)? {
+                Some(coin) => coin,
+                None => continue,
+            };
+            if last_txid != Some(out_point.txid) {
+                stats.transactions += 1;
+                last_txid = Some(out_point.txid);
+            }
+            stats.total_amount += coin.output.value;
+            //outpoint, script and the fixed fields of the coin database entry
+            stats.bogosize += 50 + coin.output.script_pub_key.len() as u64;
+            //serialized as in bitcoind's TxOutSer
+            context.update(&out_point.txid.0);
+            context.update(&out_point.vout.to_le_bytes());
+            context.update(&(coin.height * 2 + coin.is_coinbase as u32).to_le_bytes());
+            context.update(&serialize_tx_output(&coin.output));
+        }
+        stats.hash_serialized = sha256(context.finish().as_ref());
+        Ok(stats)
+    }
+}
+
+impl std::default::Default for UtxoSet {
+    fn default() -> UtxoSet {
+        UtxoSet::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::parse_block;
+    use std::collections::HashSet;
+
+    fn block() -> (Block, u32) {
+        let data = include_bytes!(
+            "../test_data/blk_0000000000000000000215160a3490f82c7203d9683802148a56282d1f80993d.bin"
+        );
+        let (_, block) = parse_block(data).unwrap();
+        let height = block.transactions[0].coinbase().unwrap().height().unwrap() as u32;
+        (block, height)
+    }
+
+    #[test]
+    fn test_apply_block() {
+        let (block, height) = block();
+        let txids: HashSet<_> = block.transactions.iter().map(|tx| tx.txid.0).collect();
+        let inputs: Vec<_> = block.transactions[1..]
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .collect();
+        let spent_in_block = inputs
+            .iter()
+            .filter(|input| txids.contains(&input.previous_tx_hash))
+            .count();
+        let outputs = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .filter(|output| !is_unspendable(output))
+            .count();
+
+        let mut utxo_set = UtxoSet::new();
+        let issues = utxo_set.apply_block(&block, height).unwrap();
+        assert_eq!(issues.len(), inputs.len() - spent_in_block);
+        assert_eq!(
+            issues[0],
+            UtxoIssue::MissingOutput {
+                txid: block.transactions[1].txid.0,
+                input_index: 0,
+                out_point: block.transactions[1].inputs[0].out_point(),
+            }
+        );
+        assert_eq!(utxo_set.len(), outputs - spent_in_block);
+        let coinbase = utxo_set
+            .get(&OutPoint::new(block.transactions[0].txid.0, 0))
+            .unwrap()
+            .unwrap();
+        assert!(coinbase.is_coinbase);
+        assert_eq!(coinbase.height, height);
+
+        let stats = utxo_set.stats().unwrap();
+        assert_eq!(stats.height, height);
+        assert_eq!(stats.best_block, block.header.hash.0);
+        assert_eq!(stats.txouts, utxo_set.len() as u64);
+        let total_amount: u64 = block
+            .transactions
+            .iter()
+            .flat_map(|tx| {
+                tx.outputs
+                    .iter()
+                    .enumerate()
+                    .map(move |(vout, output)| (OutPoint::new(tx.txid.0, vout as u32), output))
+            })
+            .filter(|(out_point, _)| utxo_set.contains(out_point))
+            .map(|(_, output)| output.value)
+            .sum();
+        assert_eq!(stats.total_amount, total_amount);
+
+        //applying it again creates every unspent output a second time
+        let unspent = utxo_set.len();
+        let issues = utxo_set.apply_block(&block, height + 1).unwrap();
+        let duplicates = issues
+            .iter()
+            .filter(|issue| matches!(issue, UtxoIssue::DuplicateOutput { .. }))
+            .count();
+        assert_eq!(duplicates, unspent);
+        assert_eq!(utxo_set.len(), unspent);
+    }
+
+    #[test]
+    fn test_spill() {
+        let (block, height) = block();
+        let mut utxo_set = UtxoSet::new();
+        let path = std::env::temp_dir().join(format!("parse_bitcoin_utxo_{}", std::process::id()));
+        let mut spilled = UtxoSet::with_spill(&path, 100).unwrap();
+        for height in height..height + 2 {
+            assert_eq!(
+                utxo_set.apply_block(&block, height).unwrap(),
+                spilled.apply_block(&block, height).unwrap()
+            );
+            assert!(spilled.coins.len() <= 100);
+            assert_eq!(utxo_set.stats().unwrap(), spilled.stats().unwrap());
+        }
+        assert!(path.exists());
+        drop(spilled);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_genesis_and_bip30() {
+        let data = include_bytes!(
+            "../test_data/blk_000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f.bin"
+        );
+        let (_, mut block) = parse_block(data).unwrap();
+        let mut utxo_set = UtxoSet::new();
+        assert!(utxo_set.apply_block(&block, 0).unwrap().is_empty());
+        assert!(utxo_set.is_empty());
+        let stats = utxo_set.stats().unwrap();
+        assert_eq!(stats.best_block, block.header.hash.0);
+        assert_eq!(
+            stats.hash_serialized,
+            Hash256::new(
+                &hex::decode("5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456")
+                    .unwrap()
+            )
+        );
+
+        //a coinbase duplicating an unspent one is only allowed in the two exception blocks
+        assert!(utxo_set.apply_block(&block, 1).unwrap().is_empty());
+        let out_point = OutPoint::new(block.transactions[0].txid.0, 0);
+        assert_eq!(
+            utxo_set.apply_block(&block, 2).unwrap(),
+            vec![UtxoIssue::DuplicateOutput { out_point }]
+        );
+        block.header.hash.0 = Hash256::new(&hex::decode(BIP30_EXCEPTIONS[0].1).unwrap());
+        assert!(utxo_set.apply_block(&block, 91842).unwrap().is_empty());
+        assert_eq!(utxo_set.len(), 1);
+        assert_eq!(utxo_set.get(&out_point).unwrap().unwrap().height, 91842);
+    }
+}